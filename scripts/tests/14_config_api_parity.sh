@@ -23,7 +23,7 @@ API_HOST=$(echo "$LISTENERS" | jq -r '.resources[0].api_server.host // empty')
 API_PORT=$(echo "$LISTENERS" | jq -r '.resources[0].api_server.port // empty')
 
 # Verify against expected values (from config.toml)
-# config.toml: host = "127.0.0.1", port = 8080, api_host = "0.0.0.0", api_port = 8000
+# config.toml: host = "127.0.0.1", port = 8080, api_host = "127.0.0.1", api_port = 8000
 if [ "$MAIN_HOST" = "127.0.0.1" ]; then
     log_pass "Listener main_server.host matches config (127.0.0.1)"
 else
@@ -36,10 +36,10 @@ else
     log_fail "Listener main_server.port mismatch (expected: 8080, got: $MAIN_PORT)"
 fi
 
-if [ "$API_HOST" = "0.0.0.0" ]; then
-    log_pass "Listener api_server.host matches config (0.0.0.0)"
+if [ "$API_HOST" = "127.0.0.1" ]; then
+    log_pass "Listener api_server.host matches config (127.0.0.1)"
 else
-    log_fail "Listener api_server.host mismatch (expected: 0.0.0.0, got: $API_HOST)"
+    log_fail "Listener api_server.host mismatch (expected: 127.0.0.1, got: $API_HOST)"
 fi
 
 if [ "$API_PORT" = "8000" ]; then
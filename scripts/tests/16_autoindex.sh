@@ -0,0 +1,60 @@
+#!/bin/bash
+# Directory Listing (autoindex) Tests
+# This script is called by integration_tests.sh, common.sh is already loaded
+
+log_section "16. Directory Listing (autoindex)"
+
+AUTOINDEX_DIR="/tmp/yarhs_autoindex_$$"
+mkdir -p "$AUTOINDEX_DIR/files/sub" "$AUTOINDEX_DIR/plain"
+echo "small" > "$AUTOINDEX_DIR/files/a.txt"
+head -c 4096 /dev/zero > "$AUTOINDEX_DIR/files/b.bin"
+echo "secret" > "$AUTOINDEX_DIR/files/.hidden"
+echo "no listing" > "$AUTOINDEX_DIR/plain/x.txt"
+
+UPDATE_RESPONSE=$(curl -s -X POST "$API_URL/v1/discovery:vhosts" \
+    -H "Content-Type: application/json" \
+    -d '{"resources": [{"virtual_hosts": [{
+      "name": "autoindex",
+      "domains": ["*"],
+      "routes": [
+        {"match": {"prefix": "/files"}, "type": "dir", "path": "'"$AUTOINDEX_DIR"'/files",
+         "autoindex": {"hide_dotfiles": true}},
+        {"match": {"prefix": "/plain"}, "type": "dir", "path": "'"$AUTOINDEX_DIR"'/plain"}
+      ]
+    }]}]}')
+assert_json_field "Configure autoindex vhost ACK" "$UPDATE_RESPONSE" ".status" "ACK"
+
+# HTML listing
+HTML=$(curl -s "$BASE_URL/files/")
+assert_contains "HTML listing shows files" "$HTML" "a.txt"
+assert_contains "HTML listing shows subdirectory" "$HTML" "sub/"
+assert_contains "HTML listing uses human-readable sizes" "$HTML" "4.0K"
+if echo "$HTML" | grep -q ".hidden"; then
+    log_fail "HTML listing hides dotfiles"
+else
+    log_pass "HTML listing hides dotfiles"
+fi
+
+# JSON listing via query parameter and Accept header
+JSON=$(curl -s "$BASE_URL/files/?format=json&sort=size&order=desc")
+assert_json_field "JSON listing: directories first" "$JSON" ".[0].name" "sub"
+assert_json_field "JSON listing: sorted by size desc" "$JSON" ".[1].name" "b.bin"
+assert_json_field "JSON listing: exact size" "$JSON" ".[1].size" "4096"
+
+JSON=$(curl -s -H "Accept: application/json" "$BASE_URL/files/")
+assert_json_field "JSON listing via Accept header" "$JSON" ".[0].is_dir" "true"
+
+# Missing trailing slash redirects, files still served
+assert_status "Directory without slash redirects" "$BASE_URL/files" "301"
+assert_status "File inside listed directory" "$BASE_URL/files/a.txt" "200"
+
+# Routes without autoindex keep returning 404
+assert_status "Directory without autoindex returns 404" "$BASE_URL/plain/" "404"
+
+# Restore
+curl -s -X POST "$API_URL/v1/discovery:vhosts" \
+    -H "Content-Type: application/json" \
+    -d '{"resources": [{"virtual_hosts": []}]}' > /dev/null
+rm -rf "$AUTOINDEX_DIR"
+
+log_info "Autoindex tests completed"
@@ -0,0 +1,81 @@
+#!/bin/bash
+# try_files / SPA Fallback Tests
+# This script is called by integration_tests.sh, common.sh is already loaded
+
+log_section "17. try_files (SPA Fallback)"
+
+SPA_DIR="/tmp/yarhs_try_files_$$"
+mkdir -p "$SPA_DIR/app/assets" "$SPA_DIR/docs/guide"
+echo "SPA Index" > "$SPA_DIR/app/index.html"
+echo "console.log(1)" > "$SPA_DIR/app/assets/main.js"
+echo "Pretty Page" > "$SPA_DIR/docs/page.html"
+echo "Guide Index" > "$SPA_DIR/docs/guide/index.html"
+
+UPDATE_RESPONSE=$(curl -s -X POST "$API_URL/v1/discovery:vhosts" \
+    -H "Content-Type: application/json" \
+    -d '{"resources": [{"virtual_hosts": [{
+      "name": "spa",
+      "domains": ["*"],
+      "routes": [
+        {"match": {"prefix": "/app"}, "type": "dir", "path": "'"$SPA_DIR"'/app",
+         "try_files": ["$uri", "$uri/", "/index.html"]},
+        {"match": {"prefix": "/docs"}, "type": "dir", "path": "'"$SPA_DIR"'/docs",
+         "try_files": ["$uri", "$uri.html", "$uri/", "@missing"]},
+        {"name": "missing", "match": {"path": "/__missing"}, "type": "direct",
+         "status": 410, "body": "Gone"}
+      ]
+    }]}]}')
+assert_json_field "Configure try_files vhost ACK" "$UPDATE_RESPONSE" ".status" "ACK"
+
+# Existing files are served as-is
+RESPONSE=$(curl -s "$BASE_URL/app/assets/main.js")
+assert_contains "try_files serves existing file" "$RESPONSE" "console.log"
+
+# Unknown paths fall back to the SPA index
+RESPONSE=$(curl -s "$BASE_URL/app/users/42")
+assert_contains "try_files SPA fallback" "$RESPONSE" "SPA Index"
+assert_status "try_files SPA fallback status" "$BASE_URL/app/users/42" "200"
+
+# Conditional requests apply to the fallback file
+ETAG=$(curl -sI "$BASE_URL/app/users/42" | grep -i "^etag:" | cut -d' ' -f2 | tr -d '\r')
+assert_status "try_files fallback honours If-None-Match" "$BASE_URL/app/other" "304" "If-None-Match: $ETAG"
+
+# Range requests apply to the fallback file
+STATUS=$(curl -s -o /dev/null -w "%{http_code}" -H "Range: bytes=0-2" "$BASE_URL/app/anything")
+if [ "$STATUS" = "206" ]; then
+    log_pass "try_files fallback supports Range (HTTP 206)"
+else
+    log_fail "try_files fallback supports Range (expected: 206, got: $STATUS)"
+fi
+
+# $uri.html and $uri/ candidates
+RESPONSE=$(curl -s "$BASE_URL/docs/page")
+assert_contains "try_files \$uri.html candidate" "$RESPONSE" "Pretty Page"
+RESPONSE=$(curl -s "$BASE_URL/docs/guide")
+assert_contains "try_files \$uri/ candidate" "$RESPONSE" "Guide Index"
+
+# Named route fallback
+STATUS=$(curl -s -o /dev/null -w "%{http_code}" "$BASE_URL/docs/nope")
+if [ "$STATUS" = "410" ]; then
+    log_pass "try_files named route fallback (HTTP 410)"
+else
+    log_fail "try_files named route fallback (expected: 410, got: $STATUS)"
+fi
+
+# Invalid try_files is rejected with NACK
+UPDATE_RESPONSE=$(curl -s -X POST "$API_URL/v1/discovery:vhosts" \
+    -H "Content-Type: application/json" \
+    -d '{"resources": [{"virtual_hosts": [{
+      "name": "bad", "domains": ["*"],
+      "routes": [{"match": {"prefix": "/"}, "type": "dir", "path": "static",
+                  "try_files": ["$uri", "@nowhere"]}]
+    }]}]}')
+assert_json_field "Unknown named route NACK" "$UPDATE_RESPONSE" ".status" "NACK"
+
+# Restore
+curl -s -X POST "$API_URL/v1/discovery:vhosts" \
+    -H "Content-Type: application/json" \
+    -d '{"resources": [{"virtual_hosts": []}]}' > /dev/null
+rm -rf "$SPA_DIR"
+
+log_info "try_files tests completed"
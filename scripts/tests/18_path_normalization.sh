@@ -0,0 +1,73 @@
+#!/bin/bash
+# Request Path Normalization Tests
+# This script is called by integration_tests.sh, common.sh is already loaded
+
+log_section "18. Path Normalization"
+
+# Helper: status code for a raw (unnormalized by curl) path
+raw_status() {
+    curl -s -o /dev/null -w "%{http_code}" --path-as-is "$BASE_URL$1"
+}
+
+check_raw_status() {
+    local name="$1" path="$2" expected="$3"
+    local status
+    status=$(raw_status "$path")
+    if [ "$status" = "$expected" ]; then
+        log_pass "$name (HTTP $status)"
+    else
+        log_fail "$name (expected: $expected, got: $status)"
+    fi
+}
+
+# Percent-encoded characters are decoded before routing
+RESPONSE=$(curl -s --path-as-is "$BASE_URL/static/%74est.txt")
+assert_contains "Percent-encoded path decoded" "$RESPONSE" "$(head -c 10 static/test.txt)"
+
+# Dot segments are resolved, never escaping the route root
+check_raw_status "Dot segments resolved" "/static/css/../test.txt" "200"
+check_raw_status "Encoded dot segments resolved" "/static/%2e%2e/static/test.txt" "200"
+for TRAVERSAL in "/static/../../../etc/passwd" "/static/%2e%2e/%2e%2e/etc/passwd"; do
+    if curl -s --path-as-is "$BASE_URL$TRAVERSAL" | grep -q "root:"; then
+        log_fail "Traversal above root clamped: $TRAVERSAL"
+    else
+        log_pass "Traversal above root clamped: $TRAVERSAL"
+    fi
+done
+
+# Repeated slashes are merged (merge_slashes = true by default)
+check_raw_status "Repeated slashes merged" "//static///test.txt" "200"
+
+UPDATE_RESPONSE=$(curl -s -X POST "$API_URL/v1/discovery:vhosts" \
+    -H "Content-Type: application/json" \
+    -d '{"resources": [{"virtual_hosts": [{
+      "name": "norm", "domains": ["*"],
+      "routes": [{"match": {"path": "/exact/page"}, "type": "direct", "status": 200, "body": "Exact"}]
+    }]}]}')
+assert_json_field "Configure exact path vhost ACK" "$UPDATE_RESPONSE" ".status" "ACK"
+check_raw_status "Exact match after merging slashes" "//exact//page" "200"
+check_raw_status "Exact match after resolving dots" "/exact/./x/../page" "200"
+
+# Encoded slashes, NUL bytes and malformed escapes are rejected
+check_raw_status "Encoded slash rejected" "/static%2Ftest.txt" "400"
+check_raw_status "Encoded NUL rejected" "/static/test.txt%00.html" "400"
+check_raw_status "Malformed escape rejected" "/static/%zz" "400"
+check_raw_status "Invalid UTF-8 rejected" "/static/%FF" "400"
+
+# merge_slashes can be disabled via the HTTP resource
+HTTP_CONFIG=$(curl -s "$API_URL/v1/discovery:http" | jq -c '.resources[0]')
+UPDATE_RESPONSE=$(curl -s -X POST "$API_URL/v1/discovery:http" \
+    -H "Content-Type: application/json" \
+    -d "{\"resources\": [$(echo "$HTTP_CONFIG" | jq -c '.merge_slashes = false')]}")
+assert_json_field "Disable merge_slashes ACK" "$UPDATE_RESPONSE" ".status" "ACK"
+check_raw_status "Repeated slashes kept when disabled" "//exact//page" "404"
+
+# Restore
+curl -s -X POST "$API_URL/v1/discovery:http" \
+    -H "Content-Type: application/json" \
+    -d "{\"resources\": [$(echo "$HTTP_CONFIG" | jq -c '.merge_slashes = true')]}" > /dev/null
+curl -s -X POST "$API_URL/v1/discovery:vhosts" \
+    -H "Content-Type: application/json" \
+    -d '{"resources": [{"virtual_hosts": []}]}' > /dev/null
+
+log_info "Path normalization tests completed"
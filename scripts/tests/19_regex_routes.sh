@@ -0,0 +1,80 @@
+#!/bin/bash
+# Regex and Segment-Prefix Route Matching Tests
+# This script is called by integration_tests.sh, common.sh is already loaded
+
+log_section "19. Regex Route Matching"
+
+UPDATE_RESPONSE=$(curl -s -X POST "$API_URL/v1/discovery:vhosts" \
+    -H "Content-Type: application/json" \
+    -d '{"resources": [{"virtual_hosts": [{
+      "name": "regex", "domains": ["*"],
+      "routes": [
+        {"match": {"safe_regex": "/users/(\\d+)"}, "type": "direct", "status": 200, "body": "User"},
+        {"match": {"safe_regex": "/blog/(?P<year>\\d{4})/(.+)"}, "type": "redirect",
+         "target": "/articles/${year}/$2", "code": 301},
+        {"match": {"path_separated_prefix": "/api"}, "type": "direct", "status": 200, "body": "API"},
+        {"match": {"prefix": "/"}, "type": "direct", "status": 404, "body": "Fallback"}
+      ]
+    }]}]}')
+assert_json_field "Configure regex vhost ACK" "$UPDATE_RESPONSE" ".status" "ACK"
+
+# safe_regex must match the whole path
+RESPONSE=$(curl -s "$BASE_URL/users/42")
+assert_contains "safe_regex matches" "$RESPONSE" "User"
+RESPONSE=$(curl -s "$BASE_URL/users/42/edit")
+assert_contains "safe_regex is anchored" "$RESPONSE" "Fallback"
+RESPONSE=$(curl -s "$BASE_URL/users/abc")
+assert_contains "safe_regex rejects non-matching path" "$RESPONSE" "Fallback"
+
+# Captures in redirect targets
+LOCATION=$(curl -s -o /dev/null -w "%{redirect_url}" "$BASE_URL/blog/2024/hello-world")
+if [[ "$LOCATION" == *"/articles/2024/hello-world" ]]; then
+    log_pass "Redirect target uses regex captures"
+else
+    log_fail "Redirect target uses regex captures (got: $LOCATION)"
+fi
+assert_status "Capture redirect status" "$BASE_URL/blog/2024/hello-world" "301"
+
+# path_separated_prefix only matches on segment boundaries
+RESPONSE=$(curl -s "$BASE_URL/api")
+assert_contains "path_separated_prefix matches exact prefix" "$RESPONSE" "API"
+RESPONSE=$(curl -s "$BASE_URL/api/v1/users")
+assert_contains "path_separated_prefix matches sub-path" "$RESPONSE" "API"
+RESPONSE=$(curl -s "$BASE_URL/apix")
+assert_contains "path_separated_prefix skips partial segment" "$RESPONSE" "Fallback"
+
+# Invalid configurations are rejected at update time
+UPDATE_RESPONSE=$(curl -s -X POST "$API_URL/v1/discovery:vhosts" \
+    -H "Content-Type: application/json" \
+    -d '{"resources": [{"virtual_hosts": [{
+      "name": "bad", "domains": ["*"],
+      "routes": [{"match": {"safe_regex": "/users/("}, "type": "direct", "status": 200}]
+    }]}]}')
+assert_json_field "Invalid regex NACK" "$UPDATE_RESPONSE" ".status" "NACK"
+
+UPDATE_RESPONSE=$(curl -s -X POST "$API_URL/v1/discovery:vhosts" \
+    -H "Content-Type: application/json" \
+    -d '{"resources": [{"virtual_hosts": [{
+      "name": "bad", "domains": ["*"],
+      "routes": [{"match": {"safe_regex": "/(a)\\1"}, "type": "direct", "status": 200}]
+    }]}]}')
+assert_json_field "Backreference regex NACK" "$UPDATE_RESPONSE" ".status" "NACK"
+
+UPDATE_RESPONSE=$(curl -s -X POST "$API_URL/v1/discovery:vhosts" \
+    -H "Content-Type: application/json" \
+    -d '{"resources": [{"virtual_hosts": [{
+      "name": "bad", "domains": ["*"],
+      "routes": [{"match": {"prefix": "/a", "safe_regex": "/a.*"}, "type": "direct", "status": 200}]
+    }]}]}')
+assert_json_field "Combined path matchers NACK" "$UPDATE_RESPONSE" ".status" "NACK"
+
+# Previous configuration is still active after NACK
+RESPONSE=$(curl -s "$BASE_URL/users/7")
+assert_contains "Config unchanged after NACK" "$RESPONSE" "User"
+
+# Restore
+curl -s -X POST "$API_URL/v1/discovery:vhosts" \
+    -H "Content-Type: application/json" \
+    -d '{"resources": [{"virtual_hosts": []}]}' > /dev/null
+
+log_info "Regex route tests completed"
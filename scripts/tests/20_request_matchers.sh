@@ -0,0 +1,76 @@
+#!/bin/bash
+# Header, Method, Query Parameter and Cookie Matching Tests
+# This script is called by integration_tests.sh, common.sh is already loaded
+
+log_section "20. Request Matchers"
+
+UPDATE_RESPONSE=$(curl -s -X POST "$API_URL/v1/discovery:vhosts" \
+    -H "Content-Type: application/json" \
+    -d '{"resources": [{"virtual_hosts": [{
+      "name": "matchers", "domains": ["*"],
+      "routes": [
+        {"match": {"prefix": "/", "headers": [{"name": "X-Beta-User", "exact": "1"}]},
+         "type": "direct", "status": 200, "body": "Beta header"},
+        {"match": {"prefix": "/", "cookies": [{"name": "channel", "prefix": "beta"}]},
+         "type": "direct", "status": 200, "body": "Beta cookie"},
+        {"match": {"prefix": "/search", "query_parameters": [{"name": "q", "regex": "[a-z ]+"}]},
+         "type": "direct", "status": 200, "body": "Search"},
+        {"match": {"prefix": "/head-only", "methods": ["HEAD"]},
+         "type": "direct", "status": 204},
+        {"match": {"prefix": "/internal", "headers": [{"name": "X-Internal", "present": true, "invert_match": true}]},
+         "type": "direct", "status": 403, "body": "Forbidden"},
+        {"match": {"prefix": "/"}, "type": "direct", "status": 200, "body": "Stable"}
+      ]
+    }]}]}')
+assert_json_field "Configure matcher vhost ACK" "$UPDATE_RESPONSE" ".status" "ACK"
+
+# Header matching sees the real request headers
+RESPONSE=$(curl -s -H "X-Beta-User: 1" "$BASE_URL/page")
+assert_contains "Header matcher routes beta users" "$RESPONSE" "Beta header"
+RESPONSE=$(curl -s -H "X-Beta-User: 0" "$BASE_URL/page")
+assert_contains "Header matcher skips other values" "$RESPONSE" "Stable"
+
+# Cookie matching
+RESPONSE=$(curl -s -H "Cookie: session=x; channel=beta-3" "$BASE_URL/page")
+assert_contains "Cookie matcher" "$RESPONSE" "Beta cookie"
+RESPONSE=$(curl -s -H "Cookie: channel=stable" "$BASE_URL/page")
+assert_contains "Cookie matcher skips other values" "$RESPONSE" "Stable"
+
+# Query parameter matching (decoded, regex must match the whole value)
+RESPONSE=$(curl -s "$BASE_URL/search?q=hello+world")
+assert_contains "Query parameter regex matcher" "$RESPONSE" "Search"
+RESPONSE=$(curl -s "$BASE_URL/search?q=Hello123")
+assert_contains "Query parameter regex is anchored" "$RESPONSE" "Stable"
+RESPONSE=$(curl -s "$BASE_URL/search")
+assert_contains "Missing query parameter does not match" "$RESPONSE" "Stable"
+
+# Method matching
+STATUS=$(curl -s -o /dev/null -w "%{http_code}" -I "$BASE_URL/head-only")
+if [ "$STATUS" = "204" ]; then
+    log_pass "Method matcher (HEAD -> 204)"
+else
+    log_fail "Method matcher (expected: 204, got: $STATUS)"
+fi
+RESPONSE=$(curl -s "$BASE_URL/head-only")
+assert_contains "Method matcher skips GET" "$RESPONSE" "Stable"
+
+# invert_match
+assert_status "invert_match blocks requests without header" "$BASE_URL/internal" "403"
+assert_status "invert_match allows requests with header" "$BASE_URL/internal" "200" "X-Internal: yes"
+
+# Invalid matcher regex is rejected at update time
+UPDATE_RESPONSE=$(curl -s -X POST "$API_URL/v1/discovery:vhosts" \
+    -H "Content-Type: application/json" \
+    -d '{"resources": [{"virtual_hosts": [{
+      "name": "bad", "domains": ["*"],
+      "routes": [{"match": {"prefix": "/", "cookies": [{"name": "c", "regex": "(["}]},
+                  "type": "direct", "status": 200}]
+    }]}]}')
+assert_json_field "Invalid cookie regex NACK" "$UPDATE_RESPONSE" ".status" "NACK"
+
+# Restore
+curl -s -X POST "$API_URL/v1/discovery:vhosts" \
+    -H "Content-Type: application/json" \
+    -d '{"resources": [{"virtual_hosts": []}]}' > /dev/null
+
+log_info "Request matcher tests completed"
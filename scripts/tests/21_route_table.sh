@@ -0,0 +1,65 @@
+#!/bin/bash
+# Compiled Route Table Tests
+# This script is called by integration_tests.sh, common.sh is already loaded
+
+log_section "21. Compiled Route Table"
+
+ORIGINAL_ROUTES=$(curl -s "$API_URL/v1/discovery:routes" | jq -c '{resources: [{
+  index_files: .resources[0].index_files,
+  custom_routes: .resources[0].custom_routes
+}]}')
+
+# Legacy routes: the longest prefix wins regardless of HashMap order
+ROUTES=$(echo "$ORIGINAL_ROUTES" | jq -c '.resources[0].custom_routes += {
+  "/nested": {type: "redirect", target: "/short"},
+  "/nested/deeper": {type: "redirect", target: "/long"},
+  "/nested/deeper/most": {type: "redirect", target: "/longest"}
+}')
+UPDATE_RESPONSE=$(curl -s -X POST "$API_URL/v1/discovery:routes" \
+    -H "Content-Type: application/json" -d "$ROUTES")
+assert_json_field "Configure nested legacy routes ACK" "$UPDATE_RESPONSE" ".status" "ACK"
+
+CONSISTENT=true
+for _ in $(seq 1 20); do
+    LOCATION=$(curl -sI "$BASE_URL/nested/deeper/x" | grep -i "^location:" | tr -d '\r')
+    [[ "$LOCATION" == *"/long" ]] || CONSISTENT=false
+done
+if [ "$CONSISTENT" = true ]; then
+    log_pass "Legacy longest prefix wins consistently"
+else
+    log_fail "Legacy longest prefix wins consistently (last: $LOCATION)"
+fi
+
+LOCATION=$(curl -sI "$BASE_URL/nested/deeper/most/page" | grep -i "^location:" | tr -d '\r')
+assert_contains "Legacy deepest prefix" "$LOCATION" "/longest"
+LOCATION=$(curl -sI "$BASE_URL/nested/other" | grep -i "^location:" | tr -d '\r')
+assert_contains "Legacy shorter prefix" "$LOCATION" "/short"
+
+curl -s -X POST "$API_URL/v1/discovery:routes" \
+    -H "Content-Type: application/json" -d "$ORIGINAL_ROUTES" > /dev/null
+
+# xDS routes: thousands of routes, first match in order still wins
+jq -cn '{resources: [{virtual_hosts: [{
+  name: "large", domains: ["*"],
+  routes: ([range(0; 2000) | {match: {prefix: "/svc-\(.)/"}, type: "direct", status: 200, body: "svc-\(.)"}]
+    + [{match: {prefix: "/svc-1999/"}, type: "direct", status: 200, body: "shadowed"},
+       {match: {prefix: "/"}, type: "direct", status: 404, body: "fallback"}])
+}]}]}' > /tmp/large_vhost.json
+UPDATE_RESPONSE=$(curl -s -X POST "$API_URL/v1/discovery:vhosts" \
+    -H "Content-Type: application/json" -d @/tmp/large_vhost.json)
+assert_json_field "Configure 2000-route vhost ACK" "$UPDATE_RESPONSE" ".status" "ACK"
+
+RESPONSE=$(curl -s "$BASE_URL/svc-1234/items")
+assert_contains "Large table prefix lookup" "$RESPONSE" "svc-1234"
+RESPONSE=$(curl -s "$BASE_URL/svc-1999/items")
+assert_contains "Large table keeps route order" "$RESPONSE" "svc-1999"
+RESPONSE=$(curl -s "$BASE_URL/unknown")
+assert_contains "Large table catch-all" "$RESPONSE" "fallback"
+
+# Restore
+curl -s -X POST "$API_URL/v1/discovery:vhosts" \
+    -H "Content-Type: application/json" \
+    -d '{"resources": [{"virtual_hosts": []}]}' > /dev/null
+rm -f /tmp/large_vhost.json
+
+log_info "Route table tests completed"
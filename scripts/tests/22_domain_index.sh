@@ -0,0 +1,46 @@
+#!/bin/bash
+# Virtual Host Domain Index Tests
+# This script is called by integration_tests.sh, common.sh is already loaded
+
+log_section "22. Domain Index"
+
+UPDATE_RESPONSE=$(curl -s -X POST "$API_URL/v1/discovery:vhosts" \
+    -H "Content-Type: application/json" \
+    -d '{"resources": [{"virtual_hosts": [
+      {"name": "short-suffix", "domains": ["*.com"],
+       "routes": [{"match": {"prefix": "/"}, "type": "direct", "status": 200, "body": "short-suffix"}]},
+      {"name": "long-suffix", "domains": ["*.example.com"],
+       "routes": [{"match": {"prefix": "/"}, "type": "direct", "status": 200, "body": "long-suffix"}]},
+      {"name": "prefix", "domains": ["api.*"],
+       "routes": [{"match": {"prefix": "/"}, "type": "direct", "status": 200, "body": "prefix"}]},
+      {"name": "exact", "domains": ["www.example.com"],
+       "routes": [{"match": {"prefix": "/"}, "type": "direct", "status": 200, "body": "exact"}]},
+      {"name": "default", "domains": ["*"],
+       "routes": [{"match": {"prefix": "/"}, "type": "direct", "status": 200, "body": "default"}]}
+    ]}]}')
+assert_json_field "Configure domain vhosts ACK" "$UPDATE_RESPONSE" ".status" "ACK"
+
+RESPONSE=$(curl -s -H "Host: www.example.com" "$BASE_URL/")
+assert_contains "Exact domain wins" "$RESPONSE" "exact"
+RESPONSE=$(curl -s -H "Host: WWW.Example.com:8080" "$BASE_URL/")
+assert_contains "Domain match ignores case and port" "$RESPONSE" "exact"
+RESPONSE=$(curl -s -H "Host: img.example.com" "$BASE_URL/")
+assert_contains "Longest suffix wildcard wins" "$RESPONSE" "long-suffix"
+RESPONSE=$(curl -s -H "Host: shop.com" "$BASE_URL/")
+assert_contains "Shorter suffix wildcard" "$RESPONSE" "short-suffix"
+RESPONSE=$(curl -s -H "Host: api.internal" "$BASE_URL/")
+assert_contains "Prefix wildcard (api.*)" "$RESPONSE" "prefix"
+RESPONSE=$(curl -s -H "Host: unknown.local" "$BASE_URL/")
+assert_contains "Catch-all domain" "$RESPONSE" "default"
+
+UPDATE_RESPONSE=$(curl -s -X POST "$API_URL/v1/discovery:vhosts" \
+    -H "Content-Type: application/json" \
+    -d '{"resources": [{"virtual_hosts": [{"name": "bad", "domains": ["a.*.com"], "routes": []}]}]}')
+assert_json_field "Unsupported wildcard NACK" "$UPDATE_RESPONSE" ".status" "NACK"
+
+# Restore
+curl -s -X POST "$API_URL/v1/discovery:vhosts" \
+    -H "Content-Type: application/json" \
+    -d '{"resources": [{"virtual_hosts": []}]}' > /dev/null
+
+log_info "Domain index tests completed"
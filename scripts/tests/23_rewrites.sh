@@ -0,0 +1,62 @@
+#!/bin/bash
+# URL Rewrite Tests
+# This script is called by integration_tests.sh, common.sh is already loaded
+
+log_section "23. URL Rewrites"
+
+UPDATE_RESPONSE=$(curl -s -X POST "$API_URL/v1/discovery:vhosts" \
+    -H "Content-Type: application/json" \
+    -d '{"resources": [{"virtual_hosts": [
+      {"name": "rewrite", "domains": ["*"], "routes": [
+        {"match": {"prefix": "/assets"}, "rewrite": {"prefix_rewrite": "/"}, "type": "dir", "path": "static"},
+        {"match": {"prefix": "/txt"}, "rewrite": {"regex_rewrite": {"pattern": "/txt/(\\w+)", "substitution": "/$1.txt"}},
+         "type": "dir", "path": "static"},
+        {"match": {"prefix": "/old"}, "rewrite": {"prefix_rewrite": "/new", "rematch": true}, "type": "direct", "status": 500},
+        {"match": {"prefix": "/new"}, "type": "direct", "status": 200, "body": "new-route"},
+        {"match": {"prefix": "/internal"}, "rewrite": {"host_rewrite": "internal.local", "rematch": true},
+         "type": "direct", "status": 500},
+        {"match": {"prefix": "/loop"}, "rewrite": {"prefix_rewrite": "/loop", "rematch": true}, "type": "direct", "status": 200}
+      ]},
+      {"name": "internal", "domains": ["internal.local"], "routes": [
+        {"match": {"prefix": "/"}, "type": "direct", "status": 200, "body": "internal-host"}
+      ]}
+    ]}]}')
+assert_json_field "Configure rewrite routes ACK" "$UPDATE_RESPONSE" ".status" "ACK"
+
+RESPONSE=$(curl -s "$BASE_URL/assets/test.txt")
+assert_contains "prefix_rewrite serves file from directory" "$RESPONSE" "Hello"
+RESPONSE=$(curl -s "$BASE_URL/txt/test")
+assert_contains "regex_rewrite substitutes capture groups" "$RESPONSE" "Hello"
+RESPONSE=$(curl -s "$BASE_URL/old/page")
+assert_contains "rematch routes rewritten path" "$RESPONSE" "new-route"
+RESPONSE=$(curl -s "$BASE_URL/internal/x")
+assert_contains "host_rewrite selects another virtual host" "$RESPONSE" "internal-host"
+assert_status "Rewrite loop is stopped with 500" "$BASE_URL/loop" "500"
+
+UPDATE_RESPONSE=$(curl -s -X POST "$API_URL/v1/discovery:vhosts" \
+    -H "Content-Type: application/json" \
+    -d '{"resources": [{"virtual_hosts": [{"name": "bad", "domains": ["*"], "routes": [
+        {"match": {"safe_regex": "/a/.*"}, "rewrite": {"prefix_rewrite": "/b"}, "type": "direct", "status": 200}
+    ]}]}]}')
+assert_json_field "prefix_rewrite on regex route NACK" "$UPDATE_RESPONSE" ".status" "NACK"
+
+UPDATE_RESPONSE=$(curl -s -X POST "$API_URL/v1/discovery:vhosts" \
+    -H "Content-Type: application/json" \
+    -d '{"resources": [{"virtual_hosts": [{"name": "bad", "domains": ["*"], "routes": [
+        {"match": {"prefix": "/a"}, "rewrite": {"prefix_rewrite": "/b"}, "type": "direct", "status": 200}
+    ]}]}]}')
+assert_json_field "prefix_rewrite on direct route without rematch NACK" "$UPDATE_RESPONSE" ".status" "NACK"
+
+UPDATE_RESPONSE=$(curl -s -X POST "$API_URL/v1/discovery:vhosts" \
+    -H "Content-Type: application/json" \
+    -d '{"resources": [{"virtual_hosts": [{"name": "bad", "domains": ["*"], "routes": [
+        {"match": {"prefix": "/a"}, "rewrite": {"rematch": true}, "type": "direct", "status": 200}
+    ]}]}]}')
+assert_json_field "rematch without rewrite NACK" "$UPDATE_RESPONSE" ".status" "NACK"
+
+# Restore
+curl -s -X POST "$API_URL/v1/discovery:vhosts" \
+    -H "Content-Type: application/json" \
+    -d '{"resources": [{"virtual_hosts": []}]}' > /dev/null
+
+log_info "URL rewrite tests completed"
@@ -0,0 +1,69 @@
+#!/bin/bash
+# Redirect Action Tests
+# This script is called by integration_tests.sh, common.sh is already loaded
+
+log_section "24. Redirect Actions"
+
+UPDATE_RESPONSE=$(curl -s -X POST "$API_URL/v1/discovery:vhosts" \
+    -H "Content-Type: application/json" \
+    -d '{"resources": [{"virtual_hosts": [
+      {"name": "apex", "domains": ["example.com"], "routes": [
+        {"match": {"prefix": "/"}, "type": "redirect", "host_redirect": "www.example.com", "code": 301}
+      ]},
+      {"name": "redirects", "domains": ["*"], "routes": [
+        {"match": {"prefix": "/secure"}, "type": "redirect", "scheme_redirect": "https", "code": 308},
+        {"match": {"prefix": "/docs"}, "type": "redirect", "prefix_rewrite": "/manual"},
+        {"match": {"safe_regex": "/item/(\\d+)"}, "type": "redirect", "path_redirect": "/products/$1", "strip_query": true, "code": 303},
+        {"match": {"path": "/fixed"}, "type": "redirect", "target": "/landing", "code": 307}
+      ]}
+    ]}]}')
+assert_json_field "Configure redirect routes ACK" "$UPDATE_RESPONSE" ".status" "ACK"
+
+# Print the raw Location header of a response
+location_of() {
+    curl -sI "$@" | tr -d '\r' | awk 'tolower($1) == "location:" { print $2 }'
+}
+
+check_location() {
+    local name=$1 expected=$2
+    shift 2
+    local location
+    location=$(location_of "$@")
+    if [ "$location" = "$expected" ]; then
+        log_pass "$name"
+    else
+        log_fail "$name (expected: $expected, got: $location)"
+    fi
+}
+
+check_location "Apex to www keeps port and query" "http://www.example.com:8080/page?a=1" \
+    -H "Host: example.com:8080" "$BASE_URL/page?a=1"
+assert_status "Apex redirect status" "$BASE_URL/page" "301" "Host: example.com"
+check_location "HTTPS redirect drops the port" "https://127.0.0.1/secure/cart?id=7" "$BASE_URL/secure/cart?id=7"
+assert_status "HTTPS redirect status" "$BASE_URL/secure" "308"
+check_location "prefix_rewrite keeps query" "/manual/intro?v=2" "$BASE_URL/docs/intro?v=2"
+check_location "path_redirect with captures and strip_query" "/products/42" "$BASE_URL/item/42?ref=x"
+assert_status "303 redirect status" "$BASE_URL/item/42" "303"
+check_location "Fixed target keeps query" "/landing?utm=1" "$BASE_URL/fixed?utm=1"
+assert_status "307 redirect status" "$BASE_URL/fixed" "307"
+
+UPDATE_RESPONSE=$(curl -s -X POST "$API_URL/v1/discovery:vhosts" \
+    -H "Content-Type: application/json" \
+    -d '{"resources": [{"virtual_hosts": [{"name": "bad", "domains": ["*"], "routes": [
+        {"match": {"prefix": "/"}, "type": "redirect", "target": "/x", "code": 200}
+    ]}]}]}')
+assert_json_field "Invalid redirect code NACK" "$UPDATE_RESPONSE" ".status" "NACK"
+
+UPDATE_RESPONSE=$(curl -s -X POST "$API_URL/v1/discovery:vhosts" \
+    -H "Content-Type: application/json" \
+    -d '{"resources": [{"virtual_hosts": [{"name": "bad", "domains": ["*"], "routes": [
+        {"match": {"prefix": "/"}, "type": "redirect", "target": "/x", "host_redirect": "example.com"}
+    ]}]}]}')
+assert_json_field "target combined with host_redirect NACK" "$UPDATE_RESPONSE" ".status" "NACK"
+
+# Restore
+curl -s -X POST "$API_URL/v1/discovery:vhosts" \
+    -H "Content-Type: application/json" \
+    -d '{"resources": [{"virtual_hosts": []}]}' > /dev/null
+
+log_info "Redirect action tests completed"
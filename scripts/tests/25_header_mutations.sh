@@ -0,0 +1,61 @@
+#!/bin/bash
+# Header Mutation Tests
+# This script is called by integration_tests.sh, common.sh is already loaded
+
+log_section "25. Header Mutations"
+
+UPDATE_RESPONSE=$(curl -s -X POST "$API_URL/v1/discovery:vhosts" \
+    -H "Content-Type: application/json" \
+    -d '{"resources": [{"virtual_hosts": [
+      {"name": "headers", "domains": ["*"],
+       "response_headers_to_add": [
+         {"name": "X-Frame-Options", "value": "DENY"},
+         {"name": "X-Client", "value": "%REMOTE_ADDR%"}
+       ],
+       "routes": [
+        {"match": {"prefix": "/static"}, "type": "dir", "path": "static",
+         "response_headers_to_remove": ["Cache-Control"],
+         "response_headers_to_add": [{"name": "Cache-Control", "value": "no-store", "append": false}]},
+        {"match": {"prefix": "/echo"}, "type": "direct", "status": 200, "body": "echo",
+         "request_headers_to_add": [{"name": "X-Tenant", "value": "acme"}],
+         "response_headers_to_add": [
+           {"name": "X-Echo-Host", "value": "%REQ(host)%"},
+           {"name": "X-Echo-Tenant", "value": "%REQ(x-tenant)%"},
+           {"name": "X-Started", "value": "%START_TIME%"}
+         ]},
+        {"match": {"prefix": "/go"}, "type": "redirect", "path_redirect": "/static/test.txt"}
+      ]}
+    ]}]}')
+assert_json_field "Configure header mutations ACK" "$UPDATE_RESPONSE" ".status" "ACK"
+
+HEADERS=$(curl -sI "$BASE_URL/static/test.txt")
+assert_contains "Vhost header on file response" "$HEADERS" "x-frame-options: DENY"
+assert_contains "Route overrides Cache-Control" "$HEADERS" "cache-control: no-store"
+assert_contains "REMOTE_ADDR interpolation" "$HEADERS" "x-client: 127.0.0.1"
+
+HEADERS=$(curl -sI -H "Host: tenant.local" "$BASE_URL/echo")
+assert_contains "REQ(host) interpolation" "$HEADERS" "x-echo-host: tenant.local"
+assert_contains "Request header added before the response" "$HEADERS" "x-echo-tenant: acme"
+if echo "$HEADERS" | grep -qiE "^x-started: [0-9]{4}-[0-9]{2}-[0-9]{2}T"; then
+    log_pass "START_TIME interpolation"
+else
+    log_fail "START_TIME interpolation"
+fi
+
+HEADERS=$(curl -sI "$BASE_URL/go")
+assert_contains "Vhost header on redirect response" "$HEADERS" "x-frame-options: DENY"
+HEADERS=$(curl -sI "$BASE_URL/missing")
+assert_contains "Vhost header on 404 response" "$HEADERS" "x-frame-options: DENY"
+
+UPDATE_RESPONSE=$(curl -s -X POST "$API_URL/v1/discovery:vhosts" \
+    -H "Content-Type: application/json" \
+    -d '{"resources": [{"virtual_hosts": [{"name": "bad", "domains": ["*"], "routes": [],
+        "response_headers_to_add": [{"name": "Bad Header", "value": "x"}]}]}]}')
+assert_json_field "Invalid header name NACK" "$UPDATE_RESPONSE" ".status" "NACK"
+
+# Restore
+curl -s -X POST "$API_URL/v1/discovery:vhosts" \
+    -H "Content-Type: application/json" \
+    -d '{"resources": [{"virtual_hosts": []}]}' > /dev/null
+
+log_info "Header mutation tests completed"
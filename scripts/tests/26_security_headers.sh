@@ -0,0 +1,94 @@
+#!/bin/bash
+# Security Headers Preset Tests
+# This script is called by integration_tests.sh, common.sh is already loaded
+
+log_section "26. Security Headers"
+
+HEADERS=$(curl -sI "$BASE_URL/static/test.txt")
+if echo "$HEADERS" | grep -qi "x-content-type-options"; then
+    log_fail "No security headers without a preset"
+else
+    log_pass "No security headers without a preset"
+fi
+
+# Global preset on the HTTP resource
+HTTP_CONFIG=$(curl -s "$API_URL/v1/discovery:http" | jq -c '.resources[0]')
+UPDATE_RESPONSE=$(curl -s -X POST "$API_URL/v1/discovery:http" \
+    -H "Content-Type: application/json" \
+    -d "{\"resources\": [$(echo "$HTTP_CONFIG" | jq -c '.security_headers = {
+        "content_security_policy": "default-src '"'self'"'",
+        "csp_report_only": true,
+        "hsts": {"max_age": 63072000, "include_subdomains": true, "preload": true}
+    }')]}")
+assert_json_field "Enable security headers ACK" "$UPDATE_RESPONSE" ".status" "ACK"
+
+HEADERS=$(curl -sI "$BASE_URL/static/test.txt")
+assert_contains "HSTS header" "$HEADERS" "strict-transport-security: max-age=63072000; includeSubDomains; preload"
+assert_contains "nosniff header" "$HEADERS" "x-content-type-options: nosniff"
+assert_contains "Frame options header" "$HEADERS" "x-frame-options: DENY"
+assert_contains "Referrer-Policy header" "$HEADERS" "referrer-policy: strict-origin-when-cross-origin"
+assert_contains "CSP report-only header" "$HEADERS" "content-security-policy-report-only: default-src 'self'"
+HEADERS=$(curl -sI "$BASE_URL/")
+assert_contains "Security headers on homepage" "$HEADERS" "x-content-type-options: nosniff"
+
+# Responses rejected before routing carry the preset as well
+HEADERS=$(curl -s -D - -o /dev/null -X POST "$BASE_URL/")
+assert_contains "Security headers on 405" "$HEADERS" "x-content-type-options: nosniff"
+HEADERS=$(curl -s -D - -o /dev/null -H "Content-Length: 1099511627776" "$BASE_URL/")
+assert_contains "Security headers on 413" "$HEADERS" "x-content-type-options: nosniff"
+HEADERS=$(curl -s -D - -o /dev/null --path-as-is "$BASE_URL/static/test.txt%00.html")
+assert_contains "Security headers on 400" "$HEADERS" "x-content-type-options: nosniff"
+
+# A virtual host preset replaces the global one
+UPDATE_RESPONSE=$(curl -s -X POST "$API_URL/v1/discovery:vhosts" \
+    -H "Content-Type: application/json" \
+    -d '{"resources": [{"virtual_hosts": [
+      {"name": "wasm", "domains": ["wasm.local"],
+       "security_headers": {"cross_origin_opener_policy": "same-origin",
+                            "cross_origin_embedder_policy": "require-corp",
+                            "frame_options": "", "hsts": {"enabled": false}},
+       "routes": [{"match": {"prefix": "/"}, "type": "direct", "status": 200, "body": "wasm"}]},
+      {"name": "default", "domains": ["*"],
+       "routes": [
+         {"match": {"prefix": "/to-wasm"}, "rewrite": {"host_rewrite": "wasm.local", "rematch": true},
+          "type": "direct", "status": 500},
+         {"match": {"prefix": "/"}, "type": "direct", "status": 200, "body": "default"}]}
+    ]}]}')
+assert_json_field "Configure vhost security headers ACK" "$UPDATE_RESPONSE" ".status" "ACK"
+
+HEADERS=$(curl -sI -H "Host: wasm.local" "$BASE_URL/")
+assert_contains "COOP header" "$HEADERS" "cross-origin-opener-policy: same-origin"
+assert_contains "COEP header" "$HEADERS" "cross-origin-embedder-policy: require-corp"
+if echo "$HEADERS" | grep -qiE "x-frame-options|strict-transport-security"; then
+    log_fail "Empty frame_options and disabled HSTS are omitted"
+else
+    log_pass "Empty frame_options and disabled HSTS are omitted"
+fi
+HEADERS=$(curl -sI -H "Host: other.local" "$BASE_URL/")
+assert_contains "Other vhosts use the global preset" "$HEADERS" "x-frame-options: DENY"
+
+# The virtual host serving the request after host_rewrite picks the preset
+HEADERS=$(curl -sI -H "Host: other.local" "$BASE_URL/to-wasm")
+assert_contains "host_rewrite uses the target vhost preset" "$HEADERS" "cross-origin-opener-policy: same-origin"
+if echo "$HEADERS" | grep -qi "x-frame-options"; then
+    log_fail "host_rewrite drops the original vhost preset"
+else
+    log_pass "host_rewrite drops the original vhost preset"
+fi
+HEADERS=$(curl -s -D - -o /dev/null -X POST -H "Host: wasm.local" "$BASE_URL/")
+assert_contains "405 uses the Host vhost preset" "$HEADERS" "cross-origin-opener-policy: same-origin"
+
+UPDATE_RESPONSE=$(curl -s -X POST "$API_URL/v1/discovery:http" \
+    -H "Content-Type: application/json" \
+    -d "{\"resources\": [$(echo "$HTTP_CONFIG" | jq -c '.security_headers = {"hsts": {"preload": true}}')]}")
+assert_json_field "HSTS preload without includeSubDomains NACK" "$UPDATE_RESPONSE" ".status" "NACK"
+
+# Restore
+curl -s -X POST "$API_URL/v1/discovery:http" \
+    -H "Content-Type: application/json" \
+    -d "{\"resources\": [$HTTP_CONFIG]}" > /dev/null
+curl -s -X POST "$API_URL/v1/discovery:vhosts" \
+    -H "Content-Type: application/json" \
+    -d '{"resources": [{"virtual_hosts": []}]}' > /dev/null
+
+log_info "Security headers tests completed"
@@ -0,0 +1,96 @@
+#!/bin/bash
+# CORS Policy Tests
+# This script is called by integration_tests.sh, common.sh is already loaded
+
+log_section "27. CORS Policy"
+
+HEADERS=$(curl -sI -H "Origin: https://app.example.com" "$BASE_URL/static/test.txt")
+if echo "$HEADERS" | grep -qi "access-control-allow-origin"; then
+    log_fail "No CORS headers without a policy"
+else
+    log_pass "No CORS headers without a policy"
+fi
+
+# enable_cors applies the default policy to actual and preflight requests
+HTTP_CONFIG=$(curl -s "$API_URL/v1/discovery:http" | jq -c '.resources[0]')
+UPDATE_RESPONSE=$(curl -s -X POST "$API_URL/v1/discovery:http" \
+    -H "Content-Type: application/json" \
+    -d "{\"resources\": [$(echo "$HTTP_CONFIG" | jq -c '.enable_cors = true')]}")
+assert_json_field "Enable CORS ACK" "$UPDATE_RESPONSE" ".status" "ACK"
+
+HEADERS=$(curl -sI -H "Origin: https://app.example.com" "$BASE_URL/static/test.txt")
+assert_contains "enable_cors sets ACAO on GET" "$HEADERS" "access-control-allow-origin: *"
+HEADERS=$(curl -si -X OPTIONS -H "Origin: https://app.example.com" \
+    -H "Access-Control-Request-Method: GET" "$BASE_URL/static/test.txt")
+assert_contains "Default preflight methods" "$HEADERS" "access-control-allow-methods: GET, HEAD, OPTIONS"
+assert_contains "Default preflight max-age" "$HEADERS" "access-control-max-age: 86400"
+
+# Virtual host and route policies
+UPDATE_RESPONSE=$(curl -s -X POST "$API_URL/v1/discovery:vhosts" \
+    -H "Content-Type: application/json" \
+    -d '{"resources": [{"virtual_hosts": [
+      {"name": "cors", "domains": ["*"],
+       "cors": {"allow_origin": [{"exact": "https://app.example.com"}, {"suffix": ".example.org"}],
+                "allow_methods": ["GET", "HEAD"], "expose_headers": ["ETag"],
+                "allow_credentials": true, "max_age": 600},
+       "routes": [
+        {"match": {"prefix": "/public"}, "type": "direct", "status": 200, "body": "public",
+         "cors": {"allow_origin": [{"safe_regex": "https://pr-\\d+\\.preview\\.dev"}]}},
+        {"match": {"prefix": "/private"}, "type": "direct", "status": 200, "body": "private",
+         "cors": {"enabled": false}},
+        {"match": {"prefix": "/api", "methods": ["GET"]}, "type": "direct", "status": 200, "body": "api"}
+      ]}
+    ]}]}')
+assert_json_field "Configure CORS policies ACK" "$UPDATE_RESPONSE" ".status" "ACK"
+
+HEADERS=$(curl -sI -H "Origin: https://app.example.com" "$BASE_URL/api")
+assert_contains "Exact origin is reflected" "$HEADERS" "access-control-allow-origin: https://app.example.com"
+assert_contains "Credentials allowed" "$HEADERS" "access-control-allow-credentials: true"
+assert_contains "Exposed headers" "$HEADERS" "access-control-expose-headers: ETag"
+assert_contains "Vary: Origin" "$HEADERS" "vary: Origin"
+HEADERS=$(curl -sI -H "Origin: https://cdn.example.org" "$BASE_URL/api")
+assert_contains "Suffix origin is reflected" "$HEADERS" "access-control-allow-origin: https://cdn.example.org"
+HEADERS=$(curl -sI -H "Origin: https://evil.example" "$BASE_URL/api")
+if echo "$HEADERS" | grep -qi "access-control-allow-origin"; then
+    log_fail "Disallowed origin is not reflected"
+else
+    log_pass "Disallowed origin is not reflected"
+fi
+
+HEADERS=$(curl -si -X OPTIONS -H "Origin: https://app.example.com" \
+    -H "Access-Control-Request-Method: GET" "$BASE_URL/api")
+assert_contains "Preflight matches GET-only route" "$HEADERS" "HTTP/1.1 204"
+assert_contains "Preflight allowed methods" "$HEADERS" "access-control-allow-methods: GET, HEAD"
+assert_contains "Preflight max-age" "$HEADERS" "access-control-max-age: 600"
+HEADERS=$(curl -si -X OPTIONS -H "Origin: https://app.example.com" \
+    -H "Access-Control-Request-Method: PUT" "$BASE_URL/api")
+if echo "$HEADERS" | grep -qi "access-control-allow-methods"; then
+    log_fail "Preflight for a disallowed method grants nothing"
+else
+    log_pass "Preflight for a disallowed method grants nothing"
+fi
+
+HEADERS=$(curl -sI -H "Origin: https://pr-42.preview.dev" "$BASE_URL/public")
+assert_contains "Route regex origin overrides vhost policy" "$HEADERS" "access-control-allow-origin: https://pr-42.preview.dev"
+HEADERS=$(curl -sI -H "Origin: https://app.example.com" "$BASE_URL/private")
+if echo "$HEADERS" | grep -qi "access-control-allow-origin"; then
+    log_fail "Route can disable CORS"
+else
+    log_pass "Route can disable CORS"
+fi
+
+UPDATE_RESPONSE=$(curl -s -X POST "$API_URL/v1/discovery:vhosts" \
+    -H "Content-Type: application/json" \
+    -d '{"resources": [{"virtual_hosts": [{"name": "bad", "domains": ["*"], "routes": [],
+        "cors": {"allow_origin": [{"exact": "*"}], "allow_credentials": true}}]}]}')
+assert_json_field "Wildcard origin with credentials NACK" "$UPDATE_RESPONSE" ".status" "NACK"
+
+# Restore
+curl -s -X POST "$API_URL/v1/discovery:http" \
+    -H "Content-Type: application/json" \
+    -d "{\"resources\": [$HTTP_CONFIG]}" > /dev/null
+curl -s -X POST "$API_URL/v1/discovery:vhosts" \
+    -H "Content-Type: application/json" \
+    -d '{"resources": [{"virtual_hosts": []}]}' > /dev/null
+
+log_info "CORS policy tests completed"
@@ -0,0 +1,104 @@
+#!/bin/bash
+# Live HTTP and Performance Config Tests
+# This script is called by integration_tests.sh, common.sh is already loaded
+
+log_section "28. Live HTTP and Performance Config"
+
+HTTP_CONFIG=$(curl -s "$API_URL/v1/discovery:http" | jq -c '.resources[0]')
+PERF_CONFIG=$(curl -s "$API_URL/v1/discovery:performance" | jq -c '.resources[0]')
+
+post_http() {
+    curl -s -X POST "$API_URL/v1/discovery:http" \
+        -H "Content-Type: application/json" \
+        -d "{\"resources\": [$(echo "$HTTP_CONFIG" | jq -c "$1")]}"
+}
+
+post_performance() {
+    curl -s -X POST "$API_URL/v1/discovery:performance" \
+        -H "Content-Type: application/json" \
+        -d "{\"resources\": [$(echo "$PERF_CONFIG" | jq -c "$1")]}"
+}
+
+# --- HTTP: server_name, default_content_type, max_body_size ---
+HEADERS=$(curl -sI "$BASE_URL/")
+assert_contains "Server header from config" "$HEADERS" "server: Tokio-Hyper/1.0"
+
+UPDATE_RESPONSE=$(post_http '.server_name = "Live-Test/2.0" | .default_content_type = "text/plain; charset=us-ascii" | .max_body_size = 16')
+assert_json_field "HTTP update ACK" "$UPDATE_RESPONSE" ".status" "ACK"
+
+HEADERS=$(curl -sI "$BASE_URL/")
+assert_contains "server_name applies live" "$HEADERS" "server: Live-Test/2.0"
+
+curl -s -X POST "$API_URL/v1/discovery:vhosts" \
+    -H "Content-Type: application/json" \
+    -d '{"resources": [{"virtual_hosts": [{"name": "live", "domains": ["*"], "routes": [
+        {"match": {"prefix": "/"}, "type": "direct", "status": 200, "body": "live"}
+    ]}]}]}' > /dev/null
+HEADERS=$(curl -sI "$BASE_URL/anything")
+assert_contains "Direct responses default to text/plain" "$HEADERS" "content-type: text/plain; charset=utf-8"
+curl -s -X POST "$API_URL/v1/discovery:vhosts" \
+    -H "Content-Type: application/json" \
+    -d '{"resources": [{"virtual_hosts": []}]}' > /dev/null
+
+STATUS=$(curl -s -o /dev/null -w "%{http_code}" -X GET --data-binary "0123456789abcdefXYZ" "$BASE_URL/")
+if [ "$STATUS" = "413" ]; then
+    log_pass "max_body_size applies live (HTTP 413)"
+else
+    log_fail "max_body_size applies live (expected: 413, got: $STATUS)"
+fi
+STATUS=$(curl -s -o /dev/null -w "%{http_code}" -X GET --data-binary "0123456789" "$BASE_URL/")
+if [ "$STATUS" = "200" ]; then
+    log_pass "Body under max_body_size accepted"
+else
+    log_fail "Body under max_body_size accepted (expected: 200, got: $STATUS)"
+fi
+
+curl -s -X POST "$API_URL/v1/discovery:http" \
+    -H "Content-Type: application/json" \
+    -d "{\"resources\": [$HTTP_CONFIG]}" > /dev/null
+
+# --- Performance: keep_alive_timeout ---
+HEADERS=$(curl -sI "$BASE_URL/")
+if echo "$HEADERS" | grep -qi "connection: close"; then
+    log_fail "Keep-alive enabled by default"
+else
+    log_pass "Keep-alive enabled by default"
+fi
+UPDATE_RESPONSE=$(post_performance '.keep_alive_timeout = 0')
+assert_json_field "Disable keep-alive ACK" "$UPDATE_RESPONSE" ".status" "ACK"
+HEADERS=$(curl -sI "$BASE_URL/")
+assert_contains "keep_alive_timeout = 0 closes connections" "$HEADERS" "connection: close"
+
+# --- Performance: header_read_timeout ---
+post_performance '.header_read_timeout = 1' > /dev/null
+START=$(date +%s)
+exec 3<>/dev/tcp/127.0.0.1/8080
+timeout 5 cat <&3 > /dev/null
+exec 3<&-
+ELAPSED=$(( $(date +%s) - START ))
+if [ "$ELAPSED" -le 3 ]; then
+    log_pass "Silent connection closed after the live header_read_timeout (${ELAPSED}s)"
+else
+    log_fail "Silent connection closed after the live header_read_timeout (took ${ELAPSED}s)"
+fi
+
+# --- Performance: max_connections ---
+post_performance '.max_connections = 1' > /dev/null
+exec 3<>/dev/tcp/127.0.0.1/8080
+sleep 0.2
+STATUS=$(curl -s -o /dev/null -w "%{http_code}" "$BASE_URL/")
+if [ "$STATUS" = "503" ]; then
+    log_pass "max_connections applies live (HTTP 503)"
+else
+    log_fail "max_connections applies live (expected: 503, got: $STATUS)"
+fi
+exec 3<&-
+
+# Restore
+curl -s -X POST "$API_URL/v1/discovery:performance" \
+    -H "Content-Type: application/json" \
+    -d "{\"resources\": [$PERF_CONFIG]}" > /dev/null
+sleep 0.2
+assert_status "Requests accepted after restore" "$BASE_URL/" "200"
+
+log_info "Live config tests completed"
@@ -0,0 +1,49 @@
+#!/bin/bash
+# Multi-core Serving and Hot Restart Tests
+# This script is called by integration_tests.sh, common.sh is already loaded
+
+log_section "29. Multi-core Serving and Hot Restart"
+
+# Slow clients must not block others: connections are spread over worker threads
+SLOW_STATUS_FILE=$(mktemp)
+(curl -s -o /dev/null -w "%{http_code}" --limit-rate 1K "$BASE_URL/static/test.txt" > "$SLOW_STATUS_FILE") &
+SLOW_PID=$!
+PIDS=()
+for i in $(seq 1 20); do
+    curl -s -o /dev/null "$BASE_URL/static/test.txt?c=$i" &
+    PIDS+=($!)
+done
+FAILED=0
+for pid in "${PIDS[@]}"; do
+    wait "$pid" || FAILED=$((FAILED + 1))
+done
+wait "$SLOW_PID"
+if [ "$FAILED" -eq 0 ] && [ "$(cat "$SLOW_STATUS_FILE")" = "200" ]; then
+    log_pass "Concurrent requests served"
+else
+    log_fail "Concurrent requests served ($FAILED failed)"
+fi
+rm -f "$SLOW_STATUS_FILE"
+
+# Hot restart on the same address keeps serving
+LISTENERS=$(curl -s "$API_URL/v1/discovery:listeners" | jq -c '.resources[0] | {main_server}')
+UPDATE_RESPONSE=$(curl -s -X POST "$API_URL/v1/discovery:listeners" \
+    -H "Content-Type: application/json" \
+    -d "{\"resources\": [$LISTENERS], \"force_restart\": true}")
+assert_json_field "Forced hot restart ACK" "$UPDATE_RESPONSE" ".status" "ACK"
+
+sleep 0.5
+assert_status "Requests served after hot restart" "$BASE_URL/static/test.txt" "200"
+STATUS_LIST=""
+for i in $(seq 1 10); do
+    STATUS_LIST+="$(curl -s -o /dev/null -w "%{http_code}" "$BASE_URL/healthz") "
+done
+if [ "$(echo "$STATUS_LIST" | tr ' ' '\n' | grep -c '^200$')" -eq 10 ]; then
+    log_pass "New listener accepts repeated connections"
+else
+    log_fail "New listener accepts repeated connections ($STATUS_LIST)"
+fi
+RESPONSE=$(curl -s "$API_URL/v1/discovery")
+assert_json_has "API still reachable after restart" "$RESPONSE" ".resources"
+
+log_info "Multi-core serving tests completed"
@@ -0,0 +1,92 @@
+#!/bin/bash
+# Connection Phase Timeout Tests
+# This script is called by integration_tests.sh, common.sh is already loaded
+
+log_section "30. Connection Phase Timeouts"
+
+PERF_CONFIG=$(curl -s "$API_URL/v1/discovery:performance" | jq -c '.resources[0]')
+UPDATE_RESPONSE=$(curl -s -X POST "$API_URL/v1/discovery:performance" \
+    -H "Content-Type: application/json" \
+    -d "{\"resources\": [$(echo "$PERF_CONFIG" | jq -c '.header_read_timeout = 2 | .keep_alive_timeout = 3 | .read_timeout = 1 | .write_timeout = 1')]}")
+assert_json_field "Configure phase timeouts ACK" "$UPDATE_RESPONSE" ".status" "ACK"
+
+# Send raw bytes on a fresh connection to $1:$2 and wait for the server to close it.
+# Prints the elapsed milliseconds; the response is saved to $RAW_RESPONSE_FILE.
+RAW_RESPONSE_FILE=$(mktemp)
+raw_request() {
+    local start
+    start=$(date +%s%N)
+    exec 3<>"/dev/tcp/$1/$2"
+    printf "$3" >&3
+    timeout 8 cat <&3 > "$RAW_RESPONSE_FILE"
+    exec 3<&-
+    echo $(( ($(date +%s%N) - start) / 1000000 ))
+}
+
+assert_closed_within() {
+    local name="$1" elapsed="$2" min="$3" max="$4"
+    if [ "$elapsed" -ge "$min" ] && [ "$elapsed" -le "$max" ]; then
+        log_pass "$name (${elapsed}ms)"
+    else
+        log_fail "$name (expected ${min}-${max}ms, took ${elapsed}ms)"
+    fi
+}
+
+# --- Header read timeout (slowloris) ---
+ELAPSED=$(raw_request 127.0.0.1 8080 'GET / HTTP/1.1\r\nHost: localhost\r\n')
+assert_closed_within "Incomplete request head closed after header_read_timeout" "$ELAPSED" 1500 3500
+ELAPSED=$(raw_request 127.0.0.1 8080 '')
+assert_closed_within "Silent connection closed after header_read_timeout" "$ELAPSED" 1500 3500
+
+# --- Keep-alive idle timeout ---
+ELAPSED=$(raw_request 127.0.0.1 8080 'GET / HTTP/1.1\r\nHost: localhost\r\n\r\n')
+RAW_RESPONSE=$(cat "$RAW_RESPONSE_FILE")
+assert_contains "Response served before idling" "$RAW_RESPONSE" "HTTP/1.1 200"
+assert_closed_within "Idle keep-alive connection closed after keep_alive_timeout" "$ELAPSED" 2500 4500
+
+# --- A healthy keep-alive connection outlives the read/write timeouts ---
+exec 3<>/dev/tcp/127.0.0.1/8080
+for i in 1 2; do
+    printf 'GET / HTTP/1.1\r\nHost: localhost\r\n\r\n' >&3
+    sleep 1.5
+done
+printf 'GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n' >&3
+COUNT=$(timeout 8 cat <&3 | grep -c "HTTP/1.1 200")
+exec 3<&-
+if [ "$COUNT" -eq 3 ]; then
+    log_pass "Keep-alive connection serves requests beyond read/write timeouts"
+else
+    log_fail "Keep-alive connection serves requests beyond read/write timeouts (got $COUNT responses)"
+fi
+
+# --- Request body read timeout (the API reads request bodies) ---
+ELAPSED=$(raw_request 127.0.0.1 8000 'POST /v1/discovery:performance HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\nContent-Length: 100\r\n\r\n{"res')
+assert_closed_within "Stalled request body closed after read_timeout" "$ELAPSED" 500 2500
+
+# --- Response write timeout (client stops reading a large response) ---
+LARGE_FILE="static/timeout_large.bin"
+head -c 67108864 /dev/zero > "$LARGE_FILE"
+exec 3<>/dev/tcp/127.0.0.1/8080
+printf 'GET /static/timeout_large.bin HTTP/1.1\r\nHost: localhost\r\n\r\n' >&3
+sleep 3
+exec 3<&-
+rm -f "$LARGE_FILE" "$RAW_RESPONSE_FILE"
+
+# Each timeout is logged with its own reason
+if [ -f /tmp/server.log ]; then
+    for reason in "header read timeout" "keep-alive idle timeout" \
+        "request body read timeout" "response write timeout"; do
+        if grep -q "closed: $reason" /tmp/server.log; then
+            log_pass "Logged: $reason"
+        else
+            log_fail "Logged: $reason"
+        fi
+    done
+fi
+
+# Restore
+curl -s -X POST "$API_URL/v1/discovery:performance" \
+    -H "Content-Type: application/json" \
+    -d "{\"resources\": [$PERF_CONFIG]}" > /dev/null
+
+log_info "Connection phase timeout tests completed"
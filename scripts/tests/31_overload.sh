@@ -0,0 +1,104 @@
+#!/bin/bash
+# Overload Policy and Per-IP Connection Limit Tests
+# This script is called by integration_tests.sh, common.sh is already loaded
+
+log_section "31. Overload Policy"
+
+PERF_CONFIG=$(curl -s "$API_URL/v1/discovery:performance" | jq -c '.resources[0]')
+
+post_performance() {
+    curl -s -X POST "$API_URL/v1/discovery:performance" \
+        -H "Content-Type: application/json" \
+        -d "{\"resources\": [$(echo "$PERF_CONFIG" | jq -c "$1")]}"
+}
+
+# Hold one idle connection open on fd 3 so the next one is over the limit
+hold_connection() {
+    exec 3<>/dev/tcp/127.0.0.1/8080
+    sleep 0.2
+}
+
+release_connection() {
+    exec 3<&-
+    sleep 0.2
+}
+
+# --- service_unavailable (default): 503 with Retry-After ---
+UPDATE_RESPONSE=$(post_performance '.max_connections = 1 | .overload = {"retry_after": 7}')
+assert_json_field "Default overload policy ACK" "$UPDATE_RESPONSE" ".status" "ACK"
+hold_connection
+HEADERS=$(curl -si "$BASE_URL/")
+assert_contains "Over max_connections gets 503" "$HEADERS" "HTTP/1.1 503"
+assert_contains "503 carries Retry-After" "$HEADERS" "retry-after: 7"
+release_connection
+
+# --- reject: connection closed without a response ---
+post_performance '.max_connections = 1 | .overload = {"policy": "reject"}' > /dev/null
+hold_connection
+STATUS=$(curl -s -o /dev/null -w "%{http_code}" "$BASE_URL/")
+if [ "$STATUS" = "000" ]; then
+    log_pass "reject policy closes the connection"
+else
+    log_fail "reject policy closes the connection (got: $STATUS)"
+fi
+release_connection
+
+# --- queue: waits for a slot, then falls back to 503 ---
+post_performance '.max_connections = 1 | .overload = {"policy": "queue", "queue_timeout_ms": 3000}' > /dev/null
+hold_connection
+STATUS_FILE=$(mktemp)
+# The background client must not inherit the held connection on fd 3
+(curl -s -o /dev/null -w "%{http_code}" "$BASE_URL/" > "$STATUS_FILE") 3<&- &
+CURL_PID=$!
+sleep 0.5
+release_connection
+wait "$CURL_PID"
+if [ "$(cat "$STATUS_FILE")" = "200" ]; then
+    log_pass "Queued connection served once a slot frees up"
+else
+    log_fail "Queued connection served once a slot frees up (got: $(cat "$STATUS_FILE"))"
+fi
+rm -f "$STATUS_FILE"
+
+post_performance '.max_connections = 1 | .overload = {"policy": "queue", "queue_timeout_ms": 300}' > /dev/null
+hold_connection
+STATUS=$(curl -s -o /dev/null -w "%{http_code}" "$BASE_URL/")
+if [ "$STATUS" = "503" ]; then
+    log_pass "Queue timeout falls back to 503"
+else
+    log_fail "Queue timeout falls back to 503 (got: $STATUS)"
+fi
+release_connection
+
+# --- Per-client-IP limit ---
+post_performance '.max_connections = 0 | .max_connections_per_ip = 1' > /dev/null
+hold_connection
+STATUS=$(curl -s -o /dev/null -w "%{http_code}" "$BASE_URL/")
+if [ "$STATUS" = "503" ]; then
+    log_pass "max_connections_per_ip limits one client"
+else
+    log_fail "max_connections_per_ip limits one client (got: $STATUS)"
+fi
+release_connection
+assert_status "Client served again after closing its connection" "$BASE_URL/" "200"
+
+UPDATE_RESPONSE=$(post_performance '.overload = {"policy": "drop"}')
+assert_json_field "Unknown overload policy NACK" "$UPDATE_RESPONSE" ".status" "NACK"
+
+# --- Rejection counters ---
+STATS=$(curl -s "$API_URL/v1/stats")
+for counter in rejected_max_connections rejected_per_ip service_unavailable closed queue_admitted queue_timeouts; do
+    if echo "$STATS" | jq -e ".connections.$counter > 0" > /dev/null; then
+        log_pass "Counter $counter recorded"
+    else
+        log_fail "Counter $counter recorded"
+    fi
+done
+assert_json_field "No connections left queued" "$STATS" ".connections.queue_waiting" "0"
+
+# Restore
+curl -s -X POST "$API_URL/v1/discovery:performance" \
+    -H "Content-Type: application/json" \
+    -d "{\"resources\": [$PERF_CONFIG]}" > /dev/null
+
+log_info "Overload policy tests completed"
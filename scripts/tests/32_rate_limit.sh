@@ -0,0 +1,121 @@
+#!/bin/bash
+# Rate Limiting Tests
+# This script is called by integration_tests.sh, common.sh is already loaded
+
+log_section "32. Rate Limiting"
+
+post_vhosts() {
+    curl -s -X POST "$API_URL/v1/discovery:vhosts" \
+        -H "Content-Type: application/json" \
+        -d "{\"resources\": [{\"virtual_hosts\": $1}]}"
+}
+
+# Status codes of N requests, space separated
+statuses() {
+    local count=$1
+    shift
+    local result=""
+    for _ in $(seq "$count"); do
+        result="$result $(curl -s -o /dev/null -w "%{http_code}" "$@")"
+    done
+    echo "${result# }"
+}
+
+assert_statuses() {
+    local name=$1 expected=$2 actual=$3
+    if [ "$actual" = "$expected" ]; then
+        log_pass "$name"
+    else
+        log_fail "$name (expected: $expected, got: $actual)"
+    fi
+}
+
+VHOSTS='[
+  {"name": "shared", "domains": ["shared.local"],
+   "rate_limits": [{"key": "vhost", "requests": 2, "interval": 60}],
+   "routes": [{"match": {"prefix": "/"}, "type": "direct", "status": 200, "body": "shared"}]},
+  {"name": "limits", "domains": ["*"], "routes": [
+    {"name": "limited", "match": {"path": "/limited"}, "type": "direct", "status": 200, "body": "ok",
+     "rate_limits": [{"requests": 3, "interval": 60}]},
+    {"match": {"path": "/keyed"}, "type": "direct", "status": 200, "body": "ok",
+     "rate_limits": [{"key": {"header": "x-api-key"}, "requests": 1, "interval": 60}]},
+    {"match": {"path": "/window"}, "type": "direct", "status": 200, "body": "ok",
+     "rate_limits": [{"requests": 2, "interval": 60, "algorithm": "sliding_window"}]},
+    {"match": {"path": "/refill"}, "type": "direct", "status": 200, "body": "ok",
+     "rate_limits": [{"requests": 2, "interval": 1}]},
+    {"match": {"path": "/burst"}, "type": "direct", "status": 200, "body": "ok",
+     "rate_limits": [{"requests": 1, "interval": 60, "burst": 3}]},
+    {"match": {"prefix": "/"}, "type": "direct", "status": 200, "body": "unlimited"}
+  ]}
+]'
+UPDATE_RESPONSE=$(post_vhosts "$VHOSTS")
+assert_json_field "Configure rate limits ACK" "$UPDATE_RESPONSE" ".status" "ACK"
+
+# --- Per-IP token bucket on a route ---
+HEADERS=$(curl -sI "$BASE_URL/limited")
+assert_contains "RateLimit-Limit header" "$HEADERS" "ratelimit-limit: 3"
+assert_contains "RateLimit-Remaining header" "$HEADERS" "ratelimit-remaining: 2"
+assert_contains "RateLimit-Policy header" "$HEADERS" "ratelimit-policy: 3;w=60"
+assert_statuses "Requests within the limit pass" "200 200" "$(statuses 2 "$BASE_URL/limited")"
+HEADERS=$(curl -si "$BASE_URL/limited")
+assert_contains "Request over the limit gets 429" "$HEADERS" "HTTP/1.1 429"
+assert_contains "429 carries Retry-After" "$HEADERS" "retry-after: 20"
+assert_contains "429 shows no remaining requests" "$HEADERS" "ratelimit-remaining: 0"
+assert_statuses "Routes without limits are unaffected" "200 200 200 200" "$(statuses 4 "$BASE_URL/other")"
+
+HEADERS=$(curl -sI "$BASE_URL/other")
+if echo "$HEADERS" | grep -qi "ratelimit-"; then
+    log_fail "No RateLimit headers without a limit"
+else
+    log_pass "No RateLimit headers without a limit"
+fi
+
+# --- Burst and refill ---
+assert_statuses "Burst allows 3 requests at once" "200 200 200 429" "$(statuses 4 "$BASE_URL/burst")"
+HEADERS=$(curl -sI "$BASE_URL/burst")
+assert_contains "Burst shown in RateLimit-Policy" "$HEADERS" "ratelimit-policy: 1;w=60;burst=3"
+
+assert_statuses "Refill limit exhausted" "200 200 429" "$(statuses 3 "$BASE_URL/refill")"
+sleep 1.1
+assert_statuses "Tokens refill over the interval" "200 200 429" "$(statuses 3 "$BASE_URL/refill")"
+
+# --- Sliding window ---
+assert_statuses "Sliding window limit" "200 200 429" "$(statuses 3 "$BASE_URL/window")"
+
+# --- Header key ---
+assert_statuses "Header key limits per value" "200 429" "$(statuses 2 -H "X-Api-Key: alice" "$BASE_URL/keyed")"
+assert_statuses "Other header value has its own counter" "200" "$(statuses 1 -H "X-Api-Key: bob" "$BASE_URL/keyed")"
+assert_statuses "Requests without the header are not limited" "200 200 200" "$(statuses 3 "$BASE_URL/keyed")"
+
+# --- Virtual host key: one counter shared by all clients of the host ---
+assert_statuses "Virtual host limit" "200 200 429" "$(statuses 3 -H "Host: shared.local" "$BASE_URL/a")"
+assert_statuses "Virtual host limit covers all paths" "429" "$(statuses 1 -H "Host: shared.local" "$BASE_URL/b")"
+
+# --- Limits update via xDS; a changed policy starts a fresh counter ---
+UPDATE_RESPONSE=$(post_vhosts "$(echo "$VHOSTS" | jq -c '.[1].routes[0].rate_limits[0].requests = 5')")
+assert_json_field "Update rate limit ACK" "$UPDATE_RESPONSE" ".status" "ACK"
+assert_statuses "Updated limit applies" "200 200 200 200 200 429" "$(statuses 6 "$BASE_URL/limited")"
+
+# --- Validation ---
+for bad in '{"requests": 0}' '{"requests": 1, "interval": 0}' \
+    '{"requests": 1, "burst": 2, "algorithm": "sliding_window"}' \
+    '{"requests": 1, "key": {"header": "bad header"}}' '{"requests": 1, "key": "cookie"}'; do
+    UPDATE_RESPONSE=$(post_vhosts "[{\"name\": \"bad\", \"domains\": [\"*\"], \"routes\": [
+        {\"match\": {\"prefix\": \"/\"}, \"type\": \"direct\", \"status\": 200, \"rate_limits\": [$bad]}]}]")
+    assert_json_field "Invalid rate limit NACK: $bad" "$UPDATE_RESPONSE" ".status" "NACK"
+done
+
+# --- Counters ---
+STATS=$(curl -s "$API_URL/v1/stats")
+for counter in allowed limited tracked_keys; do
+    if echo "$STATS" | jq -e ".rate_limits.$counter > 0" > /dev/null; then
+        log_pass "Rate limit counter $counter recorded"
+    else
+        log_fail "Rate limit counter $counter recorded"
+    fi
+done
+
+# Restore
+post_vhosts '[]' > /dev/null
+
+log_info "Rate limiting tests completed"
@@ -0,0 +1,90 @@
+#!/bin/bash
+# Rate Limit Service Tests
+# This script is called by integration_tests.sh, common.sh is already loaded
+# The service protocol itself is covered by the in-process reference server in
+# the unit tests; here the service is unreachable to exercise the failure modes.
+
+log_section "33. Rate Limit Service"
+
+HTTP_CONFIG=$(curl -s "$API_URL/v1/discovery:http" | jq -c '.resources[0]')
+
+post_http() {
+    curl -s -X POST "$API_URL/v1/discovery:http" \
+        -H "Content-Type: application/json" \
+        -d "{\"resources\": [$(echo "$HTTP_CONFIG" | jq -c "$1")]}"
+}
+
+post_vhosts() {
+    curl -s -X POST "$API_URL/v1/discovery:vhosts" \
+        -H "Content-Type: application/json" \
+        -d "{\"resources\": [{\"virtual_hosts\": $1}]}"
+}
+
+# Nothing listens on the discard port
+SERVICE='{"address": "127.0.0.1:9", "domain": "yarhs", "timeout_ms": 100}'
+
+UPDATE_RESPONSE=$(post_vhosts '[{"name": "global", "domains": ["*"],
+    "global_rate_limits": [{"actions": ["remote_address"]}],
+    "routes": [
+      {"match": {"path": "/api"}, "type": "direct", "status": 200, "body": "api",
+       "global_rate_limits": [{"actions": [
+         {"generic_key": {"descriptor_value": "api"}},
+         {"request_headers": {"header_name": "x-api-key", "descriptor_key": "api_key"}}
+       ]}]},
+      {"match": {"prefix": "/"}, "type": "direct", "status": 200, "body": "ok"}
+    ]}]')
+assert_json_field "Configure global rate limits ACK" "$UPDATE_RESPONSE" ".status" "ACK"
+
+assert_status "No service configured: descriptors are ignored" "$BASE_URL/api" "200"
+
+# --- fail_open (default): requests are served when the service is down ---
+UPDATE_RESPONSE=$(post_http ".rate_limit_service = $SERVICE")
+assert_json_field "Configure rate limit service ACK" "$UPDATE_RESPONSE" ".status" "ACK"
+assert_json_field "Default failure mode" "$(curl -s "$API_URL/v1/discovery:http")" \
+    ".resources[0].rate_limit_service.failure_mode" "fail_open"
+assert_status "fail_open serves the request" "$BASE_URL/api" "200"
+
+# --- fail_closed: 503 when the service is down ---
+post_http ".rate_limit_service = ($SERVICE | .failure_mode = \"fail_closed\")" > /dev/null
+HEADERS=$(curl -si "$BASE_URL/api")
+assert_contains "fail_closed answers 503" "$HEADERS" "HTTP/1.1 503"
+assert_contains "fail_closed 503 carries Retry-After" "$HEADERS" "retry-after: 1"
+if grep -q "Rate limit service 127.0.0.1:9 failed" /tmp/server.log; then
+    log_pass "Service failure is logged"
+else
+    log_fail "Service failure is logged"
+fi
+
+# Requests without descriptors never reach the service
+post_vhosts '[{"name": "global", "domains": ["*"], "routes": [
+      {"match": {"prefix": "/"}, "type": "direct", "status": 200, "body": "ok"}]}]' > /dev/null
+assert_status "Routes without descriptors skip the service" "$BASE_URL/other" "200"
+
+# --- Validation ---
+for bad in '{"address": "127.0.0.1"}' '{"address": "not a host:1"}' \
+    '{"address": "127.0.0.1:9", "timeout_ms": 0}' '{"address": "127.0.0.1:9", "domain": ""}' \
+    '{"address": "127.0.0.1:9", "failure_mode": "retry"}'; do
+    UPDATE_RESPONSE=$(post_http ".rate_limit_service = $bad")
+    assert_json_field "Invalid rate limit service NACK: $bad" "$UPDATE_RESPONSE" ".status" "NACK"
+done
+for bad in '{"actions": []}' '{"actions": [{"request_headers": {"header_name": "bad header", "descriptor_key": "k"}}]}' \
+    '{"actions": [{"generic_key": {"descriptor_value": ""}}]}' '{"actions": ["destination"]}'; do
+    UPDATE_RESPONSE=$(post_vhosts "[{\"name\": \"bad\", \"domains\": [\"*\"], \"routes\": [],
+        \"global_rate_limits\": [$bad]}]")
+    assert_json_field "Invalid global rate limit NACK: $bad" "$UPDATE_RESPONSE" ".status" "NACK"
+done
+
+STATS=$(curl -s "$API_URL/v1/stats")
+if echo "$STATS" | jq -e '.rate_limit_service.errors >= 2' > /dev/null; then
+    log_pass "Service errors counted"
+else
+    log_fail "Service errors counted"
+fi
+
+# Restore
+curl -s -X POST "$API_URL/v1/discovery:http" \
+    -H "Content-Type: application/json" \
+    -d "{\"resources\": [$HTTP_CONFIG]}" > /dev/null
+post_vhosts '[]' > /dev/null
+
+log_info "Rate limit service tests completed"
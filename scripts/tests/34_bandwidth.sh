@@ -0,0 +1,117 @@
+#!/bin/bash
+# Bandwidth Throttling Tests
+# This script is called by integration_tests.sh, common.sh is already loaded
+
+log_section "34. Bandwidth Throttling"
+
+post_vhosts() {
+    curl -s -X POST "$API_URL/v1/discovery:vhosts" \
+        -H "Content-Type: application/json" \
+        -d "{\"resources\": [{\"virtual_hosts\": $1}]}"
+}
+
+# Download `url` into SECONDS_TAKEN (seconds) and check the body against `expected`
+download() {
+    local name=$1 url=$2 expected=$3
+    local output
+    output=$(mktemp)
+    SECONDS_TAKEN=$(curl -s -o "$output" -w "%{time_total}" "$url")
+    if cmp -s "$output" "$expected"; then
+        log_pass "$name: body intact"
+    else
+        log_fail "$name: body intact"
+    fi
+    rm -f "$output"
+}
+
+assert_between() {
+    local name=$1 value=$2 min=$3 max=$4
+    if awk -v v="$value" -v lo="$min" -v hi="$max" 'BEGIN { exit !(v >= lo && v < hi) }'; then
+        log_pass "$name (${value}s)"
+    else
+        log_fail "$name (${value}s, expected ${min}s to ${max}s)"
+    fi
+}
+
+MEDIA_DIR=$(mktemp -d)
+head -c 65536 /dev/urandom > "$MEDIA_DIR/clip.bin"
+head -c 300000 /dev/urandom > "$MEDIA_DIR/large.bin"
+
+UPDATE_RESPONSE=$(post_vhosts "[
+  {\"name\": \"shared\", \"domains\": [\"shared.local\"], \"aggregate_limit_rate\": 65536,
+   \"routes\": [{\"match\": {\"prefix\": \"/\"}, \"type\": \"dir\", \"path\": \"$MEDIA_DIR\"}]},
+  {\"name\": \"media\", \"domains\": [\"*\"], \"routes\": [
+    {\"match\": {\"prefix\": \"/slow\"}, \"rewrite\": {\"prefix_rewrite\": \"/\"}, \"type\": \"dir\",
+     \"path\": \"$MEDIA_DIR\", \"limit_rate\": 32768},
+    {\"match\": {\"prefix\": \"/after\"}, \"rewrite\": {\"prefix_rewrite\": \"/\"}, \"type\": \"dir\",
+     \"path\": \"$MEDIA_DIR\", \"limit_rate\": 32768, \"limit_rate_after\": 49152},
+    {\"match\": {\"prefix\": \"/\"}, \"type\": \"dir\", \"path\": \"$MEDIA_DIR\"}
+  ]}
+]")
+assert_json_field "Configure bandwidth limits ACK" "$UPDATE_RESPONSE" ".status" "ACK"
+
+# --- Streamed files ---
+download "Unthrottled download" "$BASE_URL/large.bin" "$MEDIA_DIR/large.bin"
+assert_between "Unthrottled download is fast" "$SECONDS_TAKEN" 0 1
+HEADERS=$(curl -sI "$BASE_URL/large.bin")
+assert_contains "HEAD of a streamed file has Content-Length" "$HEADERS" "content-length: 300000"
+if echo "$HEADERS" | grep -Eq '^etag: "[0-9a-f]+-493e0"'; then
+    log_pass "ETag from modification time and size"
+else
+    log_fail "ETag from modification time and size"
+fi
+RANGE=$(curl -s -r 100000-100009 "$BASE_URL/large.bin" | od -An -tx1)
+EXPECTED=$(tail -c +100001 "$MEDIA_DIR/large.bin" | head -c 10 | od -An -tx1)
+if [ "$RANGE" = "$EXPECTED" ]; then
+    log_pass "Range of a streamed file"
+else
+    log_fail "Range of a streamed file"
+fi
+
+# --- limit_rate: 64 KiB at 32 KiB/s ---
+download "limit_rate" "$BASE_URL/slow/clip.bin" "$MEDIA_DIR/clip.bin"
+assert_between "limit_rate paces the response" "$SECONDS_TAKEN" 1.6 3
+HEADERS=$(curl -sI "$BASE_URL/slow/clip.bin")
+assert_contains "Throttled response keeps Content-Length" "$HEADERS" "content-length: 65536"
+
+# --- limit_rate_after: 48 KiB at full speed, 16 KiB at 32 KiB/s ---
+download "limit_rate_after" "$BASE_URL/after/clip.bin" "$MEDIA_DIR/clip.bin"
+assert_between "limit_rate_after sends the first bytes at full speed" "$SECONDS_TAKEN" 0.3 1.2
+
+# --- aggregate_limit_rate: two 64 KiB downloads share 64 KiB/s ---
+START=$(date +%s%N)
+curl -s -o /dev/null -H "Host: shared.local" "$BASE_URL/clip.bin" 3<&- &
+FIRST=$!
+curl -s -o /dev/null -H "Host: shared.local" "$BASE_URL/clip.bin" 3<&- &
+SECOND=$!
+wait $FIRST $SECOND
+ELAPSED=$(awk -v start="$START" -v end="$(date +%s%N)" 'BEGIN { printf "%.3f", (end - start) / 1e9 }')
+assert_between "aggregate_limit_rate is shared by the virtual host" "$ELAPSED" 1.6 3.5
+
+# --- Slow streams outlive the keep-alive idle timeout ---
+PERF_CONFIG=$(curl -s "$API_URL/v1/discovery:performance" | jq -c '.resources[0]')
+curl -s -X POST "$API_URL/v1/discovery:performance" \
+    -H "Content-Type: application/json" \
+    -d "{\"resources\": [$(echo "$PERF_CONFIG" | jq -c '.keep_alive_timeout = 1')]}" > /dev/null
+download "Throttled download with keep_alive_timeout = 1" \
+    "$BASE_URL/slow/clip.bin" "$MEDIA_DIR/clip.bin"
+assert_between "Keep-alive idle timeout does not cut the stream" "$SECONDS_TAKEN" 1.6 3
+curl -s -X POST "$API_URL/v1/discovery:performance" \
+    -H "Content-Type: application/json" \
+    -d "{\"resources\": [$PERF_CONFIG]}" > /dev/null
+
+# --- Validation ---
+for bad in '"limit_rate": 0' '"limit_rate_after": 1024'; do
+    UPDATE_RESPONSE=$(post_vhosts "[{\"name\": \"bad\", \"domains\": [\"*\"], \"routes\": [
+        {\"match\": {\"prefix\": \"/\"}, \"type\": \"direct\", \"status\": 200, $bad}]}]")
+    assert_json_field "Invalid bandwidth limit NACK: $bad" "$UPDATE_RESPONSE" ".status" "NACK"
+done
+UPDATE_RESPONSE=$(post_vhosts '[{"name": "bad", "domains": ["*"], "aggregate_limit_rate": 0,
+    "routes": []}]')
+assert_json_field "Invalid aggregate_limit_rate NACK" "$UPDATE_RESPONSE" ".status" "NACK"
+
+# Restore
+post_vhosts '[]' > /dev/null
+rm -rf "$MEDIA_DIR"
+
+log_info "Bandwidth throttling tests completed"
@@ -0,0 +1,162 @@
+#!/bin/bash
+# Management API Authentication Tests
+# This script is called by integration_tests.sh, common.sh is already loaded
+# A second server with [api_auth] runs on ports 8180/8100 (API over TLS).
+
+log_section "35. API Authentication"
+
+# --- Unauthenticated API stays on loopback ---
+UPDATE_RESPONSE=$(curl -s -X POST "$API_URL/v1/discovery:listeners" \
+    -H "Content-Type: application/json" \
+    -d '{"resources": [{"api_server": {"host": "0.0.0.0", "port": 8000}}]}')
+assert_json_field "Exposing the API without auth NACK" "$UPDATE_RESPONSE" ".status" "NACK"
+
+AUTH_DIR=$(mktemp -d)
+AUTH_BIN=$(readlink -f "/proc/$SERVER_PID/exe" 2>/dev/null || echo ./target/release/rust_webserver)
+AUTH_API="https://127.0.0.1:8100"
+
+# CA, server certificate, client certificate and a client certificate from another CA
+(
+    cd "$AUTH_DIR" || exit 1
+    openssl req -x509 -newkey rsa:2048 -nodes -days 1 -subj "/CN=test-ca" \
+        -keyout ca.key -out ca.crt 2>/dev/null
+    openssl req -newkey rsa:2048 -nodes -subj "/CN=localhost" \
+        -keyout server.key -out server.csr 2>/dev/null
+    printf "subjectAltName=IP:127.0.0.1,DNS:localhost\n" > server.ext
+    openssl x509 -req -in server.csr -CA ca.crt -CAkey ca.key -CAcreateserial -days 1 \
+        -extfile server.ext -out server.crt 2>/dev/null
+    openssl req -newkey rsa:2048 -nodes -subj "/CN=ops-team" \
+        -keyout client.key -out client.csr 2>/dev/null
+    openssl x509 -req -in client.csr -CA ca.crt -CAkey ca.key -CAcreateserial -days 1 \
+        -out client.crt 2>/dev/null
+    openssl req -x509 -newkey rsa:2048 -nodes -days 1 -subj "/CN=intruder" \
+        -keyout rogue.key -out rogue.crt 2>/dev/null
+)
+printf "# admins\nalice:%s\nbob:{SHA}%s\n" \
+    "$(openssl passwd -apr1 secret)" \
+    "$(printf %s hunter2 | openssl sha1 -binary | base64)" > "$AUTH_DIR/admin.htpasswd"
+TOKEN_HASH=$(printf %s "s3cr3t-token" | sha256sum | cut -d' ' -f1)
+
+sed -e 's/^port = 8080/port = 8180/' -e 's/^api_port = 8000/api_port = 8100/' \
+    -e 's/^api_host = .*/api_host = "0.0.0.0"/' \
+    -e 's/^enable_state_persistence = .*/enable_state_persistence = false/' config.toml \
+    | grep -v "^\[api_auth" > "$AUTH_DIR/config.toml"
+cat >> "$AUTH_DIR/config.toml" <<EOF
+
+[api_auth]
+realm = "yarhs-test"
+tokens = [{ name = "deploy", sha256 = "$TOKEN_HASH" }]
+htpasswd_file = "$AUTH_DIR/admin.htpasswd"
+
+[api_auth.tls]
+cert_file = "$AUTH_DIR/server.crt"
+key_file = "$AUTH_DIR/server.key"
+client_ca_file = "$AUTH_DIR/ca.crt"
+EOF
+
+"$AUTH_BIN" -c "$AUTH_DIR/config" > "$AUTH_DIR/server.log" 2>&1 3<&- &
+AUTH_PID=$!
+for _ in $(seq 1 50); do
+    curl -s -o /dev/null --cacert "$AUTH_DIR/ca.crt" "$AUTH_API/" && break
+    sleep 0.1
+done
+
+api_status() {
+    curl -s -o /dev/null -w "%{http_code}" --cacert "$AUTH_DIR/ca.crt" "$@"
+}
+
+assert_code() {
+    local name=$1 expected=$2 actual=$3
+    if [ "$actual" = "$expected" ]; then
+        log_pass "$name"
+    else
+        log_fail "$name (expected: $expected, got: $actual)"
+    fi
+}
+
+assert_code "Dashboard page without credentials" "200" "$(api_status "$AUTH_API/dashboard")"
+
+# --- 401 with challenges ---
+HEADERS=$(curl -si --cacert "$AUTH_DIR/ca.crt" "$AUTH_API/v1/discovery" | tr '[:upper:]' '[:lower:]')
+assert_contains "Missing credentials answer 401" "$HEADERS" "http/1.1 401"
+assert_contains "Bearer challenge" "$HEADERS" 'www-authenticate: bearer realm="yarhs-test"'
+assert_contains "Basic challenge" "$HEADERS" 'www-authenticate: basic realm="yarhs-test"'
+HEADERS=$(curl -si --cacert "$AUTH_DIR/ca.crt" -H "X-Requested-With: XMLHttpRequest" \
+    "$AUTH_API/v1/discovery" | tr '[:upper:]' '[:lower:]')
+if echo "$HEADERS" | grep -q "www-authenticate: basic"; then
+    log_fail "No Basic challenge for dashboard requests"
+else
+    log_pass "No Basic challenge for dashboard requests"
+fi
+
+# --- Bearer tokens ---
+assert_code "Valid bearer token" "200" \
+    "$(api_status -H "Authorization: Bearer s3cr3t-token" "$AUTH_API/v1/discovery")"
+assert_code "Wrong bearer token" "401" \
+    "$(api_status -H "Authorization: Bearer guess" "$AUTH_API/v1/discovery")"
+
+# --- Basic auth (apr1 and {SHA} hashes) ---
+assert_code "Basic auth with apr1 hash" "200" \
+    "$(api_status -u alice:secret "$AUTH_API/v1/discovery:http")"
+assert_code "Basic auth with {SHA} hash" "200" \
+    "$(api_status -u bob:hunter2 "$AUTH_API/v1/discovery:http")"
+assert_code "Basic auth with wrong password" "401" \
+    "$(api_status -u alice:wrong "$AUTH_API/v1/discovery:http")"
+assert_code "Unknown scheme" "401" \
+    "$(api_status -H "Authorization: Digest x" "$AUTH_API/v1/discovery")"
+
+# --- Client certificates ---
+assert_code "Client certificate from the client CA" "200" \
+    "$(api_status --cert "$AUTH_DIR/client.crt" --key "$AUTH_DIR/client.key" "$AUTH_API/v1/stats")"
+assert_code "Client certificate from another CA is refused" "000" \
+    "$(api_status --cert "$AUTH_DIR/rogue.crt" --key "$AUTH_DIR/rogue.key" "$AUTH_API/v1/stats")"
+assert_code "Plain HTTP is refused" "000" \
+    "$(curl -s -o /dev/null -w "%{http_code}" "http://127.0.0.1:8100/v1/stats")"
+
+# --- Writes need credentials too ---
+assert_code "Unauthenticated update" "401" "$(api_status -X POST \
+    -d '{"resources": []}' "$AUTH_API/v1/discovery:routes")"
+assert_code "Unauthenticated state clear" "401" "$(api_status -X DELETE "$AUTH_API/v1/state")"
+assert_code "Unauthenticated rollback" "401" \
+    "$(api_status -X POST -u alice:wrong "$AUTH_API/v1/rollback:http?version=1")"
+
+# --- Refused changes are audited ---
+AUDIT=$(curl -s --cacert "$AUTH_DIR/ca.crt" -H "Authorization: Bearer s3cr3t-token" "$AUTH_API/v1/audit")
+assert_json_field "Only refused changes audited" "$AUDIT" ".entries | length" "3"
+assert_json_field "Refused rollback audited" "$AUDIT" \
+    "[.entries[0].identity, .entries[0].path, .entries[0].status, .entries[0].result] | join(\" \")" \
+    "unauthenticated /v1/rollback:http 401 NACK"
+assert_json_field "Refused state clear audited" "$AUDIT" \
+    "[.entries[1].method, .entries[1].resource_type, .entries[1].source_ip] | join(\" \")" \
+    "DELETE STATE 127.0.0.1"
+assert_json_field "Refused update audited" "$AUDIT" ".entries[2].resource_type" "ROUTE"
+
+# --- Logged and counted ---
+STATS=$(curl -s --cacert "$AUTH_DIR/ca.crt" -H "Authorization: Bearer s3cr3t-token" "$AUTH_API/v1/stats")
+if echo "$STATS" | jq -e '.api_auth.enabled and .api_auth.failures >= 6 and .api_auth.authenticated >= 4' > /dev/null; then
+    log_pass "Authentication results counted"
+else
+    log_fail "Authentication results counted ($(echo "$STATS" | jq -c '.api_auth'))"
+fi
+if grep -q "API authentication failed for 127.0.0.1:[0-9]*: wrong password for user 'alice'" "$AUTH_DIR/server.log"; then
+    log_pass "Authentication failures logged"
+else
+    log_fail "Authentication failures logged"
+fi
+
+kill "$AUTH_PID" 2>/dev/null
+wait "$AUTH_PID" 2>/dev/null
+
+# --- Exposed API without [api_auth] does not start ---
+grep -v "^\[api_auth\|^realm\|^tokens\|^htpasswd_file\|^cert_file\|^key_file\|^client_ca_file" \
+    "$AUTH_DIR/config.toml" > "$AUTH_DIR/open.toml"
+timeout 5 "$AUTH_BIN" -c "$AUTH_DIR/open" > "$AUTH_DIR/open.log" 2>&1 3<&-
+if grep -q "requires authentication" "$AUTH_DIR/open.log"; then
+    log_pass "Exposed API without authentication refuses to start"
+else
+    log_fail "Exposed API without authentication refuses to start"
+fi
+
+rm -rf "$AUTH_DIR"
+
+log_info "API authentication tests completed"
@@ -0,0 +1,199 @@
+#!/bin/bash
+# Management API Role-Based Access Control Tests
+# This script is called by integration_tests.sh, common.sh is already loaded
+# A second server with [api_auth] roles runs on ports 8280/8200.
+
+log_section "36. API Access Control"
+
+RBAC_DIR=$(mktemp -d)
+RBAC_BIN=$(readlink -f "/proc/$SERVER_PID/exe" 2>/dev/null || echo ./target/release/rust_webserver)
+RBAC_API="http://127.0.0.1:8200"
+RBAC_BASE="http://127.0.0.1:8280"
+
+token_hash() {
+    printf %s "$1" | sha256sum | cut -d' ' -f1
+}
+
+sed -e 's/^port = 8080/port = 8280/' -e 's/^api_port = 8000/api_port = 8200/' \
+    -e 's/^enable_state_persistence = .*/enable_state_persistence = false/' config.toml \
+    | grep -v "^\[api_auth" > "$RBAC_DIR/config.toml"
+cat >> "$RBAC_DIR/config.toml" <<EOF
+
+[api_auth]
+tokens = [
+  { name = "root", sha256 = "$(token_hash root-token)" },
+  { name = "web-deploy", sha256 = "$(token_hash web-token)" },
+  { name = "stranger", sha256 = "$(token_hash stranger-token)" },
+  { name = "api-deploy", sha256 = "$(token_hash api-token)" },
+]
+
+[[api_auth.roles]]
+name = "admin"
+members = ["root"]
+permissions = { listener = "write", route = "write", http = "write", logging = "write", performance = "write", virtual_host = "write", state = "write", audit = "read", stats = "read" }
+
+[[api_auth.roles]]
+name = "web"
+members = ["web-deploy"]
+permissions = { virtual_host = "write", route = "read" }
+virtual_hosts = ["www-site"]
+domains = ["*.shop.local"]
+
+[[api_auth.roles]]
+name = "api"
+members = ["api-deploy"]
+permissions = { virtual_host = "write" }
+virtual_hosts = ["api"]
+EOF
+
+"$RBAC_BIN" -c "$RBAC_DIR/config" > "$RBAC_DIR/server.log" 2>&1 3<&- &
+RBAC_PID=$!
+for _ in $(seq 1 50); do
+    curl -s -o /dev/null "$RBAC_API/" && break
+    sleep 0.1
+done
+
+# api_call <token> <method> <path> [body]: response body in API_BODY, status in API_STATUS
+api_call() {
+    local token=$1 method=$2 path=$3 body=${4:-}
+    local output
+    output=$(mktemp)
+    API_STATUS=$(curl -s -o "$output" -w "%{http_code}" -X "$method" \
+        -H "Authorization: Bearer $token" -H "Content-Type: application/json" \
+        ${body:+-d "$body"} "$RBAC_API$path")
+    API_BODY=$(cat "$output")
+    rm -f "$output"
+}
+
+assert_code() {
+    local name=$1 expected=$2 actual=$3
+    if [ "$actual" = "$expected" ]; then
+        log_pass "$name"
+    else
+        log_fail "$name (expected: $expected, got: $actual)"
+    fi
+}
+
+vhost() {
+    echo "{\"name\": \"$1\", \"domains\": [\"$2\"], \"routes\": [{\"match\": {\"prefix\": \"/\"},
+        \"type\": \"direct\", \"status\": 200, \"body\": \"$3\"}]}"
+}
+
+# --- Unrestricted admin ---
+api_call root-token POST /v1/discovery:vhosts "{\"resources\": [{\"virtual_hosts\": [
+    $(vhost api api.local "api v1"), $(vhost www-site www.local "www v1"),
+    $(vhost shop eu.shop.local "shop v1")]}]}"
+assert_code "Admin updates all virtual hosts" "200" "$API_STATUS"
+
+# --- Resource type permissions ---
+api_call web-token GET /v1/discovery:routes
+assert_code "Read access to routes" "200" "$API_STATUS"
+api_call web-token POST /v1/discovery:routes '{"resources": [{"index_files": ["index.html"]}]}'
+assert_code "No write access to routes" "403" "$API_STATUS"
+assert_json_field "Forbidden update NACK" "$API_BODY" ".status" "NACK"
+api_call web-token GET /v1/discovery:listeners
+assert_code "No read access to listeners" "403" "$API_STATUS"
+api_call web-token POST /v1/discovery:listeners \
+    '{"resources": [{"main_server": {"host": "0.0.0.0", "port": 8280}}]}'
+assert_code "No write access to listeners" "403" "$API_STATUS"
+api_call web-token DELETE /v1/state
+assert_code "No access to DELETE /v1/state" "403" "$API_STATUS"
+api_call root-token DELETE /v1/state
+assert_code "Admin clears the state" "200" "$API_STATUS"
+
+# --- Snapshot filtered to the caller's scope ---
+api_call web-token GET /v1/discovery
+assert_json_field "Snapshot lists readable types" "$API_BODY" \
+    ".resources | keys | join(\",\")" "route,virtual_hosts"
+assert_json_field "Snapshot lists virtual hosts in scope" "$API_BODY" \
+    "[.resources.virtual_hosts.value[].name] | join(\",\")" "www-site,shop"
+api_call web-token GET /v1/discovery:vhosts
+assert_json_field "Virtual host discovery lists virtual hosts in scope" "$API_BODY" \
+    "[.resources[0].virtual_hosts[].name] | join(\",\")" "www-site,shop"
+api_call stranger-token GET /v1/discovery
+assert_json_field "Identity without roles sees no resources" "$API_BODY" \
+    ".resources | length" "0"
+api_call stranger-token GET /v1/discovery:vhosts
+assert_code "Identity without roles is denied" "403" "$API_STATUS"
+
+# --- Virtual host scope on updates ---
+api_call web-token POST /v1/discovery:vhosts "{\"resources\": [{\"virtual_hosts\": [
+    $(vhost www-site www.local "www v2")]}]}"
+assert_code "Update of a virtual host in scope" "200" "$API_STATUS"
+assert_contains "Updated virtual host serves traffic" \
+    "$(curl -s -H "Host: www.local" "$RBAC_BASE/")" "www v2"
+assert_contains "Virtual host outside the scope is kept" \
+    "$(curl -s -H "Host: api.local" "$RBAC_BASE/")" "api v1"
+api_call root-token GET /v1/discovery:vhosts
+assert_json_field "Omitted virtual host in scope is removed" "$API_BODY" \
+    "[.resources[0].virtual_hosts[].name] | join(\",\")" "api,www-site"
+
+api_call web-token POST /v1/discovery:vhosts "{\"resources\": [{\"virtual_hosts\": [
+    $(vhost www-site www.local "www v3"), $(vhost shop us.shop.local "shop v2")]}]}"
+assert_code "New virtual host on a domain in scope" "200" "$API_STATUS"
+api_call web-token POST /v1/discovery:vhosts "{\"resources\": [{\"virtual_hosts\": [
+    $(vhost api api.local "api hijacked")]}]}"
+assert_code "Virtual host outside the scope is refused" "403" "$API_STATUS"
+api_call web-token POST /v1/discovery:vhosts "{\"resources\": [{\"virtual_hosts\": [
+    $(vhost catch-all "*" "everything")]}]}"
+assert_code "Catch-all domain outside the scope is refused" "403" "$API_STATUS"
+assert_contains "Refused update leaves traffic unchanged" \
+    "$(curl -s -H "Host: api.local" "$RBAC_BASE/")" "api v1"
+
+# --- History filtered to the caller's scope ---
+api_call web-token GET /v1/history:listeners
+assert_code "No read access to the listener history" "403" "$API_STATUS"
+api_call web-token GET /v1/history:vhosts
+assert_json_field "History lists virtual hosts in scope" "$API_BODY" \
+    "[.entries[0].value.virtual_hosts[].name] | join(\",\")" "www-site,shop"
+
+# --- Runtime counters need the stats permission ---
+api_call web-token GET /v1/stats
+assert_code "No read access to the stats" "403" "$API_STATUS"
+api_call root-token GET /v1/stats
+assert_code "Stats readable with the stats permission" "200" "$API_STATUS"
+
+# --- Audit records carry the caller ---
+api_call web-token GET /v1/audit
+assert_code "No read access to the audit log" "403" "$API_STATUS"
+api_call root-token GET "/v1/audit?limit=3"
+assert_json_field "Denied update audited with its identity" "$API_BODY" \
+    "[.entries[0].identity, .entries[0].status] | join(\" \")" "web-deploy 403"
+assert_json_field "Accepted update in scope audited" "$API_BODY" \
+    "[.entries[2].identity, .entries[2].result] | join(\" \")" "web-deploy ACK"
+
+# --- Concurrent updates by two teams are both kept ---
+for i in $(seq 1 10); do
+    api_call web-token POST /v1/discovery:vhosts "{\"resources\": [{\"virtual_hosts\": [
+        $(vhost www-site www.local "www r$i"), $(vhost shop us.shop.local "shop v2")]}]}" &
+    WEB_JOB=$!
+    api_call api-token POST /v1/discovery:vhosts "{\"resources\": [{\"virtual_hosts\": [
+        $(vhost api api.local "api r$i")]}]}" &
+    wait "$WEB_JOB" "$!"
+done
+assert_contains "Concurrent update of one team kept" \
+    "$(curl -s -H "Host: www.local" "$RBAC_BASE/")" "www r10"
+assert_contains "Concurrent update of another team kept" \
+    "$(curl -s -H "Host: api.local" "$RBAC_BASE/")" "api r10"
+
+if grep -q "API access denied: 'web-deploy' has no write access to LISTENER" "$RBAC_DIR/server.log"; then
+    log_pass "Denied requests logged"
+else
+    log_fail "Denied requests logged"
+fi
+
+kill "$RBAC_PID" 2>/dev/null
+wait "$RBAC_PID" 2>/dev/null
+
+# --- Invalid roles do not start ---
+sed -i 's/^virtual_hosts = \["www-site"\]/virtual_hosts = ["*-site"]/' "$RBAC_DIR/config.toml"
+timeout 5 "$RBAC_BIN" -c "$RBAC_DIR/config" > "$RBAC_DIR/invalid.log" 2>&1 3<&-
+if grep -q "unsupported virtual host pattern" "$RBAC_DIR/invalid.log"; then
+    log_pass "Invalid role pattern refused at startup"
+else
+    log_fail "Invalid role pattern refused at startup"
+fi
+
+rm -rf "$RBAC_DIR"
+
+log_info "API access control tests completed"
@@ -0,0 +1,100 @@
+#!/bin/bash
+# Configuration Audit Log Tests
+# This script is called by integration_tests.sh, common.sh is already loaded
+# A second server with logging.audit_log_file runs on ports 8480/8400.
+
+log_section "37. Audit Log"
+
+AUDIT_DIR=$(mktemp -d)
+AUDIT_FILE="$AUDIT_DIR/audit.log"
+AUDIT_BIN=$(readlink -f "/proc/$SERVER_PID/exe" 2>/dev/null || echo ./target/release/rust_webserver)
+AUDIT_API="http://127.0.0.1:8400"
+
+sed -e 's/^port = 8080/port = 8480/' -e 's/^api_port = 8000/api_port = 8400/' \
+    -e "/^\[logging\]/a audit_log_file = \"$AUDIT_FILE\"" config.toml > "$AUDIT_DIR/config.toml"
+"$AUDIT_BIN" -c "$AUDIT_DIR/config" > "$AUDIT_DIR/server.log" 2>&1 3<&- &
+AUDIT_PID=$!
+for _ in $(seq 1 50); do
+    curl -s -o /dev/null "$AUDIT_API/" && break
+    sleep 0.1
+done
+
+post_resource() {
+    curl -s -X POST "$AUDIT_API/v1/discovery:$1" -H "Content-Type: application/json" -d "$2"
+}
+
+# --- Logging updates leave the audit file in place ---
+LOGGING_CONFIG=$(curl -s "$AUDIT_API/v1/discovery:logging" | jq -c '.resources[0]')
+assert_json_field "Audit file path not exposed" "$LOGGING_CONFIG" ".audit_log_file" "null"
+UPDATE_RESPONSE=$(post_resource logging "{\"resources\": [$LOGGING_CONFIG]}")
+assert_json_field "Logging update without audit_log_file ACK" "$UPDATE_RESPONSE" ".status" "ACK"
+UPDATE_RESPONSE=$(post_resource logging \
+    "{\"resources\": [$(echo "$LOGGING_CONFIG" | jq -c ".audit_log_file = \"$AUDIT_DIR/other.log\"")]}")
+assert_json_field "Audit file change refused" "$UPDATE_RESPONSE" ".status" "NACK"
+if [ -s "$AUDIT_FILE" ] && [ ! -e "$AUDIT_DIR/other.log" ]; then
+    log_pass "Audit file kept after logging updates"
+else
+    log_fail "Audit file kept after logging updates"
+fi
+
+# --- Accepted update ---
+HTTP_CONFIG=$(curl -s "$AUDIT_API/v1/discovery:http")
+OLD_VERSION=$(echo "$HTTP_CONFIG" | jq -r '.version_info')
+HTTP_RESOURCE=$(echo "$HTTP_CONFIG" | jq -c '.resources[0]')
+UPDATE_RESPONSE=$(post_resource http \
+    "{\"version_info\": \"$OLD_VERSION\", \"resources\": [$(echo "$HTTP_RESOURCE" | jq -c '.server_name = "audited/1.0"')]}")
+assert_json_field "Audited update ACK" "$UPDATE_RESPONSE" ".status" "ACK"
+NEW_VERSION=$(echo "$UPDATE_RESPONSE" | jq -r '.version_info')
+
+# --- Rejected updates ---
+UPDATE_RESPONSE=$(post_resource http \
+    "{\"version_info\": \"$OLD_VERSION\", \"resources\": [$HTTP_RESOURCE]}")
+assert_json_field "Stale version NACK" "$UPDATE_RESPONSE" ".status" "NACK"
+post_resource vhosts '{"resources": [{"virtual_hosts": [{"name": "", "domains": ["*"]}]}]}' > /dev/null
+post_resource routes 'not json' > /dev/null
+
+# --- Paging ---
+PAGE=$(curl -s "$AUDIT_API/v1/audit?limit=3")
+assert_json_field "Newest record first" "$PAGE" ".entries[0].error | startswith(\"Invalid JSON\")" "true"
+assert_json_field "Page size" "$PAGE" ".entries | length" "3"
+assert_json_field "Rejected update status" "$PAGE" ".entries[1].status" "400"
+assert_json_field "Rejected update diff" "$PAGE" \
+    ".entries[1].diff[0] | [.op, .path] | join(\" \")" "add /virtual_hosts/0"
+assert_json_field "Conflict recorded" "$PAGE" ".entries[2].status" "409"
+assert_json_field "Conflict result" "$PAGE" ".entries[2].result" "NACK"
+NEXT=$(echo "$PAGE" | jq -r '.next_before')
+PAGE=$(curl -s "$AUDIT_API/v1/audit?limit=1&before=$NEXT")
+assert_json_field "Next page continues" "$PAGE" ".entries[0].id" "$((NEXT - 1))"
+assert_json_field "Accepted update result" "$PAGE" ".entries[0].result" "ACK"
+assert_json_field "Resource type" "$PAGE" ".entries[0].resource_type" "HTTP"
+assert_json_field "Old version" "$PAGE" ".entries[0].old_version" "$OLD_VERSION"
+assert_json_field "New version" "$PAGE" ".entries[0].new_version" "$NEW_VERSION"
+assert_json_field "Caller identity" "$PAGE" ".entries[0].identity" "anonymous"
+assert_json_field "Source IP" "$PAGE" ".entries[0].source_ip" "127.0.0.1"
+assert_json_field "JSON diff of the change" "$PAGE" \
+    ".entries[0].diff | map(select(.path == \"/server_name\"))[0].value" "audited/1.0"
+
+INVALID=$(curl -s -o /dev/null -w "%{http_code}" "$AUDIT_API/v1/audit?limit=abc")
+if [ "$INVALID" = "400" ]; then
+    log_pass "Invalid page parameters rejected"
+else
+    log_fail "Invalid page parameters rejected (got: $INVALID)"
+fi
+
+# --- Audit log file ---
+curl -s -X DELETE "$AUDIT_API/v1/state" > /dev/null
+LAST=$(tail -n 1 "$AUDIT_FILE")
+assert_json_field "State clear written to the audit file" "$LAST" \
+    "[.method, .path, .resource_type, .result] | join(\" \")" "DELETE /v1/state STATE ACK"
+LINES=$(wc -l < "$AUDIT_FILE")
+if [ "$LINES" -ge 7 ] && jq -e -s 'map(.id) | . == (. | sort)' "$AUDIT_FILE" > /dev/null; then
+    log_pass "Audit file has one JSON record per line ($LINES)"
+else
+    log_fail "Audit file has one JSON record per line ($LINES)"
+fi
+
+kill "$AUDIT_PID" 2>/dev/null
+wait "$AUDIT_PID" 2>/dev/null
+rm -rf "$AUDIT_DIR"
+
+log_info "Audit log tests completed"
@@ -0,0 +1,145 @@
+#!/bin/bash
+# Configuration History, Diff and Rollback Tests
+# This script is called by integration_tests.sh, common.sh is already loaded
+# A second server with state persistence runs on ports 8380/8300.
+
+log_section "38. Configuration History"
+
+post_resource() {
+    curl -s -X POST "$API_URL/v1/discovery:$1" -H "Content-Type: application/json" -d "$2"
+}
+
+rollback() {
+    curl -s -X POST "$API_URL/v1/rollback:$1" -H "Content-Type: application/json" ${2:+-d "$2"}
+}
+
+status_of() {
+    curl -s -o /dev/null -w "%{http_code}" "$@"
+}
+
+assert_code() {
+    local name=$1 expected=$2 actual=$3
+    if [ "$actual" = "$expected" ]; then
+        log_pass "$name"
+    else
+        log_fail "$name (expected: $expected, got: $actual)"
+    fi
+}
+
+# --- Two accepted updates ---
+HTTP_CONFIG=$(curl -s "$API_URL/v1/discovery:http")
+V0=$(echo "$HTTP_CONFIG" | jq -r '.version_info')
+HTTP_RESOURCE=$(echo "$HTTP_CONFIG" | jq -c '.resources[0]')
+OLD_SERVER_NAME=$(echo "$HTTP_RESOURCE" | jq -r '.server_name')
+V1=$(post_resource http "{\"version_info\": \"$V0\", \"resources\": [$(echo "$HTTP_RESOURCE" \
+    | jq -c '.server_name = "history/1"')]}" | jq -r '.version_info')
+sleep 0.01
+V2=$(post_resource http "{\"version_info\": \"$V1\", \"resources\": [$(echo "$HTTP_RESOURCE" \
+    | jq -c '.server_name = "history/2"')]}" | jq -r '.version_info')
+
+HISTORY=$(curl -s "$API_URL/v1/history:http")
+assert_json_field "History version is the current version" "$HISTORY" ".version_info" "$V2"
+assert_json_field "Newest snapshot first" "$HISTORY" \
+    "[.entries[0].version_info, .entries[0].current] | join(\" \")" "$V2 true"
+assert_json_field "Snapshot value" "$HISTORY" ".entries[0].value.server_name" "history/2"
+assert_json_field "Snapshot caller" "$HISTORY" ".entries[0].identity" "anonymous"
+assert_json_field "Previous snapshot" "$HISTORY" \
+    "[.entries[1].version_info, .entries[1].current] | join(\" \")" "$V1 false"
+assert_json_field "Replaced configuration recorded" "$HISTORY" \
+    ".entries | map(select(.version_info == \"$V0\"))[0].value.server_name" "$OLD_SERVER_NAME"
+assert_code "Unknown history type" "404" "$(status_of "$API_URL/v1/history:nothing")"
+
+# --- Diff ---
+DIFF=$(curl -s "$API_URL/v1/diff?from=$V1&to=$V2")
+assert_json_field "Diff resource type" "$DIFF" ".resource_type" "HTTP"
+assert_json_field "Diff operations" "$DIFF" \
+    ".diff | map([.op, .path, .old, .value] | join(\" \")) | join(\",\")" \
+    "replace /server_name history/1 history/2"
+DIFF=$(curl -s "$API_URL/v1/diff?type=http&from=$V0")
+assert_json_field "Diff to the current version" "$DIFF" ".to" "$V2"
+assert_json_field "Diff from the replaced configuration" "$DIFF" \
+    ".diff | map(select(.path == \"/server_name\"))[0].value" "history/2"
+assert_code "Diff without from" "400" "$(status_of "$API_URL/v1/diff?to=$V2")"
+assert_code "Diff of an unknown version" "400" "$(status_of "$API_URL/v1/diff?from=1&to=$V2")"
+
+# --- Rollback ---
+RESPONSE=$(rollback "http?version=$V1" "{\"version_info\": \"$V1\"}")
+assert_json_field "Rollback with a stale version" "$RESPONSE" ".status" "NACK"
+assert_contains "Stale rollback conflict" "$RESPONSE" "Version conflict"
+RESPONSE=$(rollback "http?version=$V1" "{\"version_info\": \"$V2\"}")
+assert_json_field "Rollback ACK" "$RESPONSE" ".status" "ACK"
+V3=$(echo "$RESPONSE" | jq -r '.version_info')
+assert_json_field "Rolled back configuration" "$(curl -s "$API_URL/v1/discovery:http")" \
+    "[.version_info, .resources[0].server_name] | join(\" \")" "$V3 history/1"
+assert_json_field "Rollback recorded in the history" "$(curl -s "$API_URL/v1/history:http")" \
+    "[.entries[0].version_info, .entries[0].value.server_name] | join(\" \")" "$V3 history/1"
+assert_json_field "Rollback audited" "$(curl -s "$API_URL/v1/audit?limit=1")" \
+    "[.entries[0].path, .entries[0].result, .entries[0].old_version] | join(\" \")" \
+    "/v1/rollback:http ACK $V2"
+RESPONSE=$(rollback "http?version=1")
+assert_json_field "Rollback to an unknown version" "$RESPONSE" ".status" "NACK"
+assert_code "Rollback without version" "400" \
+    "$(status_of -X POST "$API_URL/v1/rollback:http")"
+
+# --- Virtual host rollback serves traffic ---
+VHOSTS=$(curl -s "$API_URL/v1/discovery:vhosts" | jq -c '.resources[0]')
+VHOST_VERSION=$(curl -s "$API_URL/v1/discovery:vhosts" | jq -r '.version_info')
+vhost_update() {
+    echo "{\"resources\": [{\"virtual_hosts\": [{\"name\": \"history\",
+        \"domains\": [\"history.local\"], \"routes\": [{\"match\": {\"prefix\": \"/\"},
+        \"type\": \"direct\", \"status\": 200, \"body\": \"$1\"}]}]}]}"
+}
+GOOD=$(post_resource vhosts "$(vhost_update "good release")" | jq -r '.version_info')
+sleep 0.01
+post_resource vhosts "$(vhost_update "bad release")" > /dev/null
+assert_contains "Bad release served" "$(curl -s -H "Host: history.local" "$BASE_URL/")" \
+    "bad release"
+rollback "vhosts?version=$GOOD" > /dev/null
+assert_contains "Rolled back virtual host served" \
+    "$(curl -s -H "Host: history.local" "$BASE_URL/")" "good release"
+rollback "vhosts?version=$VHOST_VERSION" > /dev/null
+assert_json_field "Rollback to the startup virtual hosts" \
+    "$(curl -s "$API_URL/v1/discovery:vhosts")" ".resources[0] | tojson" "$VHOSTS"
+
+# Restore
+HTTP_RESOURCE=$(curl -s "$API_URL/v1/discovery:http" | jq -c '.resources[0]')
+post_resource http \
+    "{\"resources\": [$(echo "$HTTP_RESOURCE" | jq -c ".server_name = \"$OLD_SERVER_NAME\"")]}" > /dev/null
+
+# --- History persisted next to state.toml ---
+HISTORY_DIR=$(mktemp -d)
+HISTORY_BIN=$(readlink -f "/proc/$SERVER_PID/exe" 2>/dev/null || echo ./target/release/rust_webserver)
+HISTORY_API="http://127.0.0.1:8300"
+sed -e 's/^port = 8080/port = 8380/' -e 's/^api_port = 8000/api_port = 8300/' \
+    -e '/^\[server\]/a enable_state_persistence = true' config.toml > "$HISTORY_DIR/config.toml"
+
+start_history_server() {
+    "$HISTORY_BIN" -c "$HISTORY_DIR/config" >> "$HISTORY_DIR/server.log" 2>&1 3<&- &
+    HISTORY_PID=$!
+    for _ in $(seq 1 50); do
+        curl -s -o /dev/null "$HISTORY_API/" && break
+        sleep 0.1
+    done
+}
+
+start_history_server
+HTTP_RESOURCE=$(curl -s "$HISTORY_API/v1/discovery:http" | jq -c '.resources[0]')
+PERSISTED=$(curl -s -X POST "$HISTORY_API/v1/discovery:http" -H "Content-Type: application/json" \
+    -d "{\"resources\": [$(echo "$HTTP_RESOURCE" | jq -c '.server_name = "persisted/1"')]}" \
+    | jq -r '.version_info')
+if [ -f "$HISTORY_DIR/history.json" ]; then
+    log_pass "History written to history.json"
+else
+    log_fail "History written to history.json"
+fi
+kill "$HISTORY_PID" 2>/dev/null
+wait "$HISTORY_PID" 2>/dev/null
+
+start_history_server
+assert_json_field "History kept across restarts" "$(curl -s "$HISTORY_API/v1/history:http")" \
+    ".entries | map(select(.version_info == \"$PERSISTED\"))[0].value.server_name" "persisted/1"
+kill "$HISTORY_PID" 2>/dev/null
+wait "$HISTORY_PID" 2>/dev/null
+rm -rf "$HISTORY_DIR"
+
+log_info "Configuration history tests completed"
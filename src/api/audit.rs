@@ -0,0 +1,281 @@
+// API audit module
+// Append-only records of configuration changes, written to the audit log file
+// and kept in memory for `GET /v1/audit`
+
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::net::IpAddr;
+use std::sync::Mutex;
+
+use crate::logger;
+
+/// Records kept in memory for `GET /v1/audit`
+const AUDIT_LOG_ENTRIES: usize = 1000;
+
+/// Result of an audited request
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum AuditResult {
+    #[serde(rename = "ACK")]
+    Ack,
+    #[serde(rename = "NACK")]
+    Nack,
+}
+
+/// One configuration change request (accepted or rejected)
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditRecord {
+    /// Sequence number, increasing from 1 since startup
+    pub id: u64,
+    /// RFC 3339 time of the request
+    pub timestamp: String,
+    /// Token name, user name or certificate subject of the caller
+    pub identity: String,
+    pub source_ip: IpAddr,
+    pub method: String,
+    pub path: String,
+    /// Resource type (`VIRTUAL_HOST`, ...) or `STATE`
+    pub resource_type: String,
+    /// Resource version before the request
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub old_version: Option<String>,
+    /// Resource version after an accepted update
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub new_version: Option<String>,
+    /// Changes as JSON Patch operations; for a rejected update, the changes it asked for
+    pub diff: Vec<Value>,
+    pub result: AuditResult,
+    /// HTTP status of the response
+    pub status: u16,
+    /// Reason of a NACK
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl AuditRecord {
+    /// Record of a request, rejected until its result is set
+    pub fn new(
+        identity: &str,
+        source_ip: IpAddr,
+        method: &str,
+        path: &str,
+        resource_type: &str,
+    ) -> Self {
+        Self {
+            id: 0,
+            timestamp: chrono::Local::now().to_rfc3339(),
+            identity: identity.to_string(),
+            source_ip,
+            method: method.to_string(),
+            path: path.to_string(),
+            resource_type: resource_type.to_string(),
+            old_version: None,
+            new_version: None,
+            diff: Vec::new(),
+            result: AuditResult::Nack,
+            status: 400,
+            error: None,
+        }
+    }
+}
+
+/// Page of audit records, newest first
+#[derive(Debug, Serialize)]
+pub struct AuditPage {
+    pub entries: Vec<AuditRecord>,
+    /// `before` value of the next (older) page, if any
+    pub next_before: Option<u64>,
+}
+
+/// Audit trail of the management API
+pub struct AuditLog {
+    /// Next record id and the most recent records (oldest first)
+    records: Mutex<(u64, VecDeque<AuditRecord>)>,
+}
+
+impl AuditLog {
+    pub const fn new() -> Self {
+        Self {
+            records: Mutex::new((1, VecDeque::new())),
+        }
+    }
+
+    /// Assign the record an id, append it to the audit log file and keep it in memory
+    pub fn record(&self, mut record: AuditRecord) {
+        let mut records = self.records.lock().unwrap();
+        let (next_id, entries) = &mut *records;
+        record.id = *next_id;
+        *next_id += 1;
+        // Written under the lock so the file keeps id order
+        match serde_json::to_string(&record) {
+            Ok(line) => logger::write_audit(&line),
+            Err(e) => logger::log_error(&format!("Failed to serialize audit record: {e}")),
+        }
+        if entries.len() == AUDIT_LOG_ENTRIES {
+            entries.pop_front();
+        }
+        entries.push_back(record);
+        drop(records);
+    }
+
+    /// Up to `limit` records older than `before` (the newest when `None`), newest first
+    pub fn page(&self, before: Option<u64>, limit: usize) -> AuditPage {
+        let records = self.records.lock().unwrap();
+        let mut older = records
+            .1
+            .iter()
+            .rev()
+            .filter(|record| before.is_none_or(|before| record.id < before));
+        let entries: Vec<AuditRecord> = older.by_ref().take(limit).cloned().collect();
+        let next_before = match (older.next(), entries.last()) {
+            (Some(_), Some(last)) => Some(last.id),
+            _ => None,
+        };
+        drop(records);
+        AuditPage {
+            entries,
+            next_before,
+        }
+    }
+}
+
+impl Default for AuditLog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Differences between two JSON values as JSON Patch (RFC 6902) operations
+///
+/// Objects are compared by key and arrays by index. `remove` and `replace`
+/// operations also carry the previous value in `old`.
+pub fn json_diff(old: &Value, new: &Value) -> Vec<Value> {
+    let mut operations = Vec::new();
+    diff_into(&mut operations, "", old, new);
+    operations
+}
+
+fn diff_into(operations: &mut Vec<Value>, path: &str, old: &Value, new: &Value) {
+    match (old, new) {
+        (Value::Object(old), Value::Object(new)) => {
+            for (key, old_value) in old {
+                let child = format!("{path}/{}", escape_pointer(key));
+                match new.get(key) {
+                    Some(new_value) => diff_into(operations, &child, old_value, new_value),
+                    None => operations.push(serde_json::json!({
+                        "op": "remove", "path": child, "old": old_value
+                    })),
+                }
+            }
+            for (key, new_value) in new {
+                if !old.contains_key(key) {
+                    operations.push(serde_json::json!({
+                        "op": "add", "path": format!("{path}/{}", escape_pointer(key)),
+                        "value": new_value
+                    }));
+                }
+            }
+        }
+        (Value::Array(old), Value::Array(new)) => {
+            for (index, (old_value, new_value)) in old.iter().zip(new).enumerate() {
+                diff_into(operations, &format!("{path}/{index}"), old_value, new_value);
+            }
+            for (index, new_value) in new.iter().enumerate().skip(old.len()) {
+                operations.push(serde_json::json!({
+                    "op": "add", "path": format!("{path}/{index}"), "value": new_value
+                }));
+            }
+            // Highest index first, so the operations apply in order
+            for (index, old_value) in old.iter().enumerate().skip(new.len()).rev() {
+                operations.push(serde_json::json!({
+                    "op": "remove", "path": format!("{path}/{index}"), "old": old_value
+                }));
+            }
+        }
+        _ if old != new => operations.push(serde_json::json!({
+            "op": "replace", "path": path, "old": old, "value": new
+        })),
+        _ => {}
+    }
+}
+
+/// Escape a key for a JSON Pointer (RFC 6901)
+fn escape_pointer(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn record(identity: &str) -> AuditRecord {
+        let localhost = IpAddr::from([127, 0, 0, 1]);
+        AuditRecord::new(identity, localhost, "POST", "/v1/discovery:http", "HTTP")
+    }
+
+    #[test]
+    fn test_json_diff() {
+        let old = json!({
+            "port": 8080,
+            "index_files": ["index.html", "index.htm"],
+            "cors": {"a/b": true},
+            "gone": null
+        });
+        let new = json!({
+            "port": 9090,
+            "index_files": ["index.html"],
+            "cors": {"a/b": true},
+            "added": {"x": 1}
+        });
+        assert_eq!(
+            json_diff(&old, &new),
+            vec![
+                json!({"op": "remove", "path": "/gone", "old": null}),
+                json!({"op": "remove", "path": "/index_files/1", "old": "index.htm"}),
+                json!({"op": "replace", "path": "/port", "old": 8080, "value": 9090}),
+                json!({"op": "add", "path": "/added", "value": {"x": 1}}),
+            ]
+        );
+        assert!(json_diff(&old, &old).is_empty());
+        assert_eq!(
+            json_diff(&json!({"k~/": [1]}), &json!({"k~/": [1, 2]})),
+            vec![json!({"op": "add", "path": "/k~0~1/1", "value": 2})]
+        );
+        assert_eq!(
+            json_diff(&json!({"a": 1}), &Value::Null),
+            vec![json!({"op": "replace", "path": "", "old": {"a": 1}, "value": null})]
+        );
+    }
+
+    #[test]
+    fn test_pages() {
+        let log = AuditLog::new();
+        for i in 0..5 {
+            log.record(record(&format!("caller-{i}")));
+        }
+
+        let page = log.page(None, 2);
+        let ids: Vec<u64> = page.entries.iter().map(|r| r.id).collect();
+        assert_eq!(ids, [5, 4]);
+        assert_eq!(page.entries[0].identity, "caller-4");
+        assert_eq!(page.next_before, Some(4));
+
+        let page = log.page(Some(4), 3);
+        let ids: Vec<u64> = page.entries.iter().map(|r| r.id).collect();
+        assert_eq!(ids, [3, 2, 1]);
+        assert_eq!(page.next_before, None);
+        assert!(log.page(Some(1), 10).entries.is_empty());
+    }
+
+    #[test]
+    fn test_bounded() {
+        let log = AuditLog::new();
+        for _ in 0..AUDIT_LOG_ENTRIES + 3 {
+            log.record(record("deploy"));
+        }
+        let page = log.page(None, usize::MAX);
+        assert_eq!(page.entries.len(), AUDIT_LOG_ENTRIES);
+        assert_eq!(page.entries.last().map(|r| r.id), Some(4));
+    }
+}
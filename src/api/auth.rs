@@ -0,0 +1,554 @@
+// API authentication module
+// Bearer tokens (SHA-256 hashes in config), Basic auth against an htpasswd file
+// and TLS client certificates for the management API
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use hyper::header::AUTHORIZATION;
+use hyper::Request;
+use md5::Md5;
+use serde::Serialize;
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio_rustls::TlsAcceptor;
+
+use super::rbac::{self, Scope};
+use crate::config::{ApiAuthConfig, ApiRoleConfig};
+use crate::server::tls::{self, ClientCertificate};
+
+/// How a caller authenticated
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthMethod {
+    /// Authentication is not configured
+    Anonymous,
+    Bearer,
+    Basic,
+    ClientCertificate,
+}
+
+impl fmt::Display for AuthMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Anonymous => "anonymous",
+            Self::Bearer => "bearer",
+            Self::Basic => "basic",
+            Self::ClientCertificate => "client_certificate",
+        })
+    }
+}
+
+/// Authenticated caller of the management API, added to the request's extensions
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Identity {
+    /// Token name, user name or certificate subject
+    pub name: String,
+    pub method: AuthMethod,
+}
+
+/// Authentication counters, as reported by `GET /v1/stats`
+#[derive(Debug, Default, Serialize, PartialEq, Eq)]
+pub struct ApiAuthStats {
+    pub enabled: bool,
+    /// Requests with valid credentials
+    pub authenticated: u64,
+    /// Requests answered 401
+    pub failures: u64,
+}
+
+/// Credentials accepted by the management API
+#[derive(Default)]
+pub struct ApiAuth {
+    enabled: bool,
+    realm: String,
+    /// Token name and SHA-256 of the token
+    tokens: Vec<(String, [u8; 32])>,
+    /// htpasswd users and their password hashes
+    users: HashMap<String, String>,
+    tls: Option<TlsAcceptor>,
+    client_certificates: bool,
+    /// Access control roles (full access for every identity when empty)
+    roles: Vec<Arc<ApiRoleConfig>>,
+    authenticated: AtomicU64,
+    failures: AtomicU64,
+}
+
+impl ApiAuth {
+    /// No authentication (`api_auth` not configured)
+    pub fn disabled() -> Self {
+        Self::default()
+    }
+
+    /// Load tokens, the htpasswd file and TLS settings
+    pub fn load(config: Option<&ApiAuthConfig>) -> Result<Self, String> {
+        let Some(config) = config else {
+            return Ok(Self::disabled());
+        };
+
+        let mut tokens = Vec::new();
+        for token in &config.tokens {
+            if token.name.is_empty() {
+                return Err("api_auth.tokens: name must not be empty".to_string());
+            }
+            let hash = parse_sha256(&token.sha256).ok_or_else(|| {
+                format!(
+                    "api_auth.tokens '{}': sha256 must be 64 hex digits",
+                    token.name
+                )
+            })?;
+            tokens.push((token.name.clone(), hash));
+        }
+
+        let users = match &config.htpasswd_file {
+            Some(path) => {
+                let content = std::fs::read_to_string(path)
+                    .map_err(|e| format!("api_auth.htpasswd_file '{path}': {e}"))?;
+                parse_htpasswd(&content)
+                    .map_err(|e| format!("api_auth.htpasswd_file '{path}': {e}"))?
+            }
+            None => HashMap::new(),
+        };
+
+        let tls = config.tls.as_ref().map(tls::build_acceptor).transpose()?;
+        let client_certificates = config
+            .tls
+            .as_ref()
+            .is_some_and(|tls| tls.client_ca_file.is_some());
+
+        rbac::validate_roles(&config.roles)?;
+
+        if tokens.is_empty() && users.is_empty() && !client_certificates {
+            return Err(
+                "api_auth: configure tokens, htpasswd_file or tls.client_ca_file".to_string(),
+            );
+        }
+
+        Ok(Self {
+            enabled: true,
+            realm: config.realm.clone(),
+            tokens,
+            users,
+            tls,
+            client_certificates,
+            roles: config.roles.iter().cloned().map(Arc::new).collect(),
+            authenticated: AtomicU64::new(0),
+            failures: AtomicU64::new(0),
+        })
+    }
+
+    pub const fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Check that the API may listen on `host`
+    ///
+    /// Without `api_auth`, only loopback addresses are allowed: anyone who can
+    /// reach the port could otherwise rewrite the configuration.
+    pub fn check_listener(&self, host: &str) -> Result<(), String> {
+        let loopback = host.parse::<std::net::IpAddr>().map_or_else(
+            |_| host.eq_ignore_ascii_case("localhost"),
+            |ip| ip.is_loopback(),
+        );
+        if self.enabled || loopback {
+            Ok(())
+        } else {
+            Err(format!(
+                "API server on '{host}' requires authentication: configure [api_auth] \
+                 or listen on a loopback address"
+            ))
+        }
+    }
+
+    /// TLS acceptor of the API listener (when `api_auth.tls` is set)
+    pub const fn tls_acceptor(&self) -> Option<&TlsAcceptor> {
+        self.tls.as_ref()
+    }
+
+    /// Authenticate a request
+    ///
+    /// An `Authorization` header must be valid when present; without one, a
+    /// verified client certificate identifies the caller.
+    pub fn authenticate<B>(&self, req: &Request<B>) -> Result<Identity, String> {
+        if !self.enabled {
+            return Ok(Identity {
+                name: "anonymous".to_string(),
+                method: AuthMethod::Anonymous,
+            });
+        }
+        let result = self.check_credentials(req);
+        let counter = if result.is_ok() {
+            &self.authenticated
+        } else {
+            &self.failures
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        result
+    }
+
+    fn check_credentials<B>(&self, req: &Request<B>) -> Result<Identity, String> {
+        if let Some(header) = req.headers().get(AUTHORIZATION) {
+            let header = header
+                .to_str()
+                .map_err(|_| "invalid Authorization header".to_string())?;
+            let (scheme, credentials) = header.split_once(' ').unwrap_or((header, ""));
+            let credentials = credentials.trim();
+            return if scheme.eq_ignore_ascii_case("bearer") {
+                self.check_token(credentials)
+            } else if scheme.eq_ignore_ascii_case("basic") {
+                self.check_basic(credentials)
+            } else {
+                Err(format!("unsupported authorization scheme '{scheme}'"))
+            };
+        }
+
+        if self.client_certificates {
+            if let Some(cert) = req.extensions().get::<ClientCertificate>() {
+                return Ok(Identity {
+                    name: cert.subject.clone(),
+                    method: AuthMethod::ClientCertificate,
+                });
+            }
+        }
+        Err("missing credentials".to_string())
+    }
+
+    fn check_token(&self, token: &str) -> Result<Identity, String> {
+        let hash: [u8; 32] = Sha256::digest(token.as_bytes()).into();
+        self.tokens
+            .iter()
+            .find(|(_, expected)| constant_time_eq(&hash, expected))
+            .map(|(name, _)| Identity {
+                name: name.clone(),
+                method: AuthMethod::Bearer,
+            })
+            .ok_or_else(|| "invalid bearer token".to_string())
+    }
+
+    fn check_basic(&self, credentials: &str) -> Result<Identity, String> {
+        let decoded = BASE64
+            .decode(credentials)
+            .ok()
+            .and_then(|bytes| String::from_utf8(bytes).ok())
+            .ok_or_else(|| "malformed Basic credentials".to_string())?;
+        let (user, password) = decoded
+            .split_once(':')
+            .ok_or_else(|| "malformed Basic credentials".to_string())?;
+        match self.users.get(user) {
+            Some(hash) if verify_password(password, hash) => Ok(Identity {
+                name: user.to_string(),
+                method: AuthMethod::Basic,
+            }),
+            Some(_) => Err(format!("wrong password for user '{user}'")),
+            None => {
+                // Hash anyway, so the response time does not reveal which users exist
+                if let Some(dummy) = self.users.values().next() {
+                    std::hint::black_box(verify_password(password, dummy));
+                }
+                Err(format!("unknown user '{user}'"))
+            }
+        }
+    }
+
+    /// What `identity` may read or change, from the roles listing it as a member
+    pub fn scope(&self, identity: &Identity) -> Scope {
+        if self.roles.is_empty() {
+            return Scope::unrestricted(&identity.name);
+        }
+        let roles = self
+            .roles
+            .iter()
+            .filter(|role| role.members.contains(&identity.name))
+            .cloned()
+            .collect();
+        Scope::new(&identity.name, roles)
+    }
+
+    /// `WWW-Authenticate` challenges of a 401 response
+    ///
+    /// The Basic challenge is left out for script requests (`X-Requested-With`),
+    /// so browsers do not show their own login prompt over the dashboard's.
+    pub fn challenges<B>(&self, req: &Request<B>) -> Vec<String> {
+        let mut challenges = vec![format!("Bearer realm=\"{}\"", self.realm)];
+        if !self.users.is_empty() && !req.headers().contains_key("x-requested-with") {
+            challenges.push(format!("Basic realm=\"{}\", charset=\"UTF-8\"", self.realm));
+        }
+        challenges
+    }
+
+    pub fn stats(&self) -> ApiAuthStats {
+        ApiAuthStats {
+            enabled: self.enabled,
+            authenticated: self.authenticated.load(Ordering::Relaxed),
+            failures: self.failures.load(Ordering::Relaxed),
+        }
+    }
+}
+
+fn parse_sha256(hex: &str) -> Option<[u8; 32]> {
+    if hex.len() != 64 || !hex.is_ascii() {
+        return None;
+    }
+    let mut hash = [0u8; 32];
+    for (i, byte) in hash.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).ok()?;
+    }
+    Some(hash)
+}
+
+/// Parse `user:hash` lines (blank lines and `#` comments are skipped)
+fn parse_htpasswd(content: &str) -> Result<HashMap<String, String>, String> {
+    let mut users = HashMap::new();
+    for (number, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (user, hash) = line
+            .split_once(':')
+            .ok_or_else(|| format!("line {}: expected user:hash", number + 1))?;
+        if !is_supported_hash(hash) {
+            return Err(format!(
+                "line {}: unsupported hash for user '{user}' (use bcrypt, apr1, {{SHA}}, \
+                 $1$, $5$ or $6$)",
+                number + 1
+            ));
+        }
+        users.insert(user.to_string(), hash.to_string());
+    }
+    Ok(users)
+}
+
+fn is_supported_hash(hash: &str) -> bool {
+    [
+        "{SHA}", "$apr1$", "$2a$", "$2b$", "$2y$", "$1$", "$5$", "$6$",
+    ]
+    .iter()
+    .any(|prefix| hash.starts_with(prefix))
+}
+
+fn verify_password(password: &str, hash: &str) -> bool {
+    if let Some(digest) = hash.strip_prefix("{SHA}") {
+        let computed = BASE64.encode(Sha1::digest(password.as_bytes()));
+        constant_time_eq(computed.as_bytes(), digest.as_bytes())
+    } else if hash.starts_with("$apr1$") {
+        apr1_crypt(password, hash)
+            .is_some_and(|computed| constant_time_eq(computed.as_bytes(), hash.as_bytes()))
+    } else {
+        pwhash::unix::verify(password, hash)
+    }
+}
+
+/// Apache `$apr1$` hash (MD5-crypt with the `$apr1$` magic) of `password`
+/// with the salt of `hash`
+fn apr1_crypt(password: &str, hash: &str) -> Option<String> {
+    const MAGIC: &str = "$apr1$";
+    const ITOA64: &[u8] = b"./0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+    let rest = hash.strip_prefix(MAGIC)?;
+    let salt = rest.split('$').next()?;
+    // At most 8 characters (not bytes: the salt is not checked to be ASCII)
+    let salt = salt
+        .char_indices()
+        .nth(8)
+        .map_or(salt, |(end, _)| &salt[..end]);
+    let password = password.as_bytes();
+
+    let alternate = Md5::new()
+        .chain_update(password)
+        .chain_update(salt)
+        .chain_update(password)
+        .finalize();
+    let mut context = Md5::new()
+        .chain_update(password)
+        .chain_update(MAGIC)
+        .chain_update(salt);
+    for chunk in password.chunks(16) {
+        context.update(&alternate[..chunk.len()]);
+    }
+    let mut length = password.len();
+    while length > 0 {
+        if length & 1 == 1 {
+            context.update([0]);
+        } else {
+            context.update(&password[..1]);
+        }
+        length >>= 1;
+    }
+    let mut digest = context.finalize();
+
+    for round in 0..1000 {
+        let mut context = Md5::new();
+        if round & 1 == 1 {
+            context.update(password);
+        } else {
+            context.update(digest);
+        }
+        if round % 3 != 0 {
+            context.update(salt);
+        }
+        if round % 7 != 0 {
+            context.update(password);
+        }
+        if round & 1 == 1 {
+            context.update(digest);
+        } else {
+            context.update(password);
+        }
+        digest = context.finalize();
+    }
+
+    let mut encoded = format!("{MAGIC}{salt}$");
+    let groups = [(0, 6, 12), (1, 7, 13), (2, 8, 14), (3, 9, 15), (4, 10, 5)];
+    for (a, b, c) in groups {
+        let value = u32::from(digest[a]) << 16 | u32::from(digest[b]) << 8 | u32::from(digest[c]);
+        push_base64(&mut encoded, value, 4, ITOA64);
+    }
+    push_base64(&mut encoded, u32::from(digest[11]), 2, ITOA64);
+    Some(encoded)
+}
+
+fn push_base64(out: &mut String, mut value: u32, chars: usize, alphabet: &[u8]) {
+    for _ in 0..chars {
+        out.push(char::from(alphabet[(value & 0x3f) as usize]));
+        value >>= 6;
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn auth() -> ApiAuth {
+        let config: ApiAuthConfig = serde_json::from_value(serde_json::json!({
+            "tokens": [{
+                "name": "deploy",
+                // printf %s s3cr3t-token | sha256sum
+                "sha256": "fb07916a0e7daf7f3f4823b7773f85a839a8dd46fbf3858b8f53d3fa463c8ef3"
+            }]
+        }))
+        .unwrap();
+        let mut auth = ApiAuth::load(Some(&config)).unwrap();
+        auth.users = parse_htpasswd(
+            "# admins\nalice:$apr1$r31.....$G/cElGhD0cboYkZN5h5Ne/\nbob:{SHA}5en6G6MezRroT3XKqkdPOmY/BfQ=\n",
+        )
+        .unwrap();
+        auth
+    }
+
+    fn request(authorization: Option<&str>) -> Request<()> {
+        let mut builder = Request::builder().uri("/v1/discovery");
+        if let Some(value) = authorization {
+            builder = builder.header(AUTHORIZATION, value);
+        }
+        builder.body(()).unwrap()
+    }
+
+    fn basic(credentials: &str) -> String {
+        format!("Basic {}", BASE64.encode(credentials))
+    }
+
+    #[test]
+    fn test_bearer_and_basic() {
+        let auth = auth();
+        let identity = auth
+            .authenticate(&request(Some("Bearer s3cr3t-token")))
+            .unwrap();
+        assert_eq!(identity.name, "deploy");
+        assert_eq!(identity.method, AuthMethod::Bearer);
+        assert!(auth.authenticate(&request(Some("bearer wrong"))).is_err());
+
+        let identity = auth
+            .authenticate(&request(Some(&basic("alice:secret"))))
+            .unwrap();
+        assert_eq!(
+            (identity.name.as_str(), identity.method),
+            ("alice", AuthMethod::Basic)
+        );
+        assert!(auth
+            .authenticate(&request(Some(&basic("bob:secret"))))
+            .is_ok());
+        assert!(auth
+            .authenticate(&request(Some(&basic("bob:nope"))))
+            .is_err());
+        assert!(auth
+            .authenticate(&request(Some(&basic("carol:secret"))))
+            .is_err());
+        assert!(auth.authenticate(&request(None)).is_err());
+
+        let stats = auth.stats();
+        assert_eq!((stats.authenticated, stats.failures), (3, 4));
+    }
+
+    #[test]
+    fn test_client_certificate() {
+        let mut auth = auth();
+        let mut req = request(None);
+        req.extensions_mut().insert(ClientCertificate {
+            subject: "ops".to_string(),
+        });
+        // Certificates count only when a client CA is configured
+        assert!(auth.authenticate(&req).is_err());
+        auth.client_certificates = true;
+        let identity = auth.authenticate(&req).unwrap();
+        assert_eq!(identity.method, AuthMethod::ClientCertificate);
+        assert_eq!(identity.name, "ops");
+
+        // A wrong Authorization header is not rescued by the certificate
+        req.headers_mut()
+            .insert(AUTHORIZATION, "Bearer wrong".parse().unwrap());
+        assert!(auth.authenticate(&req).is_err());
+    }
+
+    #[test]
+    fn test_password_hashes() {
+        // openssl passwd -apr1 / -6, htpasswd -s, and bcrypt
+        assert!(verify_password(
+            "pässwörd long enough to pass 16",
+            "$apr1$abc$Bue3cZusE/uwbh/mrDG5J."
+        ));
+        assert!(!verify_password(
+            "secret",
+            "$apr1$abc$Bue3cZusE/uwbh/mrDG5J."
+        ));
+        assert!(verify_password(
+            "secret",
+            "$6$saltsalt$TVLlQcbpFVof5W3Yz4DTP6gRstiNuHwwTt6GLc1E5n0U0aDehy0S5knV8wiOQSpT0Y77vwPZN.Pq.H91p5hVO1"
+        ));
+        assert!(verify_password(
+            "password",
+            "$2y$05$bvIG6Nmid91Mu9RcmmWZfO5HJIMCT8riNW0hEp8f6/FuA2/mHZFpe"
+        ));
+        assert!(verify_password(
+            "secret",
+            "{SHA}5en6G6MezRroT3XKqkdPOmY/BfQ="
+        ));
+        // The salt is cut after 8 characters, not inside a multi-byte one
+        assert!(!verify_password(
+            "secret",
+            "$apr1$aéééé$Bue3cZusE/uwbh/mrDG5J."
+        ));
+        assert_eq!(
+            apr1_crypt("secret", "$apr1$aéééééééé$"),
+            apr1_crypt("secret", "$apr1$aééééééé$")
+        );
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        assert!(parse_htpasswd("alice:plaintext").is_err());
+        assert!(parse_htpasswd("no separator").is_err());
+        assert!(parse_sha256("abc").is_none());
+
+        let config: ApiAuthConfig = serde_json::from_value(serde_json::json!({})).unwrap();
+        assert!(ApiAuth::load(Some(&config)).is_err());
+        assert!(!ApiAuth::load(None).unwrap().is_enabled());
+    }
+}
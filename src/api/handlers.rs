@@ -2,23 +2,100 @@
 
 use http_body_util::Full;
 use hyper::body::Bytes;
-use hyper::{Request, Response, StatusCode};
+use hyper::{Method, Request, Response, StatusCode};
 use serde::Deserialize;
 use std::convert::Infallible;
+use std::net::SocketAddr;
 use std::sync::Arc;
 
-use super::response::{bad_request, conflict_response, json_response};
+use super::audit::{json_diff, AuditRecord, AuditResult};
+use super::rbac::{Scope, Target};
+use super::response::{bad_request, conflict_response, forbidden, json_response};
 use super::types::{
     DiscoveryResponse, ListenerResource, Resource, ResourceSnapshot, RouteResource, ServerEndpoint,
     SnapshotResponse, VersionedValue,
 };
 use super::updaters;
-use crate::config::{AppState, ResourceType};
+use crate::config::{ApiAccess, AppState, DynamicConfig, HistoryEntry, ResourceType};
 use crate::logger;
 
-/// Get all resources snapshot
-pub async fn handle_snapshot(state: Arc<AppState>) -> Result<Response<Full<Bytes>>, Infallible> {
-    let dynamic_config = state.dynamic_config.read().await;
+/// Resource types by the name used in API paths (`/v1/discovery:{name}`)
+const RESOURCE_NAMES: [(&str, ResourceType); 6] = [
+    ("listeners", ResourceType::Listener),
+    ("routes", ResourceType::Route),
+    ("http", ResourceType::Http),
+    ("logging", ResourceType::Logging),
+    ("performance", ResourceType::Performance),
+    ("vhosts", ResourceType::VirtualHost),
+];
+
+/// Resource type of an API path name (`vhosts`, ...)
+pub fn resource_type_by_name(name: &str) -> Option<ResourceType> {
+    RESOURCE_NAMES
+        .iter()
+        .find(|(n, _)| *n == name)
+        .map(|(_, resource_type)| *resource_type)
+}
+
+fn resource_name(resource_type: ResourceType) -> &'static str {
+    RESOURCE_NAMES
+        .iter()
+        .find(|(_, t)| *t == resource_type)
+        .map_or("", |(name, _)| name)
+}
+
+/// Answer a request outside the caller's roles
+#[allow(clippy::unnecessary_wraps)]
+fn deny(method: &str, path: &str, reason: &str) -> Result<Response<Full<Bytes>>, Infallible> {
+    logger::log_warning(&format!("API access denied: {reason} ({method} {path})"));
+    logger::log_api_request(method, path, 403);
+    Ok(forbidden(reason))
+}
+
+/// Audit a configuration change refused for lack of valid credentials
+///
+/// Only requests that would change the configuration are recorded:
+/// `POST /v1/discovery:{type}`, `POST /v1/rollback:{type}` and `DELETE /v1/state`.
+pub fn audit_unauthenticated(
+    state: &AppState,
+    method: &Method,
+    path: &str,
+    remote_addr: SocketAddr,
+    reason: &str,
+) {
+    let target = match (method, path.split_once(':')) {
+        (&Method::DELETE, None) if path == "/v1/state" => Target::State,
+        (&Method::POST, Some(("/v1/discovery" | "/v1/rollback", name))) => {
+            match resource_type_by_name(name) {
+                Some(resource_type) => Target::Resource(resource_type),
+                None => return,
+            }
+        }
+        _ => return,
+    };
+    let mut record = AuditRecord::new(
+        "unauthenticated",
+        remote_addr.ip(),
+        method.as_str(),
+        path,
+        &target.to_string(),
+    );
+    record.status = 401;
+    record.error = Some(reason.to_string());
+    state.audit_log.record(record);
+}
+
+/// Get all resources snapshot, limited to the types and virtual hosts the caller may read
+pub fn handle_snapshot(
+    state: &AppState,
+    scope: &Scope,
+) -> Result<Response<Full<Bytes>>, Infallible> {
+    let dynamic_config = state.snapshot();
+    let readable = |resource_type| {
+        scope
+            .check(Target::Resource(resource_type), ApiAccess::Read)
+            .is_ok()
+    };
 
     let (listener_ver, listener_nonce) = state.xds_versions.get_version(ResourceType::Listener);
     let (route_ver, route_nonce) = state.xds_versions.get_version(ResourceType::Route);
@@ -39,7 +116,7 @@ pub async fn handle_snapshot(state: Arc<AppState>) -> Result<Response<Full<Bytes
             )
         ),
         resources: ResourceSnapshot {
-            listener: VersionedValue {
+            listener: readable(ResourceType::Listener).then(|| VersionedValue {
                 version_info: listener_ver.to_string(),
                 nonce: listener_nonce.to_string(),
                 value: ListenerResource {
@@ -53,8 +130,8 @@ pub async fn handle_snapshot(state: Arc<AppState>) -> Result<Response<Full<Bytes
                     },
                     workers: state.config.server.workers,
                 },
-            },
-            route: VersionedValue {
+            }),
+            route: readable(ResourceType::Route).then(|| VersionedValue {
                 version_info: route_ver.to_string(),
                 nonce: route_nonce.to_string(),
                 value: RouteResource {
@@ -62,27 +139,27 @@ pub async fn handle_snapshot(state: Arc<AppState>) -> Result<Response<Full<Bytes
                     custom_routes: dynamic_config.routes.custom_routes.clone(),
                     health: dynamic_config.routes.health.clone(),
                 },
-            },
-            http: VersionedValue {
+            }),
+            http: readable(ResourceType::Http).then(|| VersionedValue {
                 version_info: http_ver.to_string(),
                 nonce: http_nonce.to_string(),
                 value: (*dynamic_config.http).clone(),
-            },
-            logging: VersionedValue {
+            }),
+            logging: readable(ResourceType::Logging).then(|| VersionedValue {
                 version_info: logging_ver.to_string(),
                 nonce: logging_nonce.to_string(),
                 value: dynamic_config.logging.clone(),
-            },
-            performance: VersionedValue {
+            }),
+            performance: readable(ResourceType::Performance).then(|| VersionedValue {
                 version_info: perf_ver.to_string(),
                 nonce: perf_nonce.to_string(),
                 value: dynamic_config.performance.clone(),
-            },
-            virtual_hosts: VersionedValue {
+            }),
+            virtual_hosts: readable(ResourceType::VirtualHost).then(|| VersionedValue {
                 version_info: vhost_ver.to_string(),
                 nonce: vhost_nonce.to_string(),
-                value: (*dynamic_config.virtual_hosts).clone(),
-            },
+                value: scope.visible_vhosts(&dynamic_config.virtual_hosts),
+            }),
         },
     };
 
@@ -90,10 +167,78 @@ pub async fn handle_snapshot(state: Arc<AppState>) -> Result<Response<Full<Bytes
     json_response(StatusCode::OK, &snapshot)
 }
 
+/// Get runtime counters (connection overload)
+pub fn handle_stats(state: &AppState, scope: &Scope) -> Result<Response<Full<Bytes>>, Infallible> {
+    if let Err(reason) = scope.check(Target::Stats, ApiAccess::Read) {
+        return deny("GET", "/v1/stats", &reason);
+    }
+    logger::log_api_request("GET", "/v1/stats", 200);
+
+    let response = serde_json::json!({
+        "connections": state.connection_limiter.stats(),
+        "rate_limits": state.rate_limiter.stats(),
+        "rate_limit_service": state.rate_limit_service.stats(),
+        "api_auth": state.api_auth.stats()
+    });
+
+    json_response(StatusCode::OK, &response)
+}
+
+/// JSON value of a resource type, as returned by `GET /v1/discovery:{type}`
+fn resource_value(
+    state: &AppState,
+    dynamic_config: &DynamicConfig,
+    resource_type: ResourceType,
+) -> serde_json::Value {
+    match resource_type {
+        ResourceType::Listener => {
+            let workers_value = match state.config.server.workers {
+                Some(n) => serde_json::json!(n.to_string()),
+                None => serde_json::json!("auto"),
+            };
+            serde_json::json!({
+                "main_server": {
+                    "host": dynamic_config.server.host,
+                    "port": dynamic_config.server.port
+                },
+                "api_server": {
+                    "host": dynamic_config.server.api_host,
+                    "port": dynamic_config.server.api_port
+                },
+                "workers": workers_value
+            })
+        }
+        ResourceType::Route => serde_json::json!({
+            "index_files": &dynamic_config.routes.index_files,
+            "custom_routes": &dynamic_config.routes.custom_routes,
+            "health": &dynamic_config.routes.health
+        }),
+        ResourceType::Http => serde_json::to_value(&*dynamic_config.http).unwrap_or_else(|e| {
+            logger::log_error(&format!("Failed to serialize HTTP config: {e}"));
+            serde_json::json!({"error": "serialization_failed"})
+        }),
+        ResourceType::Logging => {
+            serde_json::to_value(&dynamic_config.logging).unwrap_or_else(|e| {
+                logger::log_error(&format!("Failed to serialize logging config: {e}"));
+                serde_json::json!({"error": "serialization_failed"})
+            })
+        }
+        ResourceType::Performance => serde_json::to_value(&dynamic_config.performance)
+            .unwrap_or_else(|e| {
+                logger::log_error(&format!("Failed to serialize performance config: {e}"));
+                serde_json::json!({"error": "serialization_failed"})
+            }),
+        ResourceType::VirtualHost => serde_json::json!({
+            "virtual_hosts": &*dynamic_config.virtual_hosts
+        }),
+    }
+}
+
 /// GET method to fetch resources (simple query)
-pub async fn handle_discovery_get(
-    state: Arc<AppState>,
+pub fn handle_discovery_get(
+    state: &AppState,
     resource_type: ResourceType,
+    scope: &Scope,
 ) -> Result<Response<Full<Bytes>>, Infallible> {
     let (version, nonce) = state.xds_versions.get_version(resource_type);
 
@@ -102,83 +247,29 @@ pub async fn handle_discovery_get(
         "/v1/discovery:{}",
         resource_type.to_string().to_lowercase() + "s"
     );
+    if let Err(reason) = scope.check(Target::Resource(resource_type), ApiAccess::Read) {
+        return deny("GET", &path, &reason);
+    }
 
-    // Build resources within a scope to release read lock early
-    let resources = {
-        let dynamic_config = state.dynamic_config.read().await;
-        match resource_type {
-            ResourceType::Listener => {
-                let workers_value = match state.config.server.workers {
-                    Some(n) => serde_json::json!(n.to_string()),
-                    None => serde_json::json!("auto"),
-                };
-                vec![Resource {
-                    type_url: type_url.clone(),
-                    name: "main".to_string(),
-                    value: serde_json::json!({
-                        "main_server": {
-                            "host": dynamic_config.server.host,
-                            "port": dynamic_config.server.port
-                        },
-                        "api_server": {
-                            "host": dynamic_config.server.api_host,
-                            "port": dynamic_config.server.api_port
-                        },
-                        "workers": workers_value
-                    }),
-                }]
-            }
-            ResourceType::Route => {
-                vec![Resource {
-                    type_url: type_url.clone(),
-                    name: "default".to_string(),
-                    value: serde_json::json!({
-                        "index_files": &dynamic_config.routes.index_files,
-                        "custom_routes": &dynamic_config.routes.custom_routes
-                    }),
-                }]
-            }
-            ResourceType::Http => {
-                vec![Resource {
-                    type_url: type_url.clone(),
-                    name: "default".to_string(),
-                    value: serde_json::to_value(&*dynamic_config.http).unwrap_or_else(|e| {
-                        logger::log_error(&format!("Failed to serialize HTTP config: {e}"));
-                        serde_json::json!({"error": "serialization_failed"})
-                    }),
-                }]
-            }
-            ResourceType::Logging => {
-                vec![Resource {
-                    type_url: type_url.clone(),
-                    name: "default".to_string(),
-                    value: serde_json::to_value(&dynamic_config.logging).unwrap_or_else(|e| {
-                        logger::log_error(&format!("Failed to serialize logging config: {e}"));
-                        serde_json::json!({"error": "serialization_failed"})
-                    }),
-                }]
-            }
-            ResourceType::Performance => {
-                vec![Resource {
-                    type_url: type_url.clone(),
-                    name: "default".to_string(),
-                    value: serde_json::to_value(&dynamic_config.performance).unwrap_or_else(|e| {
-                        logger::log_error(&format!("Failed to serialize performance config: {e}"));
-                        serde_json::json!({"error": "serialization_failed"})
-                    }),
-                }]
-            }
-            ResourceType::VirtualHost => {
-                vec![Resource {
-                    type_url: type_url.clone(),
-                    name: "default".to_string(),
-                    value: serde_json::json!({
-                        "virtual_hosts": &*dynamic_config.virtual_hosts
-                    }),
-                }]
-            }
-        }
+    // Build resources from one configuration snapshot
+    let dynamic_config = state.snapshot();
+    let value = if resource_type == ResourceType::VirtualHost {
+        serde_json::json!({
+            "virtual_hosts": scope.visible_vhosts(&dynamic_config.virtual_hosts)
+        })
+    } else {
+        resource_value(state, &dynamic_config, resource_type)
+    };
+    let name = if resource_type == ResourceType::Listener {
+        "main"
+    } else {
+        "default"
     };
+    let resources = vec![Resource {
+        type_url: type_url.clone(),
+        name: name.to_string(),
+        value,
+    }];
 
     let response = DiscoveryResponse {
         version_info: version.to_string(),
@@ -191,140 +282,269 @@ pub async fn handle_discovery_get(
     json_response(StatusCode::OK, &response)
 }
 
+/// Outcome of an update request, answered and audited by `submit_update`
+struct UpdateOutcome {
+    /// Resource as it would be applied (virtual hosts outside the caller's scope merged in)
+    requested: Option<serde_json::Value>,
+    /// New version, nonce and message, or the NACK status and reason
+    result: Result<(u64, u64, String), (StatusCode, String)>,
+}
+
+impl UpdateOutcome {
+    fn rejected(status: StatusCode, reason: impl Into<String>) -> Self {
+        Self {
+            requested: None,
+            result: Err((status, reason.into())),
+        }
+    }
+}
+
 /// POST method to update resources (xDS standard)
+///
+/// Requires write access to the resource type. A caller limited to some virtual
+/// hosts replaces only those; the others are kept. Every request is audited.
 pub async fn handle_discovery_post(
     req: Request<hyper::body::Incoming>,
     state: Arc<AppState>,
     resource_type: ResourceType,
+    scope: &Scope,
+    remote_addr: SocketAddr,
 ) -> Result<Response<Full<Bytes>>, Infallible> {
-    use http_body_util::BodyExt;
-
-    /// xDS update request structure
-    #[derive(Deserialize)]
-    struct UpdateRequest {
-        /// Version returned by client (for optimistic locking)
-        #[serde(default)]
-        version_info: String,
-        /// Resources to update
-        resources: Vec<serde_json::Value>,
-        /// Whether to force restart (only valid for Listener)
-        #[serde(default)]
-        force_restart: bool,
-    }
-
     let path = format!(
         "/v1/discovery:{}",
         resource_type.to_string().to_lowercase() + "s"
     );
-
-    // Read request body
-    let whole_body = if let Ok(collected) = req.collect().await {
-        collected.to_bytes()
-    } else {
-        logger::log_api_request("POST", &path, 400);
-        return Ok(bad_request("Failed to read request body"));
+    let update = match read_body(req).await {
+        Ok(body) => serde_json::from_slice(&body).map_err(|e| format!("Invalid JSON: {e}")),
+        Err(e) => Err(e),
     };
+    submit_update(state, resource_type, scope, remote_addr, &path, update).await
+}
+
+/// xDS update request structure
+#[derive(Deserialize)]
+struct UpdateRequest {
+    /// Version returned by client (for optimistic locking)
+    #[serde(default)]
+    version_info: String,
+    /// Resources to update
+    resources: Vec<serde_json::Value>,
+    /// Whether to force restart (only valid for Listener)
+    #[serde(default)]
+    force_restart: bool,
+}
+
+async fn read_body(req: Request<hyper::body::Incoming>) -> Result<Bytes, String> {
+    use http_body_util::BodyExt;
+
+    req.collect()
+        .await
+        .map(http_body_util::Collected::to_bytes)
+        .map_err(|_| "Failed to read request body".to_string())
+}
+
+/// Apply an update request, answer it, record it in the history and audit it
+async fn submit_update(
+    state: Arc<AppState>,
+    resource_type: ResourceType,
+    scope: &Scope,
+    remote_addr: SocketAddr,
+    path: &str,
+    update: Result<UpdateRequest, String>,
+) -> Result<Response<Full<Bytes>>, Infallible> {
+    let (old_version, _) = state.xds_versions.get_version(resource_type);
+    let before = resource_value(&state, &state.snapshot(), resource_type);
+
+    let outcome = apply_update(update, &state, resource_type, scope).await;
+
+    let mut record = AuditRecord::new(
+        scope.identity(),
+        remote_addr.ip(),
+        "POST",
+        path,
+        &resource_type.to_string(),
+    );
+    record.old_version = Some(old_version.to_string());
+    let response = match outcome.result {
+        Ok((new_version, new_nonce, message)) => {
+            logger::log_api_request("POST", path, 200);
+            let after = resource_value(&state, &state.snapshot(), resource_type);
+            record.diff = json_diff(&before, &after);
+            record.new_version = Some(new_version.to_string());
+            record.result = AuditResult::Ack;
+            record.status = 200;
+            let entry = HistoryEntry {
+                version: new_version,
+                applied_at: Some(record.timestamp.clone()),
+                identity: Some(scope.identity().to_string()),
+                value: after,
+            };
+            state
+                .config_history
+                .record(resource_type, old_version, before, entry)
+                .await;
 
-    let update_req: UpdateRequest = match serde_json::from_slice(&whole_body) {
-        Ok(r) => r,
-        Err(e) => {
-            logger::log_api_request("POST", &path, 400);
-            return Ok(bad_request(&format!("Invalid JSON: {e}")));
+            let response = serde_json::json!({
+                "status": "ACK",
+                "version_info": new_version.to_string(),
+                "nonce": new_nonce.to_string(),
+                "message": message
+            });
+            json_response(StatusCode::OK, &response)
+        }
+        Err((status, reason)) => {
+            if let Some(requested) = &outcome.requested {
+                record.diff = json_diff(&before, requested);
+            }
+            record.status = status.as_u16();
+            let response = if status == StatusCode::FORBIDDEN {
+                deny("POST", path, &reason)
+            } else {
+                logger::log_api_request("POST", path, status.as_u16());
+                if status == StatusCode::CONFLICT {
+                    Ok(conflict_response(&reason))
+                } else {
+                    Ok(bad_request(&reason))
+                }
+            };
+            record.error = Some(reason);
+            response
         }
     };
+    state.audit_log.record(record);
+    response
+}
+
+/// Check and apply an update request
+async fn apply_update(
+    update: Result<UpdateRequest, String>,
+    state: &Arc<AppState>,
+    resource_type: ResourceType,
+    scope: &Scope,
+) -> UpdateOutcome {
+    if let Err(reason) = scope.check(Target::Resource(resource_type), ApiAccess::Write) {
+        return UpdateOutcome::rejected(StatusCode::FORBIDDEN, reason);
+    }
+    let mut update_req = match update {
+        Ok(update_req) => update_req,
+        Err(e) => return UpdateOutcome::rejected(StatusCode::BAD_REQUEST, e),
+    };
 
     // Check version conflict (optimistic locking)
     if !update_req.version_info.is_empty() {
         let (current_version, _) = state.xds_versions.get_version(resource_type);
         if update_req.version_info != current_version.to_string() {
-            logger::log_api_request("POST", &path, 409);
-            return Ok(conflict_response(&format!(
-                "Version conflict: expected {current_version}, got {version}",
-                current_version = current_version,
-                version = update_req.version_info
-            )));
+            return UpdateOutcome::rejected(
+                StatusCode::CONFLICT,
+                format!(
+                    "Version conflict: expected {current_version}, got {version}",
+                    version = update_req.version_info
+                ),
+            );
         }
     }
 
     if update_req.resources.is_empty() {
-        logger::log_api_request("POST", &path, 400);
-        return Ok(bad_request("No resources provided"));
+        return UpdateOutcome::rejected(StatusCode::BAD_REQUEST, "No resources provided");
+    }
+    let resource = update_req.resources.swap_remove(0);
+    let mut requested = None;
+
+    // Check the scope against the current virtual hosts; the update merges
+    // again under the update lock, so concurrent updates are not lost
+    if resource_type == ResourceType::VirtualHost && scope.is_restricted() {
+        let posted = match updaters::parse_virtual_hosts(&resource) {
+            Ok(posted) => posted,
+            Err(e) => return UpdateOutcome::rejected(StatusCode::BAD_REQUEST, e),
+        };
+        match scope.merge_vhosts(&state.snapshot().virtual_hosts, posted) {
+            Ok(merged) => requested = Some(serde_json::json!({ "virtual_hosts": merged })),
+            Err(reason) => return UpdateOutcome::rejected(StatusCode::FORBIDDEN, reason),
+        }
     }
 
     // Process update
     let result = match resource_type {
         ResourceType::Listener => {
-            updaters::update_listener(&state, &update_req.resources[0], update_req.force_restart)
-                .await
-        }
-        ResourceType::Route => updaters::update_route(&state, &update_req.resources[0]).await,
-        ResourceType::Http => updaters::update_http(&state, &update_req.resources[0]).await,
-        ResourceType::Logging => updaters::update_logging(&state, &update_req.resources[0]).await,
-        ResourceType::Performance => {
-            updaters::update_performance(&state, &update_req.resources[0]).await
-        }
-        ResourceType::VirtualHost => {
-            updaters::update_virtual_hosts(&state, &update_req.resources[0]).await
+            updaters::update_listener(state, &resource, update_req.force_restart).await
         }
+        ResourceType::Route => updaters::update_route(state, &resource).await,
+        ResourceType::Http => updaters::update_http(state, &resource).await,
+        ResourceType::Logging => updaters::update_logging(state, &resource).await,
+        ResourceType::Performance => updaters::update_performance(state, &resource).await,
+        ResourceType::VirtualHost => updaters::update_virtual_hosts(state, &resource, scope).await,
     };
 
-    match result {
-        Ok(message) => {
-            // Update version number
-            let (new_version, new_nonce) = state.xds_versions.increment(resource_type);
-
-            logger::log_api_request("POST", &path, 200);
-
-            let response = serde_json::json!({
-                "status": "ACK",
-                "version_info": new_version.to_string(),
-                "nonce": new_nonce.to_string(),
-                "message": message
-            });
-
-            json_response(StatusCode::OK, &response)
-        }
-        Err(e) => {
-            logger::log_api_request("POST", &path, 400);
-
-            let response = serde_json::json!({
-                "status": "NACK",
-                "error_detail": {
-                    "code": 400,
-                    "message": e
-                }
-            });
-
-            json_response(StatusCode::BAD_REQUEST, &response)
-        }
+    UpdateOutcome {
+        requested: Some(requested.unwrap_or(resource)),
+        result: result
+            .map(|message| {
+                // Update version number
+                let (new_version, new_nonce) = state.xds_versions.increment(resource_type);
+                (new_version, new_nonce, message)
+            })
+            .map_err(|e| (StatusCode::BAD_REQUEST, e)),
     }
 }
 
 /// DELETE method to clear persisted state
 /// Resets to config.toml defaults on next restart
-pub async fn handle_state_clear(state: Arc<AppState>) -> Result<Response<Full<Bytes>>, Infallible> {
-    match state.state_manager.clear().await {
-        Ok(()) => {
-            logger::log_api_request("DELETE", "/v1/state", 200);
-            let response = serde_json::json!({
-                "status": "OK",
-                "message": "Persisted state cleared. Restart server to apply config.toml defaults."
-            });
-            json_response(StatusCode::OK, &response)
-        }
-        Err(e) => {
-            logger::log_api_request("DELETE", "/v1/state", 500);
-            let response = serde_json::json!({
-                "status": "ERROR",
-                "message": e
-            });
-            json_response(StatusCode::INTERNAL_SERVER_ERROR, &response)
+pub async fn handle_state_clear(
+    state: Arc<AppState>,
+    scope: &Scope,
+    remote_addr: SocketAddr,
+) -> Result<Response<Full<Bytes>>, Infallible> {
+    let mut record = AuditRecord::new(
+        scope.identity(),
+        remote_addr.ip(),
+        "DELETE",
+        "/v1/state",
+        &Target::State.to_string(),
+    );
+    let response = if let Err(reason) = scope.check(Target::State, ApiAccess::Write) {
+        record.status = 403;
+        let response = deny("DELETE", "/v1/state", &reason);
+        record.error = Some(reason);
+        response
+    } else {
+        let before =
+            serde_json::to_value(state.state_manager.get_state().await).unwrap_or_default();
+        match state.state_manager.clear().await {
+            Ok(()) => {
+                logger::log_api_request("DELETE", "/v1/state", 200);
+                record.diff = json_diff(&before, &serde_json::json!({}));
+                record.result = AuditResult::Ack;
+                record.status = 200;
+                let response = serde_json::json!({
+                    "status": "OK",
+                    "message": "Persisted state cleared. Restart server to apply config.toml defaults."
+                });
+                json_response(StatusCode::OK, &response)
+            }
+            Err(e) => {
+                logger::log_api_request("DELETE", "/v1/state", 500);
+                record.status = 500;
+                let response = serde_json::json!({
+                    "status": "ERROR",
+                    "message": e
+                });
+                record.error = Some(e);
+                json_response(StatusCode::INTERNAL_SERVER_ERROR, &response)
+            }
         }
-    }
+    };
+    state.audit_log.record(record);
+    response
 }
 
 /// GET method to view persisted state
-pub async fn handle_state_get(state: Arc<AppState>) -> Result<Response<Full<Bytes>>, Infallible> {
+pub async fn handle_state_get(
+    state: Arc<AppState>,
+    scope: &Scope,
+) -> Result<Response<Full<Bytes>>, Infallible> {
+    if let Err(reason) = scope.check(Target::State, ApiAccess::Read) {
+        return deny("GET", "/v1/state", &reason);
+    }
     let persisted = state.state_manager.get_state().await;
     let state_path = state.state_manager.state_path().display().to_string();
     let enabled = state.state_manager.is_enabled();
@@ -339,3 +559,258 @@ pub async fn handle_state_get(state: Arc<AppState>) -> Result<Response<Full<Byte
 
     json_response(StatusCode::OK, &response)
 }
+
+/// GET method to page through audit records, newest first
+///
+/// Query: `limit` (default 50) and `before` (the `next_before` of the previous page)
+pub fn handle_audit(
+    state: &AppState,
+    scope: &Scope,
+    query: Option<&str>,
+) -> Result<Response<Full<Bytes>>, Infallible> {
+    if let Err(reason) = scope.check(Target::Audit, ApiAccess::Read) {
+        return deny("GET", "/v1/audit", &reason);
+    }
+
+    let mut limit = 50;
+    let mut before = None;
+    for pair in query
+        .unwrap_or_default()
+        .split('&')
+        .filter(|p| !p.is_empty())
+    {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        let parsed = match key {
+            "limit" => value.parse().map(|v| limit = v),
+            "before" => value.parse().map(|v| before = Some(v)),
+            _ => Ok(()),
+        };
+        if parsed.is_err() || limit == 0 {
+            logger::log_api_request("GET", "/v1/audit", 400);
+            return Ok(bad_request(&format!("Invalid query parameter '{pair}'")));
+        }
+    }
+
+    logger::log_api_request("GET", "/v1/audit", 200);
+    json_response(StatusCode::OK, &state.audit_log.page(before, limit))
+}
+
+/// Resource at `version`: the current configuration or a snapshot from the history
+fn version_value(
+    state: &AppState,
+    resource_type: ResourceType,
+    version: u64,
+) -> Option<serde_json::Value> {
+    let (current_version, _) = state.xds_versions.get_version(resource_type);
+    if version == current_version {
+        Some(resource_value(state, &state.snapshot(), resource_type))
+    } else {
+        state
+            .config_history
+            .find(resource_type, version)
+            .map(|entry| entry.value)
+    }
+}
+
+/// Limit a resource value to the virtual hosts the caller may read
+fn scoped_value(
+    scope: &Scope,
+    resource_type: ResourceType,
+    value: serde_json::Value,
+) -> serde_json::Value {
+    if resource_type != ResourceType::VirtualHost || !scope.is_restricted() {
+        return value;
+    }
+    let vhosts = updaters::parse_virtual_hosts(&value).unwrap_or_default();
+    serde_json::json!({ "virtual_hosts": scope.visible_vhosts(&vhosts) })
+}
+
+/// GET method to list the applied snapshots of a resource type, newest first
+pub fn handle_history(
+    state: &AppState,
+    resource_type: ResourceType,
+    scope: &Scope,
+) -> Result<Response<Full<Bytes>>, Infallible> {
+    let path = format!("/v1/history:{}", resource_name(resource_type));
+    if let Err(reason) = scope.check(Target::Resource(resource_type), ApiAccess::Read) {
+        return deny("GET", &path, &reason);
+    }
+
+    let (version, _) = state.xds_versions.get_version(resource_type);
+    let entries: Vec<serde_json::Value> = state
+        .config_history
+        .entries(resource_type)
+        .into_iter()
+        .map(|entry| {
+            serde_json::json!({
+                "version_info": entry.version.to_string(),
+                "applied_at": entry.applied_at,
+                "identity": entry.identity,
+                "current": entry.version == version,
+                "value": scoped_value(scope, resource_type, entry.value)
+            })
+        })
+        .collect();
+
+    logger::log_api_request("GET", &path, 200);
+    let response = serde_json::json!({
+        "resource_type": resource_type,
+        "version_info": version.to_string(),
+        "entries": entries
+    });
+    json_response(StatusCode::OK, &response)
+}
+
+/// GET method to compare two versions of a resource type as JSON Patch operations
+///
+/// Query: `from`, `to` (default: the current version) and `type` (`vhosts`, ...),
+/// needed only when the versions exist for several resource types.
+pub fn handle_diff(
+    state: &AppState,
+    scope: &Scope,
+    query: Option<&str>,
+) -> Result<Response<Full<Bytes>>, Infallible> {
+    let mut from = None;
+    let mut to = None;
+    let mut requested_type = None;
+    for pair in query
+        .unwrap_or_default()
+        .split('&')
+        .filter(|p| !p.is_empty())
+    {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        let valid = match key {
+            "from" => value.parse().map(|v| from = Some(v)).is_ok(),
+            "to" => value.parse().map(|v| to = Some(v)).is_ok(),
+            "type" => resource_type_by_name(value)
+                .map(|v| requested_type = Some(v))
+                .is_some(),
+            _ => true,
+        };
+        if !valid {
+            logger::log_api_request("GET", "/v1/diff", 400);
+            return Ok(bad_request(&format!("Invalid query parameter '{pair}'")));
+        }
+    }
+    let Some(from) = from else {
+        logger::log_api_request("GET", "/v1/diff", 400);
+        return Ok(bad_request("Missing query parameter 'from'"));
+    };
+    if let Some(resource_type) = requested_type {
+        if let Err(reason) = scope.check(Target::Resource(resource_type), ApiAccess::Read) {
+            return deny("GET", "/v1/diff", &reason);
+        }
+    }
+
+    // Readable resource types that have both versions
+    let mut found = RESOURCE_NAMES
+        .iter()
+        .map(|(_, resource_type)| *resource_type)
+        .filter(|resource_type| requested_type.is_none_or(|t| t == *resource_type))
+        .filter(|resource_type| {
+            scope
+                .check(Target::Resource(*resource_type), ApiAccess::Read)
+                .is_ok()
+        })
+        .filter_map(|resource_type| {
+            let to = to.unwrap_or_else(|| state.xds_versions.get_version(resource_type).0);
+            let old = version_value(state, resource_type, from)?;
+            let new = version_value(state, resource_type, to)?;
+            Some((resource_type, to, old, new))
+        });
+    let (resource_type, to, old, new) = match (found.next(), found.next()) {
+        (Some(diff), None) => diff,
+        (None, _) => {
+            logger::log_api_request("GET", "/v1/diff", 400);
+            return Ok(bad_request(
+                "Versions not found in the configuration history",
+            ));
+        }
+        (Some(_), Some(_)) => {
+            logger::log_api_request("GET", "/v1/diff", 400);
+            return Ok(bad_request(
+                "Versions exist for several resource types, add 'type' (vhosts, routes, ...)",
+            ));
+        }
+    };
+
+    logger::log_api_request("GET", "/v1/diff", 200);
+    let response = serde_json::json!({
+        "resource_type": resource_type,
+        "from": from.to_string(),
+        "to": to.to_string(),
+        "diff": json_diff(
+            &scoped_value(scope, resource_type, old),
+            &scoped_value(scope, resource_type, new)
+        )
+    });
+    json_response(StatusCode::OK, &response)
+}
+
+/// POST method to roll a resource type back to a version from its history
+///
+/// Query: `version`. The snapshot is applied as an update of the resource type:
+/// `version_info` in the (optional) body is checked for optimistic locking, and
+/// the rollback is checked, recorded and audited like any update. A caller
+/// limited to some virtual hosts rolls back only those.
+pub async fn handle_rollback(
+    req: Request<hyper::body::Incoming>,
+    state: Arc<AppState>,
+    resource_type: ResourceType,
+    scope: &Scope,
+    remote_addr: SocketAddr,
+) -> Result<Response<Full<Bytes>>, Infallible> {
+    let path = format!("/v1/rollback:{}", resource_name(resource_type));
+    let query = req.uri().query().unwrap_or_default().to_string();
+    let update = match read_body(req).await {
+        Ok(body) => rollback_request(&state, resource_type, scope, &query, &body),
+        Err(e) => Err(e),
+    };
+    submit_update(state, resource_type, scope, remote_addr, &path, update).await
+}
+
+/// Update request restoring the snapshot named by the rollback query
+fn rollback_request(
+    state: &AppState,
+    resource_type: ResourceType,
+    scope: &Scope,
+    query: &str,
+    body: &[u8],
+) -> Result<UpdateRequest, String> {
+    /// Optional body of a rollback request
+    #[derive(Deserialize, Default)]
+    struct RollbackRequest {
+        /// Version returned by client (for optimistic locking)
+        #[serde(default)]
+        version_info: String,
+    }
+
+    let version = query
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("version="))
+        .ok_or_else(|| "Missing query parameter 'version'".to_string())?;
+    let version: u64 = version
+        .parse()
+        .map_err(|_| format!("Invalid version '{version}'"))?;
+    let rollback: RollbackRequest = if body.is_empty() {
+        RollbackRequest::default()
+    } else {
+        serde_json::from_slice(body).map_err(|e| format!("Invalid JSON: {e}"))?
+    };
+
+    let mut value = version_value(state, resource_type, version).ok_or_else(|| {
+        format!("Version {version} of {resource_type} is not in the configuration history")
+    })?;
+    // Restore only the virtual hosts the caller may change; the others are kept
+    if resource_type == ResourceType::VirtualHost && scope.is_restricted() {
+        let mut vhosts = updaters::parse_virtual_hosts(&value)?;
+        vhosts.retain(|vhost| scope.allows_vhost(vhost, ApiAccess::Write));
+        value = serde_json::json!({ "virtual_hosts": vhosts });
+    }
+
+    Ok(UpdateRequest {
+        version_info: rollback.version_info,
+        resources: vec![value],
+        force_restart: false,
+    })
+}
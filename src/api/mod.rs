@@ -1,8 +1,11 @@
 // API module entry
 // xDS-style configuration management API
 
+mod audit;
+mod auth;
 mod dashboard;
 mod handlers;
+mod rbac;
 mod response;
 mod types;
 mod updaters;
@@ -11,75 +14,141 @@ use http_body_util::Full;
 use hyper::body::Bytes;
 use hyper::{Method, Request, Response};
 use std::convert::Infallible;
+use std::net::SocketAddr;
 use std::sync::Arc;
 
 use crate::config::{AppState, ResourceType};
 use crate::logger;
 
 // Re-export public types
+pub use audit::AuditLog;
+pub use auth::ApiAuth;
 pub use response::*;
 
 /// API route handler
 ///
-/// Dispatches to handler functions based on request path and method
+/// Authenticates the caller (see `api_auth`), then dispatches to handler
+/// functions based on request path and method; handlers check the caller's roles
+#[allow(clippy::too_many_lines)]
 pub async fn handle_api_config(
-    req: Request<hyper::body::Incoming>,
+    mut req: Request<hyper::body::Incoming>,
     state: Arc<AppState>,
+    remote_addr: SocketAddr,
 ) -> Result<Response<Full<Bytes>>, Infallible> {
-    let path = req.uri().path();
     let method = req.method().clone();
 
+    // The dashboard page holds no configuration; it prompts for credentials itself
+    if method == Method::GET && matches!(req.uri().path(), "/" | "/dashboard") {
+        return Ok(dashboard::serve_dashboard());
+    }
+    let scope = match state.api_auth.authenticate(&req) {
+        Ok(identity) => {
+            let scope = state.api_auth.scope(&identity);
+            req.extensions_mut().insert(identity);
+            scope
+        }
+        Err(reason) => {
+            let path = req.uri().path();
+            logger::log_warning(&format!(
+                "API authentication failed for {remote_addr}: {reason} ({method} {path})"
+            ));
+            logger::log_api_request(method.as_str(), path, 401);
+            handlers::audit_unauthenticated(&state, &method, path, remote_addr, &reason);
+            return Ok(unauthorized(&state.api_auth.challenges(&req)));
+        }
+    };
+
+    let path = req.uri().path();
+
+    // Configuration history and rollback of a resource type
+    if let Some((endpoint, name)) = path.split_once(':') {
+        match (&method, endpoint, handlers::resource_type_by_name(name)) {
+            (&Method::GET, "/v1/history", Some(resource_type)) => {
+                return handlers::handle_history(&state, resource_type, &scope);
+            }
+            (&Method::POST, "/v1/rollback", Some(resource_type)) => {
+                return handlers::handle_rollback(req, state, resource_type, &scope, remote_addr)
+                    .await;
+            }
+            _ => {}
+        }
+    }
+
     // xDS style routes
     match (method, path) {
-        // Dashboard - Web UI
-        (Method::GET, "/" | "/dashboard") => Ok(dashboard::serve_dashboard()),
         // Get all resources snapshot
-        (Method::GET, "/v1/discovery") => handlers::handle_snapshot(state).await,
+        (Method::GET, "/v1/discovery") => handlers::handle_snapshot(&state, &scope),
         // Discover specific resource type (Listener)
         (Method::GET, "/v1/discovery:listeners") => {
-            handlers::handle_discovery_get(state, ResourceType::Listener).await
+            handlers::handle_discovery_get(&state, ResourceType::Listener, &scope)
         }
         (Method::POST, "/v1/discovery:listeners") => {
-            handlers::handle_discovery_post(req, state, ResourceType::Listener).await
+            handlers::handle_discovery_post(req, state, ResourceType::Listener, &scope, remote_addr)
+                .await
         }
         // Discover route resources
         (Method::GET, "/v1/discovery:routes") => {
-            handlers::handle_discovery_get(state, ResourceType::Route).await
+            handlers::handle_discovery_get(&state, ResourceType::Route, &scope)
         }
         (Method::POST, "/v1/discovery:routes") => {
-            handlers::handle_discovery_post(req, state, ResourceType::Route).await
+            handlers::handle_discovery_post(req, state, ResourceType::Route, &scope, remote_addr)
+                .await
         }
         // Discover HTTP configuration
         (Method::GET, "/v1/discovery:http") => {
-            handlers::handle_discovery_get(state, ResourceType::Http).await
+            handlers::handle_discovery_get(&state, ResourceType::Http, &scope)
         }
         (Method::POST, "/v1/discovery:http") => {
-            handlers::handle_discovery_post(req, state, ResourceType::Http).await
+            handlers::handle_discovery_post(req, state, ResourceType::Http, &scope, remote_addr)
+                .await
         }
         // Discover logging configuration
         (Method::GET, "/v1/discovery:logging") => {
-            handlers::handle_discovery_get(state, ResourceType::Logging).await
+            handlers::handle_discovery_get(&state, ResourceType::Logging, &scope)
         }
         (Method::POST, "/v1/discovery:logging") => {
-            handlers::handle_discovery_post(req, state, ResourceType::Logging).await
+            handlers::handle_discovery_post(req, state, ResourceType::Logging, &scope, remote_addr)
+                .await
         }
         // Discover performance configuration
         (Method::GET, "/v1/discovery:performance") => {
-            handlers::handle_discovery_get(state, ResourceType::Performance).await
+            handlers::handle_discovery_get(&state, ResourceType::Performance, &scope)
         }
         (Method::POST, "/v1/discovery:performance") => {
-            handlers::handle_discovery_post(req, state, ResourceType::Performance).await
+            handlers::handle_discovery_post(
+                req,
+                state,
+                ResourceType::Performance,
+                &scope,
+                remote_addr,
+            )
+            .await
         }
         // Discover virtual_hosts configuration
         (Method::GET, "/v1/discovery:vhosts") => {
-            handlers::handle_discovery_get(state, ResourceType::VirtualHost).await
+            handlers::handle_discovery_get(&state, ResourceType::VirtualHost, &scope)
         }
         (Method::POST, "/v1/discovery:vhosts") => {
-            handlers::handle_discovery_post(req, state, ResourceType::VirtualHost).await
+            handlers::handle_discovery_post(
+                req,
+                state,
+                ResourceType::VirtualHost,
+                &scope,
+                remote_addr,
+            )
+            .await
         }
         // State persistence management
-        (Method::GET, "/v1/state") => handlers::handle_state_get(state).await,
-        (Method::DELETE, "/v1/state") => handlers::handle_state_clear(state).await,
+        (Method::GET, "/v1/state") => handlers::handle_state_get(state, &scope).await,
+        (Method::DELETE, "/v1/state") => {
+            handlers::handle_state_clear(state, &scope, remote_addr).await
+        }
+        // Changes between two versions of a resource type
+        (Method::GET, "/v1/diff") => handlers::handle_diff(&state, &scope, req.uri().query()),
+        // Audit records of configuration changes
+        (Method::GET, "/v1/audit") => handlers::handle_audit(&state, &scope, req.uri().query()),
+        // Runtime counters
+        (Method::GET, "/v1/stats") => handlers::handle_stats(&state, &scope),
         // Unknown route
         _ => {
             logger::log_api_request(req.method().as_str(), path, 404);
@@ -0,0 +1,365 @@
+// API access control module
+// Roles granting read or write access per resource type and per virtual host
+
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::config::{ApiAccess, ApiRoleConfig, ResourceType, VirtualHost};
+use crate::routing;
+
+/// What a permission covers: a resource type, the persisted state (`/v1/state`)
+/// or the audit log (`/v1/audit`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+    Resource(ResourceType),
+    State,
+    Audit,
+    Stats,
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Resource(resource_type) => write!(f, "{resource_type}"),
+            Self::State => f.write_str("STATE"),
+            Self::Audit => f.write_str("AUDIT"),
+            Self::Stats => f.write_str("STATS"),
+        }
+    }
+}
+
+/// What an authenticated identity may read or change
+#[derive(Debug, Clone)]
+pub struct Scope {
+    identity: String,
+    /// Roles of the identity, `None` when no roles are configured (full access)
+    roles: Option<Vec<Arc<ApiRoleConfig>>>,
+}
+
+impl Scope {
+    /// Full access (access control not configured)
+    pub fn unrestricted(identity: &str) -> Self {
+        Self {
+            identity: identity.to_string(),
+            roles: None,
+        }
+    }
+
+    /// Access granted by `roles`
+    pub fn new(identity: &str, roles: Vec<Arc<ApiRoleConfig>>) -> Self {
+        Self {
+            identity: identity.to_string(),
+            roles: Some(roles),
+        }
+    }
+
+    pub fn identity(&self) -> &str {
+        &self.identity
+    }
+
+    /// Whether roles limit this identity
+    pub const fn is_restricted(&self) -> bool {
+        self.roles.is_some()
+    }
+
+    /// Check `access` to `target`
+    pub fn check(&self, target: Target, access: ApiAccess) -> Result<(), String> {
+        let allowed = self.roles.as_ref().is_none_or(|roles| {
+            roles
+                .iter()
+                .any(|role| permission(role, target).is_some_and(|granted| granted >= access))
+        });
+        if allowed {
+            Ok(())
+        } else {
+            Err(format!(
+                "'{}' has no {} access to {target}",
+                self.identity,
+                access_name(access)
+            ))
+        }
+    }
+
+    /// Whether a role with `access` to virtual hosts covers `vhost`
+    pub fn allows_vhost(&self, vhost: &VirtualHost, access: ApiAccess) -> bool {
+        self.roles.as_ref().is_none_or(|roles| {
+            roles.iter().any(|role| {
+                role.permissions
+                    .virtual_host
+                    .is_some_and(|granted| granted >= access)
+                    && covers(role, vhost)
+            })
+        })
+    }
+
+    /// Virtual hosts the identity may read
+    pub fn visible_vhosts(&self, vhosts: &[VirtualHost]) -> Vec<VirtualHost> {
+        vhosts
+            .iter()
+            .filter(|vhost| self.allows_vhost(vhost, ApiAccess::Read))
+            .cloned()
+            .collect()
+    }
+
+    /// Apply a virtual host update to `current` within the scope
+    ///
+    /// The posted list replaces the writable virtual hosts; the others are kept
+    /// in place. Every posted virtual host, and the one it replaces, must be writable.
+    pub fn merge_vhosts(
+        &self,
+        current: &[VirtualHost],
+        posted: Vec<VirtualHost>,
+    ) -> Result<Vec<VirtualHost>, String> {
+        if self.roles.is_none() {
+            return Ok(posted);
+        }
+        for vhost in &posted {
+            let replaced = current.iter().find(|existing| existing.name == vhost.name);
+            if !self.allows_vhost(vhost, ApiAccess::Write)
+                || replaced.is_some_and(|existing| !self.allows_vhost(existing, ApiAccess::Write))
+            {
+                return Err(format!(
+                    "'{}' has no write access to virtual host '{}'",
+                    self.identity, vhost.name
+                ));
+            }
+        }
+
+        let mut merged = Vec::with_capacity(current.len() + posted.len());
+        let mut posted = Some(posted);
+        for vhost in current {
+            if self.allows_vhost(vhost, ApiAccess::Write) {
+                merged.extend(posted.take().unwrap_or_default());
+            } else {
+                merged.push(vhost.clone());
+            }
+        }
+        merged.extend(posted.unwrap_or_default());
+        Ok(merged)
+    }
+}
+
+/// Check role names and virtual host patterns
+pub fn validate_roles(roles: &[ApiRoleConfig]) -> Result<(), String> {
+    let mut names = HashSet::new();
+    for role in roles {
+        if role.name.is_empty() {
+            return Err("api_auth.roles: name must not be empty".to_string());
+        }
+        if !names.insert(role.name.as_str()) {
+            return Err(format!("api_auth.roles: duplicate role '{}'", role.name));
+        }
+        for pattern in &role.virtual_hosts {
+            let prefix = pattern.strip_suffix('*').unwrap_or(pattern);
+            if prefix.contains('*') || pattern.is_empty() {
+                return Err(format!(
+                    "api_auth.roles '{}': unsupported virtual host pattern '{pattern}' \
+                     (use 'name' or 'prefix*')",
+                    role.name
+                ));
+            }
+        }
+        for pattern in &role.domains {
+            routing::validate_domain_pattern(pattern)
+                .map_err(|e| format!("api_auth.roles '{}': {e}", role.name))?;
+        }
+    }
+    Ok(())
+}
+
+const fn permission(role: &ApiRoleConfig, target: Target) -> Option<ApiAccess> {
+    let permissions = &role.permissions;
+    match target {
+        Target::Resource(ResourceType::Listener) => permissions.listener,
+        Target::Resource(ResourceType::Route) => permissions.route,
+        Target::Resource(ResourceType::Http) => permissions.http,
+        Target::Resource(ResourceType::Logging) => permissions.logging,
+        Target::Resource(ResourceType::Performance) => permissions.performance,
+        Target::Resource(ResourceType::VirtualHost) => permissions.virtual_host,
+        Target::State => permissions.state,
+        Target::Audit => permissions.audit,
+        Target::Stats => permissions.stats,
+    }
+}
+
+const fn access_name(access: ApiAccess) -> &'static str {
+    match access {
+        ApiAccess::Read => "read",
+        ApiAccess::Write => "write",
+    }
+}
+
+/// Whether the role's virtual host patterns cover `vhost`
+///
+/// A domain pattern must cover every domain of the virtual host, so a role
+/// for `*.example.com` cannot claim `*` or another team's domain.
+fn covers(role: &ApiRoleConfig, vhost: &VirtualHost) -> bool {
+    if role.virtual_hosts.is_empty() && role.domains.is_empty() {
+        return true;
+    }
+    let by_name = role.virtual_hosts.iter().any(|pattern| {
+        pattern
+            .strip_suffix('*')
+            .map_or(*pattern == vhost.name, |prefix| {
+                vhost.name.starts_with(prefix)
+            })
+    });
+    let by_domain = !role.domains.is_empty()
+        && !vhost.domains.is_empty()
+        && vhost.domains.iter().all(|domain| {
+            role.domains
+                .iter()
+                .any(|pattern| routing::domain_covers(pattern, domain))
+        });
+    by_name || by_domain
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn role(value: serde_json::Value) -> Arc<ApiRoleConfig> {
+        Arc::new(serde_json::from_value(value).unwrap())
+    }
+
+    fn vhost(name: &str, domains: &[&str]) -> VirtualHost {
+        serde_json::from_value(serde_json::json!({"name": name, "domains": domains})).unwrap()
+    }
+
+    fn names(vhosts: &[VirtualHost]) -> Vec<&str> {
+        vhosts.iter().map(|v| v.name.as_str()).collect()
+    }
+
+    fn web_team() -> Scope {
+        Scope::new(
+            "web-deploy",
+            vec![
+                role(serde_json::json!({
+                    "name": "web",
+                    "permissions": {"virtual_host": "write", "route": "read"},
+                    "virtual_hosts": ["www-site"],
+                    "domains": ["*.shop.example.com"]
+                })),
+                role(serde_json::json!({
+                    "name": "viewer",
+                    "permissions": {"virtual_host": "read", "listener": "read"}
+                })),
+            ],
+        )
+    }
+
+    #[test]
+    fn test_resource_permissions() {
+        let scope = web_team();
+        let write = ApiAccess::Write;
+        let read = ApiAccess::Read;
+        assert!(scope
+            .check(Target::Resource(ResourceType::VirtualHost), write)
+            .is_ok());
+        assert!(scope
+            .check(Target::Resource(ResourceType::Route), read)
+            .is_ok());
+        assert!(scope
+            .check(Target::Resource(ResourceType::Route), write)
+            .is_err());
+        assert!(scope
+            .check(Target::Resource(ResourceType::Listener), read)
+            .is_ok());
+        assert!(scope
+            .check(Target::Resource(ResourceType::Listener), write)
+            .is_err());
+        assert!(scope
+            .check(Target::Resource(ResourceType::Http), read)
+            .is_err());
+        assert_eq!(
+            scope.check(Target::State, write).unwrap_err(),
+            "'web-deploy' has no write access to STATE"
+        );
+
+        let nobody = Scope::new("nobody", Vec::new());
+        assert!(nobody
+            .check(Target::Resource(ResourceType::Route), read)
+            .is_err());
+        assert!(Scope::unrestricted("admin")
+            .check(Target::State, write)
+            .is_ok());
+    }
+
+    #[test]
+    fn test_vhost_scope() {
+        let scope = web_team();
+        let www = vhost("www-site", &["www.example.com", "*"]);
+        let shop = vhost("shop", &["eu.shop.example.com", "*.us.shop.example.com"]);
+        let mixed = vhost("mixed", &["eu.shop.example.com", "api.example.com"]);
+        let api = vhost("api", &["api.example.com"]);
+
+        assert!(scope.allows_vhost(&www, ApiAccess::Write));
+        assert!(scope.allows_vhost(&shop, ApiAccess::Write));
+        assert!(!scope.allows_vhost(&mixed, ApiAccess::Write));
+        assert!(!scope.allows_vhost(&api, ApiAccess::Write));
+        // The viewer role reads every virtual host
+        assert!(scope.allows_vhost(&api, ApiAccess::Read));
+
+        // Domains are covered with the router's rules
+        let upper = vhost("upper", &["EU.Shop.Example.com", "shop.example.com."]);
+        assert!(scope.allows_vhost(&upper, ApiAccess::Write));
+        let gateways = Scope::new(
+            "gateway-deploy",
+            vec![role(serde_json::json!({
+                "name": "gateway",
+                "permissions": {"virtual_host": "write"},
+                "domains": ["api.*"]
+            }))],
+        );
+        assert!(gateways.allows_vhost(&api, ApiAccess::Write));
+        assert!(gateways.allows_vhost(&vhost("any-api", &["api.*"]), ApiAccess::Write));
+        assert!(!gateways.allows_vhost(&vhost("www", &["www.example.com"]), ApiAccess::Write));
+
+        let current = vec![api.clone(), www, mixed];
+        let merged = scope
+            .merge_vhosts(
+                &current,
+                vec![vhost("www-site", &["www.example.com"]), shop],
+            )
+            .unwrap();
+        assert_eq!(names(&merged), ["api", "www-site", "shop", "mixed"]);
+        assert!(scope.merge_vhosts(&current, vec![api]).is_err());
+        // Renaming does not take over an unwritable virtual host's name
+        assert!(scope
+            .merge_vhosts(&current, vec![vhost("api", &["x.shop.example.com"])])
+            .is_err());
+        // An empty update removes only the writable virtual hosts
+        assert_eq!(
+            names(&scope.merge_vhosts(&current, Vec::new()).unwrap()),
+            ["api", "mixed"]
+        );
+    }
+
+    #[test]
+    fn test_validate_roles() {
+        let parse = |value| vec![(*role(value)).clone()];
+        assert!(validate_roles(&parse(serde_json::json!({
+            "name": "ok", "virtual_hosts": ["team-*"], "domains": ["*.example.com", "*"]
+        })))
+        .is_ok());
+        assert!(validate_roles(&parse(serde_json::json!({
+            "name": "bad", "virtual_hosts": ["*-site"]
+        })))
+        .is_err());
+        assert!(validate_roles(&parse(serde_json::json!({
+            "name": "ok", "domains": ["api.*", "Shop.Example.com"]
+        })))
+        .is_ok());
+        assert!(validate_roles(&parse(serde_json::json!({
+            "name": "bad", "domains": ["api.*.example.com"]
+        })))
+        .is_err());
+        let duplicate = [
+            parse(serde_json::json!({"name": "a"})),
+            parse(serde_json::json!({"name": "a"})),
+        ]
+        .concat();
+        assert!(validate_roles(&duplicate).is_err());
+    }
+}
@@ -46,6 +46,42 @@ pub fn not_found() -> Response<Full<Bytes>> {
         .unwrap_or_else(|_| Response::new(Full::new(Bytes::from("Not Found"))))
 }
 
+/// 401 Unauthorized response with `WWW-Authenticate` challenges
+pub fn unauthorized(challenges: &[String]) -> Response<Full<Bytes>> {
+    let body = serde_json::json!({
+        "status": "NACK",
+        "error_detail": {
+            "code": 401,
+            "message": "Authentication required"
+        }
+    });
+    let mut builder = Response::builder()
+        .status(StatusCode::UNAUTHORIZED)
+        .header("Content-Type", "application/json");
+    for challenge in challenges {
+        builder = builder.header("WWW-Authenticate", challenge);
+    }
+    builder
+        .body(Full::new(Bytes::from(body.to_string())))
+        .unwrap_or_else(|_| Response::new(Full::new(Bytes::from("Unauthorized"))))
+}
+
+/// 403 Forbidden response (outside the caller's roles)
+pub fn forbidden(message: &str) -> Response<Full<Bytes>> {
+    let body = serde_json::json!({
+        "status": "NACK",
+        "error_detail": {
+            "code": 403,
+            "message": message
+        }
+    });
+    Response::builder()
+        .status(StatusCode::FORBIDDEN)
+        .header("Content-Type", "application/json")
+        .body(Full::new(Bytes::from(body.to_string())))
+        .unwrap_or_else(|_| Response::new(Full::new(Bytes::from("Forbidden"))))
+}
+
 /// 400 Bad Request response
 pub fn bad_request(message: &str) -> Response<Full<Bytes>> {
     let body = serde_json::json!({
@@ -42,14 +42,21 @@ pub struct SnapshotResponse {
     pub resources: ResourceSnapshot,
 }
 
+/// Resources of a snapshot; types the caller may not read are left out
 #[derive(Debug, Serialize)]
 pub struct ResourceSnapshot {
-    pub listener: VersionedValue<ListenerResource>,
-    pub route: VersionedValue<RouteResource>,
-    pub http: VersionedValue<HttpConfig>,
-    pub logging: VersionedValue<LoggingConfig>,
-    pub performance: VersionedValue<DynamicPerformanceConfig>,
-    pub virtual_hosts: VersionedValue<Vec<VirtualHost>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub listener: Option<VersionedValue<ListenerResource>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub route: Option<VersionedValue<RouteResource>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub http: Option<VersionedValue<HttpConfig>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub logging: Option<VersionedValue<LoggingConfig>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub performance: Option<VersionedValue<DynamicPerformanceConfig>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub virtual_hosts: Option<VersionedValue<Vec<VirtualHost>>>,
 }
 
 #[derive(Debug, Serialize)]
@@ -1,6 +1,13 @@
 // Resource update functions module
 
-use crate::config::{AppState, DynamicPerformanceConfig, HttpConfig, LoggingConfig, RoutesConfig, VirtualHost};
+use super::rbac::Scope;
+use crate::config::{
+    AppState, CorsPolicy, DynamicPerformanceConfig, GlobalRateLimit, HeaderMutations, HttpConfig,
+    LoggingConfig, RateLimitAction, RateLimitAlgorithm, RateLimitKey, RateLimitPolicy,
+    RateLimitServiceConfig, RedirectAction, Route, RouteAction, RouteMatch, RoutesConfig,
+    SecurityHeadersConfig, VirtualHost,
+};
+use crate::routing;
 use serde::Deserialize;
 use std::sync::Arc;
 
@@ -24,13 +31,14 @@ pub async fn update_listener(
 
     let update: ListenerUpdate = serde_json::from_value(resource.clone())
         .map_err(|e| format!("Invalid listener resource: {e}"))?;
+    if let Some(api) = &update.api_server {
+        state.api_auth.check_listener(&api.host)?;
+    }
 
-    let (port_changed, api_port_changed) = {
-        let mut port_changed = false;
-        let mut api_port_changed = false;
-
-        {
-            let mut config = state.dynamic_config.write().await;
+    let (port_changed, api_port_changed) = state
+        .update_config(|config| {
+            let mut port_changed = false;
+            let mut api_port_changed = false;
 
             if let Some(main) = &update.main_server {
                 if config.server.host != main.host || config.server.port != main.port {
@@ -47,18 +55,14 @@ pub async fn update_listener(
                     config.server.api_port = api.port;
                 }
             }
-            // Write lock released here at end of scope
-        }
 
-        (port_changed, api_port_changed)
-    };
+            (port_changed, api_port_changed)
+        })
+        .await;
 
     // Trigger restart
     if port_changed || api_port_changed || force_restart {
-        let new_config = {
-            let config = state.dynamic_config.read().await;
-            config.server.clone()
-        };
+        let new_config = state.snapshot().server.clone();
 
         // Persist server config change
         if let Err(e) = state.state_manager.update_server(&new_config).await {
@@ -105,10 +109,15 @@ pub async fn update_route(
     let routes: RoutesConfig = serde_json::from_value(resource.clone())
         .map_err(|e| format!("Invalid route resource: {e}"))?;
 
-    {
-        let mut config = state.dynamic_config.write().await;
-        config.routes = Arc::new(routes.clone());
-    }
+    state
+        .update_config(|config| {
+            config.routes = Arc::new(routes.clone());
+            config.rebuild_route_table();
+        })
+        .await;
+
+    // Cached lookups may point into directories that are no longer routed
+    state.open_file_cache.clear();
 
     // Persist routes config change
     if let Err(e) = state.state_manager.update_routes(&routes).await {
@@ -125,11 +134,19 @@ pub async fn update_http(
 ) -> Result<String, String> {
     let http: HttpConfig = serde_json::from_value(resource.clone())
         .map_err(|e| format!("Invalid HTTP resource: {e}"))?;
-
-    {
-        let mut config = state.dynamic_config.write().await;
-        config.http = Arc::new(http.clone());
+    if let Some(security_headers) = &http.security_headers {
+        validate_security_headers(security_headers)?;
+    }
+    if let Some(cors) = &http.cors {
+        validate_cors(cors)?;
     }
+    if let Some(service) = &http.rate_limit_service {
+        validate_rate_limit_service(service)?;
+    }
+
+    state
+        .update_config(|config| config.http = Arc::new(http.clone()))
+        .await;
 
     // Persist HTTP config change
     if let Err(e) = state.state_manager.update_http(&http).await {
@@ -144,31 +161,36 @@ pub async fn update_logging(
     state: &Arc<AppState>,
     resource: &serde_json::Value,
 ) -> Result<String, String> {
-    let logging: LoggingConfig = serde_json::from_value(resource.clone())
+    let mut logging: LoggingConfig = serde_json::from_value(resource.clone())
         .map_err(|e| format!("Invalid logging resource: {e}"))?;
 
+    // The audit trail is set at startup; an update may only omit or repeat it
+    let audit_log_file = &state.config.logging.audit_log_file;
+    if logging.audit_log_file.is_some() && logging.audit_log_file != *audit_log_file {
+        return Err(
+            "audit_log_file cannot be changed at runtime (set it in config.toml)".to_string(),
+        );
+    }
+    logging.audit_log_file.clone_from(audit_log_file);
+
     // Update log file paths if changed
     if crate::logger::writer::is_initialized() {
         let writer = crate::logger::writer::get();
-        
+
         // Update access log file
         if let Err(e) = writer.set_access_log_file(logging.access_log_file.as_deref()) {
             return Err(format!("Failed to update access log file: {e}"));
         }
-        
+
         // Update error log file
         if let Err(e) = writer.set_error_log_file(logging.error_log_file.as_deref()) {
             return Err(format!("Failed to update error log file: {e}"));
         }
     }
 
-    {
-        let mut config = state.dynamic_config.write().await;
-        config.logging = logging.clone();
-    }
-
-    // Update cache
-    state.update_cache(&*state.dynamic_config.read().await);
+    state
+        .update_config(|config| config.logging = logging.clone())
+        .await;
 
     // Persist logging config change
     if let Err(e) = state.state_manager.update_logging(&logging).await {
@@ -186,10 +208,13 @@ pub async fn update_performance(
     let performance: DynamicPerformanceConfig = serde_json::from_value(resource.clone())
         .map_err(|e| format!("Invalid performance resource: {e}"))?;
 
-    {
-        let mut config = state.dynamic_config.write().await;
-        config.performance = performance.clone();
-    }
+    state
+        .update_config(|config| config.performance = performance.clone())
+        .await;
+
+    state
+        .open_file_cache
+        .reconfigure(performance.open_file_cache.clone());
 
     // Persist performance config change
     if let Err(e) = state.state_manager.update_performance(&performance).await {
@@ -199,23 +224,32 @@ pub async fn update_performance(
     Ok("Performance config updated".to_string())
 }
 
+/// Parse a `VirtualHost` resource
+///
+/// Supports both wrapped object format `{"virtual_hosts": [...]}` and direct array format `[...]`
+pub fn parse_virtual_hosts(resource: &serde_json::Value) -> Result<Vec<VirtualHost>, String> {
+    if let Some(obj) = resource.as_object() {
+        let vhosts = obj
+            .get("virtual_hosts")
+            .ok_or_else(|| "Missing 'virtual_hosts' field".to_string())?;
+        serde_json::from_value(vhosts.clone())
+            .map_err(|e| format!("Invalid virtual_hosts array: {e}"))
+    } else {
+        serde_json::from_value(resource.clone())
+            .map_err(|e| format!("Invalid virtual_hosts resource: {e}"))
+    }
+}
+
 /// Update `VirtualHost` configuration
+///
+/// The posted virtual hosts are merged into the current ones within `scope`
+/// under the update lock (see `Scope::merge_vhosts`).
 pub async fn update_virtual_hosts(
     state: &Arc<AppState>,
     resource: &serde_json::Value,
+    scope: &Scope,
 ) -> Result<String, String> {
-    // Support both wrapped object format {"virtual_hosts": [...]} and direct array format [...]
-    let virtual_hosts: Vec<VirtualHost> = if let Some(obj) = resource.as_object() {
-        if let Some(vhosts) = obj.get("virtual_hosts") {
-            serde_json::from_value(vhosts.clone())
-                .map_err(|e| format!("Invalid virtual_hosts array: {e}"))?
-        } else {
-            return Err("Missing 'virtual_hosts' field".to_string());
-        }
-    } else {
-        serde_json::from_value(resource.clone())
-            .map_err(|e| format!("Invalid virtual_hosts resource: {e}"))?
-    };
+    let virtual_hosts = parse_virtual_hosts(resource)?;
 
     // Validate virtual hosts
     for (i, vhost) in virtual_hosts.iter().enumerate() {
@@ -225,19 +259,639 @@ pub async fn update_virtual_hosts(
         if vhost.name.is_empty() {
             return Err(format!("VirtualHost at index {i} has no name"));
         }
+        for domain in &vhost.domains {
+            routing::validate_domain_pattern(domain)
+                .map_err(|e| format!("VirtualHost '{}': {e}", vhost.name))?;
+        }
+        validate_header_mutations(&vhost.header_mutations)
+            .map_err(|e| format!("VirtualHost '{}': {e}", vhost.name))?;
+        if let Some(security_headers) = &vhost.security_headers {
+            validate_security_headers(security_headers)
+                .map_err(|e| format!("VirtualHost '{}': {e}", vhost.name))?;
+        }
+        if let Some(cors) = &vhost.cors {
+            validate_cors(cors).map_err(|e| format!("VirtualHost '{}': {e}", vhost.name))?;
+        }
+        validate_rate_limits(&vhost.rate_limits)
+            .map_err(|e| format!("VirtualHost '{}': {e}", vhost.name))?;
+        validate_global_rate_limits(&vhost.global_rate_limits)
+            .map_err(|e| format!("VirtualHost '{}': {e}", vhost.name))?;
+        if vhost.aggregate_limit_rate == Some(0) {
+            return Err(format!(
+                "VirtualHost '{}': aggregate_limit_rate must be greater than 0",
+                vhost.name
+            ));
+        }
+        for route in &vhost.routes {
+            validate_header_mutations(&route.header_mutations)
+                .map_err(|e| format!("VirtualHost '{}': {e}", vhost.name))?;
+            validate_route_match(&route.match_rule)
+                .map_err(|e| format!("VirtualHost '{}': {e}", vhost.name))?;
+            validate_route_rewrite(route)
+                .map_err(|e| format!("VirtualHost '{}': {e}", vhost.name))?;
+            if let Some(cors) = &route.cors {
+                validate_cors(cors).map_err(|e| format!("VirtualHost '{}': {e}", vhost.name))?;
+            }
+            validate_rate_limits(&route.rate_limits)
+                .map_err(|e| format!("VirtualHost '{}': {e}", vhost.name))?;
+            validate_global_rate_limits(&route.global_rate_limits)
+                .map_err(|e| format!("VirtualHost '{}': {e}", vhost.name))?;
+            validate_limit_rate(route).map_err(|e| format!("VirtualHost '{}': {e}", vhost.name))?;
+            if let RouteAction::Redirect(redirect) = &route.action {
+                validate_redirect(redirect, &route.match_rule)
+                    .map_err(|e| format!("VirtualHost '{}': {e}", vhost.name))?;
+            }
+            if let RouteAction::Dir {
+                try_files: Some(entries),
+                ..
+            } = &route.action
+            {
+                validate_try_files(entries, vhost)
+                    .map_err(|e| format!("VirtualHost '{}': {e}", vhost.name))?;
+            }
+        }
     }
 
+    let virtual_hosts = state
+        .update_config(|config| {
+            let merged = scope.merge_vhosts(&config.virtual_hosts, virtual_hosts)?;
+            config.virtual_hosts = Arc::new(merged.clone());
+            config.rebuild_route_table();
+            Ok::<_, String>(merged)
+        })
+        .await?;
     let count = virtual_hosts.len();
 
-    {
-        let mut config = state.dynamic_config.write().await;
-        config.virtual_hosts = Arc::new(virtual_hosts.clone());
-    }
+    state.open_file_cache.clear();
+    state.vhost_bandwidth.retain(|name| {
+        virtual_hosts
+            .iter()
+            .any(|vhost| vhost.name == name && vhost.aggregate_limit_rate.is_some())
+    });
 
     // Persist virtual hosts config change
-    if let Err(e) = state.state_manager.update_virtual_hosts(&virtual_hosts).await {
+    if let Err(e) = state
+        .state_manager
+        .update_virtual_hosts(&virtual_hosts)
+        .await
+    {
         crate::logger::write_error(&format!("Failed to persist virtual_hosts config: {e}"));
     }
 
     Ok(format!("VirtualHosts updated: {count} host(s) configured"))
 }
+
+/// Validate a route's match conditions
+///
+/// Regex patterns are already compiled (and rejected if invalid) during
+/// deserialization. `safe_regex` and `path_separated_prefix` cannot be
+/// combined with another path specifier (`prefix` + `path` is still accepted,
+/// with `path` taking priority, for compatibility).
+fn validate_route_match(rule: &RouteMatch) -> Result<(), String> {
+    let specifiers = [
+        rule.prefix.is_some() || rule.path.is_some(),
+        rule.safe_regex.is_some(),
+        rule.path_separated_prefix.is_some(),
+    ];
+    if specifiers.iter().filter(|set| **set).count() > 1 {
+        return Err(
+            "safe_regex and path_separated_prefix cannot be combined with another path match"
+                .to_string(),
+        );
+    }
+
+    if let Some(prefix) = &rule.path_separated_prefix {
+        if !prefix.starts_with('/') || prefix.ends_with('/') {
+            return Err(format!(
+                "path_separated_prefix '{prefix}' must start with '/' and not end with '/'"
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Whether a rule has a prefix that `prefix_rewrite` can replace
+const fn has_rewritable_prefix(rule: &RouteMatch) -> bool {
+    rule.prefix.is_some() || rule.path.is_some() || rule.path_separated_prefix.is_some()
+}
+
+/// Validate a redirect action
+///
+/// A fixed `target` excludes the URL fields, at most one path replacement may
+/// be set, and a redirect must change something.
+fn validate_redirect(redirect: &RedirectAction, rule: &RouteMatch) -> Result<(), String> {
+    if ![301, 302, 303, 307, 308].contains(&redirect.code) {
+        return Err(format!(
+            "redirect code {} must be 301, 302, 303, 307 or 308",
+            redirect.code
+        ));
+    }
+
+    let path_fields = [
+        redirect.path_redirect.is_some(),
+        redirect.prefix_rewrite.is_some(),
+        redirect.regex_rewrite.is_some(),
+    ]
+    .iter()
+    .filter(|set| **set)
+    .count();
+    let origin_fields = redirect.scheme_redirect.is_some()
+        || redirect.host_redirect.is_some()
+        || redirect.port_redirect.is_some();
+
+    if redirect.target.is_some() {
+        if path_fields > 0 || origin_fields {
+            return Err(
+                "redirect target cannot be combined with other redirect fields".to_string(),
+            );
+        }
+        return Ok(());
+    }
+    if path_fields > 1 {
+        return Err(
+            "only one of path_redirect, prefix_rewrite and regex_rewrite may be set".to_string(),
+        );
+    }
+    if path_fields == 0 && !origin_fields {
+        return Err("redirect needs a target or a scheme, host, port or path redirect".to_string());
+    }
+
+    if let Some(scheme) = &redirect.scheme_redirect {
+        if !scheme.eq_ignore_ascii_case("http") && !scheme.eq_ignore_ascii_case("https") {
+            return Err(format!("scheme_redirect '{scheme}' must be http or https"));
+        }
+    }
+    if let Some(host) = &redirect.host_redirect {
+        if host.is_empty() || host.contains(['/', '?', '#', ' ']) {
+            return Err(format!("host_redirect '{host}' is not a valid host"));
+        }
+    }
+    if redirect.port_redirect == Some(0) {
+        return Err("port_redirect must not be 0".to_string());
+    }
+    if let Some(path) = &redirect.path_redirect {
+        if !path.starts_with('/') {
+            return Err(format!("path_redirect '{path}' must start with '/'"));
+        }
+    }
+    if redirect.prefix_rewrite.is_some() && !has_rewritable_prefix(rule) {
+        return Err(
+            "redirect prefix_rewrite requires a prefix, path or path_separated_prefix match"
+                .to_string(),
+        );
+    }
+
+    Ok(())
+}
+
+/// Validate a route's URL rewrite
+///
+/// `prefix_rewrite` needs a prefix to replace, a path rewrite only takes effect
+/// on `Dir` routes or when re-matching, `host_rewrite` only when re-matching,
+/// and a `rematch` that changes nothing would loop.
+fn validate_route_rewrite(route: &Route) -> Result<(), String> {
+    let Some(rewrite) = &route.rewrite else {
+        return Ok(());
+    };
+
+    if rewrite.prefix_rewrite.is_some() && rewrite.regex_rewrite.is_some() {
+        return Err("prefix_rewrite and regex_rewrite cannot be combined".to_string());
+    }
+
+    if let Some(replacement) = &rewrite.prefix_rewrite {
+        if !has_rewritable_prefix(&route.match_rule) {
+            return Err(
+                "prefix_rewrite requires a prefix, path or path_separated_prefix match".to_string(),
+            );
+        }
+        if !replacement.starts_with('/') {
+            return Err(format!(
+                "prefix_rewrite '{replacement}' must start with '/'"
+            ));
+        }
+    }
+
+    let rewrites_path = rewrite.prefix_rewrite.is_some() || rewrite.regex_rewrite.is_some();
+    if rewrites_path && !rewrite.rematch && !matches!(route.action, RouteAction::Dir { .. }) {
+        return Err(
+            "prefix_rewrite and regex_rewrite require rematch on routes other than dir".to_string(),
+        );
+    }
+
+    if let Some(host) = &rewrite.host_rewrite {
+        if host.is_empty() {
+            return Err("host_rewrite must not be empty".to_string());
+        }
+        if !rewrite.rematch {
+            return Err("host_rewrite requires rematch".to_string());
+        }
+    }
+
+    if rewrite.rematch
+        && rewrite.prefix_rewrite.is_none()
+        && rewrite.regex_rewrite.is_none()
+        && rewrite.host_rewrite.is_none()
+    {
+        return Err("rematch requires a prefix_rewrite, regex_rewrite or host_rewrite".to_string());
+    }
+
+    Ok(())
+}
+
+/// Validate header names and values to add or remove
+///
+/// Values are checked before interpolation; variables only insert header-safe text.
+fn validate_header_mutations(mutations: &HeaderMutations) -> Result<(), String> {
+    let names = mutations
+        .request_headers_to_add
+        .iter()
+        .chain(&mutations.response_headers_to_add)
+        .map(|header| &header.name)
+        .chain(&mutations.request_headers_to_remove)
+        .chain(&mutations.response_headers_to_remove);
+    for name in names {
+        if hyper::header::HeaderName::from_bytes(name.as_bytes()).is_err() {
+            return Err(format!("invalid header name '{name}'"));
+        }
+        if name.eq_ignore_ascii_case("host") {
+            return Err("the host header cannot be modified".to_string());
+        }
+    }
+
+    let values = mutations
+        .request_headers_to_add
+        .iter()
+        .chain(&mutations.response_headers_to_add);
+    for header in values {
+        if hyper::header::HeaderValue::from_str(&header.value).is_err() {
+            return Err(format!("invalid value for header '{}'", header.name));
+        }
+    }
+
+    Ok(())
+}
+
+/// Validate a security headers preset
+///
+/// Values must be valid header values, and HSTS `preload` needs
+/// `include_subdomains` and a max-age of at least one year (preload list rules).
+fn validate_security_headers(config: &SecurityHeadersConfig) -> Result<(), String> {
+    let values = [
+        ("content_security_policy", &config.content_security_policy),
+        ("frame_options", &config.frame_options),
+        ("referrer_policy", &config.referrer_policy),
+        ("permissions_policy", &config.permissions_policy),
+        (
+            "cross_origin_opener_policy",
+            &config.cross_origin_opener_policy,
+        ),
+        (
+            "cross_origin_embedder_policy",
+            &config.cross_origin_embedder_policy,
+        ),
+    ];
+    for (field, value) in values {
+        if hyper::header::HeaderValue::from_str(value).is_err() {
+            return Err(format!(
+                "security_headers.{field} is not a valid header value"
+            ));
+        }
+    }
+
+    if config.hsts.preload && (!config.hsts.include_subdomains || config.hsts.max_age < 31_536_000)
+    {
+        return Err(
+            "security_headers.hsts.preload requires include_subdomains and max_age >= 31536000"
+                .to_string(),
+        );
+    }
+
+    Ok(())
+}
+
+/// Validate a CORS policy
+///
+/// Each origin rule sets exactly one of `exact`, `suffix` and `safe_regex`,
+/// methods and header names must be valid tokens, and credentials cannot be
+/// combined with the `*` origin (browsers reject that response).
+fn validate_cors(policy: &CorsPolicy) -> Result<(), String> {
+    for rule in &policy.allow_origin {
+        let fields = [
+            rule.exact.as_ref().map(String::len),
+            rule.suffix.as_ref().map(String::len),
+            rule.safe_regex.as_ref().map(|regex| regex.as_str().len()),
+        ];
+        if fields.iter().flatten().count() != 1 {
+            return Err(
+                "cors.allow_origin entries need exactly one of exact, suffix, safe_regex"
+                    .to_string(),
+            );
+        }
+        if fields.contains(&Some(0)) {
+            return Err("cors.allow_origin entries must not be empty".to_string());
+        }
+    }
+
+    let any_origin = policy
+        .allow_origin
+        .iter()
+        .any(|rule| rule.exact.as_deref() == Some("*"));
+    if any_origin && policy.allow_credentials {
+        return Err("cors.allow_credentials cannot be used with the '*' origin".to_string());
+    }
+
+    if let Some(method) = policy
+        .allow_methods
+        .iter()
+        .find(|m| hyper::Method::from_bytes(m.as_bytes()).is_err())
+    {
+        return Err(format!("cors.allow_methods: invalid method '{method}'"));
+    }
+    if let Some(name) = policy
+        .allow_headers
+        .iter()
+        .chain(&policy.expose_headers)
+        .find(|name| {
+            name.as_str() != "*" && hyper::header::HeaderName::from_bytes(name.as_bytes()).is_err()
+        })
+    {
+        return Err(format!("cors: invalid header name '{name}'"));
+    }
+
+    Ok(())
+}
+
+/// Longest `rate_limits` interval in seconds (one day)
+const MAX_RATE_LIMIT_INTERVAL: u64 = 86_400;
+
+/// Validate `rate_limits` of a virtual host or route
+///
+/// Limits need a positive request count and an interval of at most one day;
+/// `burst` (token bucket only) must allow at least one request and fit in a
+/// `u32`, and header keys must be valid names.
+fn validate_rate_limits(policies: &[RateLimitPolicy]) -> Result<(), String> {
+    for policy in policies {
+        if policy.requests == 0 {
+            return Err("rate_limits: requests must be greater than 0".to_string());
+        }
+        if policy.interval == 0 {
+            return Err("rate_limits: interval must be greater than 0".to_string());
+        }
+        if policy.interval > MAX_RATE_LIMIT_INTERVAL {
+            return Err(format!(
+                "rate_limits: interval must be at most {MAX_RATE_LIMIT_INTERVAL} seconds"
+            ));
+        }
+        match policy.burst {
+            Some(0) => return Err("rate_limits: burst must be greater than 0".to_string()),
+            Some(burst) if burst > u64::from(u32::MAX) => {
+                return Err(format!("rate_limits: burst must be at most {}", u32::MAX));
+            }
+            Some(_) if policy.algorithm != RateLimitAlgorithm::TokenBucket => {
+                return Err("rate_limits: burst requires the token_bucket algorithm".to_string());
+            }
+            _ => {}
+        }
+        if let RateLimitKey::Header(name) = &policy.key {
+            if hyper::header::HeaderName::from_bytes(name.as_bytes()).is_err() {
+                return Err(format!("rate_limits: invalid header name '{name}'"));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Validate `limit_rate` and `limit_rate_after` of a route
+fn validate_limit_rate(route: &Route) -> Result<(), String> {
+    match (route.limit_rate, route.limit_rate_after) {
+        (Some(0), _) => Err("limit_rate must be greater than 0".to_string()),
+        (None, Some(_)) => Err("limit_rate_after requires limit_rate".to_string()),
+        _ => Ok(()),
+    }
+}
+
+/// Validate the rate limit service: a `host:port` address, a domain and a timeout
+fn validate_rate_limit_service(service: &RateLimitServiceConfig) -> Result<(), String> {
+    let authority = service
+        .address
+        .parse::<hyper::http::uri::Authority>()
+        .map_err(|_| {
+            format!(
+                "rate_limit_service.address '{}' is invalid",
+                service.address
+            )
+        })?;
+    if authority.port_u16().is_none() || authority.as_str().contains('@') {
+        return Err(format!(
+            "rate_limit_service.address '{}' must be host:port",
+            service.address
+        ));
+    }
+    if service.domain.is_empty() {
+        return Err("rate_limit_service.domain must not be empty".to_string());
+    }
+    if service.timeout_ms == 0 {
+        return Err("rate_limit_service.timeout_ms must be greater than 0".to_string());
+    }
+    Ok(())
+}
+
+/// Validate `global_rate_limits` descriptors of a virtual host or route
+fn validate_global_rate_limits(limits: &[GlobalRateLimit]) -> Result<(), String> {
+    for limit in limits {
+        if limit.actions.is_empty() {
+            return Err("global_rate_limits: actions must not be empty".to_string());
+        }
+        for action in &limit.actions {
+            match action {
+                RateLimitAction::RemoteAddress => {}
+                RateLimitAction::RequestHeaders {
+                    header_name,
+                    descriptor_key,
+                } => {
+                    if hyper::header::HeaderName::from_bytes(header_name.as_bytes()).is_err() {
+                        return Err(format!(
+                            "global_rate_limits: invalid header name '{header_name}'"
+                        ));
+                    }
+                    if descriptor_key.is_empty() {
+                        return Err(
+                            "global_rate_limits: descriptor_key must not be empty".to_string()
+                        );
+                    }
+                }
+                RateLimitAction::GenericKey {
+                    descriptor_key,
+                    descriptor_value,
+                } => {
+                    if descriptor_key.is_empty() || descriptor_value.is_empty() {
+                        return Err("global_rate_limits: generic_key needs a descriptor_key \
+                             and descriptor_value"
+                            .to_string());
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Validate `try_files` entries of a `Dir` route
+///
+/// Error status (`=404`, 4xx or 5xx) and named route (`@name`) entries are only allowed last,
+/// and named routes must exist in the same virtual host.
+fn validate_try_files(entries: &[String], vhost: &VirtualHost) -> Result<(), String> {
+    let Some((fallback, candidates)) = entries.split_last() else {
+        return Err("try_files must not be empty".to_string());
+    };
+
+    if let Some(entry) = candidates
+        .iter()
+        .find(|e| e.starts_with('=') || e.starts_with('@'))
+    {
+        return Err(format!(
+            "try_files entry '{entry}' is only allowed as the last entry"
+        ));
+    }
+
+    if let Some(code) = fallback.strip_prefix('=') {
+        // Only error statuses: a fallback has no body or Location to send otherwise
+        if !code.parse::<u16>().is_ok_and(|c| (400..=599).contains(&c)) {
+            return Err(format!("try_files has invalid status '{fallback}'"));
+        }
+    }
+
+    if let Some(name) = fallback.strip_prefix('@') {
+        if !vhost.routes.iter().any(|r| r.name.as_deref() == Some(name)) {
+            return Err(format!("try_files references unknown route '{fallback}'"));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn vhost_with_named_route(name: &str) -> VirtualHost {
+        serde_json::from_value(json!({
+            "name": "www",
+            "domains": ["*"],
+            "routes": [{
+                "name": name,
+                "match": {"prefix": "/"},
+                "type": "direct",
+                "status": 200
+            }]
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_validate_route_rewrite_requires_dir_or_rematch() {
+        let route = |action: serde_json::Value, rewrite: serde_json::Value| -> Route {
+            let mut route = json!({"match": {"prefix": "/a"}, "rewrite": rewrite});
+            route
+                .as_object_mut()
+                .unwrap()
+                .extend(action.as_object().unwrap().clone());
+            serde_json::from_value(route).unwrap()
+        };
+        let dir = json!({"type": "dir", "path": "static"});
+        let direct = json!({"type": "direct", "status": 200});
+        let redirect = json!({"type": "redirect", "target": "/b"});
+        let prefix = json!({"prefix_rewrite": "/b"});
+        let regex = json!({"regex_rewrite": {"pattern": "/a/(.*)", "substitution": "/$1"}});
+
+        assert_eq!(validate_route_rewrite(&route(dir, prefix.clone())), Ok(()));
+        for action in [direct.clone(), redirect] {
+            for rewrite in [&prefix, &regex] {
+                let error = validate_route_rewrite(&route(action.clone(), rewrite.clone()));
+                assert_eq!(
+                    error.unwrap_err(),
+                    "prefix_rewrite and regex_rewrite require rematch on routes other than dir"
+                );
+            }
+        }
+        let rematch = json!({"prefix_rewrite": "/b", "rematch": true});
+        assert_eq!(validate_route_rewrite(&route(direct, rematch)), Ok(()));
+    }
+
+    #[test]
+    fn test_validate_rate_limits_bounds() {
+        let policy = |value: serde_json::Value| -> RateLimitPolicy {
+            serde_json::from_value(value).unwrap()
+        };
+        let valid = [
+            policy(json!({"requests": 10, "interval": 86_400})),
+            policy(json!({"requests": 10, "interval": 1, "burst": u32::MAX})),
+        ];
+        assert_eq!(validate_rate_limits(&valid), Ok(()));
+
+        let too_long = policy(json!({
+            "requests": 1,
+            "interval": u64::MAX,
+            "algorithm": "sliding_window"
+        }));
+        assert_eq!(
+            validate_rate_limits(&[too_long]).unwrap_err(),
+            "rate_limits: interval must be at most 86400 seconds"
+        );
+        let too_large = policy(json!({"requests": 1, "interval": 1, "burst": u64::MAX}));
+        assert_eq!(
+            validate_rate_limits(&[too_large]).unwrap_err(),
+            format!("rate_limits: burst must be at most {}", u32::MAX)
+        );
+    }
+
+    fn entries(entries: &[&str]) -> Vec<String> {
+        entries.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn test_validate_try_files() {
+        let vhost = vhost_with_named_route("backend");
+        for valid in [
+            &["$uri", "$uri/", "/index.html"][..],
+            &["$uri", "=404"],
+            &["$uri", "=503"],
+            &["$uri", "@backend"],
+            &["=404"],
+        ] {
+            assert_eq!(
+                validate_try_files(&entries(valid), &vhost),
+                Ok(()),
+                "{valid:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_validate_try_files_errors() {
+        let vhost = vhost_with_named_route("backend");
+        let error = |list: &[&str]| validate_try_files(&entries(list), &vhost).unwrap_err();
+
+        assert_eq!(error(&[]), "try_files must not be empty");
+        assert_eq!(
+            error(&["=404", "$uri"]),
+            "try_files entry '=404' is only allowed as the last entry"
+        );
+        assert_eq!(
+            error(&["$uri", "@backend", "=404"]),
+            "try_files entry '@backend' is only allowed as the last entry"
+        );
+        assert_eq!(
+            error(&["$uri", "@missing"]),
+            "try_files references unknown route '@missing'"
+        );
+        for status in ["=999", "=99", "=100", "=101", "=200", "=302", "=abc", "="] {
+            assert_eq!(
+                error(&["$uri", status]),
+                format!("try_files has invalid status '{status}'")
+            );
+        }
+    }
+}
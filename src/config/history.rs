@@ -0,0 +1,307 @@
+// Configuration history module
+// Keeps the last applied snapshots of each resource type for diff and rollback,
+// optionally persisted to history.json next to state.toml
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use super::version::ResourceType;
+
+/// One applied snapshot of a resource type
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub version: u64,
+    /// RFC 3339 time the snapshot was applied (unknown for the startup configuration)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub applied_at: Option<String>,
+    /// Caller that applied the snapshot (unknown for the startup configuration)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub identity: Option<String>,
+    /// Resource as returned by the discovery API
+    pub value: Value,
+}
+
+type Entries = HashMap<ResourceType, VecDeque<HistoryEntry>>;
+
+/// Bounded history of applied snapshots per resource type
+pub struct ConfigHistory {
+    /// Snapshots per resource type, oldest first
+    snapshots: Mutex<Snapshots>,
+    /// Snapshots kept per resource type (0 disables the history)
+    capacity: usize,
+    /// history.json, when state persistence is enabled
+    path: Option<PathBuf>,
+    /// Revision of the last snapshot written to `path`; held while writing
+    written: Arc<Mutex<u64>>,
+}
+
+/// Entries with the revision counting the updates applied to them
+#[derive(Default)]
+struct Snapshots {
+    entries: Entries,
+    revision: u64,
+}
+
+impl ConfigHistory {
+    /// Create a history keeping `capacity` snapshots per resource type,
+    /// loading `path` if it exists
+    pub fn new(capacity: usize, path: Option<PathBuf>) -> Self {
+        let mut entries = path
+            .as_deref()
+            .filter(|_| capacity > 0)
+            .and_then(Self::load)
+            .unwrap_or_default();
+        for snapshots in entries.values_mut() {
+            while snapshots.len() > capacity {
+                snapshots.pop_front();
+            }
+        }
+        Self {
+            snapshots: Mutex::new(Snapshots {
+                entries,
+                revision: 0,
+            }),
+            capacity,
+            path,
+            written: Arc::new(Mutex::new(0)),
+        }
+    }
+
+    fn load(path: &Path) -> Option<Entries> {
+        if !path.exists() {
+            return None;
+        }
+        let loaded = fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|content| serde_json::from_str(&content).map_err(|e| e.to_string()));
+        match loaded {
+            Ok(entries) => {
+                crate::logger::write_info(&format!(
+                    "Loaded configuration history from {}",
+                    path.display()
+                ));
+                Some(entries)
+            }
+            Err(e) => {
+                crate::logger::write_error(&format!(
+                    "Failed to load history file {}: {e}",
+                    path.display()
+                ));
+                None
+            }
+        }
+    }
+
+    /// Record an accepted update of `resource_type`
+    ///
+    /// The replaced snapshot (`old_version`, `old_value`) is recorded first unless
+    /// it is already the latest entry, so the first change after startup can
+    /// also be rolled back. When persisted, a copy of the history is written
+    /// to history.json on the blocking thread pool.
+    pub async fn record(
+        &self,
+        resource_type: ResourceType,
+        old_version: u64,
+        old_value: Value,
+        entry: HistoryEntry,
+    ) {
+        if self.capacity == 0 {
+            return;
+        }
+        let snapshot = self.push(resource_type, old_version, old_value, entry);
+        let (Some(path), Some((entries, revision))) = (&self.path, snapshot) else {
+            return;
+        };
+
+        let (target, written) = (path.clone(), Arc::clone(&self.written));
+        let saved = tokio::task::spawn_blocking(move || {
+            // Writes finishing out of order must not replace a newer file
+            let mut written = written.lock().unwrap();
+            if *written >= revision {
+                return Ok(());
+            }
+            save(&target, &entries)?;
+            *written = revision;
+            drop(written);
+            Ok(())
+        })
+        .await
+        .map_err(|e| e.to_string())
+        .and_then(|saved| saved);
+        if let Err(e) = saved {
+            crate::logger::write_error(&format!(
+                "Failed to save history file {}: {e}",
+                path.display()
+            ));
+        }
+    }
+
+    /// Add the snapshots in memory, returning a copy of the history to persist
+    fn push(
+        &self,
+        resource_type: ResourceType,
+        old_version: u64,
+        old_value: Value,
+        entry: HistoryEntry,
+    ) -> Option<(Entries, u64)> {
+        let mut guard = self.snapshots.lock().unwrap();
+        let snapshots = guard.entries.entry(resource_type).or_default();
+        if snapshots
+            .back()
+            .is_none_or(|last| last.version != old_version)
+        {
+            snapshots.push_back(HistoryEntry {
+                version: old_version,
+                applied_at: None,
+                identity: None,
+                value: old_value,
+            });
+        }
+        snapshots.push_back(entry);
+        while snapshots.len() > self.capacity {
+            snapshots.pop_front();
+        }
+        guard.revision += 1;
+        let snapshot = self
+            .path
+            .is_some()
+            .then(|| (guard.entries.clone(), guard.revision));
+        drop(guard);
+        snapshot
+    }
+
+    /// Snapshots of `resource_type`, newest first
+    pub fn entries(&self, resource_type: ResourceType) -> Vec<HistoryEntry> {
+        let snapshots = self.snapshots.lock().unwrap();
+        snapshots
+            .entries
+            .get(&resource_type)
+            .map(|snapshots| snapshots.iter().rev().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Snapshot of `resource_type` at `version`
+    pub fn find(&self, resource_type: ResourceType, version: u64) -> Option<HistoryEntry> {
+        let snapshots = self.snapshots.lock().unwrap();
+        snapshots
+            .entries
+            .get(&resource_type)?
+            .iter()
+            .rev()
+            .find(|entry| entry.version == version)
+            .cloned()
+    }
+}
+
+/// Write `entries` to a temporary file next to `path` and rename it over `path`,
+/// so a crash never leaves a truncated history.json
+fn save(path: &Path, entries: &Entries) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(entries).map_err(|e| e.to_string())?;
+    let temp = path.with_extension("json.tmp");
+    fs::write(&temp, content).map_err(|e| e.to_string())?;
+    fs::rename(&temp, path).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn entry(version: u64, value: Value) -> HistoryEntry {
+        HistoryEntry {
+            version,
+            applied_at: Some("2026-01-01T00:00:00+00:00".to_string()),
+            identity: Some("deploy".to_string()),
+            value,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_record_and_find() {
+        let history = ConfigHistory::new(3, None);
+        history
+            .record(ResourceType::Http, 1, json!("a"), entry(2, json!("b")))
+            .await;
+        history
+            .record(ResourceType::Http, 2, json!("b"), entry(3, json!("c")))
+            .await;
+
+        let versions: Vec<u64> = history
+            .entries(ResourceType::Http)
+            .iter()
+            .map(|e| e.version)
+            .collect();
+        assert_eq!(versions, [3, 2, 1]);
+        let startup = history.find(ResourceType::Http, 1).unwrap();
+        assert_eq!(startup.value, json!("a"));
+        assert_eq!(startup.identity, None);
+        assert!(history.find(ResourceType::Route, 1).is_none());
+
+        // Bounded per resource type
+        history
+            .record(ResourceType::Http, 3, json!("c"), entry(4, json!("d")))
+            .await;
+        assert!(history.find(ResourceType::Http, 1).is_none());
+        assert_eq!(history.entries(ResourceType::Http).len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_disabled() {
+        let history = ConfigHistory::new(0, None);
+        history
+            .record(ResourceType::Http, 1, json!("a"), entry(2, json!("b")))
+            .await;
+        assert!(history.entries(ResourceType::Http).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_persisted() {
+        let dir = std::env::temp_dir().join(format!("history-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("history.json");
+
+        let history = ConfigHistory::new(5, Some(path.clone()));
+        history
+            .record(
+                ResourceType::VirtualHost,
+                1,
+                json!([]),
+                entry(2, json!(["www"])),
+            )
+            .await;
+
+        // Replaced atomically through a temporary file
+        assert!(path.exists());
+        assert!(!path.with_extension("json.tmp").exists());
+
+        // Reloaded after a restart; the startup snapshot has a new version
+        let history = ConfigHistory::new(5, Some(path.clone()));
+        assert_eq!(
+            history.find(ResourceType::VirtualHost, 2).unwrap().value,
+            json!(["www"])
+        );
+        history
+            .record(
+                ResourceType::VirtualHost,
+                7,
+                json!(["www"]),
+                entry(8, json!([])),
+            )
+            .await;
+        let versions: Vec<u64> = history
+            .entries(ResourceType::VirtualHost)
+            .iter()
+            .map(|e| e.version)
+            .collect();
+        assert_eq!(versions, [8, 7, 2, 1]);
+
+        // Trimmed to a smaller capacity
+        let history = ConfigHistory::new(2, Some(path));
+        assert_eq!(history.entries(ResourceType::VirtualHost).len(), 2);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
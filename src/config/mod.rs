@@ -1,21 +1,28 @@
 // Configuration module entry point
 // Manages application configuration, runtime state, and version control
 
+mod history;
 mod persist;
 mod state;
 mod types;
 mod version;
 
+use crate::routing::RouteTable;
 use std::net::SocketAddr;
 use std::sync::Arc;
 
 // Re-export public types
 pub use state::AppState;
 pub use types::{
-    Config, DynamicConfig, DynamicPerformanceConfig, DynamicServerConfig, HeaderMatcher,
-    HealthConfig, HttpConfig, LoggingConfig, Route, RouteAction, RouteHandler, RouteMatch,
-    RoutesConfig, VirtualHost,
+    ApiAccess, ApiAuthConfig, ApiRoleConfig, ApiTlsConfig, AutoindexConfig, Config, CorsPolicy,
+    DynamicConfig, DynamicPerformanceConfig, DynamicServerConfig, GlobalRateLimit, HeaderMutations,
+    HeaderValueOption, HealthConfig, HstsConfig, HttpConfig, KeyValueMatcher, LoggingConfig,
+    OpenFileCacheConfig, OverloadPolicy, RateLimitAction, RateLimitAlgorithm, RateLimitFailureMode,
+    RateLimitKey, RateLimitPolicy, RateLimitServiceConfig, RedirectAction, RegexRewrite, Route,
+    RouteAction, RouteHandler, RouteMatch, RouteRewrite, RoutesConfig, SafeRegex,
+    SecurityHeadersConfig, VirtualHost,
 };
+pub use history::HistoryEntry;
 pub use persist::{create_state_manager, PersistentState};
 pub use version::ResourceType;
 
@@ -31,17 +38,20 @@ impl Config {
             .set_default("server.api_host", "127.0.0.1")?
             .set_default("server.api_port", 8000)?
             .set_default("server.enable_state_persistence", false)?
+            .set_default("server.config_history_size", 20)?
             .set_default("logging.level", "info")?
             .set_default("logging.access_log", true)?
             .set_default("logging.show_headers", false)?
             .set_default("logging.access_log_format", "combined")?
             .set_default("performance.keep_alive_timeout", 75)?
+            .set_default("performance.header_read_timeout", 10)?
             .set_default("performance.read_timeout", 30)?
             .set_default("performance.write_timeout", 30)?
             .set_default("http.default_content_type", "text/html; charset=utf-8")?
             .set_default("http.server_name", "Tokio-Hyper/1.0")?
             .set_default("http.enable_cors", false)?
             .set_default("http.max_body_size", 10_485_760)? // 10MB
+            .set_default("http.merge_slashes", true)?
             .build()?;
 
         settings.try_deserialize()
@@ -60,6 +70,9 @@ impl Config {
     }
 
     pub fn to_dynamic(&self) -> DynamicConfig {
+        let routes = Arc::new(self.routes.clone());
+        // Load virtual hosts from config (empty if not configured)
+        let virtual_hosts = Arc::new(self.virtual_hosts.clone());
         DynamicConfig {
             server: DynamicServerConfig {
                 host: self.server.host.clone(),
@@ -69,15 +82,19 @@ impl Config {
             },
             logging: self.logging.clone(),
             http: Arc::new(self.http.clone()),
-            routes: Arc::new(self.routes.clone()),
+            route_table: Arc::new(RouteTable::build(&routes, &virtual_hosts)),
+            routes,
             performance: DynamicPerformanceConfig {
                 keep_alive_timeout: self.performance.keep_alive_timeout,
+                header_read_timeout: self.performance.header_read_timeout,
                 read_timeout: self.performance.read_timeout,
                 write_timeout: self.performance.write_timeout,
                 max_connections: self.performance.max_connections,
+                max_connections_per_ip: self.performance.max_connections_per_ip,
+                overload: self.performance.overload.clone(),
+                open_file_cache: self.performance.open_file_cache.clone(),
             },
-            // Load virtual hosts from config (empty if not configured)
-            virtual_hosts: Arc::new(self.virtual_hosts.clone()),
+            virtual_hosts,
         }
     }
 
@@ -105,6 +122,7 @@ impl Config {
         if !state.virtual_hosts.is_empty() {
             dynamic.virtual_hosts = Arc::new(state.virtual_hosts.clone());
         }
+        dynamic.rebuild_route_table();
 
         dynamic
     }
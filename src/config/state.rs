@@ -1,30 +1,61 @@
 // Application state module
 // Manages runtime state and configuration cache
 
-use std::sync::atomic::{AtomicBool, Ordering};
+use arc_swap::ArcSwap;
 use std::sync::Arc;
-use tokio::sync::{Notify, RwLock};
+use tokio::sync::{Mutex, Notify, RwLock};
 
+use super::history::ConfigHistory;
 use super::persist::SharedStateManager;
 use super::types::{Config, DynamicConfig, DynamicServerConfig};
 use super::version::XdsVersionManager;
+use crate::api::{ApiAuth, AuditLog};
+use crate::handler::open_file_cache::OpenFileCache;
+use crate::handler::rate_limit::RateLimiter;
+use crate::handler::rate_limit_service::RateLimitServiceClient;
+use crate::http::throttle::SharedRates;
+use crate::server::overload::ConnectionLimiter;
 
 /// Application state
 pub struct AppState {
     pub config: Config,
-    pub dynamic_config: RwLock<DynamicConfig>,
+    /// Current configuration snapshot, replaced as a whole by `update_config`
+    dynamic_config: ArcSwap<DynamicConfig>,
+    /// Serializes snapshot updates so concurrent API calls do not lose changes
+    config_update_lock: Mutex<()>,
     pub restart_signal: Arc<Notify>,
     pub new_server_config: Arc<RwLock<Option<DynamicServerConfig>>>,
     pub api_restart_signal: Arc<Notify>,
 
-    // Cached config values for fast access without locks
-    pub cached_access_log: Arc<AtomicBool>,
-
     // xDS version management
     pub xds_versions: XdsVersionManager,
 
     // State persistence manager
     pub state_manager: SharedStateManager,
+
+    // Applied snapshots of each resource type for diff and rollback
+    pub config_history: ConfigHistory,
+
+    // Open file cache for static file lookups
+    pub open_file_cache: OpenFileCache,
+
+    // Connection limits and overload counters for the main server
+    pub connection_limiter: ConnectionLimiter,
+
+    // Rate limit counters of virtual hosts and routes
+    pub rate_limiter: RateLimiter,
+
+    // Client for the external rate limit service
+    pub rate_limit_service: RateLimitServiceClient,
+
+    // Bandwidth shared by the responses of each virtual host (`aggregate_limit_rate`)
+    pub vhost_bandwidth: SharedRates,
+
+    // Credentials of the management API (loaded from `api_auth` at startup)
+    pub api_auth: ApiAuth,
+
+    // Audit records of configuration changes made through the API
+    pub audit_log: AuditLog,
 }
 
 impl AppState {
@@ -34,25 +65,50 @@ impl AppState {
         // Get persisted state and merge with base config
         let persisted_state = state_manager.get_state().await;
         let dynamic = config.to_dynamic_with_state(&persisted_state);
-        
-        // Update cached values based on merged config
-        let cached_access_log = Arc::new(AtomicBool::new(dynamic.logging.access_log));
+        let open_file_cache = OpenFileCache::new(dynamic.performance.open_file_cache.clone());
+        // Persisted next to state.toml along with the state
+        let history_path = state_manager
+            .is_enabled()
+            .then(|| state_manager.state_path().with_file_name("history.json"));
+        let config_history = ConfigHistory::new(config.server.config_history_size, history_path);
 
         Self {
             config: config.clone(),
-            dynamic_config: RwLock::new(dynamic),
+            dynamic_config: ArcSwap::from_pointee(dynamic),
+            config_update_lock: Mutex::new(()),
             restart_signal: Arc::new(Notify::new()),
             new_server_config: Arc::new(RwLock::new(None)),
             api_restart_signal: Arc::new(Notify::new()),
-            cached_access_log,
             xds_versions: XdsVersionManager::new(),
             state_manager,
+            config_history,
+            open_file_cache,
+            connection_limiter: ConnectionLimiter::new(),
+            rate_limiter: RateLimiter::new(),
+            rate_limit_service: RateLimitServiceClient::new(),
+            vhost_bandwidth: SharedRates::new(),
+            api_auth: ApiAuth::disabled(),
+            audit_log: AuditLog::new(),
         }
     }
 
-    /// Update cached configuration values
-    pub fn update_cache(&self, new_config: &DynamicConfig) {
-        self.cached_access_log
-            .store(new_config.logging.access_log, Ordering::Relaxed);
+    /// Current configuration snapshot
+    ///
+    /// Lock-free; the snapshot stays consistent for as long as it is held,
+    /// even if an update is swapped in meanwhile.
+    pub fn snapshot(&self) -> Arc<DynamicConfig> {
+        self.dynamic_config.load_full()
+    }
+
+    /// Build a new snapshot from the current one and swap it in
+    ///
+    /// Requests keep using the snapshot they loaded; new requests see the
+    /// whole update at once. Returns what `update` returns.
+    pub async fn update_config<R>(&self, update: impl FnOnce(&mut DynamicConfig) -> R) -> R {
+        let _guard = self.config_update_lock.lock().await;
+        let mut next = DynamicConfig::clone(&self.dynamic_config.load());
+        let result = update(&mut next);
+        self.dynamic_config.store(Arc::new(next));
+        result
     }
 }
@@ -1,9 +1,10 @@
 // Configuration types module
 // Defines all configuration-related data structures
 
+use crate::routing::RouteTable;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::sync::{Arc, LazyLock};
 
 /// Main configuration structure
 #[derive(Debug, Deserialize, Clone)]
@@ -16,6 +17,9 @@ pub struct Config {
     /// Virtual hosts configuration (optional, xDS-compatible)
     #[serde(default)]
     pub virtual_hosts: Vec<VirtualHost>,
+    /// Management API authentication (required when the API listens beyond loopback)
+    #[serde(default)]
+    pub api_auth: Option<ApiAuthConfig>,
 }
 
 /// Dynamic configuration - can be modified at runtime
@@ -28,15 +32,144 @@ pub struct DynamicConfig {
     pub performance: DynamicPerformanceConfig,
     /// Virtual hosts configuration (xDS-compatible)
     pub virtual_hosts: Arc<Vec<VirtualHost>>,
+    /// Compiled `routes` + `virtual_hosts`, rebuilt whenever either changes
+    pub route_table: Arc<RouteTable>,
+}
+
+impl DynamicConfig {
+    /// Recompile the route table after `routes` or `virtual_hosts` changed
+    pub fn rebuild_route_table(&mut self) {
+        self.route_table = Arc::new(RouteTable::build(&self.routes, &self.virtual_hosts));
+    }
 }
 
 /// Dynamic performance configuration
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct DynamicPerformanceConfig {
     pub keep_alive_timeout: u64,
+    /// Seconds to receive a complete request head; 0 = no limit
+    #[serde(default = "default_header_read_timeout")]
+    pub header_read_timeout: u64,
     pub read_timeout: u64,
     pub write_timeout: u64,
     pub max_connections: Option<u64>,
+    /// Max concurrent connections from one client IP; 0 = unlimited
+    #[serde(default)]
+    pub max_connections_per_ip: u64,
+    /// What happens to connections over a limit
+    #[serde(default)]
+    pub overload: OverloadConfig,
+    /// Open file cache settings
+    #[serde(default)]
+    pub open_file_cache: OpenFileCacheConfig,
+}
+
+/// Overload behavior when `max_connections` or `max_connections_per_ip` is reached
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+pub struct OverloadConfig {
+    /// How connections over a limit are handled (default: `service_unavailable`)
+    #[serde(default)]
+    pub policy: OverloadPolicy,
+    /// `Retry-After` seconds sent with 503 responses (default: 1)
+    #[serde(default = "default_overload_retry_after")]
+    pub retry_after: u64,
+    /// Max connections waiting for a free slot with the `queue` policy (default: 128)
+    #[serde(default = "default_overload_queue_size")]
+    pub queue_size: usize,
+    /// Milliseconds a queued connection waits before it gets a 503 (default: 500)
+    #[serde(default = "default_overload_queue_timeout_ms")]
+    pub queue_timeout_ms: u64,
+}
+
+/// Overload policy
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum OverloadPolicy {
+    /// Close the connection without a response
+    Reject,
+    /// Reply `503 Service Unavailable` with `Retry-After`
+    #[default]
+    ServiceUnavailable,
+    /// Hold the connection in a bounded queue until a slot frees up,
+    /// then fall back to 503
+    Queue,
+}
+
+#[allow(clippy::missing_const_for_fn)]
+fn default_overload_retry_after() -> u64 {
+    1
+}
+
+#[allow(clippy::missing_const_for_fn)]
+fn default_overload_queue_size() -> usize {
+    128
+}
+
+#[allow(clippy::missing_const_for_fn)]
+fn default_overload_queue_timeout_ms() -> u64 {
+    500
+}
+
+impl Default for OverloadConfig {
+    fn default() -> Self {
+        Self {
+            policy: OverloadPolicy::default(),
+            retry_after: default_overload_retry_after(),
+            queue_size: default_overload_queue_size(),
+            queue_timeout_ms: default_overload_queue_timeout_ms(),
+        }
+    }
+}
+
+/// Open file cache configuration (nginx `open_file_cache` equivalent)
+///
+/// Caches canonicalized paths, file metadata and failed lookups
+/// so that repeated requests skip the filesystem syscalls.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+pub struct OpenFileCacheConfig {
+    /// Enable the cache (default: false)
+    #[serde(default)]
+    pub enabled: bool,
+    /// Maximum number of cached entries (default: 10000)
+    #[serde(default = "default_open_file_cache_max")]
+    pub max_entries: usize,
+    /// Seconds an entry stays valid before it is re-checked (default: 60)
+    #[serde(default = "default_open_file_cache_valid")]
+    pub valid: u64,
+    /// Also cache failed lookups, i.e. 404s (default: true)
+    #[serde(default = "default_open_file_cache_errors")]
+    pub cache_errors: bool,
+}
+
+#[allow(clippy::missing_const_for_fn)]
+fn default_header_read_timeout() -> u64 {
+    10
+}
+
+#[allow(clippy::missing_const_for_fn)]
+fn default_open_file_cache_max() -> usize {
+    10_000
+}
+
+#[allow(clippy::missing_const_for_fn)]
+fn default_open_file_cache_valid() -> u64 {
+    60
+}
+
+#[allow(clippy::missing_const_for_fn)]
+fn default_open_file_cache_errors() -> bool {
+    true
+}
+
+impl Default for OpenFileCacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_entries: default_open_file_cache_max(),
+            valid: default_open_file_cache_valid(),
+            cache_errors: default_open_file_cache_errors(),
+        }
+    }
 }
 
 /// Routes configuration
@@ -92,11 +225,57 @@ impl Default for HealthConfig {
 #[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum RouteHandler {
-    Dir { path: String },
+    Dir {
+        path: String,
+        /// Directory listing when no index file exists (optional)
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        autoindex: Option<AutoindexConfig>,
+    },
     File { path: String },
     Redirect { target: String },
 }
 
+/// Directory listing (autoindex) configuration
+///
+/// Rendered for `Dir` routes when a directory has no index file.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+pub struct AutoindexConfig {
+    /// Enable directory listing (default: true)
+    #[serde(default = "default_autoindex_enabled")]
+    pub enabled: bool,
+    /// Hide entries starting with '.' (default: true)
+    #[serde(default = "default_autoindex_hide_dotfiles")]
+    pub hide_dotfiles: bool,
+    /// Show sizes as 1.2K / 3.4M instead of exact bytes in HTML (default: true)
+    #[serde(default = "default_autoindex_human_readable")]
+    pub human_readable_sizes: bool,
+}
+
+#[allow(clippy::missing_const_for_fn)]
+fn default_autoindex_enabled() -> bool {
+    true
+}
+
+#[allow(clippy::missing_const_for_fn)]
+fn default_autoindex_hide_dotfiles() -> bool {
+    true
+}
+
+#[allow(clippy::missing_const_for_fn)]
+fn default_autoindex_human_readable() -> bool {
+    true
+}
+
+impl Default for AutoindexConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_autoindex_enabled(),
+            hide_dotfiles: default_autoindex_hide_dotfiles(),
+            human_readable_sizes: default_autoindex_human_readable(),
+        }
+    }
+}
+
 impl Default for RoutesConfig {
     fn default() -> Self {
         Self {
@@ -127,6 +306,120 @@ pub struct ServerConfig {
     /// Enable state persistence to state.toml (default: false)
     #[serde(default)]
     pub enable_state_persistence: bool,
+    /// Applied snapshots kept per resource type for rollback (default: 20, 0 disables)
+    #[serde(default = "default_config_history_size")]
+    pub config_history_size: usize,
+}
+
+#[allow(clippy::missing_const_for_fn)]
+fn default_config_history_size() -> usize {
+    20
+}
+
+/// Management API authentication
+///
+/// A request is accepted with a valid bearer token, a valid Basic auth user
+/// or a client certificate issued by `tls.client_ca_file`.
+#[derive(Debug, Deserialize, Clone)]
+pub struct ApiAuthConfig {
+    /// Realm of the `WWW-Authenticate` challenge
+    #[serde(default = "default_api_auth_realm")]
+    pub realm: String,
+    /// Static bearer tokens, stored as SHA-256 hashes
+    #[serde(default)]
+    pub tokens: Vec<ApiToken>,
+    /// htpasswd file of Basic auth users (bcrypt, apr1, `{SHA}` or crypt hashes)
+    #[serde(default)]
+    pub htpasswd_file: Option<String>,
+    /// Serve the API over TLS (required for client certificates)
+    #[serde(default)]
+    pub tls: Option<ApiTlsConfig>,
+    /// Roles limiting what each identity may read or change (full access when empty)
+    #[serde(default)]
+    pub roles: Vec<ApiRoleConfig>,
+}
+
+#[allow(clippy::missing_const_for_fn)]
+fn default_api_auth_realm() -> String {
+    "yarhs".to_string()
+}
+
+/// Bearer token of the management API
+#[derive(Debug, Deserialize, Clone)]
+pub struct ApiToken {
+    /// Identity of callers using this token
+    pub name: String,
+    /// Hex SHA-256 of the token (`printf %s "$TOKEN" | sha256sum`)
+    pub sha256: String,
+}
+
+/// Role of the management API
+///
+/// Members get the listed permissions. Virtual host permissions cover the
+/// virtual hosts matched by `virtual_hosts` or `domains` (all when both are empty).
+#[derive(Debug, Deserialize, Clone)]
+pub struct ApiRoleConfig {
+    pub name: String,
+    /// Identities with this role: token names, user names or certificate subjects
+    #[serde(default)]
+    pub members: Vec<String>,
+    #[serde(default)]
+    pub permissions: ApiPermissions,
+    /// Virtual host names (`www-site`, or a `team-*` prefix)
+    #[serde(default)]
+    pub virtual_hosts: Vec<String>,
+    /// Domain patterns covering every domain of a virtual host (`*.example.com`)
+    #[serde(default)]
+    pub domains: Vec<String>,
+}
+
+/// Access of a role per resource type (none when omitted)
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct ApiPermissions {
+    #[serde(default)]
+    pub listener: Option<ApiAccess>,
+    #[serde(default)]
+    pub route: Option<ApiAccess>,
+    #[serde(default)]
+    pub http: Option<ApiAccess>,
+    #[serde(default)]
+    pub logging: Option<ApiAccess>,
+    #[serde(default)]
+    pub performance: Option<ApiAccess>,
+    #[serde(default)]
+    pub virtual_host: Option<ApiAccess>,
+    /// Persisted state (`GET` / `DELETE /v1/state`)
+    #[serde(default)]
+    pub state: Option<ApiAccess>,
+    /// Audit log (`GET /v1/audit`)
+    #[serde(default)]
+    pub audit: Option<ApiAccess>,
+    /// Runtime counters (`GET /v1/stats`)
+    #[serde(default)]
+    pub stats: Option<ApiAccess>,
+}
+
+/// Access level; `write` includes `read`
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiAccess {
+    Read,
+    Write,
+}
+
+/// TLS settings of the management API
+#[derive(Debug, Deserialize, Clone)]
+pub struct ApiTlsConfig {
+    /// PEM certificate chain of the API server
+    pub cert_file: String,
+    /// PEM private key of the API server
+    pub key_file: String,
+    /// PEM CA certificates that issue client certificates (enables mTLS)
+    #[serde(default)]
+    pub client_ca_file: Option<String>,
+    /// Reject TLS connections without a client certificate
+    #[serde(default)]
+    pub require_client_cert: bool,
 }
 
 /// Logging configuration
@@ -144,6 +437,11 @@ pub struct LoggingConfig {
     /// Error log file path (optional, stderr if not set)
     #[serde(default)]
     pub error_log_file: Option<String>,
+    /// Audit log file path for configuration changes (optional, not written if not set)
+    ///
+    /// Startup only: not returned by the API, and a logging update cannot change it.
+    #[serde(default, skip_serializing)]
+    pub audit_log_file: Option<String>,
 }
 
 #[allow(clippy::missing_const_for_fn)]
@@ -155,9 +453,18 @@ fn default_access_log_format() -> String {
 #[derive(Debug, Deserialize, Clone)]
 pub struct PerformanceConfig {
     pub keep_alive_timeout: u64,
+    pub header_read_timeout: u64,
     pub read_timeout: u64,
     pub write_timeout: u64,
     pub max_connections: Option<u64>,
+    #[serde(default)]
+    pub max_connections_per_ip: u64,
+    /// Overload behavior when a connection limit is reached
+    #[serde(default)]
+    pub overload: OverloadConfig,
+    /// Open file cache settings
+    #[serde(default)]
+    pub open_file_cache: OpenFileCacheConfig,
 }
 
 /// HTTP configuration
@@ -167,6 +474,284 @@ pub struct HttpConfig {
     pub server_name: String,
     pub enable_cors: bool,
     pub max_body_size: u64,
+    /// Collapse repeated slashes in request paths (`//a///b` -> `/a/b`)
+    #[serde(default = "default_merge_slashes")]
+    pub merge_slashes: bool,
+    /// Security headers added to every routed response (virtual hosts may override)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub security_headers: Option<SecurityHeadersConfig>,
+    /// CORS policy for all requests (virtual hosts and routes may override)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cors: Option<CorsPolicy>,
+    /// External service checked for `global_rate_limits` of virtual hosts and routes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate_limit_service: Option<RateLimitServiceConfig>,
+}
+
+impl HttpConfig {
+    /// Global CORS policy: `cors`, or the default policy when `enable_cors` is set
+    pub fn cors_policy(&self) -> Option<&CorsPolicy> {
+        static DEFAULT_CORS_POLICY: LazyLock<CorsPolicy> = LazyLock::new(CorsPolicy::default);
+        self.cors
+            .as_ref()
+            .or_else(|| self.enable_cors.then(|| &*DEFAULT_CORS_POLICY))
+    }
+}
+
+#[allow(clippy::missing_const_for_fn)]
+fn default_merge_slashes() -> bool {
+    true
+}
+
+/// Security headers preset
+///
+/// An empty block enables the defaults (HSTS, `nosniff`, `X-Frame-Options: DENY`,
+/// `Referrer-Policy`). Setting a string header to "" omits it. Headers already
+/// set by `response_headers_to_add` are left unchanged.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct SecurityHeadersConfig {
+    /// Emit the headers (default: true)
+    pub enabled: bool,
+    /// `Strict-Transport-Security`
+    pub hsts: HstsConfig,
+    /// `Content-Security-Policy` value (default: none)
+    pub content_security_policy: String,
+    /// Send the CSP as `Content-Security-Policy-Report-Only`
+    pub csp_report_only: bool,
+    /// `X-Content-Type-Options: nosniff` (default: true)
+    pub content_type_nosniff: bool,
+    /// `X-Frame-Options` (default: "DENY")
+    pub frame_options: String,
+    /// `Referrer-Policy` (default: "strict-origin-when-cross-origin")
+    pub referrer_policy: String,
+    /// `Permissions-Policy` (default: none)
+    pub permissions_policy: String,
+    /// `Cross-Origin-Opener-Policy` (e.g. "same-origin" for `SharedArrayBuffer`)
+    pub cross_origin_opener_policy: String,
+    /// `Cross-Origin-Embedder-Policy` (e.g. "require-corp" for `SharedArrayBuffer`)
+    pub cross_origin_embedder_policy: String,
+}
+
+impl Default for SecurityHeadersConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            hsts: HstsConfig::default(),
+            content_security_policy: String::new(),
+            csp_report_only: false,
+            content_type_nosniff: true,
+            frame_options: "DENY".to_string(),
+            referrer_policy: "strict-origin-when-cross-origin".to_string(),
+            permissions_policy: String::new(),
+            cross_origin_opener_policy: String::new(),
+            cross_origin_embedder_policy: String::new(),
+        }
+    }
+}
+
+/// `Strict-Transport-Security` settings
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct HstsConfig {
+    /// Emit the header (default: true)
+    pub enabled: bool,
+    /// `max-age` in seconds (default: one year)
+    pub max_age: u64,
+    /// Add `includeSubDomains`
+    pub include_subdomains: bool,
+    /// Add `preload` (requires `include_subdomains` and a max-age of at least one year)
+    pub preload: bool,
+}
+
+impl Default for HstsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_age: 31_536_000,
+            include_subdomains: false,
+            preload: false,
+        }
+    }
+}
+
+/// CORS policy (modeled on Envoy's `CorsPolicy`)
+///
+/// An empty block allows any origin for simple and preflight requests with
+/// the methods and headers the server supports. The most specific policy
+/// applies: route, then virtual host, then `http.cors`.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct CorsPolicy {
+    /// Apply the policy (false disables CORS inherited from a wider scope)
+    pub enabled: bool,
+    /// Allowed origins; `{"exact": "*"}` allows any origin (default)
+    pub allow_origin: Vec<OriginMatcher>,
+    /// `Access-Control-Allow-Methods` (default: GET, HEAD, OPTIONS)
+    pub allow_methods: Vec<String>,
+    /// `Access-Control-Allow-Headers` (default: Content-Type, Range)
+    pub allow_headers: Vec<String>,
+    /// `Access-Control-Expose-Headers` (default: none)
+    pub expose_headers: Vec<String>,
+    /// Send `Access-Control-Allow-Credentials: true` (not allowed with `*`)
+    pub allow_credentials: bool,
+    /// `Access-Control-Max-Age` in seconds, 0 omits it (default: 86400)
+    pub max_age: u64,
+}
+
+impl Default for CorsPolicy {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            allow_origin: vec![OriginMatcher {
+                exact: Some("*".to_string()),
+                ..OriginMatcher::default()
+            }],
+            allow_methods: vec!["GET".to_string(), "HEAD".to_string(), "OPTIONS".to_string()],
+            allow_headers: vec!["Content-Type".to_string(), "Range".to_string()],
+            expose_headers: Vec::new(),
+            allow_credentials: false,
+            max_age: 86_400,
+        }
+    }
+}
+
+/// Allowed CORS origin; exactly one field must be set
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq, Default)]
+pub struct OriginMatcher {
+    /// Exact origin (e.g., `https://app.example.com`), or "*" for any origin
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exact: Option<String>,
+    /// Origin suffix (e.g., ".example.com")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub suffix: Option<String>,
+    /// Regex that must match the whole origin
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub safe_regex: Option<SafeRegex>,
+}
+
+/// Rate limit on a virtual host or route
+///
+/// Limits of the virtual host and of the matched route all apply; a request
+/// over any of them gets `429 Too Many Requests`.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq, Hash)]
+pub struct RateLimitPolicy {
+    /// What requests are counted by (default: `client_ip`)
+    #[serde(default)]
+    pub key: RateLimitKey,
+    /// Requests allowed per `interval`
+    pub requests: u64,
+    /// Interval in seconds (default: 1)
+    #[serde(default = "default_rate_limit_interval")]
+    pub interval: u64,
+    /// Token bucket capacity: requests allowed at once after an idle period (default: `requests`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub burst: Option<u64>,
+    /// Counting algorithm (default: `token_bucket`)
+    #[serde(default)]
+    pub algorithm: RateLimitAlgorithm,
+}
+
+/// Rate limit key: which requests share a counter
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq, Hash, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum RateLimitKey {
+    /// One counter per client IP
+    #[default]
+    ClientIp,
+    /// One counter per value of this request header (requests without it are not limited)
+    Header(String),
+    /// One counter per route
+    Route,
+    /// One counter per virtual host
+    Vhost,
+}
+
+/// Rate limit algorithm
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum RateLimitAlgorithm {
+    /// Tokens refill continuously at `requests / interval`, up to `burst`
+    #[default]
+    TokenBucket,
+    /// Weighted count over the current and previous interval
+    SlidingWindow,
+}
+
+#[allow(clippy::missing_const_for_fn)]
+fn default_rate_limit_interval() -> u64 {
+    1
+}
+
+/// External rate limit service for limits shared across server instances
+///
+/// Speaks the JSON form of Envoy's rate limit service protocol: descriptors
+/// are posted to `http://<address>/json` and the service answers `OK` or `OVER_LIMIT`.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+pub struct RateLimitServiceConfig {
+    /// Service `host:port`
+    pub address: String,
+    /// Domain sent with every check (default: "yarhs")
+    #[serde(default = "default_rate_limit_domain")]
+    pub domain: String,
+    /// Time allowed per check in milliseconds (default: 20)
+    #[serde(default = "default_rate_limit_service_timeout")]
+    pub timeout_ms: u64,
+    /// What happens when the service fails or times out (default: `fail_open`)
+    #[serde(default)]
+    pub failure_mode: RateLimitFailureMode,
+}
+
+/// Outcome of a failed rate limit service check
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum RateLimitFailureMode {
+    /// Serve the request
+    #[default]
+    FailOpen,
+    /// Answer `503 Service Unavailable`
+    FailClosed,
+}
+
+fn default_rate_limit_domain() -> String {
+    "yarhs".to_string()
+}
+
+#[allow(clippy::missing_const_for_fn)]
+fn default_rate_limit_service_timeout() -> u64 {
+    20
+}
+
+/// Descriptor sent to the rate limit service, built from request attributes
+///
+/// Each action adds one entry; if any action has no value (e.g. a missing
+/// header), the descriptor is not sent.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+pub struct GlobalRateLimit {
+    pub actions: Vec<RateLimitAction>,
+}
+
+/// Descriptor entry source
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RateLimitAction {
+    /// `remote_address` entry with the client IP
+    RemoteAddress,
+    /// Entry with the value of a request header
+    RequestHeaders {
+        header_name: String,
+        descriptor_key: String,
+    },
+    /// Fixed entry (`generic_key` unless `descriptor_key` is set)
+    GenericKey {
+        #[serde(default = "default_generic_key")]
+        descriptor_key: String,
+        descriptor_value: String,
+    },
+}
+
+fn default_generic_key() -> String {
+    "generic_key".to_string()
 }
 
 // ============================================
@@ -186,6 +771,27 @@ pub struct VirtualHost {
     /// Default index files for this virtual host
     #[serde(default)]
     pub index_files: Option<Vec<String>>,
+    /// Directory listing for `Dir` routes without their own setting
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub autoindex: Option<AutoindexConfig>,
+    /// Security headers for this host (replaces the `http.security_headers` preset)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub security_headers: Option<SecurityHeadersConfig>,
+    /// CORS policy for this host (replaces `http.cors`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cors: Option<CorsPolicy>,
+    /// Rate limits applied to every request of this host
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rate_limits: Vec<RateLimitPolicy>,
+    /// Descriptors checked with the rate limit service for every request of this host
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub global_rate_limits: Vec<GlobalRateLimit>,
+    /// Bandwidth cap in bytes per second shared by all responses of this host
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aggregate_limit_rate: Option<u64>,
+    /// Headers added to or removed from every request and response of this host
+    #[serde(flatten)]
+    pub header_mutations: HeaderMutations,
 }
 
 /// xDS Route - matches requests and dispatches to actions
@@ -197,12 +803,111 @@ pub struct Route {
     /// Match conditions (prefix, path, headers)
     #[serde(rename = "match")]
     pub match_rule: RouteMatch,
+    /// URL rewrite applied before the action runs (optional)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rewrite: Option<RouteRewrite>,
+    /// CORS policy for this route (replaces the virtual host's)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cors: Option<CorsPolicy>,
+    /// Rate limits for this route (in addition to the virtual host's)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rate_limits: Vec<RateLimitPolicy>,
+    /// Descriptors checked with the rate limit service (in addition to the virtual host's)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub global_rate_limits: Vec<GlobalRateLimit>,
+    /// Bandwidth limit in bytes per second for each response (nginx `limit_rate`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit_rate: Option<u64>,
+    /// Bytes sent at full speed before `limit_rate` applies (nginx `limit_rate_after`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit_rate_after: Option<u64>,
+    /// Headers added to or removed from requests and responses of this route
+    #[serde(flatten)]
+    pub header_mutations: HeaderMutations,
     /// Action to take when matched
     #[serde(flatten)]
     pub action: RouteAction,
 }
 
+/// Request and response header changes of a virtual host or route
+///
+/// Removals are applied before additions. Virtual host changes are applied
+/// first, so a route's `append: false` header overrides the host's.
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct HeaderMutations {
+    /// Headers added to the request before the action runs
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub request_headers_to_add: Vec<HeaderValueOption>,
+    /// Header names removed from the request
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub request_headers_to_remove: Vec<String>,
+    /// Headers added to the response
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub response_headers_to_add: Vec<HeaderValueOption>,
+    /// Header names removed from the response
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub response_headers_to_remove: Vec<String>,
+}
+
+impl HeaderMutations {
+    /// Whether any request header is added or removed
+    pub const fn has_request_mutations(&self) -> bool {
+        !self.request_headers_to_add.is_empty() || !self.request_headers_to_remove.is_empty()
+    }
+}
+
+/// Header to add, e.g. `{"name": "X-Served-By", "value": "%REQ(host)%"}`
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+pub struct HeaderValueOption {
+    pub name: String,
+    /// Value; may contain `%REMOTE_ADDR%`, `%REQ(name)%` and `%START_TIME%`
+    pub value: String,
+    /// Append to existing values (true) or replace them (false)
+    #[serde(default = "default_header_append")]
+    pub append: bool,
+}
+
+#[allow(clippy::missing_const_for_fn)]
+fn default_header_append() -> bool {
+    true
+}
+
+/// URL rewrite for a matched route
+///
+/// At most one of `prefix_rewrite` and `regex_rewrite` may be set. The
+/// rewritten path is what `Dir` routes resolve against; with `rematch` the
+/// rewritten request goes through virtual host and route matching again.
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct RouteRewrite {
+    /// Replace the matched prefix (e.g., "/v2" turns "/api/users" on prefix "/api" into "/v2/users")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prefix_rewrite: Option<String>,
+    /// Rebuild the path from regex capture groups
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub regex_rewrite: Option<RegexRewrite>,
+    /// Replace the Host used for virtual host matching (only with `rematch`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host_rewrite: Option<String>,
+    /// Re-enter virtual host and route matching with the rewritten request
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub rematch: bool,
+}
+
+/// Regex path rewrite
+///
+/// When `pattern` matches the whole path, the path is replaced with
+/// `substitution`, where `$1`, `${1}` and `${name}` refer to capture groups.
+/// Paths that do not match are left unchanged.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+pub struct RegexRewrite {
+    pub pattern: SafeRegex,
+    pub substitution: String,
+}
+
 /// Route matching conditions
+///
+/// At most one path specifier (`prefix`, `path`, `safe_regex`,
+/// `path_separated_prefix`) may be set; none means match all paths.
 #[derive(Debug, Deserialize, Serialize, Clone, Default)]
 pub struct RouteMatch {
     /// Path prefix match (e.g., "/api" matches "/api/users")
@@ -211,15 +916,90 @@ pub struct RouteMatch {
     /// Exact path match
     #[serde(default)]
     pub path: Option<String>,
+    /// Regex that must match the whole path (e.g., "/users/(\\d+)")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub safe_regex: Option<SafeRegex>,
+    /// Prefix match on segment boundaries ("/api" matches "/api" and "/api/users", not "/apix")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path_separated_prefix: Option<String>,
     /// Header matchers (optional)
     #[serde(default)]
-    pub headers: Option<Vec<HeaderMatcher>>,
+    pub headers: Option<Vec<KeyValueMatcher>>,
+    /// Allowed methods, e.g. `["GET"]` (HEAD also matches GET)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub methods: Option<Vec<String>>,
+    /// Query parameter matchers (values are percent-decoded)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub query_parameters: Option<Vec<KeyValueMatcher>>,
+    /// Cookie matchers
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cookies: Option<Vec<KeyValueMatcher>>,
 }
 
-/// Header matching condition
-#[derive(Debug, Deserialize, Serialize, Clone)]
-pub struct HeaderMatcher {
-    /// Header name
+/// Maximum compiled size of a route regex (bytes)
+const SAFE_REGEX_SIZE_LIMIT: usize = 1 << 20;
+
+/// Compiled linear-time regex, (de)serialized as its source pattern
+///
+/// Uses the `regex` crate (RE2 semantics: no backreferences or lookaround),
+/// so matching time is linear in the input. The pattern is anchored to match
+/// the whole input (path or value). Invalid patterns fail deserialization, which rejects the
+/// configuration update.
+#[derive(Debug, Clone)]
+pub struct SafeRegex {
+    pattern: String,
+    regex: regex::Regex,
+}
+
+impl SafeRegex {
+    /// Compile a pattern
+    pub fn new(pattern: &str) -> Result<Self, String> {
+        let regex = regex::RegexBuilder::new(&format!("^(?:{pattern})$"))
+            .size_limit(SAFE_REGEX_SIZE_LIMIT)
+            .build()
+            .map_err(|e| format!("invalid safe_regex '{pattern}': {e}"))?;
+        Ok(Self {
+            pattern: pattern.to_string(),
+            regex,
+        })
+    }
+
+    /// Source pattern as configured
+    pub fn as_str(&self) -> &str {
+        &self.pattern
+    }
+
+    /// Compiled (anchored) regex
+    pub const fn regex(&self) -> &regex::Regex {
+        &self.regex
+    }
+}
+
+impl PartialEq for SafeRegex {
+    fn eq(&self, other: &Self) -> bool {
+        self.pattern == other.pattern
+    }
+}
+
+impl Eq for SafeRegex {}
+
+impl Serialize for SafeRegex {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for SafeRegex {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let pattern = String::deserialize(deserializer)?;
+        Self::new(&pattern).map_err(serde::de::Error::custom)
+    }
+}
+
+/// Header, query parameter or cookie matching condition
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct KeyValueMatcher {
+    /// Header, query parameter or cookie name
     pub name: String,
     /// Expected value (exact match)
     #[serde(default)]
@@ -227,9 +1007,15 @@ pub struct HeaderMatcher {
     /// Prefix match
     #[serde(default)]
     pub prefix: Option<String>,
-    /// Check if header is present
+    /// Check if the value is present
     #[serde(default)]
     pub present: Option<bool>,
+    /// Regex that must match the whole value
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub regex: Option<SafeRegex>,
+    /// Negate the result of this matcher
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub invert_match: bool,
 }
 
 /// Route action - what to do when a route matches
@@ -237,15 +1023,20 @@ pub struct HeaderMatcher {
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum RouteAction {
     /// Serve files from a directory
-    Dir { path: String },
+    Dir {
+        path: String,
+        /// Directory listing when no index file exists (overrides vhost setting)
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        autoindex: Option<AutoindexConfig>,
+        /// nginx-style `try_files` candidates, e.g. `["$uri", "$uri/", "/index.html", "=404"]`
+        /// The last entry is a fallback file, a named route (`@name`) or a status (`=404`)
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        try_files: Option<Vec<String>>,
+    },
     /// Serve a specific file
     File { path: String },
     /// HTTP redirect
-    Redirect {
-        target: String,
-        #[serde(default = "default_redirect_code")]
-        code: u16,
-    },
+    Redirect(RedirectAction),
     /// Direct response (e.g., for health checks, errors)
     Direct {
         status: u16,
@@ -256,6 +1047,43 @@ pub enum RouteAction {
     },
 }
 
+/// HTTP redirect (modeled on Envoy's `RedirectAction`)
+///
+/// Either a fixed `target`, or a Location built from the request URL with the
+/// scheme, host, port and path replaced as configured. At most one of
+/// `path_redirect`, `prefix_rewrite` and `regex_rewrite` may be set. The query
+/// string is kept unless `strip_query` is set or the new path has its own query.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq, Default)]
+pub struct RedirectAction {
+    /// Fixed Location (may reference regex captures); excludes the fields below
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+    /// Replace the scheme (e.g., "https")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scheme_redirect: Option<String>,
+    /// Replace the host (e.g., "www.example.com")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host_redirect: Option<String>,
+    /// Replace the port (omitted from Location when it is the scheme's default)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub port_redirect: Option<u16>,
+    /// Replace the whole path (may reference regex captures and contain a query)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path_redirect: Option<String>,
+    /// Replace the matched prefix of the path
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prefix_rewrite: Option<String>,
+    /// Rebuild the path from regex capture groups
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub regex_rewrite: Option<RegexRewrite>,
+    /// Drop the request's query string
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub strip_query: bool,
+    /// Status code: 301, 302, 303, 307 or 308
+    #[serde(default = "default_redirect_code")]
+    pub code: u16,
+}
+
 #[allow(clippy::missing_const_for_fn)]
 fn default_redirect_code() -> u16 {
     302
@@ -266,12 +1094,17 @@ impl RouteAction {
     #[allow(dead_code)]
     pub fn from_handler(handler: &RouteHandler) -> Self {
         match handler {
-            RouteHandler::Dir { path } => Self::Dir { path: path.clone() },
+            RouteHandler::Dir { path, autoindex } => Self::Dir {
+                path: path.clone(),
+                autoindex: autoindex.clone(),
+                try_files: None,
+            },
             RouteHandler::File { path } => Self::File { path: path.clone() },
-            RouteHandler::Redirect { target } => Self::Redirect {
-                target: target.clone(),
+            RouteHandler::Redirect { target } => Self::Redirect(RedirectAction {
+                target: Some(target.clone()),
                 code: 302,
-            },
+                ..RedirectAction::default()
+            }),
         }
     }
 }
@@ -0,0 +1,329 @@
+//! Directory listing (autoindex) module
+//!
+//! Renders the contents of a directory as a sortable HTML page or a JSON array
+//! when a `Dir` route has no index file. Output format is chosen by the
+//! `format` query parameter (`html`/`json`) or the `Accept` header.
+//!
+//! Sorting uses the `sort` (`name`, `size`, `mtime`) and `order` (`asc`, `desc`)
+//! query parameters. Directories are always listed before files.
+
+use crate::config::AutoindexConfig;
+use crate::handler::router::RequestContext;
+use crate::http::{self, cache, uri, ResponseBody};
+use hyper::Response;
+use serde::Serialize;
+use std::fmt::Write;
+use std::path::Path;
+use std::time::SystemTime;
+use tokio::fs;
+
+/// Single directory entry
+#[derive(Debug, Serialize)]
+struct DirEntry {
+    name: String,
+    size: u64,
+    mtime: String,
+    is_dir: bool,
+    #[serde(skip)]
+    modified: SystemTime,
+}
+
+/// Sort column
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SortKey {
+    Name,
+    Size,
+    Mtime,
+}
+
+/// Listing request options parsed from the query string
+#[derive(Debug, Clone, Copy)]
+struct ListingQuery {
+    sort: SortKey,
+    descending: bool,
+    json: Option<bool>,
+}
+
+/// Location of a directory URL with the trailing '/' added
+///
+/// Leading slashes are collapsed so `//host` cannot become a protocol-relative
+/// redirect to another host.
+fn directory_location(path: &str, query: Option<&str>) -> String {
+    let path = format!("/{}/", path.trim_start_matches('/'));
+    let path = uri::encode_location_path(&path);
+    match query {
+        Some(q) => format!("{path}?{q}"),
+        None => path,
+    }
+}
+
+/// Render a directory listing for an already resolved (and traversal-checked) directory
+pub async fn serve_listing(
+    ctx: &RequestContext<'_>,
+    dir: &Path,
+    config: &AutoindexConfig,
+) -> Response<ResponseBody> {
+    // Relative links only work when the URL ends with '/'
+    if !ctx.path.ends_with('/') {
+        let target = directory_location(ctx.path, ctx.query);
+        return http::build_redirect_response_with_code(&target, 301);
+    }
+
+    let Some(mut entries) = read_entries(dir, config.hide_dotfiles).await else {
+        return http::build_404_response();
+    };
+
+    let query = parse_query(ctx.query);
+    sort_entries(&mut entries, query.sort, query.descending);
+
+    let wants_json = query.json.unwrap_or_else(|| {
+        ctx.accept
+            .as_deref()
+            .is_some_and(|accept| accept.contains("application/json"))
+    });
+
+    if wants_json {
+        let json = serde_json::to_string(&entries).unwrap_or_else(|_| "[]".to_string());
+        http::response::build_json_response(json, ctx.is_head)
+    } else {
+        let html = render_html(ctx.path, &entries, query, config.human_readable_sizes);
+        http::response::build_html_response(html, ctx.is_head)
+    }
+}
+
+/// Read directory entries, skipping entries whose metadata cannot be read
+async fn read_entries(dir: &Path, hide_dotfiles: bool) -> Option<Vec<DirEntry>> {
+    let mut read_dir = fs::read_dir(dir).await.ok()?;
+    let mut entries = Vec::new();
+
+    while let Ok(Some(entry)) = read_dir.next_entry().await {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if hide_dotfiles && name.starts_with('.') {
+            continue;
+        }
+        let Ok(metadata) = fs::metadata(entry.path()).await else {
+            continue;
+        };
+        let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+        entries.push(DirEntry {
+            name,
+            size: if metadata.is_dir() { 0 } else { metadata.len() },
+            mtime: cache::format_http_date(modified),
+            is_dir: metadata.is_dir(),
+            modified,
+        });
+    }
+
+    Some(entries)
+}
+
+/// Parse `sort`, `order` and `format` query parameters
+fn parse_query(query: Option<&str>) -> ListingQuery {
+    let mut result = ListingQuery {
+        sort: SortKey::Name,
+        descending: false,
+        json: None,
+    };
+
+    for pair in query.unwrap_or("").split('&') {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        match (key, value) {
+            ("sort", "name") => result.sort = SortKey::Name,
+            ("sort", "size") => result.sort = SortKey::Size,
+            ("sort", "mtime") => result.sort = SortKey::Mtime,
+            ("order", "desc") => result.descending = true,
+            ("order", "asc") => result.descending = false,
+            ("format", "json") => result.json = Some(true),
+            ("format", "html") => result.json = Some(false),
+            _ => {}
+        }
+    }
+
+    result
+}
+
+/// Sort entries by the given key, keeping directories first
+fn sort_entries(entries: &mut [DirEntry], sort: SortKey, descending: bool) {
+    entries.sort_by(|a, b| {
+        let ordering = match sort {
+            SortKey::Name => a.name.cmp(&b.name),
+            SortKey::Size => a.size.cmp(&b.size).then_with(|| a.name.cmp(&b.name)),
+            SortKey::Mtime => a.modified.cmp(&b.modified).then_with(|| a.name.cmp(&b.name)),
+        };
+        let ordering = if descending { ordering.reverse() } else { ordering };
+        b.is_dir.cmp(&a.is_dir).then(ordering)
+    });
+}
+
+/// Render the HTML listing page
+fn render_html(
+    path: &str,
+    entries: &[DirEntry],
+    query: ListingQuery,
+    human_readable: bool,
+) -> String {
+    let title = escape_html(path);
+    let mut html = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Index of {title}</title>\n\
+         <style>body{{font-family:monospace}}td,th{{padding:2px 12px;text-align:left}}</style>\n\
+         </head>\n<body>\n<h1>Index of {title}</h1>\n<table>\n<tr>"
+    );
+
+    for (label, key, name) in [
+        ("Name", SortKey::Name, "name"),
+        ("Last modified", SortKey::Mtime, "mtime"),
+        ("Size", SortKey::Size, "size"),
+    ] {
+        // Clicking the active column toggles the order
+        let order = if query.sort == key && !query.descending {
+            "desc"
+        } else {
+            "asc"
+        };
+        let _ = write!(html, "<th><a href=\"?sort={name}&amp;order={order}\">{label}</a></th>");
+    }
+    html.push_str("</tr>\n");
+
+    if path != "/" {
+        html.push_str("<tr><td><a href=\"../\">../</a></td><td></td><td>-</td></tr>\n");
+    }
+
+    for entry in entries {
+        let suffix = if entry.is_dir { "/" } else { "" };
+        let size = if entry.is_dir {
+            "-".to_string()
+        } else if human_readable {
+            format_size(entry.size)
+        } else {
+            entry.size.to_string()
+        };
+        let _ = writeln!(
+            html,
+            "<tr><td><a href=\"{href}{suffix}\">{name}{suffix}</a></td><td>{mtime}</td><td>{size}</td></tr>",
+            href = encode_href(&entry.name),
+            name = escape_html(&entry.name),
+            mtime = entry.mtime,
+        );
+    }
+
+    html.push_str("</table>\n</body>\n</html>");
+    html
+}
+
+/// Format a byte count like nginx `autoindex_exact_size off` (e.g. "1.5K", "20M")
+#[allow(clippy::cast_precision_loss)]
+fn format_size(size: u64) -> String {
+    const UNITS: [&str; 4] = ["K", "M", "G", "T"];
+    if size < 1024 {
+        return size.to_string();
+    }
+    let mut value = size as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if value < 10.0 {
+        format!("{value:.1}{}", UNITS[unit])
+    } else {
+        format!("{value:.0}{}", UNITS[unit])
+    }
+}
+
+/// Escape text for HTML content and attributes
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+/// Percent-encode a file name for use as a relative link
+fn encode_href(name: &str) -> String {
+    let mut encoded = String::with_capacity(name.len());
+    for byte in name.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'_' | b'.' | b'~') {
+            encoded.push(char::from(byte));
+        } else {
+            let _ = write!(encoded, "%{byte:02X}");
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(name: &str, size: u64, secs: u64, is_dir: bool) -> DirEntry {
+        let modified = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(secs);
+        DirEntry {
+            name: name.to_string(),
+            size,
+            mtime: cache::format_http_date(modified),
+            is_dir,
+            modified,
+        }
+    }
+
+    #[test]
+    fn test_directory_location() {
+        assert_eq!(directory_location("/docs", None), "/docs/");
+        assert_eq!(directory_location("/docs", Some("sort=size")), "/docs/?sort=size");
+        assert_eq!(directory_location("/my docs", None), "/my%20docs/");
+        // Not a protocol-relative URL pointing at another host
+        assert_eq!(directory_location("//evil.example", None), "/evil.example/");
+        assert_eq!(directory_location("///evil.example", None), "/evil.example/");
+        assert_eq!(directory_location("/\\evil.example", None), "/%5Cevil.example/");
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(512), "512");
+        assert_eq!(format_size(1536), "1.5K");
+        assert_eq!(format_size(20 * 1024 * 1024), "20M");
+    }
+
+    #[test]
+    fn test_sort_entries_dirs_first() {
+        let mut entries = vec![
+            entry("b.txt", 10, 3, false),
+            entry("z", 0, 1, true),
+            entry("a.txt", 30, 2, false),
+        ];
+        sort_entries(&mut entries, SortKey::Name, false);
+        let names: Vec<_> = entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["z", "a.txt", "b.txt"]);
+
+        sort_entries(&mut entries, SortKey::Size, true);
+        let names: Vec<_> = entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["z", "a.txt", "b.txt"]);
+
+        sort_entries(&mut entries, SortKey::Mtime, true);
+        let names: Vec<_> = entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["z", "b.txt", "a.txt"]);
+    }
+
+    #[test]
+    fn test_parse_query() {
+        let q = parse_query(Some("sort=size&order=desc&format=json"));
+        assert_eq!(q.sort, SortKey::Size);
+        assert!(q.descending);
+        assert_eq!(q.json, Some(true));
+
+        let q = parse_query(None);
+        assert_eq!(q.sort, SortKey::Name);
+        assert_eq!(q.json, None);
+    }
+
+    #[test]
+    fn test_render_html_escapes_names() {
+        let entries = vec![entry("<a b>.txt", 1, 0, false)];
+        let query = parse_query(None);
+        let html = render_html("/files/", &entries, query, true);
+        assert!(html.contains("href=\"%3Ca%20b%3E.txt\""));
+        assert!(html.contains("&lt;a b&gt;.txt"));
+        assert!(html.contains("<a href=\"../\">"));
+    }
+}
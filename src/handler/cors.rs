@@ -0,0 +1,222 @@
+//! CORS module
+//!
+//! Adds `Access-Control-*` headers to actual and preflight responses according
+//! to the applicable `CorsPolicy`. The request's origin is reflected only when
+//! it is allowed; with an `*` origin rule the response carries `*` instead.
+
+use crate::config::CorsPolicy;
+use hyper::header::{self, HeaderValue};
+use hyper::HeaderMap;
+
+/// Add CORS headers to a response
+///
+/// `preflight` adds the method, header and max-age grants of an `OPTIONS`
+/// request carrying `Access-Control-Request-Method`; they are omitted when
+/// the requested method is not allowed.
+pub fn apply_cors_headers(
+    headers: &mut HeaderMap,
+    policy: Option<&CorsPolicy>,
+    request_headers: &HeaderMap,
+    preflight: bool,
+) {
+    let Some(policy) = policy.filter(|p| p.enabled) else {
+        return;
+    };
+
+    if reflects_origin(policy) {
+        // The response depends on the Origin request header
+        headers.append(header::VARY, HeaderValue::from_static("Origin"));
+    }
+
+    let Some(allow_origin) = request_headers
+        .get(header::ORIGIN)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|origin| allowed_origin(policy, origin))
+    else {
+        return;
+    };
+
+    if preflight {
+        let method_allowed = request_headers
+            .get(header::ACCESS_CONTROL_REQUEST_METHOD)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|method| policy.allow_methods.iter().any(|m| m == method));
+        if !method_allowed {
+            return;
+        }
+        insert_list(
+            headers,
+            header::ACCESS_CONTROL_ALLOW_METHODS,
+            &policy.allow_methods,
+        );
+        insert_list(
+            headers,
+            header::ACCESS_CONTROL_ALLOW_HEADERS,
+            &policy.allow_headers,
+        );
+        if policy.max_age > 0 {
+            headers.insert(
+                header::ACCESS_CONTROL_MAX_AGE,
+                HeaderValue::from(policy.max_age),
+            );
+        }
+    } else {
+        insert_list(
+            headers,
+            header::ACCESS_CONTROL_EXPOSE_HEADERS,
+            &policy.expose_headers,
+        );
+    }
+
+    headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, allow_origin);
+    if policy.allow_credentials {
+        headers.insert(
+            header::ACCESS_CONTROL_ALLOW_CREDENTIALS,
+            HeaderValue::from_static("true"),
+        );
+    }
+}
+
+/// Whether the request is a CORS preflight (`OPTIONS` with a requested method)
+pub fn is_preflight(request_headers: &HeaderMap) -> bool {
+    request_headers.contains_key(header::ACCESS_CONTROL_REQUEST_METHOD)
+}
+
+/// `Access-Control-Allow-Origin` value for an origin, or `None` if it is not allowed
+fn allowed_origin(policy: &CorsPolicy, origin: &str) -> Option<HeaderValue> {
+    if !reflects_origin(policy) {
+        return Some(HeaderValue::from_static("*"));
+    }
+
+    let allowed = policy.allow_origin.iter().any(|matcher| {
+        matcher.exact.as_deref() == Some(origin)
+            || matcher
+                .suffix
+                .as_deref()
+                .is_some_and(|suffix| origin.ends_with(suffix))
+            || matcher
+                .safe_regex
+                .as_ref()
+                .is_some_and(|regex| regex.regex().is_match(origin))
+    });
+    if allowed {
+        HeaderValue::from_str(origin).ok()
+    } else {
+        None
+    }
+}
+
+/// Whether allowed origins are echoed back (no `*` rule)
+fn reflects_origin(policy: &CorsPolicy) -> bool {
+    !policy
+        .allow_origin
+        .iter()
+        .any(|matcher| matcher.exact.as_deref() == Some("*"))
+}
+
+/// Insert a comma-separated list header (skipped when the list is empty)
+fn insert_list(headers: &mut HeaderMap, name: header::HeaderName, values: &[String]) {
+    if values.is_empty() {
+        return;
+    }
+    if let Ok(value) = HeaderValue::from_str(&values.join(", ")) {
+        headers.insert(name, value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.insert(*name, HeaderValue::from_str(value).unwrap());
+        }
+        headers
+    }
+
+    fn parse_policy(value: serde_json::Value) -> CorsPolicy {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_default_policy_allows_any_origin() {
+        let policy = CorsPolicy::default();
+        let mut headers = HeaderMap::new();
+        let req = request(&[("origin", "https://a.example")]);
+        apply_cors_headers(&mut headers, Some(&policy), &req, false);
+        assert_eq!(headers["access-control-allow-origin"], "*");
+        assert!(!headers.contains_key("vary"));
+
+        let mut headers = HeaderMap::new();
+        let req = request(&[
+            ("origin", "https://a.example"),
+            ("access-control-request-method", "GET"),
+        ]);
+        apply_cors_headers(&mut headers, Some(&policy), &req, true);
+        assert_eq!(
+            headers["access-control-allow-methods"],
+            "GET, HEAD, OPTIONS"
+        );
+        assert_eq!(
+            headers["access-control-allow-headers"],
+            "Content-Type, Range"
+        );
+        assert_eq!(headers["access-control-max-age"], "86400");
+    }
+
+    #[test]
+    fn test_origin_reflection() {
+        let policy = parse_policy(serde_json::json!({
+            "allow_origin": [
+                {"exact": "https://app.example.com"},
+                {"suffix": ".example.org"},
+                {"safe_regex": "https://pr-\\d+\\.preview\\.dev"}
+            ],
+            "expose_headers": ["ETag"],
+            "allow_credentials": true
+        }));
+
+        for origin in [
+            "https://app.example.com",
+            "https://cdn.example.org",
+            "https://pr-42.preview.dev",
+        ] {
+            let mut headers = HeaderMap::new();
+            apply_cors_headers(
+                &mut headers,
+                Some(&policy),
+                &request(&[("origin", origin)]),
+                false,
+            );
+            assert_eq!(headers["access-control-allow-origin"], origin);
+            assert_eq!(headers["access-control-allow-credentials"], "true");
+            assert_eq!(headers["access-control-expose-headers"], "ETag");
+            assert_eq!(headers["vary"], "Origin");
+        }
+
+        let mut headers = HeaderMap::new();
+        let req = request(&[("origin", "https://evil.example")]);
+        apply_cors_headers(&mut headers, Some(&policy), &req, false);
+        assert!(!headers.contains_key("access-control-allow-origin"));
+        assert_eq!(headers["vary"], "Origin");
+    }
+
+    #[test]
+    fn test_preflight_method_not_allowed() {
+        let policy = parse_policy(serde_json::json!({"allow_methods": ["GET"]}));
+        let mut headers = HeaderMap::new();
+        let req = request(&[
+            ("origin", "https://a.example"),
+            ("access-control-request-method", "PUT"),
+        ]);
+        apply_cors_headers(&mut headers, Some(&policy), &req, true);
+        assert!(headers.is_empty());
+
+        let disabled = parse_policy(serde_json::json!({"enabled": false}));
+        let mut headers = HeaderMap::new();
+        apply_cors_headers(&mut headers, Some(&disabled), &req, false);
+        assert!(headers.is_empty());
+    }
+}
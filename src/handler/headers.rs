@@ -0,0 +1,360 @@
+//! Header mutation module
+//!
+//! Applies `request_headers_to_add/remove` and `response_headers_to_add/remove`
+//! of virtual hosts and routes. Added values may contain variables:
+//! - `%REMOTE_ADDR%` - client IP address
+//! - `%REQ(name)%` - request header value (empty if missing)
+//! - `%START_TIME%` - request start time (RFC 3339, UTC)
+//! - `%%` - a literal `%`
+//!
+//! Also renders the `security_headers` preset.
+
+use crate::config::{HeaderMutations, HeaderValueOption, HstsConfig, SecurityHeadersConfig};
+use crate::logger;
+use chrono::{DateTime, SecondsFormat, Utc};
+use hyper::header::{HeaderName, HeaderValue};
+use hyper::HeaderMap;
+use std::net::SocketAddr;
+use std::time::SystemTime;
+
+/// Values available to header interpolation
+pub struct HeaderVariables<'a> {
+    pub remote_addr: SocketAddr,
+    pub start_time: SystemTime,
+    pub request_headers: &'a HeaderMap,
+}
+
+/// Apply request header mutations of each layer in order
+///
+/// Returns `None` when no layer changes request headers, so the original
+/// headers can be used without a copy.
+pub fn mutate_request_headers(
+    layers: &[&HeaderMutations],
+    vars: &HeaderVariables<'_>,
+) -> Option<HeaderMap> {
+    if !layers.iter().any(|layer| layer.has_request_mutations()) {
+        return None;
+    }
+
+    let mut headers = vars.request_headers.clone();
+    for layer in layers {
+        apply(
+            &mut headers,
+            &layer.request_headers_to_add,
+            &layer.request_headers_to_remove,
+            vars,
+        );
+    }
+    Some(headers)
+}
+
+/// Apply response header mutations of each layer in order
+pub fn mutate_response_headers(
+    headers: &mut HeaderMap,
+    layers: &[&HeaderMutations],
+    vars: &HeaderVariables<'_>,
+) {
+    for layer in layers {
+        apply(
+            headers,
+            &layer.response_headers_to_add,
+            &layer.response_headers_to_remove,
+            vars,
+        );
+    }
+}
+
+/// Remove, then add headers
+///
+/// Values that are empty after interpolation are not added.
+fn apply(
+    headers: &mut HeaderMap,
+    to_add: &[HeaderValueOption],
+    to_remove: &[String],
+    vars: &HeaderVariables<'_>,
+) {
+    for name in to_remove {
+        headers.remove(name.as_str());
+    }
+
+    for option in to_add {
+        let value = interpolate(&option.value, vars);
+        if value.is_empty() {
+            continue;
+        }
+        let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(option.name.as_bytes()),
+            HeaderValue::from_str(&value),
+        ) else {
+            logger::log_warning(&format!("Skipping invalid header '{}'", option.name));
+            continue;
+        };
+        if option.append {
+            headers.append(name, value);
+        } else {
+            headers.insert(name, value);
+        }
+    }
+}
+
+/// Add the headers of a security preset
+///
+/// Headers that are already set (e.g. by `response_headers_to_add`) are kept,
+/// and empty values are skipped.
+pub fn apply_security_headers(headers: &mut HeaderMap, config: &SecurityHeadersConfig) {
+    if !config.enabled {
+        return;
+    }
+
+    let hsts = if config.hsts.enabled {
+        hsts_value(&config.hsts)
+    } else {
+        String::new()
+    };
+    let csp_name = if config.csp_report_only {
+        "content-security-policy-report-only"
+    } else {
+        "content-security-policy"
+    };
+    let nosniff = if config.content_type_nosniff {
+        "nosniff"
+    } else {
+        ""
+    };
+
+    let entries = [
+        ("strict-transport-security", hsts.as_str()),
+        (csp_name, config.content_security_policy.as_str()),
+        ("x-content-type-options", nosniff),
+        ("x-frame-options", config.frame_options.as_str()),
+        ("referrer-policy", config.referrer_policy.as_str()),
+        ("permissions-policy", config.permissions_policy.as_str()),
+        (
+            "cross-origin-opener-policy",
+            config.cross_origin_opener_policy.as_str(),
+        ),
+        (
+            "cross-origin-embedder-policy",
+            config.cross_origin_embedder_policy.as_str(),
+        ),
+    ];
+    for (name, value) in entries {
+        if value.is_empty() || headers.contains_key(name) {
+            continue;
+        }
+        if let Ok(value) = HeaderValue::from_str(value) {
+            headers.insert(name, value);
+        }
+    }
+}
+
+/// `Strict-Transport-Security` value, e.g. `max-age=31536000; includeSubDomains`
+fn hsts_value(hsts: &HstsConfig) -> String {
+    let mut value = format!("max-age={}", hsts.max_age);
+    if hsts.include_subdomains {
+        value.push_str("; includeSubDomains");
+    }
+    if hsts.preload {
+        value.push_str("; preload");
+    }
+    value
+}
+
+/// Substitute `%VARIABLE%` references in a header value
+///
+/// Unknown variables are kept literally.
+pub fn interpolate(template: &str, vars: &HeaderVariables<'_>) -> String {
+    if !template.contains('%') {
+        return template.to_string();
+    }
+
+    let mut result = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('%') {
+        result.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let Some(end) = after.find('%') else {
+            result.push_str(&rest[start..]);
+            return result;
+        };
+
+        let variable = &after[..end];
+        if let Some(value) = resolve_variable(variable, vars) {
+            result.push_str(&value);
+            rest = &after[end + 1..];
+        } else {
+            // Keep the '%' and retry from the next one
+            result.push('%');
+            rest = after;
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+/// Value of a single variable, or `None` if it is unknown
+fn resolve_variable(variable: &str, vars: &HeaderVariables<'_>) -> Option<String> {
+    match variable {
+        "" => Some("%".to_string()),
+        "REMOTE_ADDR" => Some(vars.remote_addr.ip().to_string()),
+        "START_TIME" => Some(
+            DateTime::<Utc>::from(vars.start_time).to_rfc3339_opts(SecondsFormat::Millis, true),
+        ),
+        _ => {
+            let name = variable.strip_prefix("REQ(")?.strip_suffix(')')?;
+            Some(
+                vars.request_headers
+                    .get_all(name)
+                    .iter()
+                    .filter_map(|v| v.to_str().ok())
+                    .collect::<Vec<_>>()
+                    .join(","),
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn header_map(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.append(*name, HeaderValue::from_str(value).unwrap());
+        }
+        headers
+    }
+
+    fn vars(headers: &HeaderMap) -> HeaderVariables<'_> {
+        HeaderVariables {
+            remote_addr: "192.0.2.7:51000".parse().unwrap(),
+            start_time: SystemTime::UNIX_EPOCH + Duration::from_millis(1_700_000_000_123),
+            request_headers: headers,
+        }
+    }
+
+    fn add(name: &str, value: &str, append: bool) -> HeaderValueOption {
+        HeaderValueOption {
+            name: name.to_string(),
+            value: value.to_string(),
+            append,
+        }
+    }
+
+    #[test]
+    fn test_interpolate() {
+        let headers = header_map(&[("host", "example.com")]);
+        let vars = vars(&headers);
+        assert_eq!(interpolate("%REMOTE_ADDR%", &vars), "192.0.2.7");
+        assert_eq!(interpolate("h=%REQ(Host)%", &vars), "h=example.com");
+        assert_eq!(interpolate("%REQ(x-missing)%", &vars), "");
+        assert_eq!(
+            interpolate("%START_TIME%", &vars),
+            "2023-11-14T22:13:20.123Z"
+        );
+        assert_eq!(
+            interpolate("100%% %UNKNOWN% 50%", &vars),
+            "100% %UNKNOWN% 50%"
+        );
+        assert_eq!(interpolate("plain", &vars), "plain");
+    }
+
+    #[test]
+    fn test_append_and_overwrite() {
+        let request = HeaderMap::new();
+        let vars = vars(&request);
+        let mut headers = header_map(&[("x-tag", "a"), ("cache-control", "public")]);
+        let vhost = HeaderMutations {
+            response_headers_to_add: vec![add("x-tag", "b", true)],
+            ..HeaderMutations::default()
+        };
+        let route = HeaderMutations {
+            response_headers_to_add: vec![add("cache-control", "no-store", false)],
+            ..HeaderMutations::default()
+        };
+        mutate_response_headers(&mut headers, &[&vhost, &route], &vars);
+
+        let tags: Vec<_> = headers.get_all("x-tag").iter().collect();
+        assert_eq!(tags, ["a", "b"]);
+        assert_eq!(headers["cache-control"], "no-store");
+    }
+
+    #[test]
+    fn test_remove_before_add() {
+        let request = header_map(&[("x-debug", "1"), ("accept", "text/html")]);
+        let vars = vars(&request);
+        let mutations = HeaderMutations {
+            request_headers_to_add: vec![
+                add("x-debug", "0", true),
+                add("x-empty", "%REQ(x-missing)%", true),
+            ],
+            request_headers_to_remove: vec!["x-debug".to_string(), "accept".to_string()],
+            ..HeaderMutations::default()
+        };
+
+        let headers = mutate_request_headers(&[&mutations], &vars).unwrap();
+        assert_eq!(headers["x-debug"], "0");
+        assert!(!headers.contains_key("accept"));
+        assert!(!headers.contains_key("x-empty"));
+
+        assert!(mutate_request_headers(&[&HeaderMutations::default()], &vars).is_none());
+    }
+
+    #[test]
+    fn test_security_headers_defaults() {
+        let mut headers = header_map(&[("x-frame-options", "SAMEORIGIN")]);
+        apply_security_headers(&mut headers, &SecurityHeadersConfig::default());
+
+        assert_eq!(headers["strict-transport-security"], "max-age=31536000");
+        assert_eq!(headers["x-content-type-options"], "nosniff");
+        assert_eq!(
+            headers["referrer-policy"],
+            "strict-origin-when-cross-origin"
+        );
+        // Already set headers win
+        assert_eq!(headers["x-frame-options"], "SAMEORIGIN");
+        assert!(!headers.contains_key("content-security-policy"));
+        assert!(!headers.contains_key("cross-origin-opener-policy"));
+    }
+
+    #[test]
+    fn test_security_headers_options() {
+        let config = SecurityHeadersConfig {
+            hsts: HstsConfig {
+                include_subdomains: true,
+                preload: true,
+                ..HstsConfig::default()
+            },
+            content_security_policy: "default-src 'self'".to_string(),
+            csp_report_only: true,
+            frame_options: String::new(),
+            cross_origin_opener_policy: "same-origin".to_string(),
+            cross_origin_embedder_policy: "require-corp".to_string(),
+            ..SecurityHeadersConfig::default()
+        };
+        let mut headers = HeaderMap::new();
+        apply_security_headers(&mut headers, &config);
+
+        assert_eq!(
+            headers["strict-transport-security"],
+            "max-age=31536000; includeSubDomains; preload"
+        );
+        assert_eq!(
+            headers["content-security-policy-report-only"],
+            "default-src 'self'"
+        );
+        assert!(!headers.contains_key("x-frame-options"));
+        assert_eq!(headers["cross-origin-opener-policy"], "same-origin");
+        assert_eq!(headers["cross-origin-embedder-policy"], "require-corp");
+
+        let disabled = SecurityHeadersConfig {
+            enabled: false,
+            ..SecurityHeadersConfig::default()
+        };
+        let mut headers = HeaderMap::new();
+        apply_security_headers(&mut headers, &disabled);
+        assert!(headers.is_empty());
+    }
+}
@@ -3,6 +3,12 @@
 //! Responsible for request routing dispatch and business logic processing.
 //! Currently supports static file serving, with future extensibility for reverse proxy and other features.
 
+pub mod autoindex;
+pub mod cors;
+pub mod headers;
+pub mod open_file_cache;
+pub mod rate_limit;
+pub mod rate_limit_service;
 pub mod router;
 pub mod static_files;
 
@@ -0,0 +1,216 @@
+//! Open file cache module
+//!
+//! nginx-style `open_file_cache`: remembers canonicalized paths, file metadata
+//! and failed lookups so the static file hot path can skip `canonicalize`,
+//! `is_dir`, `exists` and `metadata` syscalls on repeated requests.
+//!
+//! Entries expire after the configured validity period. The whole cache is
+//! invalidated when route or virtual host configuration changes.
+
+use crate::config::OpenFileCacheConfig;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, RwLock};
+use std::time::{Duration, Instant, SystemTime};
+
+/// Metadata of a successfully resolved path
+#[derive(Debug, Clone)]
+pub struct FileInfo {
+    /// Canonical (absolute, symlink-free) path
+    pub canonical: PathBuf,
+    /// Whether the path is a directory
+    pub is_dir: bool,
+    /// Whether the path is a regular file
+    pub is_file: bool,
+    /// Last modification time
+    pub modified: Option<SystemTime>,
+    /// Size in bytes
+    pub len: u64,
+}
+
+/// Cached lookup result (`None` means the path does not exist)
+struct CacheEntry {
+    info: Option<FileInfo>,
+    cached_at: Instant,
+}
+
+/// Open file cache shared by all connections
+pub struct OpenFileCache {
+    settings: RwLock<OpenFileCacheConfig>,
+    entries: Mutex<HashMap<PathBuf, CacheEntry>>,
+}
+
+impl OpenFileCache {
+    /// Create a cache with the given settings
+    pub fn new(settings: OpenFileCacheConfig) -> Self {
+        Self {
+            settings: RwLock::new(settings),
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Apply new settings (drops all cached entries)
+    pub fn reconfigure(&self, settings: OpenFileCacheConfig) {
+        *self.settings.write().unwrap() = settings;
+        self.clear();
+    }
+
+    /// Drop all cached entries
+    pub fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+
+    /// Number of cached entries
+    #[cfg(test)]
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    /// Resolve a path to its canonical form and metadata
+    ///
+    /// Returns `None` if the path does not exist or cannot be read.
+    /// Filesystem queries run on the blocking thread pool.
+    pub async fn lookup(&self, path: &Path) -> Option<FileInfo> {
+        let settings = self.settings.read().unwrap().clone();
+        if !settings.enabled {
+            return stat(path).await;
+        }
+
+        let valid = Duration::from_secs(settings.valid);
+        if let Some(entry) = self.entries.lock().unwrap().get(path) {
+            if entry.cached_at.elapsed() < valid {
+                return entry.info.clone();
+            }
+        }
+
+        let info = stat(path).await;
+        if info.is_some() || settings.cache_errors {
+            self.insert(path, info.clone(), &settings);
+        }
+        info
+    }
+
+    /// Insert an entry, evicting expired entries when the cache is full
+    fn insert(&self, path: &Path, info: Option<FileInfo>, settings: &OpenFileCacheConfig) {
+        let mut entries = self.entries.lock().unwrap();
+
+        if entries.len() >= settings.max_entries && !entries.contains_key(path) {
+            let valid = Duration::from_secs(settings.valid);
+            entries.retain(|_, e| e.cached_at.elapsed() < valid);
+            // Still full: start over rather than tracking LRU order
+            if entries.len() >= settings.max_entries {
+                entries.clear();
+            }
+        }
+
+        if settings.max_entries > 0 {
+            entries.insert(
+                path.to_path_buf(),
+                CacheEntry {
+                    info,
+                    cached_at: Instant::now(),
+                },
+            );
+        }
+    }
+}
+
+/// Query the filesystem for a path (uncached) without blocking the runtime
+async fn stat(path: &Path) -> Option<FileInfo> {
+    let path = path.to_path_buf();
+    tokio::task::spawn_blocking(move || stat_blocking(&path))
+        .await
+        .ok()
+        .flatten()
+}
+
+/// Canonicalize a path and read its metadata
+fn stat_blocking(path: &Path) -> Option<FileInfo> {
+    let canonical = path.canonicalize().ok()?;
+    let metadata = std::fs::metadata(&canonical).ok()?;
+    Some(FileInfo {
+        canonical,
+        is_dir: metadata.is_dir(),
+        is_file: metadata.is_file(),
+        modified: metadata.modified().ok(),
+        len: metadata.len(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("yarhs_ofc_{name}_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn enabled(cache_errors: bool) -> OpenFileCacheConfig {
+        OpenFileCacheConfig {
+            enabled: true,
+            max_entries: 16,
+            valid: 60,
+            cache_errors,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_lookup_caches_metadata() {
+        let dir = temp_dir("meta");
+        let file = dir.join("a.txt");
+        std::fs::write(&file, b"hello").unwrap();
+
+        let cache = OpenFileCache::new(enabled(true));
+        let info = cache.lookup(&file).await.unwrap();
+        assert!(info.is_file);
+        assert_eq!(info.canonical, file.canonicalize().unwrap());
+
+        // Cached entry survives file removal until invalidated
+        std::fs::remove_file(&file).unwrap();
+        assert!(cache.lookup(&file).await.is_some());
+        cache.clear();
+        assert!(cache.lookup(&file).await.is_none());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_negative_lookups() {
+        let dir = temp_dir("neg");
+        let file = dir.join("missing.txt");
+
+        let cache = OpenFileCache::new(enabled(true));
+        assert!(cache.lookup(&file).await.is_none());
+        std::fs::write(&file, b"now exists").unwrap();
+        assert!(cache.lookup(&file).await.is_none(), "404 should be cached");
+
+        let cache = OpenFileCache::new(enabled(false));
+        std::fs::remove_file(&file).unwrap();
+        assert!(cache.lookup(&file).await.is_none());
+        std::fs::write(&file, b"now exists").unwrap();
+        assert!(cache.lookup(&file).await.is_some(), "404 should not be cached");
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_disabled_and_bounded() {
+        let dir = temp_dir("bound");
+        let cache = OpenFileCache::new(OpenFileCacheConfig::default());
+        assert!(cache.lookup(&dir).await.is_some_and(|i| i.is_dir));
+        assert_eq!(cache.len(), 0);
+
+        cache.reconfigure(OpenFileCacheConfig {
+            max_entries: 2,
+            ..enabled(true)
+        });
+        for name in ["a", "b", "c", "d"] {
+            cache.lookup(&dir.join(name)).await;
+        }
+        assert!(cache.len() <= 2);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
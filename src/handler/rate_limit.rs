@@ -0,0 +1,655 @@
+//! Rate limiting module
+//!
+//! Token bucket and sliding window counters for the `rate_limits` of virtual
+//! hosts and routes. Counters live in a sharded map so worker threads rarely
+//! contend on the same lock. Each shard holds a bounded number of keys: idle
+//! counters (fully refilled, so equivalent to a missing one) are evicted first,
+//! then the least recently used.
+
+use crate::config::{RateLimitAlgorithm, RateLimitKey, RateLimitPolicy};
+use hyper::header::{HeaderName, HeaderValue, RETRY_AFTER};
+use hyper::HeaderMap;
+use serde::Serialize;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::hash::{Hash, Hasher};
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+/// Number of independently locked shards
+const SHARDS: usize = 16;
+
+/// Keys per shard (65536 counters in total)
+const MAX_KEYS_PER_SHARD: usize = 4096;
+
+/// Request attributes rate limit keys are taken from
+pub struct RateLimitRequest<'a> {
+    pub client_ip: IpAddr,
+    pub headers: &'a HeaderMap,
+    /// Virtual host name
+    pub vhost: &'a str,
+    /// Matched route: position within the virtual host and optional name
+    pub route: Option<(usize, Option<&'a str>)>,
+}
+
+/// Result of the rate limits that applied to a request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimitStatus {
+    /// Whether the request may proceed
+    pub allowed: bool,
+    /// Requests allowed per interval
+    pub limit: u64,
+    /// Interval in seconds
+    pub interval: u64,
+    /// Token bucket capacity, if different from `limit`
+    pub burst: Option<u64>,
+    /// Requests left right now
+    pub remaining: u64,
+    /// Seconds until the counter is fully replenished
+    pub reset: u64,
+    /// Seconds until a request would be allowed again (0 when allowed)
+    pub retry_after: u64,
+}
+
+/// Rate limit counters, as reported by `GET /v1/stats`
+#[derive(Debug, Default, Serialize, PartialEq, Eq)]
+pub struct RateLimitStats {
+    /// Requests that passed all limits that applied to them
+    pub allowed: u64,
+    /// Requests answered with 429
+    pub limited: u64,
+    /// Counters currently held in memory
+    pub tracked_keys: usize,
+    /// Counters dropped to stay within the key limit
+    pub evicted: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct CounterKey {
+    /// Identifies the policy (scope, position and settings)
+    policy: u64,
+    /// Key value (client IP, header value, route or virtual host)
+    value: String,
+}
+
+#[derive(Debug)]
+enum CounterState {
+    TokenBucket {
+        tokens: f64,
+        updated: Instant,
+    },
+    SlidingWindow {
+        window_start: Instant,
+        previous: u64,
+        current: u64,
+    },
+}
+
+#[derive(Debug)]
+struct Counter {
+    state: CounterState,
+    last_seen: Instant,
+    /// Time without requests after which the counter is back to its initial state
+    idle_after: Duration,
+}
+
+type Shard = HashMap<CounterKey, Counter>;
+
+/// Shared rate limit counters for all virtual hosts and routes
+#[derive(Debug)]
+pub struct RateLimiter {
+    shards: Vec<Mutex<Shard>>,
+    max_keys_per_shard: usize,
+    allowed: AtomicU64,
+    limited: AtomicU64,
+    evicted: AtomicU64,
+}
+
+impl RateLimiter {
+    pub fn new() -> Self {
+        Self::with_capacity(MAX_KEYS_PER_SHARD)
+    }
+
+    fn with_capacity(max_keys_per_shard: usize) -> Self {
+        Self {
+            shards: (0..SHARDS).map(|_| Mutex::new(HashMap::new())).collect(),
+            max_keys_per_shard,
+            allowed: AtomicU64::new(0),
+            limited: AtomicU64::new(0),
+            evicted: AtomicU64::new(0),
+        }
+    }
+
+    /// Count a request against the virtual host's and the matched route's limits
+    ///
+    /// Returns `None` when no limit applies. Otherwise the status of the first
+    /// limit that rejected the request (later limits are not charged), or of
+    /// the limit with the fewest remaining requests.
+    pub fn check(
+        &self,
+        request: &RateLimitRequest<'_>,
+        vhost_limits: &[RateLimitPolicy],
+        route_limits: &[RateLimitPolicy],
+    ) -> Option<RateLimitStatus> {
+        self.check_at(request, vhost_limits, route_limits, Instant::now())
+    }
+
+    fn check_at(
+        &self,
+        request: &RateLimitRequest<'_>,
+        vhost_limits: &[RateLimitPolicy],
+        route_limits: &[RateLimitPolicy],
+        now: Instant,
+    ) -> Option<RateLimitStatus> {
+        let route_index = request.route.map(|(index, _)| index);
+        let scoped = vhost_limits
+            .iter()
+            .enumerate()
+            .map(|(i, policy)| (None, i, policy))
+            .chain(
+                route_limits
+                    .iter()
+                    .enumerate()
+                    .map(|(i, policy)| (route_index, i, policy)),
+            );
+
+        let mut result: Option<RateLimitStatus> = None;
+        for (scope, position, policy) in scoped {
+            let Some(value) = key_value(&policy.key, request) else {
+                continue;
+            };
+            let key = CounterKey {
+                policy: policy_id(request.vhost, scope, position, policy),
+                value,
+            };
+            let status = self.take(key, policy, now);
+            if !status.allowed {
+                self.limited.fetch_add(1, Ordering::Relaxed);
+                return Some(status);
+            }
+            if result.is_none_or(|r| status.remaining < r.remaining) {
+                result = Some(status);
+            }
+        }
+
+        if result.is_some() {
+            self.allowed.fetch_add(1, Ordering::Relaxed);
+        }
+        result
+    }
+
+    /// Current counter values
+    pub fn stats(&self) -> RateLimitStats {
+        RateLimitStats {
+            allowed: self.allowed.load(Ordering::Relaxed),
+            limited: self.limited.load(Ordering::Relaxed),
+            tracked_keys: (0..self.shards.len()).map(|i| self.lock(i).len()).sum(),
+            evicted: self.evicted.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Charge one request to a counter
+    fn take(&self, key: CounterKey, policy: &RateLimitPolicy, now: Instant) -> RateLimitStatus {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        #[allow(clippy::cast_possible_truncation)]
+        let shard_index = hasher.finish() as usize % self.shards.len();
+
+        let mut shard = self.lock(shard_index);
+        if !shard.contains_key(&key) && shard.len() >= self.max_keys_per_shard {
+            let evicted = evict(&mut shard, now);
+            self.evicted.fetch_add(evicted, Ordering::Relaxed);
+        }
+        let counter = shard
+            .entry(key)
+            .or_insert_with(|| Counter::new(policy, now));
+        counter.last_seen = now;
+        let status = counter.take(policy, now);
+        drop(shard);
+        status
+    }
+
+    fn lock(&self, index: usize) -> MutexGuard<'_, Shard> {
+        self.shards[index]
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Key value of a policy for a request (`None` if the request has no such value)
+fn key_value(key: &RateLimitKey, request: &RateLimitRequest<'_>) -> Option<String> {
+    match key {
+        RateLimitKey::ClientIp => Some(request.client_ip.to_string()),
+        RateLimitKey::Header(name) => request
+            .headers
+            .get(name.as_str())
+            .and_then(|v| v.to_str().ok())
+            .map(ToString::to_string),
+        RateLimitKey::Route => Some(match request.route {
+            Some((_, Some(name))) => name.to_string(),
+            Some((index, None)) => format!("#{index}"),
+            None => String::new(),
+        }),
+        RateLimitKey::Vhost => Some(request.vhost.to_string()),
+    }
+}
+
+/// Stable identity of a policy: counters survive unrelated config updates,
+/// while a changed policy starts with fresh counters
+fn policy_id(vhost: &str, route: Option<usize>, position: usize, policy: &RateLimitPolicy) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    (vhost, route, position, policy).hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Make room in a full shard: drop idle counters, or else the least recently used one
+fn evict(shard: &mut Shard, now: Instant) -> u64 {
+    let before = shard.len();
+    shard.retain(|_, counter| now.duration_since(counter.last_seen) < counter.idle_after);
+    if shard.len() == before {
+        if let Some(oldest) = shard
+            .iter()
+            .min_by_key(|(_, counter)| counter.last_seen)
+            .map(|(key, _)| key.clone())
+        {
+            shard.remove(&oldest);
+        }
+    }
+    (before - shard.len()) as u64
+}
+
+#[allow(
+    clippy::cast_precision_loss,
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss
+)]
+impl Counter {
+    fn new(policy: &RateLimitPolicy, now: Instant) -> Self {
+        let interval = Duration::from_secs(policy.interval);
+        let (state, idle_after) = match policy.algorithm {
+            RateLimitAlgorithm::TokenBucket => {
+                let capacity = capacity(policy);
+                (
+                    CounterState::TokenBucket {
+                        tokens: capacity,
+                        updated: now,
+                    },
+                    // Time to refill a full bucket, saturating for extreme policies
+                    Duration::try_from_secs_f64(
+                        interval.as_secs_f64() * capacity / policy.requests as f64,
+                    )
+                    .unwrap_or(Duration::MAX),
+                )
+            }
+            RateLimitAlgorithm::SlidingWindow => (
+                CounterState::SlidingWindow {
+                    window_start: now,
+                    previous: 0,
+                    current: 0,
+                },
+                interval.checked_mul(2).unwrap_or(Duration::MAX),
+            ),
+        };
+        Self {
+            state,
+            last_seen: now,
+            idle_after,
+        }
+    }
+
+    fn take(&mut self, policy: &RateLimitPolicy, now: Instant) -> RateLimitStatus {
+        let limit = policy.requests as f64;
+        let interval = policy.interval as f64;
+        let mut status = RateLimitStatus {
+            allowed: false,
+            limit: policy.requests,
+            interval: policy.interval,
+            burst: policy.burst.filter(|&burst| burst != policy.requests),
+            remaining: 0,
+            reset: 0,
+            retry_after: 0,
+        };
+
+        match &mut self.state {
+            CounterState::TokenBucket { tokens, updated } => {
+                let capacity = capacity(policy);
+                let rate = limit / interval;
+                let elapsed = now.duration_since(*updated).as_secs_f64();
+                *tokens = elapsed.mul_add(rate, *tokens).min(capacity);
+                *updated = now;
+
+                status.allowed = *tokens >= 1.0;
+                if status.allowed {
+                    *tokens -= 1.0;
+                } else {
+                    status.retry_after = whole_seconds((1.0 - *tokens) / rate);
+                }
+                status.remaining = tokens.floor() as u64;
+                status.reset = whole_seconds((capacity - *tokens) / rate);
+            }
+            CounterState::SlidingWindow {
+                window_start,
+                previous,
+                current,
+            } => {
+                let window = Duration::from_secs(policy.interval);
+                let windows_passed = now.duration_since(*window_start).as_secs() / policy.interval;
+                if windows_passed >= 1 {
+                    *previous = if windows_passed == 1 { *current } else { 0 };
+                    *current = 0;
+                    *window_start += window * u32::try_from(windows_passed).unwrap_or(u32::MAX);
+                }
+
+                let into_window = now.duration_since(*window_start).as_secs_f64();
+                let weight = 1.0 - into_window / interval;
+                let estimate = (*previous as f64).mul_add(weight, *current as f64);
+
+                status.allowed = estimate + 1.0 <= limit;
+                if status.allowed {
+                    *current += 1;
+                } else {
+                    status.retry_after =
+                        window_retry_after(limit, interval, into_window, *previous, *current);
+                }
+                let used = estimate + if status.allowed { 1.0 } else { 0.0 };
+                status.remaining = (limit - used).max(0.0).floor() as u64;
+                status.reset = whole_seconds(interval - into_window);
+            }
+        }
+        status
+    }
+}
+
+/// Token bucket capacity
+#[allow(clippy::cast_precision_loss)]
+fn capacity(policy: &RateLimitPolicy) -> f64 {
+    policy.burst.unwrap_or(policy.requests) as f64
+}
+
+/// Seconds until the sliding window estimate leaves room for one more request
+#[allow(clippy::cast_precision_loss)]
+fn window_retry_after(
+    limit: f64,
+    interval: f64,
+    into_window: f64,
+    previous: u64,
+    current: u64,
+) -> u64 {
+    let (previous, current) = (previous as f64, current as f64);
+
+    // Later in this window the previous window's weight drops
+    let room = limit - 1.0 - current;
+    if room >= 0.0 && previous > 0.0 {
+        let at = interval * (1.0 - room / previous);
+        if at < interval {
+            return whole_seconds(at - into_window);
+        }
+    }
+
+    // Next window: this window's count becomes the weighted previous one
+    let at = if current > limit - 1.0 {
+        interval * (1.0 - (limit - 1.0) / current)
+    } else {
+        0.0
+    };
+    whole_seconds(interval - into_window + at)
+}
+
+/// Round seconds up, at least 1 for any positive wait
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn whole_seconds(secs: f64) -> u64 {
+    if secs <= 0.0 {
+        0
+    } else {
+        (secs.ceil() as u64).max(1)
+    }
+}
+
+/// Add `RateLimit-*` headers (and `Retry-After` when the request was limited)
+pub fn apply_rate_limit_headers(headers: &mut HeaderMap, status: &RateLimitStatus) {
+    let mut policy = format!("{};w={}", status.limit, status.interval);
+    if let Some(burst) = status.burst {
+        let _ = write!(policy, ";burst={burst}");
+    }
+    let values = [
+        ("ratelimit-limit", status.limit.to_string()),
+        ("ratelimit-remaining", status.remaining.to_string()),
+        ("ratelimit-reset", status.reset.to_string()),
+        ("ratelimit-policy", policy),
+    ];
+    for (name, value) in values {
+        if let Ok(value) = HeaderValue::from_str(&value) {
+            headers.insert(HeaderName::from_static(name), value);
+        }
+    }
+    if !status.allowed {
+        headers.insert(RETRY_AFTER, HeaderValue::from(status.retry_after));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    fn parse_policy(value: serde_json::Value) -> RateLimitPolicy {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn request<'a>(ip: &str, headers: &'a HeaderMap) -> RateLimitRequest<'a> {
+        RateLimitRequest {
+            client_ip: ip.parse().unwrap(),
+            headers,
+            vhost: "test",
+            route: Some((0, None)),
+        }
+    }
+
+    #[test]
+    fn test_extreme_policies_do_not_panic() {
+        let limiter = RateLimiter::new();
+        let headers = HeaderMap::new();
+        let req = request("10.0.0.1", &headers);
+        let now = Instant::now();
+        for policy in [
+            serde_json::json!({"requests": 1, "interval": u64::MAX, "algorithm": "sliding_window"}),
+            serde_json::json!({"requests": 1, "interval": u64::MAX, "burst": u64::MAX}),
+            serde_json::json!({"requests": u64::MAX, "interval": u64::MAX}),
+        ] {
+            let limits = [parse_policy(policy)];
+            let status = limiter.check_at(&req, &limits, &[], now).unwrap();
+            assert!(status.allowed);
+            limiter.check_at(&req, &limits, &[], now).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_token_bucket_burst_and_refill() {
+        let limiter = RateLimiter::new();
+        let headers = HeaderMap::new();
+        let policy = parse_policy(serde_json::json!({"requests": 2, "interval": 10, "burst": 3}));
+        let limits = [policy];
+        let now = Instant::now();
+        let req = request("10.0.0.1", &headers);
+
+        for remaining in [2, 1, 0] {
+            let status = limiter.check_at(&req, &limits, &[], now).unwrap();
+            assert!(status.allowed);
+            assert_eq!(status.remaining, remaining);
+        }
+        let status = limiter.check_at(&req, &limits, &[], now).unwrap();
+        assert!(!status.allowed);
+        // One token refills every 5 seconds
+        assert_eq!(status.retry_after, 5);
+        assert_eq!(status.reset, 15);
+        assert_eq!(status.burst, Some(3));
+
+        // Another client has its own bucket
+        let other = request("10.0.0.2", &headers);
+        assert!(limiter.check_at(&other, &limits, &[], now).unwrap().allowed);
+
+        let later = now + Duration::from_secs(5);
+        assert!(limiter.check_at(&req, &limits, &[], later).unwrap().allowed);
+        assert!(!limiter.check_at(&req, &limits, &[], later).unwrap().allowed);
+    }
+
+    #[test]
+    fn test_sliding_window() {
+        let limiter = RateLimiter::new();
+        let headers = HeaderMap::new();
+        let policy = parse_policy(serde_json::json!({
+            "requests": 4, "interval": 10, "algorithm": "sliding_window"
+        }));
+        let limits = [policy];
+        let start = Instant::now();
+        let req = request("10.0.0.1", &headers);
+
+        for _ in 0..4 {
+            assert!(limiter.check_at(&req, &limits, &[], start).unwrap().allowed);
+        }
+        let status = limiter.check_at(&req, &limits, &[], start).unwrap();
+        assert!(!status.allowed);
+        // Next window starts at 10s with 4 * (1 - t/10) previous requests: room at 12.5s
+        assert_eq!(status.retry_after, 13);
+
+        // Halfway through the next window half of the previous requests still count
+        let mid = start + Duration::from_secs(15);
+        let status = limiter.check_at(&req, &limits, &[], mid).unwrap();
+        assert!(status.allowed);
+        assert_eq!(status.remaining, 1);
+        assert!(limiter.check_at(&req, &limits, &[], mid).unwrap().allowed);
+        assert!(!limiter.check_at(&req, &limits, &[], mid).unwrap().allowed);
+    }
+
+    #[test]
+    fn test_keys_and_scopes() {
+        let limiter = RateLimiter::new();
+        let mut headers = HeaderMap::new();
+        let vhost_limits = [parse_policy(
+            serde_json::json!({"key": "vhost", "requests": 3}),
+        )];
+        let route_limits = [parse_policy(serde_json::json!({
+            "key": {"header": "x-api-key"}, "requests": 1
+        }))];
+        let now = Instant::now();
+
+        // Without the header only the virtual host limit applies
+        let status = limiter
+            .check_at(
+                &request("10.0.0.1", &headers),
+                &vhost_limits,
+                &route_limits,
+                now,
+            )
+            .unwrap();
+        assert_eq!(status.limit, 3);
+
+        headers.insert("x-api-key", HeaderValue::from_static("alice"));
+        let req = request("10.0.0.2", &headers);
+        assert!(
+            limiter
+                .check_at(&req, &vhost_limits, &route_limits, now)
+                .unwrap()
+                .allowed
+        );
+        let status = limiter
+            .check_at(&req, &vhost_limits, &route_limits, now)
+            .unwrap();
+        assert!(!status.allowed);
+        assert_eq!(status.limit, 1);
+
+        // The virtual host counter is shared by all clients: 3 used, then limited
+        let status = limiter
+            .check_at(
+                &request("10.0.0.3", &HeaderMap::new()),
+                &vhost_limits,
+                &[],
+                now,
+            )
+            .unwrap();
+        assert!(!status.allowed);
+
+        assert!(limiter.check_at(&req, &[], &[], now).is_none());
+        let counts = limiter.stats();
+        assert_eq!((counts.allowed, counts.limited), (2, 2));
+    }
+
+    #[test]
+    fn test_idle_keys_are_evicted() {
+        let limiter = RateLimiter::with_capacity(2);
+        let headers = HeaderMap::new();
+        let limits = [parse_policy(
+            serde_json::json!({"requests": 1, "interval": 1}),
+        )];
+        let now = Instant::now();
+
+        for i in 0..200 {
+            let ip = format!("10.0.{}.{}", i / 100, i % 100);
+            limiter.check_at(&request(&ip, &headers), &limits, &[], now);
+        }
+        let stats = limiter.stats();
+        assert!(stats.tracked_keys <= SHARDS * 2);
+        assert_eq!(stats.tracked_keys as u64 + stats.evicted, 200);
+
+        // A limited key that is evicted while active only resets that key;
+        // after a full interval every key is idle and evicted first
+        let later = now + Duration::from_secs(1);
+        limiter.check_at(&request("192.168.0.1", &headers), &limits, &[], later);
+        assert!(limiter.stats().tracked_keys <= SHARDS * 2);
+    }
+
+    #[test]
+    fn test_concurrent_requests_share_one_counter() {
+        let limiter = Arc::new(RateLimiter::new());
+        let limits = Arc::new([parse_policy(serde_json::json!({
+            "key": "route", "requests": 100, "interval": 3600
+        }))]);
+
+        let threads: Vec<_> = (0..8)
+            .map(|t| {
+                let (limiter, limits) = (Arc::clone(&limiter), Arc::clone(&limits));
+                std::thread::spawn(move || {
+                    let headers = HeaderMap::new();
+                    let req = request(&format!("10.0.0.{t}"), &headers);
+                    (0..50)
+                        .filter(|_| limiter.check(&req, &[], &*limits).unwrap().allowed)
+                        .count()
+                })
+            })
+            .collect();
+        let mut allowed = 0;
+        for thread in threads {
+            allowed += thread.join().unwrap();
+        }
+        assert_eq!(allowed, 100);
+    }
+
+    #[test]
+    fn test_headers() {
+        let mut headers = HeaderMap::new();
+        let status = RateLimitStatus {
+            allowed: false,
+            limit: 10,
+            interval: 60,
+            burst: Some(20),
+            remaining: 0,
+            reset: 120,
+            retry_after: 6,
+        };
+        apply_rate_limit_headers(&mut headers, &status);
+        assert_eq!(headers["ratelimit-limit"], "10");
+        assert_eq!(headers["ratelimit-remaining"], "0");
+        assert_eq!(headers["ratelimit-reset"], "120");
+        assert_eq!(headers["ratelimit-policy"], "10;w=60;burst=20");
+        assert_eq!(headers["retry-after"], "6");
+    }
+}
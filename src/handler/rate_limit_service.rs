@@ -0,0 +1,483 @@
+//! Rate limit service client
+//!
+//! Checks `global_rate_limits` descriptors with an external service so limits
+//! are shared by all server instances. The protocol is the JSON form of
+//! Envoy's rate limit service: `POST /json` with the domain and descriptors,
+//! answered with an overall `OK` or `OVER_LIMIT`. Connections are pooled.
+
+use crate::config::{
+    GlobalRateLimit, RateLimitAction, RateLimitFailureMode, RateLimitServiceConfig,
+};
+use crate::handler::rate_limit::RateLimitRequest;
+use crate::logger;
+use http_body_util::{BodyExt, Full};
+use hyper::body::Bytes;
+use hyper::{Method, Request};
+use hyper_util::client::legacy::connect::HttpConnector;
+use hyper_util::client::legacy::Client;
+use hyper_util::rt::TokioExecutor;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// One descriptor: ordered key/value entries
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Descriptor {
+    pub entries: Vec<DescriptorEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DescriptorEntry {
+    pub key: String,
+    pub value: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ServiceRequest {
+    domain: String,
+    descriptors: Vec<Descriptor>,
+    #[serde(rename = "hitsAddend")]
+    hits_addend: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ServiceResponse {
+    #[serde(rename = "overallCode", alias = "overall_code")]
+    overall_code: String,
+    #[serde(default)]
+    statuses: Vec<DescriptorStatus>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct DescriptorStatus {
+    code: String,
+    /// Protobuf JSON duration, e.g. "30s" or "1.5s"
+    #[serde(
+        default,
+        rename = "durationUntilReset",
+        alias = "duration_until_reset",
+        skip_serializing_if = "Option::is_none"
+    )]
+    duration_until_reset: Option<String>,
+}
+
+/// Outcome of a rate limit service check
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ServiceDecision {
+    /// Serve the request (also when the service failed with `fail_open`)
+    Allow,
+    /// Over a limit: answer 429 with this `Retry-After`
+    OverLimit { retry_after: u64 },
+    /// The service failed with `fail_closed`: answer 503
+    Unavailable,
+}
+
+/// Rate limit service counters, as reported by `GET /v1/stats`
+#[derive(Debug, Default, Serialize, PartialEq, Eq)]
+pub struct RateLimitServiceStats {
+    /// Checks answered `OK`
+    pub ok: u64,
+    /// Checks answered `OVER_LIMIT`
+    pub over_limit: u64,
+    /// Checks that failed (connection error, timeout, bad response)
+    pub errors: u64,
+}
+
+/// Pooled client for the rate limit service
+pub struct RateLimitServiceClient {
+    client: Client<HttpConnector, Full<Bytes>>,
+    ok: AtomicU64,
+    over_limit: AtomicU64,
+    errors: AtomicU64,
+}
+
+impl RateLimitServiceClient {
+    pub fn new() -> Self {
+        Self {
+            client: Client::builder(TokioExecutor::new()).build_http(),
+            ok: AtomicU64::new(0),
+            over_limit: AtomicU64::new(0),
+            errors: AtomicU64::new(0),
+        }
+    }
+
+    /// Check descriptors with the service
+    ///
+    /// Errors and timeouts are resolved by the configured failure mode.
+    pub async fn check(
+        &self,
+        config: &RateLimitServiceConfig,
+        descriptors: Vec<Descriptor>,
+    ) -> ServiceDecision {
+        let timeout = Duration::from_millis(config.timeout_ms);
+        let result = tokio::time::timeout(timeout, self.call(config, descriptors))
+            .await
+            .unwrap_or_else(|_| Err(format!("no response within {}ms", config.timeout_ms)));
+
+        match result {
+            Ok(response) if response.overall_code == "OVER_LIMIT" => {
+                self.over_limit.fetch_add(1, Ordering::Relaxed);
+                ServiceDecision::OverLimit {
+                    retry_after: retry_after(&response.statuses),
+                }
+            }
+            Ok(_) => {
+                self.ok.fetch_add(1, Ordering::Relaxed);
+                ServiceDecision::Allow
+            }
+            Err(e) => {
+                self.errors.fetch_add(1, Ordering::Relaxed);
+                let decision = match config.failure_mode {
+                    RateLimitFailureMode::FailOpen => ServiceDecision::Allow,
+                    RateLimitFailureMode::FailClosed => ServiceDecision::Unavailable,
+                };
+                logger::log_warning(&format!(
+                    "Rate limit service {} failed: {e} ({})",
+                    config.address,
+                    if decision == ServiceDecision::Allow {
+                        "failing open"
+                    } else {
+                        "failing closed"
+                    }
+                ));
+                decision
+            }
+        }
+    }
+
+    /// Current counter values
+    pub fn stats(&self) -> RateLimitServiceStats {
+        RateLimitServiceStats {
+            ok: self.ok.load(Ordering::Relaxed),
+            over_limit: self.over_limit.load(Ordering::Relaxed),
+            errors: self.errors.load(Ordering::Relaxed),
+        }
+    }
+
+    async fn call(
+        &self,
+        config: &RateLimitServiceConfig,
+        descriptors: Vec<Descriptor>,
+    ) -> Result<ServiceResponse, String> {
+        let body = serde_json::to_vec(&ServiceRequest {
+            domain: config.domain.clone(),
+            descriptors,
+            hits_addend: 1,
+        })
+        .map_err(|e| e.to_string())?;
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri(format!("http://{}/json", config.address))
+            .header("Content-Type", "application/json")
+            .body(Full::new(Bytes::from(body)))
+            .map_err(|e| e.to_string())?;
+
+        let response = self
+            .client
+            .request(request)
+            .await
+            .map_err(|e| e.to_string())?;
+        let status = response.status();
+        let body = response
+            .into_body()
+            .collect()
+            .await
+            .map_err(|e| e.to_string())?
+            .to_bytes();
+        if !status.is_success() {
+            return Err(format!("status {status}"));
+        }
+        serde_json::from_slice(&body).map_err(|e| format!("invalid response: {e}"))
+    }
+}
+
+impl Default for RateLimitServiceClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Build descriptors for a request (descriptors with a missing value are skipped)
+pub fn build_descriptors(
+    limits: &[GlobalRateLimit],
+    request: &RateLimitRequest<'_>,
+) -> Vec<Descriptor> {
+    limits
+        .iter()
+        .filter_map(|limit| {
+            let entries = limit
+                .actions
+                .iter()
+                .map(|action| descriptor_entry(action, request))
+                .collect::<Option<Vec<_>>>()?;
+            Some(Descriptor { entries })
+        })
+        .collect()
+}
+
+fn descriptor_entry(
+    action: &RateLimitAction,
+    request: &RateLimitRequest<'_>,
+) -> Option<DescriptorEntry> {
+    let (key, value) = match action {
+        RateLimitAction::RemoteAddress => {
+            ("remote_address".to_string(), request.client_ip.to_string())
+        }
+        RateLimitAction::RequestHeaders {
+            header_name,
+            descriptor_key,
+        } => (
+            descriptor_key.clone(),
+            request
+                .headers
+                .get(header_name.as_str())?
+                .to_str()
+                .ok()?
+                .to_string(),
+        ),
+        RateLimitAction::GenericKey {
+            descriptor_key,
+            descriptor_value,
+        } => (descriptor_key.clone(), descriptor_value.clone()),
+    };
+    Some(DescriptorEntry { key, value })
+}
+
+/// Longest `durationUntilReset` of the exceeded limits, in whole seconds (at least 1)
+fn retry_after(statuses: &[DescriptorStatus]) -> u64 {
+    statuses
+        .iter()
+        .filter(|status| status.code == "OVER_LIMIT")
+        .filter_map(|status| {
+            let secs = status.duration_until_reset.as_deref()?.strip_suffix('s')?;
+            secs.parse::<f64>()
+                .ok()
+                .filter(|secs| secs.is_finite() && *secs >= 0.0)
+        })
+        .map(|secs| {
+            #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+            let whole = secs.ceil() as u64;
+            whole
+        })
+        .max()
+        .unwrap_or(1)
+        .max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hyper::server::conn::http1;
+    use hyper::service::service_fn;
+    use hyper::{HeaderMap, Response};
+    use hyper_util::rt::TokioIo;
+    use std::collections::HashMap;
+    use std::convert::Infallible;
+    use std::net::SocketAddr;
+    use std::sync::{Arc, Mutex};
+    use tokio::net::TcpListener;
+
+    /// Minimal in-process rate limit service: each distinct descriptor may be
+    /// hit `limit` times; `delay` holds every answer back
+    async fn reference_server(limit: u64, delay: Duration) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let hits: Arc<Mutex<HashMap<String, u64>>> = Arc::default();
+
+        tokio::spawn(async move {
+            loop {
+                let Ok((stream, _)) = listener.accept().await else {
+                    return;
+                };
+                let hits = Arc::clone(&hits);
+                let service = service_fn(move |req: Request<hyper::body::Incoming>| {
+                    let hits = Arc::clone(&hits);
+                    async move {
+                        let body = req.into_body().collect().await.unwrap().to_bytes();
+                        let request: ServiceRequest = serde_json::from_slice(&body).unwrap();
+                        tokio::time::sleep(delay).await;
+
+                        let statuses: Vec<DescriptorStatus> = request
+                            .descriptors
+                            .iter()
+                            .map(|descriptor| {
+                                let key = format!(
+                                    "{}|{}",
+                                    request.domain,
+                                    serde_json::to_string(descriptor).unwrap()
+                                );
+                                let mut hits = hits.lock().unwrap();
+                                let count = hits.entry(key).or_insert(0);
+                                *count += request.hits_addend;
+                                let over = *count > limit;
+                                drop(hits);
+                                DescriptorStatus {
+                                    code: if over { "OVER_LIMIT" } else { "OK" }.to_string(),
+                                    duration_until_reset: Some("42.5s".to_string()),
+                                }
+                            })
+                            .collect();
+                        let overall = if statuses.iter().any(|s| s.code == "OVER_LIMIT") {
+                            "OVER_LIMIT"
+                        } else {
+                            "OK"
+                        };
+                        let response = ServiceResponse {
+                            overall_code: overall.to_string(),
+                            statuses,
+                        };
+                        let body = serde_json::to_vec(&response).unwrap();
+                        Ok::<_, Infallible>(Response::new(Full::new(Bytes::from(body))))
+                    }
+                });
+                tokio::spawn(async move {
+                    let _ = http1::Builder::new()
+                        .serve_connection(TokioIo::new(stream), service)
+                        .await;
+                });
+            }
+        });
+        addr
+    }
+
+    fn service_config(address: &str, failure_mode: &str) -> RateLimitServiceConfig {
+        serde_json::from_value(serde_json::json!({
+            "address": address,
+            "timeout_ms": 200,
+            "failure_mode": failure_mode
+        }))
+        .unwrap()
+    }
+
+    fn descriptors(headers: &HeaderMap) -> Vec<Descriptor> {
+        let limits: Vec<GlobalRateLimit> = serde_json::from_value(serde_json::json!([
+            {"actions": ["remote_address"]},
+            {"actions": [
+                {"generic_key": {"descriptor_value": "api"}},
+                {"request_headers": {"header_name": "x-api-key", "descriptor_key": "api_key"}}
+            ]}
+        ]))
+        .unwrap();
+        let request = RateLimitRequest {
+            client_ip: "10.0.0.1".parse().unwrap(),
+            headers,
+            vhost: "test",
+            route: None,
+        };
+        build_descriptors(&limits, &request)
+    }
+
+    #[test]
+    fn test_build_descriptors() {
+        // The descriptor with a missing header is skipped
+        let built = descriptors(&HeaderMap::new());
+        assert_eq!(built.len(), 1);
+        assert_eq!(built[0].entries[0].key, "remote_address");
+        assert_eq!(built[0].entries[0].value, "10.0.0.1");
+
+        let mut headers = HeaderMap::new();
+        headers.insert("x-api-key", "alice".parse().unwrap());
+        let built = descriptors(&headers);
+        let entries: Vec<_> = built[1]
+            .entries
+            .iter()
+            .map(|e| (e.key.as_str(), e.value.as_str()))
+            .collect();
+        assert_eq!(entries, [("generic_key", "api"), ("api_key", "alice")]);
+    }
+
+    #[test]
+    fn test_protocol_format() {
+        let request = ServiceRequest {
+            domain: "yarhs".to_string(),
+            descriptors: descriptors(&HeaderMap::new()),
+            hits_addend: 1,
+        };
+        assert_eq!(
+            serde_json::to_value(&request).unwrap(),
+            serde_json::json!({
+                "domain": "yarhs",
+                "descriptors": [{"entries": [{"key": "remote_address", "value": "10.0.0.1"}]}],
+                "hitsAddend": 1
+            })
+        );
+
+        let response: ServiceResponse = serde_json::from_value(serde_json::json!({
+            "overallCode": "OVER_LIMIT",
+            "statuses": [
+                {"code": "OK"},
+                {"code": "OVER_LIMIT", "durationUntilReset": "2.1s"},
+                {"code": "OVER_LIMIT", "durationUntilReset": "0s"}
+            ]
+        }))
+        .unwrap();
+        assert_eq!(retry_after(&response.statuses), 3);
+        assert_eq!(retry_after(&[]), 1);
+    }
+
+    #[tokio::test]
+    async fn test_reference_server_limits() {
+        let addr = reference_server(2, Duration::ZERO).await;
+        let client = RateLimitServiceClient::new();
+        let config = service_config(&addr.to_string(), "fail_open");
+        let headers = HeaderMap::new();
+
+        for _ in 0..2 {
+            let decision = client.check(&config, descriptors(&headers)).await;
+            assert_eq!(decision, ServiceDecision::Allow);
+        }
+        let decision = client.check(&config, descriptors(&headers)).await;
+        assert_eq!(decision, ServiceDecision::OverLimit { retry_after: 43 });
+
+        // Another domain has its own counters
+        let other = RateLimitServiceConfig {
+            domain: "other".to_string(),
+            ..config
+        };
+        let decision = client.check(&other, descriptors(&headers)).await;
+        assert_eq!(decision, ServiceDecision::Allow);
+        assert_eq!(
+            client.stats(),
+            RateLimitServiceStats {
+                ok: 3,
+                over_limit: 1,
+                errors: 0
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_failure_modes() {
+        let client = RateLimitServiceClient::new();
+        let headers = HeaderMap::new();
+
+        // Nothing listens on this port once the listener is dropped
+        let closed = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = closed.local_addr().unwrap().to_string();
+        drop(closed);
+        let decision = client
+            .check(
+                &service_config(&address, "fail_open"),
+                descriptors(&headers),
+            )
+            .await;
+        assert_eq!(decision, ServiceDecision::Allow);
+        let decision = client
+            .check(
+                &service_config(&address, "fail_closed"),
+                descriptors(&headers),
+            )
+            .await;
+        assert_eq!(decision, ServiceDecision::Unavailable);
+
+        // Slower than the 200ms timeout
+        let slow = reference_server(100, Duration::from_secs(2))
+            .await
+            .to_string();
+        let decision = client
+            .check(&service_config(&slow, "fail_closed"), descriptors(&headers))
+            .await;
+        assert_eq!(decision, ServiceDecision::Unavailable);
+        assert_eq!(client.stats().errors, 3);
+    }
+}
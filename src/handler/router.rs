@@ -2,18 +2,26 @@
 //!
 //! Entry point for HTTP request processing, responsible for method validation, route matching, and dispatching.
 
-use crate::config::{AppState, RouteAction, RouteHandler, RoutesConfig, VirtualHost};
-use crate::handler::static_files;
-use crate::http;
+use crate::config::{
+    AppState, GlobalRateLimit, HeaderMutations, HttpConfig, Route, RouteAction, RouteHandler,
+    VirtualHost,
+};
+use crate::handler::cors;
+use crate::handler::headers::{self, HeaderVariables};
+use crate::handler::open_file_cache::OpenFileCache;
+use crate::handler::rate_limit::{self, RateLimitRequest, RateLimitStatus, RateLimiter};
+use crate::handler::rate_limit_service::{self, RateLimitServiceClient, ServiceDecision};
+use crate::handler::static_files::{self, DirOptions, DirOutcome};
+use crate::http::throttle::{SharedRates, Throttle};
+use crate::http::{self, ResponseBody};
 use crate::logger;
-use crate::routing;
-use http_body_util::Full;
-use hyper::body::{Body, Bytes};
-use hyper::{Method, Request, Response};
+use crate::routing::{self, MatchRequest, PathCaptures, RedirectRequest, RouteTable};
+use hyper::body::Body;
+use hyper::{HeaderMap, Method, Request, Response};
 use std::convert::Infallible;
 use std::net::SocketAddr;
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Instant, SystemTime};
 
 /// Get elapsed time in microseconds, saturating to `u64::MAX` if overflow
 #[inline]
@@ -23,12 +31,61 @@ fn elapsed_micros(start: Instant) -> u64 {
 }
 
 /// Request context encapsulating information needed for request processing
+#[derive(Clone)]
 pub struct RequestContext<'a> {
     pub path: &'a str,
+    /// Host header (after `host_rewrite` when re-matching)
+    pub host: &'a str,
+    pub query: Option<&'a str>,
+    pub method: &'a Method,
+    pub headers: &'a HeaderMap,
     pub is_head: bool,
+    pub accept: Option<String>,
     pub if_none_match: Option<String>,
     pub if_modified_since: Option<String>,
     pub range_header: Option<String>,
+    pub file_cache: &'a OpenFileCache,
+    pub rate_limiter: &'a RateLimiter,
+    pub rate_limit_service: &'a RateLimitServiceClient,
+    pub vhost_bandwidth: &'a SharedRates,
+    pub remote_addr: SocketAddr,
+    pub start_time: SystemTime,
+    /// Live HTTP settings (CORS policy, default content type)
+    pub http: &'a HttpConfig,
+}
+
+impl<'a> RequestContext<'a> {
+    /// Same request with different headers (header-derived fields are re-read)
+    fn with_headers<'b>(&self, headers: &'b HeaderMap) -> RequestContext<'b>
+    where
+        'a: 'b,
+    {
+        RequestContext {
+            headers,
+            accept: header_string(headers, "accept"),
+            if_none_match: header_string(headers, "if-none-match"),
+            if_modified_since: header_string(headers, "if-modified-since"),
+            range_header: header_string(headers, "range"),
+            ..self.clone()
+        }
+    }
+
+    /// Variables for header value interpolation
+    const fn header_variables(&self) -> HeaderVariables<'a> {
+        HeaderVariables {
+            remote_addr: self.remote_addr,
+            start_time: self.start_time,
+            request_headers: self.headers,
+        }
+    }
+}
+
+/// Get a header value as an owned string (`None` if missing or not visible ASCII)
+fn header_string(headers: &HeaderMap, name: &str) -> Option<String> {
+    headers
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .map(ToString::to_string)
 }
 
 /// Main entry point for HTTP request handling
@@ -37,8 +94,9 @@ pub async fn handle_request(
     req: Request<hyper::body::Incoming>,
     state: Arc<AppState>,
     remote_addr: SocketAddr,
-) -> Result<Response<Full<Bytes>>, Infallible> {
+) -> Result<Response<ResponseBody>, Infallible> {
     let start_time = Instant::now();
+    let request_time = SystemTime::now();
     let method = req.method().clone();
     let uri = req.uri().clone();
     let path = uri.path().to_string();
@@ -66,94 +124,72 @@ pub async fn handle_request(
         .unwrap_or("localhost")
         .to_string();
 
-    let access_log = state
-        .cached_access_log
-        .load(std::sync::atomic::Ordering::Relaxed);
+    // One configuration snapshot for the whole request (lock-free)
+    let config = state.snapshot();
+    let access_log = config.logging.access_log;
+    let log_format = config.logging.access_log_format.as_str();
+    let http_config = config.http.as_ref();
 
-    // Get log format early
-    let log_format = {
-        let config = state.dynamic_config.read().await;
-        config.logging.access_log_format.clone()
-    };
+    // 1-3. Check the method and body size, decode and normalize the path
+    //      (routing and file lookup only see the normalized form)
+    let rejected =
+        check_http_method(&method).or_else(|| check_body_size(&req, http_config.max_body_size));
+    let normalized = http::uri::normalize_path(&path, http_config.merge_slashes);
+    let route_table = config.route_table.as_ref();
 
-    // 1. Check HTTP method
-    if let Some(resp) = check_http_method(&method, state.config.http.enable_cors) {
-        if access_log {
-            logger::log_access_request(
-                &remote_addr,
-                method.as_str(),
-                &path,
-                query.as_deref(),
-                &http_version,
-                resp.status().as_u16(),
-                0,
-                referer.as_deref(),
-                user_agent.as_deref(),
-                elapsed_micros(start_time),
-                &log_format,
-            );
+    // Requests rejected before routing use the virtual host of the Host header
+    let (mut response, served_by) = match (rejected, normalized) {
+        (Some(response), _) => (response, route_table.resolve_virtual_host(&host)),
+        (None, Err(e)) => {
+            logger::log_warning(&format!("Rejected request path '{path}': {e}"));
+            let response = http::build_400_response();
+            (response, route_table.resolve_virtual_host(&host))
         }
-        return Ok(resp);
-    }
+        (None, Ok(normalized_path)) => {
+            // 4. Log headers if enabled
+            logger::log_headers_count(req.headers().len(), config.logging.show_headers);
 
-    // 2. Check body size
-    if let Some(resp) = check_body_size(&req, state.config.http.max_body_size) {
-        if access_log {
-            logger::log_access_request(
-                &remote_addr,
-                method.as_str(),
-                &path,
-                query.as_deref(),
-                &http_version,
-                resp.status().as_u16(),
-                0,
-                referer.as_deref(),
-                user_agent.as_deref(),
-                elapsed_micros(start_time),
-                &log_format,
-            );
-        }
-        return Ok(resp);
-    }
+            // 5. Extract headers for caching and range requests
+            let ctx = RequestContext {
+                path: &normalized_path,
+                host: &host,
+                query: query.as_deref(),
+                method: &method,
+                headers: req.headers(),
+                is_head,
+                accept: header_string(req.headers(), "accept"),
+                if_none_match: header_string(req.headers(), "if-none-match"),
+                if_modified_since: header_string(req.headers(), "if-modified-since"),
+                range_header: header_string(req.headers(), "range"),
+                file_cache: &state.open_file_cache,
+                rate_limiter: &state.rate_limiter,
+                rate_limit_service: &state.rate_limit_service,
+                vhost_bandwidth: &state.vhost_bandwidth,
+                remote_addr,
+                start_time: request_time,
+                http: http_config,
+            };
 
-    // 3. Log headers if enabled
-    let show_headers = state.dynamic_config.read().await.logging.show_headers;
-    logger::log_headers_count(req.headers().len(), show_headers);
-
-    // 4. Extract headers for caching and range requests
-    let ctx = RequestContext {
-        path: &path,
-        is_head,
-        if_none_match: req
-            .headers()
-            .get("if-none-match")
-            .and_then(|v| v.to_str().ok())
-            .map(ToString::to_string),
-        if_modified_since: req
-            .headers()
-            .get("if-modified-since")
-            .and_then(|v| v.to_str().ok())
-            .map(ToString::to_string),
-        range_header: req
-            .headers()
-            .get("range")
-            .and_then(|v| v.to_str().ok())
-            .map(ToString::to_string),
-    };
-
-    // 5. Get config and dispatch based on virtual hosts or legacy routes
-    let (virtual_hosts, routes) = {
-        let config = state.dynamic_config.read().await;
-        (Arc::clone(&config.virtual_hosts), Arc::clone(&config.routes))
+            // 6. Dispatch based on virtual hosts or legacy routes
+            if route_table.virtual_hosts.is_empty() {
+                // Fallback to legacy route configuration
+                (route_request(&ctx, route_table).await, None)
+            } else {
+                // Use xDS-style virtual host routing
+                route_with_vhosts(&ctx, route_table, 0).await
+            }
+        }
     };
 
-    let response = if virtual_hosts.is_empty() {
-        // Fallback to legacy route configuration
-        route_request(&ctx, &routes, &state).await
-    } else {
-        // Use xDS-style virtual host routing
-        route_with_vhosts(&ctx, &host, &virtual_hosts, &routes, &state).await
-    };
+    // 7. Security headers of the virtual host that served the request (replacing the
+    //    global preset), on every response including early errors
+    let vhost_security_headers = served_by
+        .and_then(|index| route_table.virtual_hosts[index].security_headers.as_ref());
+    if let Some(security_headers) = vhost_security_headers.or(http_config.security_headers.as_ref())
+    {
+        headers::apply_security_headers(response.headers_mut(), security_headers);
+    }
+    set_server_header(&mut response, http_config);
 
     // Log access after response is built
     if access_log {
@@ -174,18 +210,34 @@ pub async fn handle_request(
             referer.as_deref(),
             user_agent.as_deref(),
             elapsed_micros(start_time),
-            &log_format,
+            log_format,
         );
     }
 
     Ok(response)
 }
 
-/// Check HTTP method and return appropriate response for non-GET/HEAD methods
-fn check_http_method(method: &Method, enable_cors: bool) -> Option<Response<Full<Bytes>>> {
+/// Set the `Server` header from `http.server_name`
+///
+/// Omitted when empty; a `Server` header added by `response_headers_to_add` is kept.
+pub fn set_server_header(response: &mut Response<ResponseBody>, http_config: &HttpConfig) {
+    if http_config.server_name.is_empty() {
+        return;
+    }
+    if let Ok(value) = hyper::header::HeaderValue::from_str(&http_config.server_name) {
+        response
+            .headers_mut()
+            .entry(hyper::header::SERVER)
+            .or_insert(value);
+    }
+}
+
+/// Check HTTP method and return 405 for methods other than GET/HEAD/OPTIONS
+///
+/// OPTIONS is answered after routing, with the CORS policy of the matched route.
+fn check_http_method(method: &Method) -> Option<Response<ResponseBody>> {
     match method {
-        &Method::GET | &Method::HEAD => None,
-        &Method::OPTIONS => Some(http::build_options_response(enable_cors)),
+        &Method::GET | &Method::HEAD | &Method::OPTIONS => None,
         _ => {
             logger::log_warning(&format!("Method not allowed: {method}"));
             Some(http::build_405_response())
@@ -197,7 +249,7 @@ fn check_http_method(method: &Method, enable_cors: bool) -> Option<Response<Full
 fn check_body_size(
     req: &Request<hyper::body::Incoming>,
     max_body_size: u64,
-) -> Option<Response<Full<Bytes>>> {
+) -> Option<Response<ResponseBody>> {
     let content_length = req.headers().get("content-length")?;
     content_length.to_str().map_or_else(
         |_| {
@@ -223,28 +275,33 @@ fn check_body_size(
 }
 
 /// Route request using xDS-style virtual hosts
+///
+/// `rematches` counts how often the request re-entered matching after a rewrite.
+/// Returns the response with the index of the virtual host that served it
+/// (after any `host_rewrite`), or `None` for health checks and legacy routes.
 async fn route_with_vhosts(
     ctx: &RequestContext<'_>,
-    host: &str,
-    virtual_hosts: &[VirtualHost],
-    legacy_routes: &Arc<RoutesConfig>,
-    state: &Arc<AppState>,
-) -> Response<Full<Bytes>> {
+    route_table: &RouteTable,
+    rematches: usize,
+) -> (Response<ResponseBody>, Option<usize>) {
+    let legacy_routes = &route_table.routes;
+
     // 0. Health check endpoints (global, highest priority)
-    if legacy_routes.health.enabled {
+    if legacy_routes.health.enabled && ctx.method != Method::OPTIONS {
         if ctx.path == legacy_routes.health.liveness_path {
-            return http::build_health_response("ok");
+            return (http::build_health_response("ok"), None);
         }
         if ctx.path == legacy_routes.health.readiness_path {
-            return http::build_health_response("ok");
+            return (http::build_health_response("ok"), None);
         }
     }
 
     // 1. Find matching virtual host
-    let Some(vhost) = routing::resolve_virtual_host(host, virtual_hosts) else {
+    let Some(vhost_index) = route_table.resolve_virtual_host(ctx.host) else {
         // No matching virtual host, fall back to legacy routes
-        return route_request(ctx, legacy_routes, state).await;
+        return (route_request(ctx, route_table).await, None);
     };
+    let vhost = &route_table.virtual_hosts[vhost_index];
 
     // 2. Get index files (use vhost override or legacy default)
     let index_files = vhost
@@ -252,28 +309,227 @@ async fn route_with_vhosts(
         .as_ref()
         .unwrap_or(&legacy_routes.index_files);
 
-    // 3. Find matching route within virtual host
-    if let Some(route) = routing::match_route(ctx.path, None, &vhost.routes) {
-        // Get the route prefix for path stripping (if prefix match)
-        let route_prefix = route
-            .match_rule
-            .prefix
-            .as_deref()
-            .or(route.match_rule.path.as_deref())
-            .unwrap_or("");
-        return dispatch_route_action(ctx, &route.action, route_prefix, index_files).await;
+    // 3. Find matching route within virtual host (a preflight matches with the requested method)
+    let preflight = ctx.method == Method::OPTIONS && cors::is_preflight(ctx.headers);
+    let requested_method = if preflight {
+        ctx.headers
+            .get(hyper::header::ACCESS_CONTROL_REQUEST_METHOD)
+            .and_then(|v| Method::from_bytes(v.as_bytes()).ok())
+    } else {
+        None
+    };
+    let request = MatchRequest {
+        path: ctx.path,
+        method: requested_method.as_ref().unwrap_or(ctx.method),
+        query: ctx.query,
+        headers: ctx.headers,
+    };
+    let matched = route_table.match_vhost_route(vhost_index, &request);
+
+    // Rate limits of the virtual host (charged once, not again after a rewrite) and route
+    let limit_request = RateLimitRequest {
+        client_ip: ctx.remote_addr.ip(),
+        headers: ctx.headers,
+        vhost: &vhost.name,
+        route: matched.as_ref().map(|m| (m.index, m.route.name.as_deref())),
+    };
+    let vhost_limits = rematches == 0;
+    let rate_limit = ctx.rate_limiter.check(
+        &limit_request,
+        if vhost_limits { &vhost.rate_limits } else { &[] },
+        matched.as_ref().map_or(&[], |m| &m.route.rate_limits),
+    );
+
+    // Global limits are only checked for requests within the local limits
+    let service_decision = if rate_limit.is_none_or(|status| status.allowed) {
+        check_global_rate_limits(
+            ctx,
+            &limit_request,
+            if vhost_limits { &vhost.global_rate_limits } else { &[] },
+            matched.as_ref().map_or(&[], |m| &m.route.global_rate_limits),
+        )
+        .await
+    } else {
+        None
+    };
+    let rejection = rate_limit_rejection(ctx, rate_limit.as_ref(), service_decision);
+
+    let layers = [
+        &vhost.header_mutations,
+        matched
+            .as_ref()
+            .map_or(&NO_HEADER_MUTATIONS, |m| &m.route.header_mutations),
+    ];
+
+    // 4. Apply request header changes before the action (or re-matching)
+    let mutated_headers = headers::mutate_request_headers(&layers, &ctx.header_variables());
+    let mutated_ctx = mutated_headers.as_ref().map(|h| ctx.with_headers(h));
+    let ctx = mutated_ctx.as_ref().unwrap_or(ctx);
+
+    let mut response = match (&matched, rejection) {
+        (_, Some(rejection)) => rejection,
+        (Some(m), None) if m.route.rewrite.as_ref().is_some_and(|rewrite| rewrite.rematch) => {
+            return rematch_request(ctx, m.route, route_table, rematches).await;
+        }
+        _ if ctx.method == Method::OPTIONS => http::build_options_response(),
+        (Some(m), None) => {
+            dispatch_route_action(ctx, m.route, &m.captures, index_files, vhost, 0).await
+        }
+        // No route matched, return 404
+        (None, None) => http::build_404_response(),
+    };
+
+    // 5. CORS headers (route policy, then virtual host, then global)
+    let cors_policy = matched
+        .as_ref()
+        .and_then(|m| m.route.cors.as_ref())
+        .or(vhost.cors.as_ref())
+        .or_else(|| ctx.http.cors_policy());
+    cors::apply_cors_headers(response.headers_mut(), cors_policy, ctx.headers, preflight);
+    if let Some(status) = &rate_limit {
+        rate_limit::apply_rate_limit_headers(response.headers_mut(), status);
+    }
+
+    // 6. Apply response header changes (including 404s and 429s of this host)
+    headers::mutate_response_headers(response.headers_mut(), &layers, &ctx.header_variables());
+
+    // 7. Bandwidth limits of the route and virtual host
+    let route = matched.as_ref().map(|m| m.route);
+    let throttle = Throttle::new(
+        route.and_then(|route| route.limit_rate),
+        route.and_then(|route| route.limit_rate_after).unwrap_or(0),
+        vhost
+            .aggregate_limit_rate
+            .map(|rate| ctx.vhost_bandwidth.get(&vhost.name, rate)),
+    );
+    if let Some(throttle) = throttle {
+        response.body_mut().set_throttle(throttle);
     }
+    (response, Some(vhost_index))
+}
 
-    // 4. No route matched, return 404
-    http::build_404_response()
+/// Check descriptors of the virtual host and route with the rate limit service
+///
+/// `None` when no service is configured or no descriptor applies.
+async fn check_global_rate_limits(
+    ctx: &RequestContext<'_>,
+    request: &RateLimitRequest<'_>,
+    vhost_limits: &[GlobalRateLimit],
+    route_limits: &[GlobalRateLimit],
+) -> Option<ServiceDecision> {
+    let service = ctx.http.rate_limit_service.as_ref()?;
+    let mut descriptors = rate_limit_service::build_descriptors(vhost_limits, request);
+    descriptors.extend(rate_limit_service::build_descriptors(route_limits, request));
+    if descriptors.is_empty() {
+        return None;
+    }
+    Some(ctx.rate_limit_service.check(service, descriptors).await)
 }
 
-/// Route request based on path and configuration (legacy mode)
-async fn route_request(
+/// Response for a request over a local or global rate limit
+///
+/// 429 for an exceeded limit (local limits add their `Retry-After` with the
+/// other `RateLimit-*` headers later), 503 when the rate limit service failed
+/// with `fail_closed`.
+fn rate_limit_rejection(
     ctx: &RequestContext<'_>,
-    routes: &Arc<RoutesConfig>,
-    state: &Arc<AppState>,
-) -> Response<Full<Bytes>> {
+    local: Option<&RateLimitStatus>,
+    service: Option<ServiceDecision>,
+) -> Option<Response<ResponseBody>> {
+    let response = if local.is_some_and(|status| !status.allowed) {
+        http::build_429_response()
+    } else {
+        match service? {
+            ServiceDecision::Allow => return None,
+            ServiceDecision::OverLimit { retry_after } => {
+                let mut response = http::build_429_response();
+                response
+                    .headers_mut()
+                    .insert(hyper::header::RETRY_AFTER, retry_after.into());
+                response
+            }
+            ServiceDecision::Unavailable => http::build_503_response(1),
+        }
+    };
+    logger::log_warning(&format!(
+        "Rate limit exceeded: {} {} from {} ({})",
+        ctx.method,
+        ctx.path,
+        ctx.remote_addr.ip(),
+        if response.status() == 429 {
+            "429"
+        } else {
+            "503, rate limit service unavailable"
+        }
+    ));
+    Some(response)
+}
+
+/// Header mutations of a request without a matched route
+static NO_HEADER_MUTATIONS: HeaderMutations = HeaderMutations {
+    request_headers_to_add: Vec::new(),
+    request_headers_to_remove: Vec::new(),
+    response_headers_to_add: Vec::new(),
+    response_headers_to_remove: Vec::new(),
+};
+
+/// Maximum number of times a request may re-enter matching after a rewrite
+const MAX_REWRITE_REMATCHES: usize = 8;
+
+/// Re-enter virtual host and route matching with the rewritten path and host
+async fn rematch_request(
+    ctx: &RequestContext<'_>,
+    route: &Route,
+    route_table: &RouteTable,
+    rematches: usize,
+) -> (Response<ResponseBody>, Option<usize>) {
+    if rematches >= MAX_REWRITE_REMATCHES {
+        logger::log_error(&format!(
+            "Rewrite loop detected at route '{}' (path: {})",
+            route.name.as_deref().unwrap_or("unnamed"),
+            ctx.path
+        ));
+        let response = http::build_direct_response(500, Some("500 Internal Server Error"), None);
+        return (response, route_table.resolve_virtual_host(ctx.host));
+    }
+
+    let path = routing::rewrite_path(route, ctx.path);
+    let host = route
+        .rewrite
+        .as_ref()
+        .and_then(|rewrite| rewrite.host_rewrite.as_deref())
+        .unwrap_or(ctx.host);
+    let rewritten = RequestContext {
+        path: &path,
+        host,
+        ..ctx.clone()
+    };
+    Box::pin(route_with_vhosts(&rewritten, route_table, rematches + 1)).await
+}
+
+/// Route request based on path and configuration (legacy mode)
+///
+/// Applies the global CORS policy; OPTIONS requests are answered directly.
+async fn route_request(ctx: &RequestContext<'_>, route_table: &RouteTable) -> Response<ResponseBody> {
+    let mut response = if ctx.method == Method::OPTIONS {
+        http::build_options_response()
+    } else {
+        route_legacy(ctx, route_table).await
+    };
+    let preflight = ctx.method == Method::OPTIONS && cors::is_preflight(ctx.headers);
+    cors::apply_cors_headers(
+        response.headers_mut(),
+        ctx.http.cors_policy(),
+        ctx.headers,
+        preflight,
+    );
+    response
+}
+
+/// Match health checks, custom routes and the default homepage (legacy mode)
+async fn route_legacy(ctx: &RequestContext<'_>, route_table: &RouteTable) -> Response<ResponseBody> {
+    let routes = &route_table.routes;
+
     // 0. Health check endpoints (highest priority, always fast)
     if routes.health.enabled {
         if ctx.path == routes.health.liveness_path {
@@ -285,40 +541,59 @@ async fn route_request(
         }
     }
 
-    // 1. Custom routes (exact match)
-    if let Some(handler) = routes.custom_routes.get(ctx.path) {
-        return dispatch_route_handler(ctx, handler, ctx.path, &routes.index_files).await;
-    }
-
-    // 2. Custom routes (prefix match)
-    if let Some((prefix, handler)) = routes
-        .custom_routes
-        .iter()
-        .find(|(p, _)| ctx.path.starts_with(p.as_str()))
-    {
+    // 1. Custom routes (exact match, then longest prefix)
+    if let Some((prefix, handler)) = route_table.match_legacy(ctx.path) {
         return dispatch_route_handler(ctx, handler, prefix, &routes.index_files).await;
     }
 
-    // 3. Default: homepage
-    serve_default_homepage(ctx, state).await
+    // 2. Default: homepage
+    serve_default_homepage(ctx)
 }
 
+/// Maximum depth of `try_files` fallbacks to named routes
+const MAX_NAMED_ROUTE_DEPTH: usize = 8;
+
 /// Dispatch to xDS `RouteAction`
 async fn dispatch_route_action(
     ctx: &RequestContext<'_>,
-    action: &RouteAction,
-    route_prefix: &str,
+    route: &Route,
+    captures: &PathCaptures,
     index_files: &[String],
-) -> Response<Full<Bytes>> {
-    match action {
-        RouteAction::Dir { path: dir } => {
-            static_files::serve_directory(ctx, dir, route_prefix, index_files).await
+    vhost: &VirtualHost,
+    depth: usize,
+) -> Response<ResponseBody> {
+    match &route.action {
+        RouteAction::Dir {
+            path: dir,
+            autoindex,
+            try_files,
+        } => {
+            let options = DirOptions {
+                // Route-level autoindex overrides the virtual host setting
+                autoindex: autoindex.as_ref().or(vhost.autoindex.as_ref()),
+                try_files: try_files.as_deref(),
+            };
+            // The route's rewrite (by default: strip the matched prefix)
+            // gives the path inside the directory
+            let dir_path = routing::rewrite_path(route, ctx.path);
+            match static_files::serve_directory(ctx, dir, &dir_path, index_files, options).await {
+                DirOutcome::Response(response) => response,
+                DirOutcome::NamedRoute(name) => {
+                    dispatch_named_route(ctx, &name, captures, index_files, vhost, depth).await
+                }
+            }
         }
         RouteAction::File { path: file_path } => {
             static_files::serve_file(ctx, file_path).await
         }
-        RouteAction::Redirect { target, code } => {
-            http::build_redirect_response_with_code(target, *code)
+        RouteAction::Redirect(redirect) => {
+            let request = RedirectRequest {
+                path: ctx.path,
+                query: ctx.query,
+                host: ctx.host,
+            };
+            let location = routing::redirect_location(route, redirect, captures, &request);
+            http::build_redirect_response_with_code(&location, redirect.code)
         }
         RouteAction::Direct { status, body, content_type } => {
             http::build_direct_response(*status, body.as_deref(), content_type.as_deref())
@@ -326,16 +601,57 @@ async fn dispatch_route_action(
     }
 }
 
+/// Dispatch a `try_files` fallback to the route with the given name
+async fn dispatch_named_route(
+    ctx: &RequestContext<'_>,
+    name: &str,
+    captures: &PathCaptures,
+    index_files: &[String],
+    vhost: &VirtualHost,
+    depth: usize,
+) -> Response<ResponseBody> {
+    if depth >= MAX_NAMED_ROUTE_DEPTH {
+        logger::log_error(&format!(
+            "try_files named route cycle detected at '@{name}' (vhost: {})",
+            vhost.name
+        ));
+        return http::build_direct_response(500, Some("500 Internal Server Error"), None);
+    }
+
+    let Some(route) = vhost
+        .routes
+        .iter()
+        .find(|route| route.name.as_deref() == Some(name))
+    else {
+        logger::log_warning(&format!(
+            "try_files named route '@{name}' not found (vhost: {})",
+            vhost.name
+        ));
+        return http::build_404_response();
+    };
+
+    Box::pin(dispatch_route_action(ctx, route, captures, index_files, vhost, depth + 1)).await
+}
+
 /// Dispatch to specific route handler (legacy mode)
 async fn dispatch_route_handler(
     ctx: &RequestContext<'_>,
     handler: &RouteHandler,
     route_prefix: &str,
     index_files: &[String],
-) -> Response<Full<Bytes>> {
+) -> Response<ResponseBody> {
     match handler {
-        RouteHandler::Dir { path: dir } => {
-            static_files::serve_directory(ctx, dir, route_prefix, index_files).await
+        RouteHandler::Dir { path: dir, autoindex } => {
+            let options = DirOptions {
+                autoindex: autoindex.as_ref(),
+                try_files: None,
+            };
+            let dir_path = routing::replace_prefix(ctx.path, route_prefix, "/");
+            match static_files::serve_directory(ctx, dir, &dir_path, index_files, options).await {
+                DirOutcome::Response(response) => response,
+                // Legacy routes have no names and never set try_files
+                DirOutcome::NamedRoute(_) => http::build_404_response(),
+            }
         }
         RouteHandler::File { path: file_path } => static_files::serve_file(ctx, file_path).await,
         RouteHandler::Redirect { target } => http::build_redirect_response(target),
@@ -343,16 +659,109 @@ async fn dispatch_route_handler(
 }
 
 /// Serve default homepage
-async fn serve_default_homepage(
-    ctx: &RequestContext<'_>,
-    state: &Arc<AppState>,
-) -> Response<Full<Bytes>> {
-    let _http_config = {
-        let config = state.dynamic_config.read().await;
-        Arc::clone(&config.http)
-    };
-
+fn serve_default_homepage(ctx: &RequestContext<'_>) -> Response<ResponseBody> {
     let html = static_files::get_default_homepage();
 
     http::response::build_html_response(html, ctx.is_head)
 }
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// Owned state behind a `RequestContext` for handler tests
+    pub struct TestContext {
+        method: Method,
+        headers: HeaderMap,
+        file_cache: OpenFileCache,
+        rate_limiter: RateLimiter,
+        rate_limit_service: RateLimitServiceClient,
+        vhost_bandwidth: SharedRates,
+        http: HttpConfig,
+    }
+
+    impl Default for TestContext {
+        fn default() -> Self {
+            Self {
+                method: Method::GET,
+                headers: HeaderMap::new(),
+                file_cache: OpenFileCache::new(serde_json::from_value(json!({})).unwrap()),
+                rate_limiter: RateLimiter::new(),
+                rate_limit_service: RateLimitServiceClient::new(),
+                vhost_bandwidth: SharedRates::new(),
+                http: serde_json::from_value(json!({
+                    "default_content_type": "text/html; charset=utf-8",
+                    "server_name": "test",
+                    "enable_cors": false,
+                    "max_body_size": 1024
+                }))
+                .unwrap(),
+            }
+        }
+    }
+
+    impl TestContext {
+        /// GET request for `path` without conditional or range headers
+        pub fn request<'a>(&'a self, path: &'a str) -> RequestContext<'a> {
+            RequestContext {
+                path,
+                host: "localhost",
+                query: None,
+                method: &self.method,
+                headers: &self.headers,
+                is_head: false,
+                accept: None,
+                if_none_match: None,
+                if_modified_since: None,
+                range_header: None,
+                file_cache: &self.file_cache,
+                rate_limiter: &self.rate_limiter,
+                rate_limit_service: &self.rate_limit_service,
+                vhost_bandwidth: &self.vhost_bandwidth,
+                remote_addr: SocketAddr::from(([127, 0, 0, 1], 40000)),
+                start_time: SystemTime::now(),
+                http: &self.http,
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_named_route_depth_guard() {
+        let dir = std::env::temp_dir().join(format!("named-route-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        // A route falling back to itself stops after MAX_NAMED_ROUTE_DEPTH hops
+        let vhost: VirtualHost = serde_json::from_value(json!({
+            "name": "loop",
+            "domains": ["*"],
+            "routes": [{
+                "name": "loop",
+                "match": {"prefix": "/"},
+                "type": "dir",
+                "path": dir.to_str().unwrap(),
+                "try_files": ["$uri", "@loop"]
+            }, {
+                "name": "fallback",
+                "match": {"prefix": "/fallback"},
+                "type": "direct",
+                "status": 200,
+                "body": "fallback"
+            }]
+        }))
+        .unwrap();
+        let test = TestContext::default();
+        let ctx = test.request("/missing.html");
+        let captures = PathCaptures::default();
+
+        let response = dispatch_named_route(&ctx, "loop", &captures, &[], &vhost, 0).await;
+        assert_eq!(response.status(), 500);
+        let response =
+            dispatch_named_route(&ctx, "loop", &captures, &[], &vhost, MAX_NAMED_ROUTE_DEPTH).await;
+        assert_eq!(response.status(), 500);
+        let response = dispatch_named_route(&ctx, "fallback", &captures, &[], &vhost, 0).await;
+        assert_eq!(response.status(), 200);
+        let response = dispatch_named_route(&ctx, "missing", &captures, &[], &vhost, 0).await;
+        assert_eq!(response.status(), 404);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -3,15 +3,34 @@
 //! Handles static file loading, MIME type detection, and response building.
 //! Implements the "mtime-first" optimization for conditional requests.
 
+use crate::config::AutoindexConfig;
+use crate::handler::autoindex;
+use crate::handler::open_file_cache::{FileInfo, OpenFileCache};
 use crate::handler::router::RequestContext;
-use crate::http::{self, cache, mime, range::RangeParseResult};
+use crate::http::{self, cache, mime, range::RangeParseResult, ResponseBody};
 use crate::logger;
-use http_body_util::Full;
-use hyper::body::Bytes;
 use hyper::Response;
+use std::io::SeekFrom;
 use std::path::Path;
-use std::time::SystemTime;
 use tokio::fs;
+use tokio::io::AsyncSeekExt;
+
+/// Per-route options for serving a `Dir` route
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DirOptions<'a> {
+    /// Directory listing for directories without an index file
+    pub autoindex: Option<&'a AutoindexConfig>,
+    /// nginx-style `try_files` candidates
+    pub try_files: Option<&'a [String]>,
+}
+
+/// Result of serving a `Dir` route
+pub enum DirOutcome {
+    /// Response is ready
+    Response(Response<ResponseBody>),
+    /// `try_files` fell through to a named route (`@name`)
+    NamedRoute(String),
+}
 
 /// Serve static files from a directory
 ///
@@ -19,34 +38,125 @@ use tokio::fs;
 /// 1. Check file metadata (mtime) first - cheap I/O
 /// 2. If If-Modified-Since matches, return 304 without reading file content
 /// 3. Only read file content when necessary
+///
+/// `dir_path` is the request path after the route's rewrite, relative to the
+/// directory root. Directories without an index file are listed when
+/// `autoindex` is enabled. When `try_files` is set, candidates are evaluated
+/// in order and the chosen file goes through the same conditional and range handling.
 pub async fn serve_directory(
     ctx: &RequestContext<'_>,
     dir: &str,
-    route_prefix: &str,
+    dir_path: &str,
     index_files: &[String],
-) -> Response<Full<Bytes>> {
-    match load_from_directory_optimized(
-        dir,
-        ctx.path,
-        route_prefix,
-        index_files,
+    options: DirOptions<'_>,
+) -> DirOutcome {
+    if let Some(entries) = options.try_files.filter(|entries| !entries.is_empty()) {
+        return evaluate_try_files(ctx, dir, dir_path, index_files, options, entries).await;
+    }
+
+    let response = serve_candidate(ctx, dir, dir_path, index_files, options.autoindex)
+        .await
+        .unwrap_or_else(http::build_404_response);
+    DirOutcome::Response(response)
+}
+
+/// Evaluate `try_files` entries in order
+///
+/// `$uri` is replaced with the rewritten path inside the directory.
+/// Entries ending with `/` match directories (index file or listing),
+/// other entries must resolve to a regular file.
+async fn evaluate_try_files(
+    ctx: &RequestContext<'_>,
+    dir: &str,
+    uri: &str,
+    index_files: &[String],
+    options: DirOptions<'_>,
+    entries: &[String],
+) -> DirOutcome {
+    let Some((fallback, candidates)) = entries.split_last() else {
+        return DirOutcome::Response(http::build_404_response());
+    };
+
+    for entry in candidates {
+        let candidate = entry.replace("$uri", uri);
+        if let Some(response) = serve_try_files_entry(ctx, dir, &candidate, index_files, options).await
+        {
+            return DirOutcome::Response(response);
+        }
+    }
+
+    if let Some(code) = fallback.strip_prefix('=') {
+        let status = code.parse::<u16>().unwrap_or(404);
+        let response = if status == 404 {
+            http::build_404_response()
+        } else {
+            http::build_direct_response(status, None, None)
+        };
+        return DirOutcome::Response(response);
+    }
+
+    if let Some(name) = fallback.strip_prefix('@') {
+        return DirOutcome::NamedRoute(name.to_string());
+    }
+
+    let candidate = fallback.replace("$uri", uri);
+    let response = serve_try_files_entry(ctx, dir, &candidate, index_files, options)
+        .await
+        .unwrap_or_else(http::build_404_response);
+    DirOutcome::Response(response)
+}
+
+/// Serve a single `try_files` candidate
+///
+/// Only entries ending with `/` may match a directory (like nginx).
+async fn serve_try_files_entry(
+    ctx: &RequestContext<'_>,
+    dir: &str,
+    candidate: &str,
+    index_files: &[String],
+    options: DirOptions<'_>,
+) -> Option<Response<ResponseBody>> {
+    if candidate.ends_with('/') {
+        serve_candidate(ctx, dir, candidate, index_files, options.autoindex).await
+    } else {
+        serve_candidate(ctx, dir, candidate, &[], None).await
+    }
+}
+
+/// Serve a path relative to the directory root, or `None` if it does not resolve
+///
+/// Directories are resolved through index files or an autoindex listing.
+async fn serve_candidate(
+    ctx: &RequestContext<'_>,
+    dir: &str,
+    relative_path: &str,
+    index_files: &[String],
+    autoindex: Option<&AutoindexConfig>,
+) -> Option<Response<ResponseBody>> {
+    let file_info = resolve_file_path(ctx.file_cache, dir, relative_path, index_files).await?;
+
+    if file_info.is_dir {
+        // A directory without index file is only served as a listing
+        let config = autoindex.filter(|a| a.enabled)?;
+        return Some(autoindex::serve_listing(ctx, &file_info.canonical, config).await);
+    }
+
+    load_from_directory_optimized(
+        file_info,
         ctx.if_modified_since.as_deref(),
         ctx.if_none_match.as_deref(),
         ctx.is_head,
         ctx.range_header.as_deref(),
     )
     .await
-    {
-        Some(response) => response,
-        None => http::build_404_response(),
-    }
 }
 
 /// Serve a single file
 ///
 /// Implements the "mtime-first" optimization for conditional requests.
-pub async fn serve_file(ctx: &RequestContext<'_>, file_path: &str) -> Response<Full<Bytes>> {
+pub async fn serve_file(ctx: &RequestContext<'_>, file_path: &str) -> Response<ResponseBody> {
     match load_single_file_optimized(
+        ctx.file_cache,
         file_path,
         ctx.if_modified_since.as_deref(),
         ctx.if_none_match.as_deref(),
@@ -64,145 +174,163 @@ pub async fn serve_file(ctx: &RequestContext<'_>, file_path: &str) -> Response<F
 ///
 /// This function checks file modification time before reading content,
 /// allowing early 304 responses without file I/O.
-#[allow(clippy::too_many_arguments)]
 async fn load_from_directory_optimized(
-    static_dir: &str,
-    path: &str,
-    route_prefix: &str,
-    index_files: &[String],
+    file_info: FileInfo,
     if_modified_since: Option<&str>,
     if_none_match: Option<&str>,
     is_head: bool,
     range_header: Option<&str>,
-) -> Option<Response<Full<Bytes>>> {
-    // Resolve file path (reuse existing logic)
-    let file_path = resolve_file_path(static_dir, path, route_prefix, index_files)?;
-    let content_type = mime::get_content_type(file_path.extension().and_then(|e| e.to_str()));
-
-    // Step 1: Get file metadata (cheap I/O - only reads inode)
-    let metadata = fs::metadata(&file_path).await.ok()?;
-    let mtime = metadata.modified().ok()?;
-    let last_modified = cache::format_http_date(mtime);
-
-    // Step 2: Fast path - check If-Modified-Since first
-    if cache::check_not_modified_since(if_modified_since, mtime) {
-        // File hasn't changed, return 304 without reading content
-        // Generate ETag from mtime for consistency
-        let etag = format!("\"{}\"", mtime_to_etag(mtime));
-        return Some(http::response::build_304_response_with_mtime(&etag, &last_modified));
-    }
-
-    // Step 3: Slow path - read file content
-    let content = fs::read(&file_path).await.ok()?;
-
-    // Generate content-based ETag for accuracy
-    let etag = cache::generate_etag(&content);
-
-    // Check ETag match (client might have used If-None-Match)
-    if cache::check_etag_match(if_none_match, &etag) {
-        return Some(http::response::build_304_response_with_mtime(&etag, &last_modified));
+) -> Option<Response<ResponseBody>> {
+    if !file_info.is_file {
+        return None;
     }
-
-    // Build full response with Last-Modified header
-    Some(build_static_file_response_with_mtime(
-        &content,
-        content_type,
-        &etag,
-        &last_modified,
+    respond_with_file(
+        &file_info,
+        if_modified_since,
+        if_none_match,
         is_head,
         range_header,
-    ))
+    )
+    .await
 }
 
 /// Optimized single file loading with mtime-first check
 async fn load_single_file_optimized(
+    file_cache: &OpenFileCache,
     file_path: &str,
     if_modified_since: Option<&str>,
     if_none_match: Option<&str>,
     is_head: bool,
     range_header: Option<&str>,
-) -> Option<Response<Full<Bytes>>> {
-    let path = Path::new(file_path);
+) -> Option<Response<ResponseBody>> {
+    let file_info = file_cache
+        .lookup(Path::new(file_path))
+        .await
+        .filter(|info| info.is_file)?;
+    respond_with_file(
+        &file_info,
+        if_modified_since,
+        if_none_match,
+        is_head,
+        range_header,
+    )
+    .await
+}
+
+/// Build the response for a regular file
+///
+/// 1. Metadata (served from the open file cache when enabled) gives the
+///    `ETag` and `Last-Modified`, so 304 responses never touch the content
+/// 2. Otherwise the file is opened and streamed in chunks (whole, or the
+///    requested range)
+async fn respond_with_file(
+    file_info: &FileInfo,
+    if_modified_since: Option<&str>,
+    if_none_match: Option<&str>,
+    is_head: bool,
+    range_header: Option<&str>,
+) -> Option<Response<ResponseBody>> {
+    let path = &file_info.canonical;
     let content_type = mime::get_content_type(path.extension().and_then(|e| e.to_str()));
 
-    // Step 1: Get file metadata
-    let metadata = fs::metadata(path).await.ok()?;
-    let mtime = metadata.modified().ok()?;
+    // Step 1: Conditional requests from metadata only
+    let mtime = file_info.modified?;
     let last_modified = cache::format_http_date(mtime);
-
-    // Step 2: Fast path - check If-Modified-Since
-    if cache::check_not_modified_since(if_modified_since, mtime) {
-        let etag = format!("\"{}\"", mtime_to_etag(mtime));
+    let etag = cache::generate_etag(mtime, file_info.len);
+    if cache::check_not_modified_since(if_modified_since, mtime)
+        || cache::check_etag_match(if_none_match, &etag)
+    {
         return Some(http::response::build_304_response_with_mtime(&etag, &last_modified));
     }
 
-    // Step 3: Slow path - read content
-    let content = fs::read(path).await.ok()?;
-
-    let etag = cache::generate_etag(&content);
+    // Step 2: Open the file; its own metadata is authoritative for the body
+    let file = fs::File::open(path).await.ok()?;
+    let len = file.metadata().await.ok()?.len();
+    let total_size = usize::try_from(len).ok()?;
 
-    if cache::check_etag_match(if_none_match, &etag) {
-        return Some(http::response::build_304_response_with_mtime(&etag, &last_modified));
+    match http::parse_range_header(range_header, total_size) {
+        RangeParseResult::Valid(range) => {
+            let start = range.start;
+            let end = range.end_position(total_size);
+            let body = if is_head {
+                ResponseBody::empty()
+            } else {
+                let mut file = file;
+                file.seek(SeekFrom::Start(start as u64)).await.ok()?;
+                ResponseBody::file(file, (end - start + 1) as u64)
+            };
+            Some(http::response::build_partial_response(
+                body,
+                content_type,
+                &etag,
+                Some(&last_modified),
+                start,
+                end,
+                total_size,
+            ))
+        }
+        RangeParseResult::NotSatisfiable => Some(http::build_416_response(total_size)),
+        // No Range header or malformed, return full content
+        RangeParseResult::None => {
+            let body = if is_head {
+                ResponseBody::empty()
+            } else {
+                ResponseBody::file(file, len)
+            };
+            Some(http::response::build_cached_response(
+                body,
+                len,
+                content_type,
+                &etag,
+                Some(&last_modified),
+            ))
+        }
     }
-
-    Some(build_static_file_response_with_mtime(
-        &content,
-        content_type,
-        &etag,
-        &last_modified,
-        is_head,
-        range_header,
-    ))
 }
 
-/// Resolve file path from request, handling index files
-fn resolve_file_path(
+/// Resolve a path relative to the directory root, handling index files
+///
+/// All filesystem lookups go through the open file cache. The resolved path
+/// must stay inside the directory root (path traversal guard).
+async fn resolve_file_path(
+    file_cache: &OpenFileCache,
     static_dir: &str,
-    path: &str,
-    route_prefix: &str,
+    relative_path: &str,
     index_files: &[String],
-) -> Option<std::path::PathBuf> {
-    let clean_path = path.trim_start_matches('/').replace("..", "");
-    let prefix_clean = route_prefix.trim_matches('/');
-    let relative_path = if prefix_clean.is_empty() {
-        clean_path.as_str()
-    } else {
-        clean_path
-            .strip_prefix(&format!("{prefix_clean}/"))
-            .unwrap_or(&clean_path)
-    };
+) -> Option<FileInfo> {
+    let relative_path = relative_path.trim_start_matches('/');
+    let file_path = Path::new(static_dir).join(relative_path);
 
-    let mut file_path = Path::new(static_dir).join(relative_path);
+    let static_dir_canonical = file_cache.lookup(Path::new(static_dir)).await?.canonical;
 
-    let static_dir_canonical = Path::new(static_dir).canonicalize().ok()?;
+    let mut file_info = file_cache.lookup(&file_path).await;
 
-    if file_path.is_dir() || relative_path.is_empty() || relative_path.ends_with('/') {
+    if file_info.as_ref().is_some_and(|info| info.is_dir)
+        || relative_path.is_empty()
+        || relative_path.ends_with('/')
+    {
         for index_file in index_files {
             let index_path = file_path.join(index_file);
-            if index_path.exists() && index_path.is_file() {
-                file_path = index_path;
+            if let Some(index_info) =
+                file_cache.lookup(&index_path).await.filter(|info| info.is_file)
+            {
+                file_info = Some(index_info);
                 break;
             }
         }
     }
 
-    let file_path_canonical = file_path.canonicalize().ok()?;
-    if !file_path_canonical.starts_with(&static_dir_canonical) {
+    let file_info = file_info?;
+    if !file_info.canonical.starts_with(&static_dir_canonical) {
         logger::log_warning(&format!(
             "Path traversal attempt blocked: {} -> {}",
-            path,
-            file_path_canonical.display()
+            relative_path,
+            file_info.canonical.display()
         ));
         return None;
     }
 
-    Some(file_path_canonical)
-}
-
-/// Convert mtime to a simple `ETag` (for mtime-only 304 responses)
-fn mtime_to_etag(mtime: SystemTime) -> String {
-    let duration = mtime.duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default();
-    format!("{:x}", duration.as_secs())
+    Some(file_info)
 }
 
 /// Get default homepage HTML
@@ -318,54 +446,90 @@ pub fn get_default_homepage() -> String {
     )
 }
 
-/// Build static file response with `Last-Modified` support (optimized path)
-fn build_static_file_response_with_mtime(
-    data: &[u8],
-    content_type: &str,
-    etag: &str,
-    last_modified: &str,
-    is_head: bool,
-    range_header: Option<&str>,
-) -> Response<Full<Bytes>> {
-    let total_size = data.len();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handler::router::tests::TestContext;
+    use http_body_util::BodyExt;
+    use std::path::PathBuf;
 
-    // Check for Range request
-    match http::parse_range_header(range_header, total_size) {
-        RangeParseResult::Valid(range) => {
-            let start = range.start;
-            let end = range.end_position(total_size);
+    /// Document root with `a.html`, `fallback.html` and `sub/index.html`
+    fn document_root(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("try-files-{name}-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("sub")).unwrap();
+        std::fs::write(dir.join("a.html"), "a").unwrap();
+        std::fs::write(dir.join("fallback.html"), "fallback").unwrap();
+        std::fs::write(dir.join("sub/index.html"), "sub index").unwrap();
+        dir
+    }
 
-            let body = if is_head {
-                Bytes::new()
-            } else {
-                Bytes::from(data[start..=end].to_vec())
-            };
+    async fn try_files(dir: &Path, uri: &str, entries: &[&str]) -> DirOutcome {
+        let test = TestContext::default();
+        let ctx = test.request(uri);
+        let entries: Vec<String> = entries.iter().map(ToString::to_string).collect();
+        let options = DirOptions {
+            autoindex: None,
+            try_files: Some(&entries),
+        };
+        let index_files = ["index.html".to_string()];
+        serve_directory(&ctx, dir.to_str().unwrap(), uri, &index_files, options).await
+    }
 
-            return http::response::build_partial_response(
-                body,
-                content_type,
-                etag,
-                Some(last_modified),
-                start,
-                end,
-                total_size,
-                is_head,
-            );
-        }
-        RangeParseResult::NotSatisfiable => {
-            return http::build_416_response(total_size);
-        }
-        RangeParseResult::None => {
-            // No Range header or malformed, return full content
-        }
+    /// Status and body of a response outcome
+    async fn response(outcome: DirOutcome) -> (u16, String) {
+        let DirOutcome::Response(response) = outcome else {
+            panic!("expected a response");
+        };
+        let status = response.status().as_u16();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        (status, String::from_utf8(body.to_vec()).unwrap())
     }
 
-    // Full response
-    let body = if is_head {
-        Bytes::new()
-    } else {
-        Bytes::from(data.to_owned())
-    };
+    #[tokio::test]
+    async fn test_try_files_candidate_order() {
+        let dir = document_root("order");
+        let outcome = try_files(&dir, "/a.html", &["$uri", "/fallback.html", "=404"]).await;
+        assert_eq!(response(outcome).await, (200, "a".to_string()));
+        let outcome = try_files(&dir, "/a.html", &["/fallback.html", "$uri", "=404"]).await;
+        assert_eq!(response(outcome).await, (200, "fallback".to_string()));
+        // The last entry may be a file as well
+        let outcome = try_files(&dir, "/missing", &["$uri", "/fallback.html"]).await;
+        assert_eq!(response(outcome).await, (200, "fallback".to_string()));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_try_files_directory_candidates() {
+        let dir = document_root("directory");
+        // Only entries ending with '/' match a directory
+        let outcome = try_files(&dir, "/sub", &["$uri", "/fallback.html"]).await;
+        assert_eq!(response(outcome).await, (200, "fallback".to_string()));
+        let outcome = try_files(&dir, "/sub", &["$uri", "$uri/", "/fallback.html"]).await;
+        assert_eq!(response(outcome).await, (200, "sub index".to_string()));
+        // A file does not match a directory candidate
+        let outcome = try_files(&dir, "/a.html", &["$uri/", "=404"]).await;
+        assert_eq!(response(outcome).await.0, 404);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_try_files_status_fallback() {
+        let dir = document_root("status");
+        let outcome = try_files(&dir, "/missing", &["$uri", "$uri/", "=404"]).await;
+        assert_eq!(response(outcome).await.0, 404);
+        let outcome = try_files(&dir, "/missing", &["$uri", "=403"]).await;
+        assert_eq!(response(outcome).await.0, 403);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
 
-    http::response::build_cached_response(body, content_type, etag, Some(last_modified), is_head)
+    #[tokio::test]
+    async fn test_try_files_named_route_fallback() {
+        let dir = document_root("named");
+        let outcome = try_files(&dir, "/missing", &["$uri", "@backend"]).await;
+        assert!(matches!(outcome, DirOutcome::NamedRoute(name) if name == "backend"));
+        // Not reached when a candidate matches
+        let outcome = try_files(&dir, "/a.html", &["$uri", "@backend"]).await;
+        assert_eq!(response(outcome).await, (200, "a".to_string()));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
 }
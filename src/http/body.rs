@@ -0,0 +1,223 @@
+//! Response body module
+//!
+//! Bodies of the main server: in-memory data, or a file region streamed from
+//! disk in chunks. Either can be paced by a bandwidth `Throttle`.
+
+use crate::http::throttle::Throttle;
+use hyper::body::{Body, Bytes, Frame, SizeHint};
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+use tokio::fs::File;
+use tokio::io::{AsyncRead, ReadBuf};
+use tokio::time::{Instant, Sleep};
+
+/// Chunk size for file reads (and for in-memory data while throttled)
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Response body of the main server
+pub struct ResponseBody {
+    source: Source,
+    pacing: Option<Box<Pacing>>,
+}
+
+/// Bandwidth pacing of a throttled body
+struct Pacing {
+    throttle: Throttle,
+    /// Chunk waiting for `delay` before it is sent
+    held: Option<Bytes>,
+    delay: Option<Pin<Box<Sleep>>>,
+}
+
+enum Source {
+    Memory(Bytes),
+    File {
+        file: Box<File>,
+        remaining: u64,
+        buf: Vec<u8>,
+    },
+}
+
+impl ResponseBody {
+    /// In-memory body
+    pub const fn full(data: Bytes) -> Self {
+        Self::from_source(Source::Memory(data))
+    }
+
+    /// Empty body (HEAD responses, 304)
+    pub const fn empty() -> Self {
+        Self::full(Bytes::new())
+    }
+
+    /// `len` bytes of `file`, read from its current position in chunks
+    pub fn file(file: File, len: u64) -> Self {
+        Self::from_source(Source::File {
+            file: Box::new(file),
+            remaining: len,
+            buf: Vec::new(),
+        })
+    }
+
+    const fn from_source(source: Source) -> Self {
+        Self {
+            source,
+            pacing: None,
+        }
+    }
+
+    /// Pace the body with bandwidth limits
+    pub fn set_throttle(&mut self, throttle: Throttle) {
+        self.pacing = Some(Box::new(Pacing {
+            throttle,
+            held: None,
+            delay: None,
+        }));
+    }
+
+    fn remaining(&self) -> u64 {
+        let held = self.held().map_or(0, Bytes::len) as u64;
+        held + match &self.source {
+            Source::Memory(data) => data.len() as u64,
+            Source::File { remaining, .. } => *remaining,
+        }
+    }
+
+    fn held(&self) -> Option<&Bytes> {
+        self.pacing.as_ref().and_then(|pacing| pacing.held.as_ref())
+    }
+}
+
+impl Source {
+    /// Next chunk of at most `max` bytes (`None` at the end)
+    fn poll_chunk(&mut self, cx: &mut Context<'_>, max: usize) -> Poll<Option<io::Result<Bytes>>> {
+        match self {
+            Self::Memory(data) if data.is_empty() => Poll::Ready(None),
+            Self::Memory(data) => Poll::Ready(Some(Ok(data.split_to(max.min(data.len()))))),
+            Self::File { remaining: 0, .. } => Poll::Ready(None),
+            Self::File {
+                file,
+                remaining,
+                buf,
+            } => {
+                let want = usize::try_from(*remaining).unwrap_or(usize::MAX).min(max);
+                buf.resize(want, 0);
+                let mut read_buf = ReadBuf::new(buf);
+                if let Err(e) = ready!(Pin::new(&mut **file).poll_read(cx, &mut read_buf)) {
+                    return Poll::Ready(Some(Err(e)));
+                }
+                let filled = read_buf.filled();
+                if filled.is_empty() {
+                    // The file shrank after Content-Length was sent
+                    *remaining = 0;
+                    return Poll::Ready(Some(Err(io::ErrorKind::UnexpectedEof.into())));
+                }
+                *remaining -= filled.len() as u64;
+                Poll::Ready(Some(Ok(Bytes::copy_from_slice(filled))))
+            }
+        }
+    }
+
+    const fn is_end(&self) -> bool {
+        match self {
+            Self::Memory(data) => data.is_empty(),
+            Self::File { remaining, .. } => *remaining == 0,
+        }
+    }
+}
+
+impl Body for ResponseBody {
+    type Data = Bytes;
+    type Error = io::Error;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Bytes>, io::Error>>> {
+        let this = &mut *self;
+        let Some(pacing) = this.pacing.as_deref_mut() else {
+            let chunk = ready!(this.source.poll_chunk(cx, CHUNK_SIZE));
+            return Poll::Ready(chunk.map(|result| result.map(Frame::data)));
+        };
+
+        if let Some(delay) = &mut pacing.delay {
+            ready!(delay.as_mut().poll(cx));
+            pacing.delay = None;
+        }
+        if let Some(chunk) = pacing.held.take() {
+            return Poll::Ready(Some(Ok(Frame::data(chunk))));
+        }
+
+        let max = pacing.throttle.chunk_size(CHUNK_SIZE);
+        let chunk = match ready!(this.source.poll_chunk(cx, max)) {
+            Some(Ok(chunk)) => chunk,
+            other => return Poll::Ready(other.map(|result| result.map(Frame::data))),
+        };
+
+        let Some(send_at) = pacing.throttle.schedule(chunk.len(), Instant::now()) else {
+            return Poll::Ready(Some(Ok(Frame::data(chunk))));
+        };
+        let mut delay = Box::pin(tokio::time::sleep_until(send_at));
+        if delay.as_mut().poll(cx).is_ready() {
+            return Poll::Ready(Some(Ok(Frame::data(chunk))));
+        }
+        pacing.held = Some(chunk);
+        pacing.delay = Some(delay);
+        Poll::Pending
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.held().is_none() && self.source.is_end()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        SizeHint::with_exact(self.remaining())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http_body_util::BodyExt;
+    use std::time::Duration;
+    use tokio::io::AsyncSeekExt;
+
+    #[tokio::test]
+    async fn test_file_body_streams_region() {
+        let path = std::env::temp_dir().join("yarhs_body_test.bin");
+        let content: Vec<u8> = (0..200_000u32).map(|i| (i % 251) as u8).collect();
+        std::fs::write(&path, &content).unwrap();
+
+        let mut file = File::open(&path).await.unwrap();
+        file.seek(io::SeekFrom::Start(1000)).await.unwrap();
+        let mut body = ResponseBody::file(file, 150_000);
+        assert_eq!(body.size_hint().exact(), Some(150_000));
+
+        let mut frames = 0;
+        let mut received = Vec::new();
+        while let Some(frame) = body.frame().await {
+            received.extend_from_slice(&frame.unwrap().into_data().unwrap());
+            frames += 1;
+        }
+        assert_eq!(received, content[1000..151_000]);
+        assert!(frames >= 3, "streamed in chunks, got {frames} frame(s)");
+        assert!(body.is_end_stream());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_throttled_body() {
+        let mut body = ResponseBody::full(Bytes::from(vec![7u8; 6000]));
+        body.set_throttle(Throttle::new(Some(8192), 2000, None).unwrap());
+
+        let start = std::time::Instant::now();
+        let mut sizes = Vec::new();
+        while let Some(frame) = body.frame().await {
+            sizes.push(frame.unwrap().into_data().unwrap().len());
+        }
+        // 2000 free bytes, then 4000 bytes in 1024-byte chunks at 8 KiB/s:
+        // the last chunk waits for 3072 bytes = 375ms
+        assert_eq!(sizes, [2000, 1024, 1024, 1024, 928]);
+        assert!(start.elapsed() >= Duration::from_millis(370));
+    }
+}
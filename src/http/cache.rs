@@ -2,22 +2,21 @@
 //!
 //! Provides `ETag` generation, `Last-Modified` handling, and conditional request support.
 
-use std::collections::hash_map::DefaultHasher;
-use std::hash::{Hash, Hasher};
 use std::time::SystemTime;
 
-/// Generate `ETag` using fast hashing
+/// Generate `ETag` from file metadata (nginx style)
 ///
-/// # Arguments
-/// * `content` - File content
+/// Files are streamed from disk, so the tag is derived from the modification
+/// time and size instead of the content.
 ///
 /// # Returns
-/// Quoted `ETag` string, e.g., `"abc123def"`
-pub fn generate_etag(content: &[u8]) -> String {
-    let mut hasher = DefaultHasher::new();
-    content.hash(&mut hasher);
-    let v = hasher.finish();
-    format!("\"{v:x}\"")
+/// Quoted `ETag` string, e.g., `"65a1b2c3-1f40"`
+pub fn generate_etag(mtime: SystemTime, len: u64) -> String {
+    let secs = mtime
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    format!("\"{secs:x}-{len:x}\"")
 }
 
 /// Format `SystemTime` as HTTP date (RFC 7231)
@@ -85,6 +84,7 @@ fn days_to_ymd_weekday(days: u64) -> (u64, usize, u64, usize) {
     (year, month, day, weekday)
 }
 
+#[allow(clippy::manual_is_multiple_of)]
 const fn is_leap_year(year: u64) -> bool {
     (year % 4 == 0 && year % 100 != 0) || (year % 400 == 0)
 }
@@ -165,6 +165,7 @@ pub fn parse_http_date(date_str: &str) -> Option<SystemTime> {
 /// # Note
 /// HTTP dates only have second precision, but file system mtime may have
 /// nanosecond precision. We truncate mtime to seconds for proper comparison.
+#[allow(clippy::map_unwrap_or)]
 pub fn check_not_modified_since(if_modified_since: Option<&str>, file_mtime: SystemTime) -> bool {
     if_modified_since.is_some_and(|client_date| {
         parse_http_date(client_date).is_some_and(|client_time| {
@@ -242,24 +243,22 @@ mod tests {
 
     #[test]
     fn test_generate_etag() {
-        let etag = generate_etag(b"hello world");
-        assert!(etag.starts_with('"'));
-        assert!(etag.ends_with('"'));
-        assert!(etag.len() > 2);
+        let mtime = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(0x65a1_b2c3);
+        assert_eq!(generate_etag(mtime, 8000), "\"65a1b2c3-1f40\"");
     }
 
     #[test]
     fn test_etag_consistency() {
-        let etag1 = generate_etag(b"same content");
-        let etag2 = generate_etag(b"same content");
-        assert_eq!(etag1, etag2);
+        let mtime = SystemTime::now();
+        assert_eq!(generate_etag(mtime, 42), generate_etag(mtime, 42));
     }
 
     #[test]
     fn test_etag_difference() {
-        let etag1 = generate_etag(b"content a");
-        let etag2 = generate_etag(b"content b");
-        assert_ne!(etag1, etag2);
+        let mtime = SystemTime::now();
+        assert_ne!(generate_etag(mtime, 1), generate_etag(mtime, 2));
+        let later = mtime + std::time::Duration::from_secs(1);
+        assert_ne!(generate_etag(mtime, 1), generate_etag(later, 1));
     }
 
     #[test]
@@ -336,6 +335,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::duration_suboptimal_units)]
     fn test_check_not_modified_since_nanosecond_precision() {
         // Test nanosecond precision handling:
         // File systems store mtime with nanoseconds, but HTTP dates are second-precision
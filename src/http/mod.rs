@@ -3,15 +3,20 @@
 //! Provides HTTP protocol-related base functionality, decoupled from specific business logic.
 //! Can be shared between static file serving and reverse proxy in the future.
 
+pub mod body;
 pub mod cache;
 pub mod mime;
 pub mod range;
 pub mod response;
+pub mod throttle;
+pub mod uri;
 
 // Re-export commonly used types
+pub use body::ResponseBody;
 pub use range::parse_range_header;
 pub use response::{
-    build_404_response, build_405_response, build_413_response,
-    build_416_response, build_direct_response, build_health_response, build_options_response,
-    build_redirect_response, build_redirect_response_with_code,
+    build_400_response, build_404_response, build_405_response, build_413_response,
+    build_416_response, build_429_response, build_503_response, build_direct_response,
+    build_health_response, build_options_response, build_redirect_response,
+    build_redirect_response_with_code,
 };
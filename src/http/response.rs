@@ -2,122 +2,151 @@
 //!
 //! Provides builders for various HTTP status code responses, decoupled from specific business logic.
 
-use http_body_util::Full;
+use crate::http::body::ResponseBody;
 use hyper::body::Bytes;
 use hyper::Response;
 
 /// Build 304 Not Modified response with Last-Modified
-pub fn build_304_response_with_mtime(etag: &str, last_modified: &str) -> Response<Full<Bytes>> {
+pub fn build_304_response_with_mtime(etag: &str, last_modified: &str) -> Response<ResponseBody> {
     Response::builder()
         .status(304)
         .header("ETag", etag)
         .header("Last-Modified", last_modified)
         .header("Cache-Control", "public, max-age=3600")
-        .body(Full::new(Bytes::new()))
+        .body(ResponseBody::full(Bytes::new()))
         .unwrap_or_else(|e| {
             log_build_error("304", &e);
-            Response::new(Full::new(Bytes::new()))
+            Response::new(ResponseBody::full(Bytes::new()))
         })
 }
 
 /// Build 404 Not Found response
-pub fn build_404_response() -> Response<Full<Bytes>> {
+pub fn build_404_response() -> Response<ResponseBody> {
     Response::builder()
         .status(404)
         .header("Content-Type", "text/plain")
-        .body(Full::new(Bytes::from("404 Not Found")))
+        .body(ResponseBody::full(Bytes::from("404 Not Found")))
         .unwrap_or_else(|e| {
             log_build_error("404", &e);
-            Response::new(Full::new(Bytes::from("404 Not Found")))
+            Response::new(ResponseBody::full(Bytes::from("404 Not Found")))
         })
 }
 
 /// Build 405 Method Not Allowed response
-pub fn build_405_response() -> Response<Full<Bytes>> {
+pub fn build_405_response() -> Response<ResponseBody> {
     Response::builder()
         .status(405)
         .header("Content-Type", "text/plain")
         .header("Allow", "GET, HEAD, OPTIONS")
-        .body(Full::new(Bytes::from("405 Method Not Allowed")))
+        .body(ResponseBody::full(Bytes::from("405 Method Not Allowed")))
         .unwrap_or_else(|e| {
             log_build_error("405", &e);
-            Response::new(Full::new(Bytes::from("405 Method Not Allowed")))
+            Response::new(ResponseBody::full(Bytes::from("405 Method Not Allowed")))
         })
 }
 
-/// Build OPTIONS response (preflight request)
-pub fn build_options_response(enable_cors: bool) -> Response<Full<Bytes>> {
-    let mut builder = Response::builder()
+/// Build OPTIONS response (CORS headers are added by the router)
+pub fn build_options_response() -> Response<ResponseBody> {
+    Response::builder()
         .status(204)
-        .header("Allow", "GET, HEAD, OPTIONS");
-
-    if enable_cors {
-        builder = builder
-            .header("Access-Control-Allow-Origin", "*")
-            .header("Access-Control-Allow-Methods", "GET, HEAD, OPTIONS")
-            .header("Access-Control-Allow-Headers", "Content-Type, Range")
-            .header("Access-Control-Max-Age", "86400");
-    }
-
-    builder.body(Full::new(Bytes::new())).unwrap_or_else(|e| {
-        log_build_error("OPTIONS", &e);
-        Response::new(Full::new(Bytes::new()))
-    })
+        .header("Allow", "GET, HEAD, OPTIONS")
+        .body(ResponseBody::full(Bytes::new()))
+        .unwrap_or_else(|e| {
+            log_build_error("OPTIONS", &e);
+            Response::new(ResponseBody::full(Bytes::new()))
+        })
 }
 
 /// Build health check response (200 OK with status text)
-pub fn build_health_response(status: &str) -> Response<Full<Bytes>> {
+pub fn build_health_response(status: &str) -> Response<ResponseBody> {
     Response::builder()
         .status(200)
         .header("Content-Type", "text/plain; charset=utf-8")
         .header("Cache-Control", "no-cache, no-store, must-revalidate")
-        .body(Full::new(Bytes::from(status.to_string())))
+        .body(ResponseBody::full(Bytes::from(status.to_string())))
         .unwrap_or_else(|e| {
             log_build_error("health", &e);
-            Response::new(Full::new(Bytes::from("error")))
+            Response::new(ResponseBody::full(Bytes::from("error")))
+        })
+}
+
+/// Build 400 Bad Request response
+pub fn build_400_response() -> Response<ResponseBody> {
+    Response::builder()
+        .status(400)
+        .header("Content-Type", "text/plain")
+        .body(ResponseBody::full(Bytes::from("400 Bad Request")))
+        .unwrap_or_else(|e| {
+            log_build_error("400", &e);
+            Response::new(ResponseBody::full(Bytes::from("400 Bad Request")))
         })
 }
 
 /// Build 413 Payload Too Large response
-pub fn build_413_response() -> Response<Full<Bytes>> {
+pub fn build_413_response() -> Response<ResponseBody> {
     Response::builder()
         .status(413)
         .header("Content-Type", "text/plain")
-        .body(Full::new(Bytes::from("413 Payload Too Large")))
+        .body(ResponseBody::full(Bytes::from("413 Payload Too Large")))
         .unwrap_or_else(|e| {
             log_build_error("413", &e);
-            Response::new(Full::new(Bytes::from("413 Payload Too Large")))
+            Response::new(ResponseBody::full(Bytes::from("413 Payload Too Large")))
         })
 }
 
 /// Build 416 Range Not Satisfiable response
-pub fn build_416_response(file_size: usize) -> Response<Full<Bytes>> {
+pub fn build_416_response(file_size: usize) -> Response<ResponseBody> {
     Response::builder()
         .status(416)
         .header("Content-Type", "text/plain")
         .header("Content-Range", format!("bytes */{file_size}"))
-        .body(Full::new(Bytes::from("Range Not Satisfiable")))
+        .body(ResponseBody::full(Bytes::from("Range Not Satisfiable")))
         .unwrap_or_else(|e| {
             log_build_error("416", &e);
-            Response::new(Full::new(Bytes::from("Range Not Satisfiable")))
+            Response::new(ResponseBody::full(Bytes::from("Range Not Satisfiable")))
+        })
+}
+
+/// Build 503 Service Unavailable response with `Retry-After` (seconds)
+pub fn build_503_response(retry_after: u64) -> Response<ResponseBody> {
+    Response::builder()
+        .status(503)
+        .header("Content-Type", "text/plain")
+        .header("Retry-After", retry_after)
+        .body(ResponseBody::full(Bytes::from("503 Service Unavailable")))
+        .unwrap_or_else(|e| {
+            log_build_error("503", &e);
+            Response::new(ResponseBody::full(Bytes::from("503 Service Unavailable")))
+        })
+}
+
+/// Build 429 Too Many Requests response (rate limit headers are added by the caller)
+pub fn build_429_response() -> Response<ResponseBody> {
+    Response::builder()
+        .status(429)
+        .header("Content-Type", "text/plain")
+        .body(ResponseBody::full(Bytes::from("429 Too Many Requests")))
+        .unwrap_or_else(|e| {
+            log_build_error("429", &e);
+            Response::new(ResponseBody::full(Bytes::from("429 Too Many Requests")))
         })
 }
 
 /// Build 302 redirect response
-pub fn build_redirect_response(target: &str) -> Response<Full<Bytes>> {
+pub fn build_redirect_response(target: &str) -> Response<ResponseBody> {
     build_redirect_response_with_code(target, 302)
 }
 
 /// Build redirect response with custom status code
-pub fn build_redirect_response_with_code(target: &str, code: u16) -> Response<Full<Bytes>> {
+pub fn build_redirect_response_with_code(target: &str, code: u16) -> Response<ResponseBody> {
     Response::builder()
         .status(code)
         .header("Location", target)
         .header("Content-Type", "text/plain")
-        .body(Full::new(Bytes::from("Redirecting...")))
+        .body(ResponseBody::full(Bytes::from("Redirecting...")))
         .unwrap_or_else(|e| {
             log_build_error(&code.to_string(), &e);
-            Response::new(Full::new(Bytes::from("Redirecting...")))
+            Response::new(ResponseBody::full(Bytes::from("Redirecting...")))
         })
 }
 
@@ -126,22 +155,22 @@ pub fn build_direct_response(
     status: u16,
     body: Option<&str>,
     content_type: Option<&str>,
-) -> Response<Full<Bytes>> {
+) -> Response<ResponseBody> {
     let body_bytes = body.map(|b| Bytes::from(b.to_string())).unwrap_or_default();
     let ct = content_type.unwrap_or("text/plain; charset=utf-8");
 
     Response::builder()
         .status(status)
         .header("Content-Type", ct)
-        .body(Full::new(body_bytes))
+        .body(ResponseBody::full(body_bytes))
         .unwrap_or_else(|e| {
             log_build_error(&status.to_string(), &e);
-            Response::new(Full::new(Bytes::new()))
+            Response::new(ResponseBody::full(Bytes::new()))
         })
 }
 
 /// Build generic HTML response
-pub fn build_html_response(content: String, is_head: bool) -> Response<Full<Bytes>> {
+pub fn build_html_response(content: String, is_head: bool) -> Response<ResponseBody> {
     let content_length = content.len();
     let body = if is_head {
         Bytes::new()
@@ -153,24 +182,43 @@ pub fn build_html_response(content: String, is_head: bool) -> Response<Full<Byte
         .status(200)
         .header("Content-Type", "text/html; charset=utf-8")
         .header("Content-Length", content_length)
-        .body(Full::new(body))
+        .body(ResponseBody::full(body))
         .unwrap_or_else(|e| {
             log_build_error("HTML", &e);
-            Response::new(Full::new(Bytes::new()))
+            Response::new(ResponseBody::full(Bytes::new()))
+        })
+}
+
+/// Build generic JSON response
+pub fn build_json_response(content: String, is_head: bool) -> Response<ResponseBody> {
+    let content_length = content.len();
+    let body = if is_head {
+        Bytes::new()
+    } else {
+        Bytes::from(content)
+    };
+
+    Response::builder()
+        .status(200)
+        .header("Content-Type", "application/json")
+        .header("Content-Length", content_length)
+        .body(ResponseBody::full(body))
+        .unwrap_or_else(|e| {
+            log_build_error("JSON", &e);
+            Response::new(ResponseBody::full(Bytes::new()))
         })
 }
 
 /// Build success response with cache control
+///
+/// `body` is empty for HEAD requests; `content_length` is the full length either way.
 pub fn build_cached_response(
-    data: Bytes,
+    body: ResponseBody,
+    content_length: u64,
     content_type: &str,
     etag: &str,
     last_modified: Option<&str>,
-    is_head: bool,
-) -> Response<Full<Bytes>> {
-    let content_length = data.len();
-    let body = if is_head { Bytes::new() } else { data };
-
+) -> Response<ResponseBody> {
     let mut builder = Response::builder()
         .status(200)
         .header("Content-Type", content_type)
@@ -183,26 +231,25 @@ pub fn build_cached_response(
         builder = builder.header("Last-Modified", mtime);
     }
 
-    builder.body(Full::new(body)).unwrap_or_else(|e| {
+    builder.body(body).unwrap_or_else(|e| {
         log_build_error("200", &e);
-        Response::new(Full::new(Bytes::new()))
+        Response::new(ResponseBody::empty())
     })
 }
 
 /// Build 206 Partial Content response
-#[allow(clippy::too_many_arguments)]
+///
+/// `body` holds bytes `start..=end` (empty for HEAD requests).
 pub fn build_partial_response(
-    data: Bytes,
+    body: ResponseBody,
     content_type: &str,
     etag: &str,
     last_modified: Option<&str>,
     start: usize,
     end: usize,
     total_size: usize,
-    is_head: bool,
-) -> Response<Full<Bytes>> {
+) -> Response<ResponseBody> {
     let content_length = end - start + 1;
-    let body = if is_head { Bytes::new() } else { data };
 
     let mut builder = Response::builder()
         .status(206)
@@ -217,9 +264,9 @@ pub fn build_partial_response(
         builder = builder.header("Last-Modified", mtime);
     }
 
-    builder.body(Full::new(body)).unwrap_or_else(|e| {
+    builder.body(body).unwrap_or_else(|e| {
         log_build_error("206", &e);
-        Response::new(Full::new(Bytes::new()))
+        Response::new(ResponseBody::empty())
     })
 }
 
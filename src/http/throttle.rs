@@ -0,0 +1,226 @@
+//! Bandwidth throttling module
+//!
+//! Paces response bodies for nginx-style `limit_rate` / `limit_rate_after`
+//! and for aggregate caps shared by all responses of a virtual host.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Largest chunk sent at once while throttled: about 1/8 s of the rate,
+/// so throttled streams stay smooth instead of bursting
+const CHUNKS_PER_SECOND: u64 = 8;
+
+/// Smallest chunk sent while throttled
+const MIN_THROTTLED_CHUNK: u64 = 1024;
+
+/// Rate shared by several responses (aggregate cap)
+#[derive(Debug)]
+pub struct SharedRate {
+    /// Bytes per second
+    rate: u64,
+    /// Time at which the bytes reserved so far have been paid off
+    next_free: Mutex<Instant>,
+}
+
+impl SharedRate {
+    pub fn new(rate: u64) -> Self {
+        Self {
+            rate,
+            next_free: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Reserve `bytes`; returns when they may be sent
+    fn reserve(&self, bytes: u64, now: Instant) -> Instant {
+        let mut next_free = self
+            .next_free
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        let at = (*next_free).max(now);
+        *next_free = at + transfer_time(bytes, self.rate);
+        drop(next_free);
+        at
+    }
+}
+
+/// Shared rates by name (one per virtual host with an aggregate cap)
+#[derive(Debug, Default)]
+pub struct SharedRates {
+    rates: Mutex<HashMap<String, Arc<SharedRate>>>,
+}
+
+impl SharedRates {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Shared rate for `name`; a changed rate starts a new one
+    pub fn get(&self, name: &str, rate: u64) -> Arc<SharedRate> {
+        let mut rates = self
+            .rates
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        let shared = rates
+            .entry(name.to_string())
+            .and_modify(|shared| {
+                if shared.rate != rate {
+                    *shared = Arc::new(SharedRate::new(rate));
+                }
+            })
+            .or_insert_with(|| Arc::new(SharedRate::new(rate)));
+        let shared = Arc::clone(shared);
+        drop(rates);
+        shared
+    }
+
+    /// Drop rates whose name is no longer in use
+    pub fn retain(&self, keep: impl Fn(&str) -> bool) {
+        self.rates
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .retain(|name, _| keep(name));
+    }
+}
+
+/// Pacing state of one response body
+#[derive(Debug)]
+pub struct Throttle {
+    /// Per-response limit in bytes per second
+    rate: Option<u64>,
+    /// Bytes still sent at full speed before `rate` applies
+    unthrottled: u64,
+    /// Start of the throttled phase, and bytes sent since
+    throttled_since: Option<Instant>,
+    throttled_bytes: u64,
+    /// Aggregate cap (applies to all bytes)
+    shared: Option<Arc<SharedRate>>,
+}
+
+impl Throttle {
+    /// `None` when neither limit is set
+    pub fn new(
+        rate: Option<u64>,
+        rate_after: u64,
+        shared: Option<Arc<SharedRate>>,
+    ) -> Option<Self> {
+        let rate = rate.filter(|&rate| rate > 0);
+        if rate.is_none() && shared.is_none() {
+            return None;
+        }
+        Some(Self {
+            rate,
+            unthrottled: rate_after,
+            throttled_since: None,
+            throttled_bytes: 0,
+            shared,
+        })
+    }
+
+    /// Largest chunk to send next (`full` when not throttled yet)
+    pub fn chunk_size(&self, full: usize) -> usize {
+        let per_response = self.rate.map(|rate| {
+            if self.unthrottled > 0 {
+                // Stop at the `limit_rate_after` boundary
+                self.unthrottled
+            } else {
+                chunk_for_rate(rate)
+            }
+        });
+        let shared = self
+            .shared
+            .as_ref()
+            .map(|shared| chunk_for_rate(shared.rate));
+        let limit = [per_response, shared].into_iter().flatten().min();
+        limit.map_or(full, |limit| {
+            usize::try_from(limit).unwrap_or(usize::MAX).min(full)
+        })
+    }
+
+    /// Account for a chunk; returns when it may be sent (`None` = now)
+    pub fn schedule(&mut self, bytes: usize, now: Instant) -> Option<Instant> {
+        let bytes = bytes as u64;
+
+        let mut at = None;
+        if let Some(rate) = self.rate {
+            let free = bytes.min(self.unthrottled);
+            self.unthrottled -= free;
+            let throttled = bytes - free;
+            if throttled > 0 {
+                let since = *self.throttled_since.get_or_insert(now);
+                at = Some(since + transfer_time(self.throttled_bytes, rate));
+                self.throttled_bytes += throttled;
+            }
+        }
+        if let Some(shared) = &self.shared {
+            let shared_at = shared.reserve(bytes, now);
+            at = Some(at.map_or(shared_at, |at: Instant| at.max(shared_at)));
+        }
+        at.filter(|&at| at > now)
+    }
+}
+
+fn chunk_for_rate(rate: u64) -> u64 {
+    (rate / CHUNKS_PER_SECOND).max(MIN_THROTTLED_CHUNK)
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn transfer_time(bytes: u64, rate: u64) -> Duration {
+    Duration::from_secs_f64(bytes as f64 / rate as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_limit_rate_after() {
+        let mut throttle = Throttle::new(Some(8192), 2000, None).unwrap();
+        let now = Instant::now();
+
+        // The first 2000 bytes go out at full speed, in one chunk at most
+        assert_eq!(throttle.chunk_size(65536), 2000);
+        assert_eq!(throttle.schedule(2000, now), None);
+
+        // Then 1024-byte chunks (1/8 s at 8 KiB/s), the first one right away
+        assert_eq!(throttle.chunk_size(65536), 1024);
+        assert_eq!(throttle.schedule(1024, now), None);
+        assert_eq!(
+            throttle.schedule(1024, now),
+            Some(now + Duration::from_millis(125))
+        );
+        let later = now + Duration::from_millis(125);
+        assert_eq!(
+            throttle.schedule(1024, later),
+            Some(now + Duration::from_millis(250))
+        );
+    }
+
+    #[test]
+    fn test_shared_rate_is_split_between_responses() {
+        let rates = SharedRates::new();
+        let shared = rates.get("media", 1000);
+        let mut first = Throttle::new(None, 0, Some(Arc::clone(&shared))).unwrap();
+        let mut second = Throttle::new(None, 0, Some(rates.get("media", 1000))).unwrap();
+        let now = Instant::now();
+
+        assert_eq!(first.schedule(1000, now), None);
+        assert_eq!(
+            second.schedule(1000, now),
+            Some(now + Duration::from_secs(1))
+        );
+        assert_eq!(first.schedule(500, now), Some(now + Duration::from_secs(2)));
+
+        // A changed rate starts over; unused names are dropped
+        assert!(!Arc::ptr_eq(&shared, &rates.get("media", 2000)));
+        rates.retain(|name| name != "media");
+        assert!(rates.rates.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_no_limits() {
+        assert!(Throttle::new(None, 100, None).is_none());
+        assert!(Throttle::new(Some(0), 0, None).is_none());
+    }
+}
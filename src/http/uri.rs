@@ -0,0 +1,402 @@
+//! Request path normalization module
+//!
+//! Turns the raw request path into the form used for routing and file lookup:
+//! 1. Percent-decode (rejecting encoded `/` and NUL bytes)
+//! 2. Optionally merge repeated slashes
+//! 3. Remove `.` and `..` segments (RFC 3986, section 5.2.4)
+
+use std::fmt;
+
+/// Reason a request path was rejected
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathError {
+    /// Path does not start with '/'
+    NotAbsolute,
+    /// Malformed percent-encoding (e.g. `%zz` or a truncated `%4`)
+    InvalidEncoding,
+    /// Encoded slash (`%2F`), which would change the path structure
+    EncodedSlash,
+    /// NUL byte (`%00`)
+    NulByte,
+    /// Decoded bytes are not valid UTF-8
+    InvalidUtf8,
+}
+
+impl fmt::Display for PathError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotAbsolute => write!(f, "path is not absolute"),
+            Self::InvalidEncoding => write!(f, "invalid percent-encoding"),
+            Self::EncodedSlash => write!(f, "encoded slash in path"),
+            Self::NulByte => write!(f, "NUL byte in path"),
+            Self::InvalidUtf8 => write!(f, "path is not valid UTF-8"),
+        }
+    }
+}
+
+/// Normalize a raw request path
+///
+/// `/%61bout` -> `/about`, `/a/./b/../c` -> `/a/c`, `//a//b` -> `/a/b` (with `merge_slashes`)
+pub fn normalize_path(raw: &str, merge_slashes: bool) -> Result<String, PathError> {
+    if !raw.starts_with('/') {
+        return Err(PathError::NotAbsolute);
+    }
+
+    let decoded = percent_decode(raw)?;
+    let merged = if merge_slashes {
+        merge_repeated_slashes(&decoded)
+    } else {
+        decoded
+    };
+
+    Ok(remove_dot_segments(&merged))
+}
+
+/// Normalize a path produced by a route rewrite
+///
+/// The input is already decoded, so only a leading '/' is ensured and dot
+/// segments are removed (a substitution may introduce `..`).
+pub fn normalize_rewritten_path(path: &str) -> String {
+    if path.starts_with('/') {
+        remove_dot_segments(path)
+    } else {
+        remove_dot_segments(&format!("/{path}"))
+    }
+}
+
+/// Percent-decode a path, rejecting encoded slashes and NUL bytes
+fn percent_decode(raw: &str) -> Result<String, PathError> {
+    let bytes = raw.as_bytes();
+    if !bytes.contains(&b'%') {
+        return Ok(raw.to_string());
+    }
+
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hi = bytes.get(i + 1).and_then(|b| hex_value(*b));
+            let lo = bytes.get(i + 2).and_then(|b| hex_value(*b));
+            let (Some(hi), Some(lo)) = (hi, lo) else {
+                return Err(PathError::InvalidEncoding);
+            };
+            match (hi << 4) | lo {
+                b'/' => return Err(PathError::EncodedSlash),
+                0 => return Err(PathError::NulByte),
+                byte => decoded.push(byte),
+            }
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+
+    String::from_utf8(decoded).map_err(|_| PathError::InvalidUtf8)
+}
+
+/// Decode a query string name or value (`+` is a space)
+///
+/// Lenient: malformed escapes are kept literally and invalid UTF-8 is replaced.
+pub fn decode_query_component(raw: &str) -> String {
+    let bytes = raw.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' => {
+                let hi = bytes.get(i + 1).and_then(|b| hex_value(*b));
+                let lo = bytes.get(i + 2).and_then(|b| hex_value(*b));
+                if let (Some(hi), Some(lo)) = (hi, lo) {
+                    decoded.push((hi << 4) | lo);
+                    i += 2;
+                } else {
+                    decoded.push(b'%');
+                }
+            }
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Uppercase hex digits for percent-encoding
+const HEX_DIGITS: &[u8; 16] = b"0123456789ABCDEF";
+
+/// Percent-encode a path for use in a `Location` header
+///
+/// Characters outside the RFC 3986 path set (including `?` and `#`) are
+/// encoded; existing `%XX` escapes are kept, so configured paths pass through.
+pub fn encode_location_path(path: &str) -> String {
+    let bytes = path.as_bytes();
+    let mut encoded = String::with_capacity(path.len());
+    for (i, &byte) in bytes.iter().enumerate() {
+        let is_escape = byte == b'%'
+            && bytes.get(i + 1).and_then(|b| hex_value(*b)).is_some()
+            && bytes.get(i + 2).and_then(|b| hex_value(*b)).is_some();
+        if is_escape || is_path_char(byte) {
+            encoded.push(char::from(byte));
+        } else {
+            encoded.push('%');
+            encoded.push(char::from(HEX_DIGITS[usize::from(byte >> 4)]));
+            encoded.push(char::from(HEX_DIGITS[usize::from(byte & 0x0F)]));
+        }
+    }
+    encoded
+}
+
+/// Whether a byte may appear unencoded in a URL path (unreserved, sub-delims, `:@/`)
+const fn is_path_char(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric()
+        || matches!(
+            byte,
+            b'-' | b'.'
+                | b'_'
+                | b'~'
+                | b'!'
+                | b'$'
+                | b'&'
+                | b'\''
+                | b'('
+                | b')'
+                | b'*'
+                | b'+'
+                | b','
+                | b';'
+                | b'='
+                | b':'
+                | b'@'
+                | b'/'
+        )
+}
+
+/// Value of a single hex digit
+const fn hex_value(byte: u8) -> Option<u8> {
+    match byte {
+        b'0'..=b'9' => Some(byte - b'0'),
+        b'a'..=b'f' => Some(byte - b'a' + 10),
+        b'A'..=b'F' => Some(byte - b'A' + 10),
+        _ => None,
+    }
+}
+
+/// Collapse runs of '/' into a single '/'
+fn merge_repeated_slashes(path: &str) -> String {
+    let mut merged = String::with_capacity(path.len());
+    let mut previous_slash = false;
+    for c in path.chars() {
+        if c == '/' && previous_slash {
+            continue;
+        }
+        previous_slash = c == '/';
+        merged.push(c);
+    }
+    merged
+}
+
+/// Remove `.` and `..` segments from an absolute path (RFC 3986, section 5.2.4)
+///
+/// `..` never climbs above the root, and a trailing `.` or `..` keeps the
+/// trailing slash (`/a/b/..` becomes `/a/`).
+fn remove_dot_segments(path: &str) -> String {
+    let mut output: Vec<&str> = Vec::new();
+    let segments: Vec<&str> = path[1..].split('/').collect();
+    let last = segments.len() - 1;
+    let mut trailing_slash = false;
+
+    for (i, segment) in segments.iter().enumerate() {
+        match *segment {
+            "." => trailing_slash = i == last,
+            ".." => {
+                output.pop();
+                trailing_slash = i == last;
+            }
+            _ => {
+                output.push(segment);
+                trailing_slash = false;
+            }
+        }
+    }
+
+    let mut result = String::with_capacity(path.len());
+    for segment in &output {
+        result.push('/');
+        result.push_str(segment);
+    }
+    if trailing_slash || result.is_empty() {
+        result.push('/');
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percent_decoding() {
+        assert_eq!(normalize_path("/%61bout", true).unwrap(), "/about");
+        assert_eq!(normalize_path("/a%20b.txt", true).unwrap(), "/a b.txt");
+        assert_eq!(normalize_path("/%E4%BD%A0", true).unwrap(), "/你");
+    }
+
+    #[test]
+    fn test_rejected_paths() {
+        assert_eq!(normalize_path("/a%2Fb", true), Err(PathError::EncodedSlash));
+        assert_eq!(normalize_path("/a%2fb", true), Err(PathError::EncodedSlash));
+        assert_eq!(normalize_path("/a%00", true), Err(PathError::NulByte));
+        assert_eq!(
+            normalize_path("/a%zz", true),
+            Err(PathError::InvalidEncoding)
+        );
+        assert_eq!(
+            normalize_path("/a%4", true),
+            Err(PathError::InvalidEncoding)
+        );
+        assert_eq!(normalize_path("/%FF", true), Err(PathError::InvalidUtf8));
+        assert_eq!(normalize_path("a", true), Err(PathError::NotAbsolute));
+    }
+
+    #[test]
+    fn test_dot_segments() {
+        assert_eq!(normalize_path("/a/./b/../c", true).unwrap(), "/a/c");
+        assert_eq!(
+            normalize_path("/../../etc/passwd", true).unwrap(),
+            "/etc/passwd"
+        );
+        assert_eq!(normalize_path("/a/b/..", true).unwrap(), "/a/");
+        assert_eq!(normalize_path("/a/.", true).unwrap(), "/a/");
+        assert_eq!(normalize_path("/..", true).unwrap(), "/");
+        assert_eq!(normalize_path("/%2e%2e/secret", true).unwrap(), "/secret");
+        // Dots inside names are not segments
+        assert_eq!(normalize_path("/a..b.txt", true).unwrap(), "/a..b.txt");
+        assert_eq!(normalize_path("/...", true).unwrap(), "/...");
+    }
+
+    #[test]
+    fn test_merge_slashes() {
+        assert_eq!(normalize_path("//a///b/", true).unwrap(), "/a/b/");
+        assert_eq!(normalize_path("//a///b/", false).unwrap(), "//a///b/");
+        assert_eq!(normalize_path("/", true).unwrap(), "/");
+        assert_eq!(normalize_path("/dir/", false).unwrap(), "/dir/");
+    }
+
+    #[test]
+    fn test_normalize_rewritten_path() {
+        assert_eq!(normalize_rewritten_path("v2/users"), "/v2/users");
+        assert_eq!(normalize_rewritten_path("/a/../../b"), "/b");
+        assert_eq!(normalize_rewritten_path(""), "/");
+        // Already decoded: '%' is kept literally
+        assert_eq!(normalize_rewritten_path("/100%25"), "/100%25");
+    }
+
+    #[test]
+    fn test_encode_location_path() {
+        assert_eq!(encode_location_path("/a b/é"), "/a%20b/%C3%A9");
+        assert_eq!(encode_location_path("/x?y#z"), "/x%3Fy%23z");
+        assert_eq!(encode_location_path("/100%25/50%"), "/100%25/50%25");
+        assert_eq!(encode_location_path("/a-b_c~d/@:"), "/a-b_c~d/@:");
+    }
+
+    #[test]
+    fn test_decode_query_component() {
+        assert_eq!(decode_query_component("a+b%20c"), "a b c");
+        assert_eq!(decode_query_component("%2Fx%"), "/x%");
+        assert_eq!(decode_query_component("100%zz"), "100%zz");
+    }
+
+    /// Small deterministic PRNG (xorshift) so the fuzz tests need no extra crates
+    struct XorShift(u64);
+
+    impl XorShift {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn pick<'a>(&mut self, items: &[&'a str]) -> &'a str {
+            #[allow(clippy::cast_possible_truncation)]
+            items[(self.next() % items.len() as u64) as usize]
+        }
+    }
+
+    const FRAGMENTS: &[&str] = &[
+        "/",
+        "//",
+        ".",
+        "..",
+        "a",
+        "b.txt",
+        "a..b",
+        "%2e",
+        "%2E%2e",
+        "%2f",
+        "%00",
+        "%41",
+        "%",
+        "%g1",
+        "%c3%a9",
+        "%ff",
+        "~",
+        " ",
+        "%20",
+        "index.html",
+        "-_",
+        "\\",
+        "é",
+    ];
+
+    fn random_path(rng: &mut XorShift) -> String {
+        let mut path = String::from("/");
+        let len = rng.next() % 12;
+        for _ in 0..len {
+            path.push_str(rng.pick(FRAGMENTS));
+        }
+        path
+    }
+
+    #[test]
+    fn fuzz_normalized_paths_are_canonical() {
+        let mut rng = XorShift(0x9E37_79B9_7F4A_7C15);
+        for _ in 0..20_000 {
+            let raw = random_path(&mut rng);
+            let Ok(normalized) = normalize_path(&raw, true) else {
+                continue;
+            };
+
+            assert!(normalized.starts_with('/'), "{raw:?} -> {normalized:?}");
+            assert!(!normalized.contains('\0'), "{raw:?} -> {normalized:?}");
+            assert!(!normalized.contains("//"), "{raw:?} -> {normalized:?}");
+            assert!(
+                normalized.split('/').all(|s| s != "." && s != ".."),
+                "{raw:?} -> {normalized:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn fuzz_rejections_and_idempotence() {
+        let mut rng = XorShift(0xD1B5_4A32_D192_ED03);
+        for _ in 0..20_000 {
+            let raw = random_path(&mut rng);
+            let lower = raw.to_ascii_lowercase();
+            match normalize_path(&raw, false) {
+                Ok(normalized) => {
+                    // Never accepts an encoded slash or NUL
+                    assert!(!lower.contains("%2f") && !lower.contains("%00"), "{raw:?}");
+                    // Without '%' in the output, normalizing again is a no-op
+                    if !normalized.contains('%') {
+                        assert_eq!(normalize_path(&normalized, false).unwrap(), normalized);
+                    }
+                }
+                Err(PathError::EncodedSlash) => assert!(lower.contains("%2f"), "{raw:?}"),
+                Err(PathError::NulByte) => assert!(lower.contains("%00"), "{raw:?}"),
+                Err(_) => assert!(raw.contains('%'), "{raw:?}"),
+            }
+        }
+    }
+}
@@ -4,6 +4,7 @@
 //! - Server lifecycle logging
 //! - Access logging with multiple formats
 //! - Error and warning logging
+//! - Audit records of configuration changes
 //! - File-based logging support
 
 mod format;
@@ -21,6 +22,7 @@ pub fn init(config: &Config) -> std::io::Result<()> {
     writer::init(
         config.logging.access_log_file.as_deref(),
         config.logging.error_log_file.as_deref(),
+        config.logging.audit_log_file.as_deref(),
     )
 }
 
@@ -42,6 +44,13 @@ pub fn write_error(message: &str) {
     }
 }
 
+/// Write to audit log (dropped when no audit log file is configured)
+pub fn write_audit(message: &str) {
+    if writer::is_initialized() {
+        writer::get().write_audit(message);
+    }
+}
+
 /// Write to access log specifically
 fn write_access(message: &str) {
     if writer::is_initialized() {
@@ -65,6 +74,9 @@ pub fn log_server_start(addr: &SocketAddr, config: &Config) {
     if let Some(ref path) = config.logging.error_log_file {
         write_info(&format!("Error log: {path}"));
     }
+    if let Some(ref path) = config.logging.audit_log_file {
+        write_info(&format!("Audit log: {path}"));
+    }
     write_info("Using Tokio runtime for concurrency");
     write_info("======================================\n");
 }
@@ -77,6 +89,12 @@ pub fn log_connection_error(err: &impl std::fmt::Debug) {
     write_error(&format!("[ERROR] Failed to serve connection: {err:?}"));
 }
 
+pub fn log_connection_timeout(peer_addr: &SocketAddr, reason: &str, limit_secs: u64) {
+    write_error(&format!(
+        "[WARN] Connection from {peer_addr} closed: {reason} ({limit_secs}s)"
+    ));
+}
+
 pub fn log_error(message: &str) {
     write_error(&format!("[ERROR] {message}"));
 }
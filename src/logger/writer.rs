@@ -19,6 +19,8 @@ enum LogTarget {
     Stderr,
     /// Write to file
     File(Mutex<File>),
+    /// Drop messages (audit log without a file)
+    Discard,
 }
 
 /// Thread-safe log writer
@@ -27,11 +29,17 @@ pub struct LogWriter {
     access: Mutex<LogTarget>,
     /// Error log target
     error: Mutex<LogTarget>,
+    /// Audit log target (configuration changes), fixed at startup
+    audit: LogTarget,
 }
 
 impl LogWriter {
     /// Create a new log writer with optional file paths
-    fn new(access_log_file: Option<&str>, error_log_file: Option<&str>) -> io::Result<Self> {
+    fn new(
+        access_log_file: Option<&str>,
+        error_log_file: Option<&str>,
+        audit_log_file: Option<&str>,
+    ) -> io::Result<Self> {
         let access = match access_log_file {
             Some(path) => {
                 let file = open_log_file(path)?;
@@ -48,9 +56,18 @@ impl LogWriter {
             None => LogTarget::Stderr,
         };
 
+        let audit = match audit_log_file {
+            Some(path) => {
+                let file = open_log_file(path)?;
+                LogTarget::File(Mutex::new(file))
+            }
+            None => LogTarget::Discard,
+        };
+
         Ok(Self {
             access: Mutex::new(access),
             error: Mutex::new(error),
+            audit,
         })
     }
 
@@ -72,6 +89,11 @@ impl LogWriter {
         write_to_target(&target, message);
     }
 
+    /// Write to audit log
+    pub fn write_audit(&self, message: &str) {
+        write_to_target(&self.audit, message);
+    }
+
     /// Update access log file path (for runtime reconfiguration)
     pub fn set_access_log_file(&self, path: Option<&str>) -> io::Result<()> {
         let mut target = self.access.lock().unwrap();
@@ -125,6 +147,7 @@ fn write_to_target(target: &LogTarget, message: &str) {
                 let _ = writeln!(f, "{message}");
             }
         }
+        LogTarget::Discard => {}
     }
 }
 
@@ -132,8 +155,12 @@ fn write_to_target(target: &LogTarget, message: &str) {
 ///
 /// This should be called once at application startup.
 /// Returns error if log files cannot be opened.
-pub fn init(access_log_file: Option<&str>, error_log_file: Option<&str>) -> io::Result<()> {
-    let writer = LogWriter::new(access_log_file, error_log_file)?;
+pub fn init(
+    access_log_file: Option<&str>,
+    error_log_file: Option<&str>,
+    audit_log_file: Option<&str>,
+) -> io::Result<()> {
+    let writer = LogWriter::new(access_log_file, error_log_file, audit_log_file)?;
     LOG_WRITER.set(writer).map_err(|_| {
         io::Error::new(
             io::ErrorKind::AlreadyExists,
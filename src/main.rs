@@ -75,21 +75,28 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     runtime.block_on(async_main(cfg, config_path))
 }
 
-// Allow `future_not_send`: This function uses `LocalSet::run_until()` which doesn't
-// require Send futures. Clippy warns because the Future holds non-Send types across
-// await points, but since we run via `block_on()` (not `spawn()`), Send is not required.
+// Allow `future_not_send`: this future runs on the main thread via `block_on()` and
+// holds a `Box<dyn Error>` across awaits. Connection tasks are spawned with
+// `tokio::spawn` and are `Send`, so they are distributed over all worker threads.
 #[allow(clippy::similar_names, clippy::future_not_send)]
 async fn async_main(cfg: config::Config, config_path: String) -> Result<(), Box<dyn std::error::Error>> {
     let app_addr = cfg.get_socket_addr()?;
     let api_addr = cfg.get_api_socket_addr()?;
 
+    // Management API credentials; an exposed API without them is refused
+    let api_auth = api::ApiAuth::load(cfg.api_auth.as_ref())?;
+    api_auth.check_listener(&cfg.server.api_host)?;
+    let api_scheme = if api_auth.tls_acceptor().is_some() { "https" } else { "http" };
+
     let app_listener = server::create_reusable_listener(app_addr)?;
     let api_listener = server::create_reusable_listener(api_addr)?;
 
     // Create state manager for persistence (state.toml alongside config.toml)
     // Only enabled if config.server.enable_state_persistence is true
     let state_manager = config::create_state_manager(&config_path, cfg.server.enable_state_persistence);
-    let state = Arc::new(config::AppState::new(&cfg, state_manager).await);
+    let mut state = config::AppState::new(&cfg, state_manager).await;
+    state.api_auth = api_auth;
+    let state = Arc::new(state);
     let app_connections = Arc::new(AtomicUsize::new(0));
     let api_connections = Arc::new(AtomicUsize::new(0));
 
@@ -102,9 +109,12 @@ async fn async_main(cfg: config::Config, config_path: String) -> Result<(), Box<
     );
 
     logger::log_server_start(&app_addr, &cfg);
-    println!("[API] Management API running on: http://{api_addr}");
-    println!("  - GET  http://{api_addr}/v1/discovery  (view current snapshot)");
-    println!("  - POST http://{api_addr}/v1/discovery:routes  (update routes)");
+    println!("[API] Management API running on: {api_scheme}://{api_addr}");
+    println!("  - GET  {api_scheme}://{api_addr}/v1/discovery  (view current snapshot)");
+    println!("  - POST {api_scheme}://{api_addr}/v1/discovery:routes  (update routes)");
+    if !state.api_auth.is_enabled() {
+        println!("[WARN] Management API authentication is disabled (loopback only)");
+    }
     println!("[INFO] Application and API ports are separated");
     println!("[INFO] Server supports graceful restart for host/port changes");
     println!("[CONFIG] Loaded configuration:");
@@ -123,23 +133,19 @@ async fn async_main(cfg: config::Config, config_path: String) -> Result<(), Box<
         if cfg.server.enable_state_persistence { "enabled" } else { "disabled" }
     );
 
-    // Use LocalSet for spawn_local support
-    let local = tokio::task::LocalSet::new();
-    local
-        .run_until(run_dual_servers(
-            app_listener,
-            api_listener,
-            state,
-            app_connections,
-            api_connections,
-            signal_handler,
-        ))
-        .await
+    // Connections are spawned as Send tasks, so they run on all worker threads
+    run_dual_servers(
+        app_listener,
+        api_listener,
+        state,
+        app_connections,
+        api_connections,
+        signal_handler,
+    )
+    .await
 }
 
-// Allow `future_not_send`: This function is called within `LocalSet::run_until()`,
-// which executes futures on the current thread without requiring Send. The internal
-// `spawn_local()` also doesn't require Send. Clippy's warning is a false positive here.
+// Allow `future_not_send`: driven by `async_main()` on the main thread (see above).
 #[allow(clippy::similar_names, clippy::future_not_send)]
 async fn run_dual_servers(
     app_listener: TcpListener,
@@ -154,7 +160,7 @@ async fn run_dual_servers(
     let shutdown_clone = Arc::clone(&signal_handler.shutdown);
 
     // Spawn API server task
-    tokio::task::spawn_local(async move {
+    tokio::spawn(async move {
         if let Err(e) = run_api_server(api_listener, state_clone, api_connections_clone).await {
             logger::log_api_error(&format!("API server error: {e}"));
         }
@@ -190,13 +196,10 @@ async fn graceful_shutdown(state: Arc<config::AppState>, conn_counter: Arc<Atomi
 
     println!("[SHUTDOWN] ========== Graceful Shutdown Started ==========");
 
-    let timeout = Duration::from_secs(
-        state
-            .config
-            .performance
-            .read_timeout
-            .max(state.config.performance.write_timeout),
-    );
+    let timeout = {
+        let performance = &state.snapshot().performance;
+        Duration::from_secs(performance.read_timeout.max(performance.write_timeout))
+    };
     let start = std::time::Instant::now();
 
     // Wait for connections to drain (with timeout)
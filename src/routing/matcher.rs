@@ -1,18 +1,135 @@
 //! Route matching module
 //!
-//! Implements path and header matching for xDS Route.
+//! Implements path, method, header, query parameter and cookie matching for xDS Route.
+//! Regex matches expose their capture groups for redirect targets and rewrites.
 
-use crate::config::{HeaderMatcher, Route, RouteMatch};
+use crate::config::{KeyValueMatcher, Route, RouteMatch, SafeRegex};
+use crate::http::uri;
+use hyper::header::COOKIE;
+use hyper::{HeaderMap, Method};
 
-/// Find the first matching route for a given path and headers
+/// Capture groups of a `safe_regex` path match
+///
+/// Group 0 is the whole path. Groups that did not participate in the match
+/// are empty strings.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PathCaptures {
+    groups: Vec<String>,
+    names: Vec<(String, usize)>,
+}
+
+impl PathCaptures {
+    /// Capture groups of a whole-input regex match, or `None` if it does not match
+    pub fn from_regex(regex: &SafeRegex, input: &str) -> Option<Self> {
+        let caps = regex.regex().captures(input)?;
+        Some(Self {
+            groups: caps
+                .iter()
+                .map(|group| group.map_or_else(String::new, |m| m.as_str().to_string()))
+                .collect(),
+            names: regex
+                .regex()
+                .capture_names()
+                .enumerate()
+                .filter_map(|(index, name)| name.map(|n| (n.to_string(), index)))
+                .collect(),
+        })
+    }
+
+    /// Get a capture group by index
+    pub fn get(&self, index: usize) -> Option<&str> {
+        self.groups.get(index).map(String::as_str)
+    }
+
+    /// Get a named capture group
+    pub fn name(&self, name: &str) -> Option<&str> {
+        self.names
+            .iter()
+            .find(|(n, _)| n == name)
+            .and_then(|(_, index)| self.get(*index))
+    }
+
+    /// Substitute `$1`, `${1}` and `${name}` references in a template
+    ///
+    /// `$$` produces a literal `$`. Unknown references expand to an empty
+    /// string. Without captures (non-regex routes) the template is returned as-is.
+    pub fn expand(&self, template: &str) -> String {
+        if self.groups.is_empty() || !template.contains('$') {
+            return template.to_string();
+        }
+
+        let mut result = String::with_capacity(template.len());
+        let mut rest = template;
+        while let Some(pos) = rest.find('$') {
+            result.push_str(&rest[..pos]);
+            rest = &rest[pos + 1..];
+
+            if let Some(after) = rest.strip_prefix('$') {
+                result.push('$');
+                rest = after;
+            } else if let Some(braced) = rest.strip_prefix('{') {
+                if let Some(end) = braced.find('}') {
+                    let reference = &braced[..end];
+                    let value = reference
+                        .parse::<usize>()
+                        .map_or_else(|_| self.name(reference), |index| self.get(index));
+                    result.push_str(value.unwrap_or(""));
+                    rest = &braced[end + 1..];
+                } else {
+                    result.push('$');
+                }
+            } else {
+                let digits = rest.bytes().take_while(u8::is_ascii_digit).count();
+                if digits == 0 {
+                    result.push('$');
+                } else {
+                    let index = rest[..digits].parse::<usize>().unwrap_or(usize::MAX);
+                    result.push_str(self.get(index).unwrap_or(""));
+                    rest = &rest[digits..];
+                }
+            }
+        }
+        result.push_str(rest);
+        result
+    }
+}
+
+/// Matched route with its path captures
+#[derive(Debug)]
+pub struct RouteMatchResult<'a> {
+    pub route: &'a Route,
+    /// Position of the route within its virtual host
+    pub index: usize,
+    pub captures: PathCaptures,
+}
+
+/// Request attributes used for route matching
+pub struct MatchRequest<'a> {
+    /// Normalized request path
+    pub path: &'a str,
+    pub method: &'a Method,
+    /// Raw query string (without '?')
+    pub query: Option<&'a str>,
+    pub headers: &'a HeaderMap,
+}
+
+/// Find the first matching route for a request (linear scan)
+///
+/// Request handling goes through the compiled `RouteTable`; this is the
+/// reference implementation it is tested and benchmarked against.
+#[cfg(test)]
 pub fn match_route<'a>(
-    path: &str,
-    headers: Option<&[(&str, &str)]>,
+    request: &MatchRequest<'_>,
     routes: &'a [Route],
-) -> Option<&'a Route> {
-    routes
-        .iter()
-        .find(|route| matches_route_rule(&route.match_rule, path, headers))
+) -> Option<RouteMatchResult<'a>> {
+    routes.iter().enumerate().find_map(|(index, route)| {
+        let captures = matches_route_rule(&route.match_rule, request)?;
+        Some(RouteMatchResult {
+            route,
+            index,
+            captures,
+        })
+    })
 }
 
 /// Check if a path matches a route rule
@@ -27,62 +144,140 @@ pub fn match_path(rule: &RouteMatch, path: &str) -> bool {
         return path.starts_with(prefix);
     }
 
+    // Regex match (whole path)
+    if let Some(regex) = &rule.safe_regex {
+        return regex.regex().is_match(path);
+    }
+
+    // Prefix match on path segment boundaries
+    if let Some(prefix) = &rule.path_separated_prefix {
+        return path
+            .strip_prefix(prefix.as_str())
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'));
+    }
+
     // No path rule means match all
     true
 }
 
-/// Check if request matches a route rule (path + headers)
-fn matches_route_rule(rule: &RouteMatch, path: &str, headers: Option<&[(&str, &str)]>) -> bool {
+/// Extract capture groups of a `safe_regex` match
+fn path_captures(regex: &SafeRegex, path: &str) -> PathCaptures {
+    PathCaptures::from_regex(regex, path).unwrap_or_default()
+}
+
+/// Check if request matches a route rule, returning path captures
+pub(super) fn matches_route_rule(
+    rule: &RouteMatch,
+    request: &MatchRequest<'_>,
+) -> Option<PathCaptures> {
     // First check path
-    if !match_path(rule, path) {
-        return false;
+    if !match_path(rule, request.path) {
+        return None;
     }
 
-    // Then check headers if specified
-    if let Some(header_matchers) = &rule.headers {
-        if !match_headers(header_matchers, headers) {
-            return false;
+    // Then check method, headers, query parameters and cookies if specified
+    if let Some(methods) = &rule.methods {
+        if !match_method(methods, request.method) {
+            return None;
         }
     }
 
-    true
-}
+    if let Some(matchers) = &rule.headers {
+        if !matchers
+            .iter()
+            .all(|m| match_value(m, header_value(request.headers, &m.name).as_deref()))
+        {
+            return None;
+        }
+    }
 
-/// Check if headers match all header matchers
-fn match_headers(matchers: &[HeaderMatcher], headers: Option<&[(&str, &str)]>) -> bool {
-    let Some(headers) = headers else {
-        return matchers.is_empty();
-    };
+    if let Some(matchers) = &rule.query_parameters {
+        if !matchers
+            .iter()
+            .all(|m| match_value(m, query_value(request.query, &m.name).as_deref()))
+        {
+            return None;
+        }
+    }
 
-    for matcher in matchers {
-        if !match_single_header(matcher, headers) {
-            return false;
+    if let Some(matchers) = &rule.cookies {
+        if !matchers
+            .iter()
+            .all(|m| match_value(m, cookie_value(request.headers, &m.name)))
+        {
+            return None;
         }
     }
-    true
+
+    Some(
+        rule.safe_regex
+            .as_ref()
+            .map_or_else(PathCaptures::default, |regex| {
+                path_captures(regex, request.path)
+            }),
+    )
 }
 
-/// Check if a single header matcher is satisfied
-fn match_single_header(matcher: &HeaderMatcher, headers: &[(&str, &str)]) -> bool {
-    let header_value = headers
+/// Check the request method against the allowed list (HEAD also matches GET)
+fn match_method(methods: &[String], method: &Method) -> bool {
+    methods.iter().any(|m| {
+        m.eq_ignore_ascii_case(method.as_str())
+            || (method == Method::HEAD && m.eq_ignore_ascii_case("GET"))
+    })
+}
+
+/// Get a header value; repeated headers are joined with ','
+fn header_value(headers: &HeaderMap, name: &str) -> Option<String> {
+    let mut values = headers.get_all(name).iter().filter_map(|v| v.to_str().ok());
+    let first = values.next()?;
+    Some(values.fold(first.to_string(), |mut joined, value| {
+        joined.push(',');
+        joined.push_str(value);
+        joined
+    }))
+}
+
+/// Get the first value of a query parameter (percent-decoded)
+fn query_value(query: Option<&str>, name: &str) -> Option<String> {
+    query?
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| pair.split_once('=').unwrap_or((pair, "")))
+        .find(|(key, _)| uri::decode_query_component(key) == name)
+        .map(|(_, value)| uri::decode_query_component(value))
+}
+
+/// Get a cookie value from the `Cookie` header(s)
+fn cookie_value<'h>(headers: &'h HeaderMap, name: &str) -> Option<&'h str> {
+    headers
+        .get_all(COOKIE)
         .iter()
-        .find(|(name, _)| name.eq_ignore_ascii_case(&matcher.name))
-        .map(|(_, value)| *value);
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|cookies| cookies.split(';'))
+        .filter_map(|cookie| cookie.trim().split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value)
+}
 
+/// Check a header, query parameter or cookie value against a matcher
+fn match_value(matcher: &KeyValueMatcher, value: Option<&str>) -> bool {
+    match_value_inner(matcher, value) != matcher.invert_match
+}
+
+fn match_value_inner(matcher: &KeyValueMatcher, value: Option<&str>) -> bool {
     // Check "present" condition
     if let Some(should_present) = matcher.present {
-        let is_present = header_value.is_some();
-        if is_present != should_present {
+        if value.is_some() != should_present {
             return false;
         }
         // If we only check presence, we're done
-        if matcher.exact.is_none() && matcher.prefix.is_none() {
+        if matcher.exact.is_none() && matcher.prefix.is_none() && matcher.regex.is_none() {
             return true;
         }
     }
 
-    let Some(value) = header_value else {
-        // Header not present but we need to match value
+    let Some(value) = value else {
+        // Value not present but we need to match it
         return false;
     };
 
@@ -93,7 +288,12 @@ fn match_single_header(matcher: &HeaderMatcher, headers: &[(&str, &str)]) -> boo
 
     // Check prefix match
     if let Some(prefix) = &matcher.prefix {
-        return value.starts_with(prefix);
+        return value.starts_with(prefix.as_str());
+    }
+
+    // Check regex match (whole value)
+    if let Some(regex) = &matcher.regex {
+        return regex.regex().is_match(value);
     }
 
     true
@@ -105,19 +305,18 @@ mod tests {
     use crate::config::RouteAction;
 
     fn make_route(prefix: Option<&str>, path: Option<&str>) -> Route {
-        Route {
-            name: None,
-            match_rule: RouteMatch {
-                prefix: prefix.map(String::from),
-                path: path.map(String::from),
-                headers: None,
-            },
-            action: RouteAction::Direct {
-                status: 200,
-                body: None,
-                content_type: None,
-            },
-        }
+        let rule = RouteMatch {
+            prefix: prefix.map(String::from),
+            path: path.map(String::from),
+            headers: None,
+            ..RouteMatch::default()
+        };
+        let action = RouteAction::Direct {
+            status: 200,
+            body: None,
+            content_type: None,
+        };
+        crate::routing::test_route(rule, None, action)
     }
 
     #[test]
@@ -126,6 +325,7 @@ mod tests {
             path: Some("/about".to_string()),
             prefix: None,
             headers: None,
+            ..RouteMatch::default()
         };
         assert!(match_path(&rule, "/about"));
         assert!(!match_path(&rule, "/about/"));
@@ -138,6 +338,7 @@ mod tests {
             path: None,
             prefix: Some("/api".to_string()),
             headers: None,
+            ..RouteMatch::default()
         };
         assert!(match_path(&rule, "/api"));
         assert!(match_path(&rule, "/api/users"));
@@ -151,10 +352,41 @@ mod tests {
             path: None,
             prefix: None,
             headers: None,
+            ..RouteMatch::default()
         };
         assert!(match_path(&rule, "/anything"));
     }
 
+    fn header_map(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.append(*name, value.parse().unwrap());
+        }
+        headers
+    }
+
+    fn rule_matches(
+        rule: &RouteMatch,
+        method: &Method,
+        query: Option<&str>,
+        headers: &HeaderMap,
+    ) -> bool {
+        let request = MatchRequest {
+            path: "/",
+            method,
+            query,
+            headers,
+        };
+        matches_route_rule(rule, &request).is_some()
+    }
+
+    fn matcher(name: &str) -> KeyValueMatcher {
+        KeyValueMatcher {
+            name: name.to_string(),
+            ..KeyValueMatcher::default()
+        }
+    }
+
     #[test]
     fn test_match_route_order() {
         let routes = vec![
@@ -162,51 +394,217 @@ mod tests {
             make_route(Some("/api"), None),
             make_route(None, None), // catch-all
         ];
+        let headers = HeaderMap::new();
+        let request = |path| MatchRequest {
+            path,
+            method: &Method::GET,
+            query: None,
+            headers: &headers,
+        };
 
         // Should match first applicable route in order
-        let result = match_route("/api/v1/users", None, &routes);
+        let result = match_route(&request("/api/v1/users"), &routes);
         assert!(result.is_some());
         assert_eq!(
-            result.unwrap().match_rule.prefix,
+            result.unwrap().route.match_rule.prefix,
             Some("/api/v1".to_string())
         );
 
-        let result = match_route("/api/v2/users", None, &routes);
+        let result = match_route(&request("/api/v2/users"), &routes);
         assert!(result.is_some());
-        assert_eq!(result.unwrap().match_rule.prefix, Some("/api".to_string()));
+        assert_eq!(
+            result.unwrap().route.match_rule.prefix,
+            Some("/api".to_string())
+        );
     }
 
     #[test]
     fn test_match_headers() {
-        let matchers = vec![HeaderMatcher {
-            name: "X-Api-Key".to_string(),
-            exact: Some("secret".to_string()),
-            prefix: None,
-            present: None,
-        }];
+        let rule = RouteMatch {
+            headers: Some(vec![KeyValueMatcher {
+                exact: Some("secret".to_string()),
+                ..matcher("X-Api-Key")
+            }]),
+            ..RouteMatch::default()
+        };
 
-        let headers = vec![("X-Api-Key", "secret")];
-        assert!(match_headers(&matchers, Some(&headers)));
+        let headers = header_map(&[("x-api-key", "secret")]);
+        assert!(rule_matches(&rule, &Method::GET, None, &headers));
 
-        let headers = vec![("X-Api-Key", "wrong")];
-        assert!(!match_headers(&matchers, Some(&headers)));
+        let headers = header_map(&[("x-api-key", "wrong")]);
+        assert!(!rule_matches(&rule, &Method::GET, None, &headers));
 
-        assert!(!match_headers(&matchers, None));
+        assert!(!rule_matches(&rule, &Method::GET, None, &HeaderMap::new()));
     }
 
     #[test]
     fn test_match_header_present() {
-        let matchers = vec![HeaderMatcher {
-            name: "Authorization".to_string(),
-            exact: None,
-            prefix: None,
-            present: Some(true),
-        }];
+        let rule = RouteMatch {
+            headers: Some(vec![KeyValueMatcher {
+                present: Some(true),
+                ..matcher("Authorization")
+            }]),
+            ..RouteMatch::default()
+        };
 
-        let headers = vec![("Authorization", "Bearer token")];
-        assert!(match_headers(&matchers, Some(&headers)));
+        let headers = header_map(&[("authorization", "Bearer token")]);
+        assert!(rule_matches(&rule, &Method::GET, None, &headers));
+        assert!(!rule_matches(&rule, &Method::GET, None, &HeaderMap::new()));
+    }
 
-        let headers: Vec<(&str, &str)> = vec![];
-        assert!(!match_headers(&matchers, Some(&headers)));
+    #[test]
+    fn test_match_header_regex_and_invert() {
+        let beta = KeyValueMatcher {
+            regex: Some(SafeRegex::new("beta-[0-9]+").unwrap()),
+            ..matcher("X-Channel")
+        };
+        let rule = RouteMatch {
+            headers: Some(vec![beta.clone()]),
+            ..RouteMatch::default()
+        };
+        let inverted = RouteMatch {
+            headers: Some(vec![KeyValueMatcher {
+                invert_match: true,
+                ..beta
+            }]),
+            ..RouteMatch::default()
+        };
+
+        let headers = header_map(&[("x-channel", "beta-7")]);
+        assert!(rule_matches(&rule, &Method::GET, None, &headers));
+        assert!(!rule_matches(&inverted, &Method::GET, None, &headers));
+
+        let headers = header_map(&[("x-channel", "stable")]);
+        assert!(!rule_matches(&rule, &Method::GET, None, &headers));
+        assert!(rule_matches(&inverted, &Method::GET, None, &headers));
+        assert!(rule_matches(
+            &inverted,
+            &Method::GET,
+            None,
+            &HeaderMap::new()
+        ));
+    }
+
+    #[test]
+    fn test_match_methods() {
+        let rule = RouteMatch {
+            methods: Some(vec!["get".to_string()]),
+            ..RouteMatch::default()
+        };
+        let headers = HeaderMap::new();
+        assert!(rule_matches(&rule, &Method::GET, None, &headers));
+        assert!(rule_matches(&rule, &Method::HEAD, None, &headers));
+        assert!(!rule_matches(&rule, &Method::POST, None, &headers));
+
+        let rule = RouteMatch {
+            methods: Some(vec!["HEAD".to_string()]),
+            ..RouteMatch::default()
+        };
+        assert!(!rule_matches(&rule, &Method::GET, None, &headers));
+    }
+
+    #[test]
+    fn test_match_query_parameters() {
+        let rule = RouteMatch {
+            query_parameters: Some(vec![
+                KeyValueMatcher {
+                    exact: Some("a b".to_string()),
+                    ..matcher("q")
+                },
+                KeyValueMatcher {
+                    present: Some(false),
+                    ..matcher("debug")
+                },
+            ]),
+            ..RouteMatch::default()
+        };
+        let headers = HeaderMap::new();
+        assert!(rule_matches(
+            &rule,
+            &Method::GET,
+            Some("x=1&q=a+b"),
+            &headers
+        ));
+        assert!(rule_matches(&rule, &Method::GET, Some("q=a%20b"), &headers));
+        assert!(!rule_matches(
+            &rule,
+            &Method::GET,
+            Some("q=a%20b&debug"),
+            &headers
+        ));
+        assert!(!rule_matches(
+            &rule,
+            &Method::GET,
+            Some("q=other"),
+            &headers
+        ));
+        assert!(!rule_matches(&rule, &Method::GET, None, &headers));
+    }
+
+    #[test]
+    fn test_match_cookies() {
+        let rule = RouteMatch {
+            cookies: Some(vec![KeyValueMatcher {
+                prefix: Some("beta".to_string()),
+                ..matcher("channel")
+            }]),
+            ..RouteMatch::default()
+        };
+        let headers = header_map(&[("cookie", "session=abc; channel=beta-2")]);
+        assert!(rule_matches(&rule, &Method::GET, None, &headers));
+
+        let headers = header_map(&[("cookie", "session=abc"), ("cookie", "channel=beta")]);
+        assert!(rule_matches(&rule, &Method::GET, None, &headers));
+
+        let headers = header_map(&[("cookie", "xchannel=beta; channel=stable")]);
+        assert!(!rule_matches(&rule, &Method::GET, None, &headers));
+    }
+
+    #[test]
+    fn test_match_safe_regex_captures() {
+        let rule = RouteMatch {
+            safe_regex: Some(SafeRegex::new(r"/users/(\d+)/(?P<tab>[a-z]+)").unwrap()),
+            ..RouteMatch::default()
+        };
+        // Regex must match the whole path
+        assert!(match_path(&rule, "/users/42/posts"));
+        assert!(!match_path(&rule, "/users/42/posts/extra"));
+        assert!(!match_path(&rule, "/api/users/42/posts"));
+
+        let captures = path_captures(rule.safe_regex.as_ref().unwrap(), "/users/42/posts");
+        assert_eq!(captures.get(0), Some("/users/42/posts"));
+        assert_eq!(captures.get(1), Some("42"));
+        assert_eq!(captures.name("tab"), Some("posts"));
+        assert_eq!(
+            captures.expand("/u/$1?tab=${tab}&x=${9}$$"),
+            "/u/42?tab=posts&x=$"
+        );
+    }
+
+    #[test]
+    fn test_safe_regex_rejects_invalid_patterns() {
+        assert!(SafeRegex::new("/users/(").is_err());
+        // Backreferences are not supported (linear-time engine)
+        assert!(SafeRegex::new(r"/(a)\1").is_err());
+        let parsed: Result<RouteMatch, _> = serde_json::from_str(r#"{"safe_regex": "[z-a]"}"#);
+        assert!(parsed.is_err());
+    }
+
+    #[test]
+    fn test_match_path_separated_prefix() {
+        let rule = RouteMatch {
+            path_separated_prefix: Some("/api".to_string()),
+            ..RouteMatch::default()
+        };
+        assert!(match_path(&rule, "/api"));
+        assert!(match_path(&rule, "/api/users"));
+        assert!(!match_path(&rule, "/apix"));
+        assert!(!match_path(&rule, "/ap"));
+    }
+
+    #[test]
+    fn test_expand_without_captures() {
+        let captures = PathCaptures::default();
+        assert_eq!(captures.expand("/price/$5"), "/price/$5");
     }
 }
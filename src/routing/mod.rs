@@ -1,12 +1,34 @@
 //! Routing module
 //!
 //! Provides xDS-compatible routing capabilities including:
-//! - Virtual host matching based on Host header
-//! - Route matching based on path prefix/exact match
-//! - Header-based routing
+//! - Virtual host matching based on Host header (precompiled domain index)
+//! - Route matching based on path prefix/exact/regex/segment-prefix match
+//! - Method, header, query parameter and cookie based routing
+//! - URL rewrites (prefix, regex, host) with optional re-matching
+//! - Redirect locations (scheme, host, port and path redirects)
+//! - Compiled route tables (radix trie) built when configuration is applied
 
 mod matcher;
+mod redirect;
+mod rewrite;
+mod table;
 mod vhost;
 
-pub use matcher::match_route;
-pub use vhost::resolve_virtual_host;
+pub use matcher::{MatchRequest, PathCaptures};
+pub use redirect::{redirect_location, RedirectRequest};
+pub use rewrite::{replace_prefix, rewrite_path};
+pub use table::RouteTable;
+pub use vhost::{domain_covers, validate_domain_pattern};
+
+/// Route with the given match rule, rewrite and action, other fields at their defaults
+#[cfg(test)]
+fn test_route(
+    rule: crate::config::RouteMatch,
+    rewrite: Option<crate::config::RouteRewrite>,
+    action: crate::config::RouteAction,
+) -> crate::config::Route {
+    let mut route = serde_json::to_value(action).unwrap();
+    route["match"] = serde_json::to_value(rule).unwrap();
+    route["rewrite"] = serde_json::to_value(rewrite).unwrap();
+    serde_json::from_value(route).unwrap()
+}
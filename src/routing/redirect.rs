@@ -0,0 +1,295 @@
+//! Redirect location module
+//!
+//! Builds the `Location` of a redirect route from the request URL:
+//! scheme, host, port and path are replaced as configured, and the query
+//! string is carried over unless stripped.
+
+use super::matcher::PathCaptures;
+use super::rewrite;
+use crate::config::{RedirectAction, Route};
+use crate::http::uri;
+
+/// Request URL parts a redirect is built from
+pub struct RedirectRequest<'a> {
+    /// Normalized request path
+    pub path: &'a str,
+    /// Raw query string (without '?')
+    pub query: Option<&'a str>,
+    /// Host header (may include a port)
+    pub host: &'a str,
+}
+
+/// Build the `Location` for a redirect route
+///
+/// Without a scheme, host or port redirect the location is a path (relative
+/// to the current origin); otherwise it is an absolute URL.
+pub fn redirect_location(
+    route: &Route,
+    redirect: &RedirectAction,
+    captures: &PathCaptures,
+    request: &RedirectRequest<'_>,
+) -> String {
+    if let Some(target) = &redirect.target {
+        let target = captures.expand(target);
+        return with_query(target, redirect.strip_query, request.query);
+    }
+
+    let (path, query) = if let Some(path_redirect) = &redirect.path_redirect {
+        let expanded = captures.expand(path_redirect);
+        match expanded.split_once('?') {
+            // A query in path_redirect replaces the request's query
+            Some((path, query)) => (uri::encode_location_path(path), Some(query.to_string())),
+            None => (
+                uri::encode_location_path(&expanded),
+                keep_query(redirect, request),
+            ),
+        }
+    } else {
+        let path = if let Some(replacement) = &redirect.prefix_rewrite {
+            rewrite::replace_prefix(request.path, rewrite::route_prefix(route), replacement)
+        } else if let Some(regex_rewrite) = &redirect.regex_rewrite {
+            rewrite::regex_rewrite_path(regex_rewrite, request.path)
+        } else {
+            request.path.to_string()
+        };
+        (
+            uri::encode_location_path(&path),
+            keep_query(redirect, request),
+        )
+    };
+
+    let mut location = origin(redirect, request.host).unwrap_or_default();
+    location.push_str(&path);
+    if let Some(query) = query.filter(|q| !q.is_empty()) {
+        location.push('?');
+        location.push_str(&query);
+    }
+    location
+}
+
+/// The request's query string, unless `strip_query` is set
+fn keep_query(redirect: &RedirectAction, request: &RedirectRequest<'_>) -> Option<String> {
+    if redirect.strip_query {
+        None
+    } else {
+        request.query.map(ToString::to_string)
+    }
+}
+
+/// Append the request's query to a fixed target (unless it has its own)
+fn with_query(mut target: String, strip_query: bool, query: Option<&str>) -> String {
+    match query.filter(|q| !q.is_empty()) {
+        Some(query) if !strip_query && !target.contains('?') => {
+            target.push('?');
+            target.push_str(query);
+            target
+        }
+        _ => target,
+    }
+}
+
+/// `scheme://host[:port]` when the scheme, host or port is redirected
+///
+/// The request's port is kept only when the scheme stays the same, and a
+/// port equal to the scheme's default is omitted.
+fn origin(redirect: &RedirectAction, host_header: &str) -> Option<String> {
+    if redirect.scheme_redirect.is_none()
+        && redirect.host_redirect.is_none()
+        && redirect.port_redirect.is_none()
+    {
+        return None;
+    }
+
+    let (request_host, request_port) = split_host_port(host_header);
+    let scheme = redirect
+        .scheme_redirect
+        .as_deref()
+        .unwrap_or("http")
+        .to_ascii_lowercase();
+    let host = redirect.host_redirect.as_deref().unwrap_or(request_host);
+    let port = redirect
+        .port_redirect
+        .or_else(|| request_port.filter(|_| redirect.scheme_redirect.is_none()));
+
+    let default_port = match scheme.as_str() {
+        "https" => Some(443),
+        "http" => Some(80),
+        _ => None,
+    };
+    Some(match port.filter(|p| Some(*p) != default_port) {
+        Some(port) => format!("{scheme}://{host}:{port}"),
+        None => format!("{scheme}://{host}"),
+    })
+}
+
+/// Split a Host header into host and port (`[::1]:8080` keeps the brackets)
+fn split_host_port(host: &str) -> (&str, Option<u16>) {
+    let port_start = if host.starts_with('[') {
+        host.find("]:").map(|i| i + 1)
+    } else {
+        host.rfind(':')
+    };
+    match port_start {
+        Some(i) => match host[i + 1..].parse::<u16>() {
+            Ok(port) => (&host[..i], Some(port)),
+            Err(_) => (host, None),
+        },
+        None => (host, None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{RegexRewrite, RouteAction, RouteMatch, SafeRegex};
+
+    fn redirect_route(rule: RouteMatch, redirect: RedirectAction) -> Route {
+        crate::routing::test_route(rule, None, RouteAction::Redirect(redirect))
+    }
+
+    fn location(
+        rule: RouteMatch,
+        redirect: &RedirectAction,
+        path: &str,
+        query: Option<&str>,
+        host: &str,
+    ) -> String {
+        let route = redirect_route(rule, redirect.clone());
+        let captures = rule_captures(&route, path);
+        let request = RedirectRequest { path, query, host };
+        redirect_location(&route, redirect, &captures, &request)
+    }
+
+    fn rule_captures(route: &Route, path: &str) -> PathCaptures {
+        route
+            .match_rule
+            .safe_regex
+            .as_ref()
+            .and_then(|regex| PathCaptures::from_regex(regex, path))
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn test_fixed_target_keeps_query() {
+        let redirect = RedirectAction {
+            target: Some("/new".to_string()),
+            ..RedirectAction::default()
+        };
+        let rule = RouteMatch::default();
+        assert_eq!(
+            location(rule.clone(), &redirect, "/old", Some("a=1"), "example.com"),
+            "/new?a=1"
+        );
+        assert_eq!(
+            location(rule, &redirect, "/old", None, "example.com"),
+            "/new"
+        );
+
+        let redirect = RedirectAction {
+            target: Some("/new?b=2".to_string()),
+            ..RedirectAction::default()
+        };
+        assert_eq!(
+            location(RouteMatch::default(), &redirect, "/old", Some("a=1"), "x"),
+            "/new?b=2"
+        );
+    }
+
+    #[test]
+    fn test_https_redirect() {
+        let redirect = RedirectAction {
+            scheme_redirect: Some("https".to_string()),
+            ..RedirectAction::default()
+        };
+        assert_eq!(
+            location(
+                RouteMatch::default(),
+                &redirect,
+                "/cart",
+                Some("id=7"),
+                "shop.example.com:8080"
+            ),
+            "https://shop.example.com/cart?id=7"
+        );
+    }
+
+    #[test]
+    fn test_host_and_port_redirect() {
+        let redirect = RedirectAction {
+            host_redirect: Some("www.example.com".to_string()),
+            strip_query: true,
+            ..RedirectAction::default()
+        };
+        assert_eq!(
+            location(
+                RouteMatch::default(),
+                &redirect,
+                "/a b",
+                Some("x=1"),
+                "example.com:8080"
+            ),
+            "http://www.example.com:8080/a%20b"
+        );
+
+        let redirect = RedirectAction {
+            port_redirect: Some(80),
+            ..RedirectAction::default()
+        };
+        assert_eq!(
+            location(RouteMatch::default(), &redirect, "/", None, "[::1]:8080"),
+            "http://[::1]/"
+        );
+    }
+
+    #[test]
+    fn test_path_redirect_and_rewrites() {
+        let regex_rule = RouteMatch {
+            safe_regex: Some(SafeRegex::new("/item/(\\d+)").unwrap()),
+            ..RouteMatch::default()
+        };
+        let redirect = RedirectAction {
+            path_redirect: Some("/products/$1?ref=old".to_string()),
+            ..RedirectAction::default()
+        };
+        assert_eq!(
+            location(regex_rule, &redirect, "/item/42", Some("a=1"), "x"),
+            "/products/42?ref=old"
+        );
+
+        let prefix_rule = RouteMatch {
+            prefix: Some("/docs".to_string()),
+            ..RouteMatch::default()
+        };
+        let redirect = RedirectAction {
+            prefix_rewrite: Some("/manual".to_string()),
+            ..RedirectAction::default()
+        };
+        assert_eq!(
+            location(prefix_rule, &redirect, "/docs/intro", Some("v=2"), "x"),
+            "/manual/intro?v=2"
+        );
+
+        let redirect = RedirectAction {
+            regex_rewrite: Some(RegexRewrite {
+                pattern: SafeRegex::new("/(\\w+)/(\\w+)").unwrap(),
+                substitution: "/$2/$1".to_string(),
+            }),
+            ..RedirectAction::default()
+        };
+        assert_eq!(
+            location(RouteMatch::default(), &redirect, "/a/b", None, "x"),
+            "/b/a"
+        );
+    }
+
+    #[test]
+    fn test_split_host_port() {
+        assert_eq!(
+            split_host_port("example.com:8080"),
+            ("example.com", Some(8080))
+        );
+        assert_eq!(split_host_port("example.com"), ("example.com", None));
+        assert_eq!(split_host_port("[::1]:443"), ("[::1]", Some(443)));
+        assert_eq!(split_host_port("[::1]"), ("[::1]", None));
+    }
+}
@@ -0,0 +1,206 @@
+//! URL rewrite module
+//!
+//! Computes the path a matched route works with:
+//! - `prefix_rewrite` replaces the matched prefix
+//! - `regex_rewrite` rebuilds the path from capture groups
+//! - `Dir` routes without a rewrite strip the matched prefix, which is the
+//!   same as a `prefix_rewrite` of "/"
+
+use super::matcher::PathCaptures;
+use crate::config::{RegexRewrite, Route, RouteAction, RouteRewrite};
+use crate::http::uri;
+
+/// Get the prefix a route matched on (prefix, exact path or segment prefix)
+///
+/// Regex routes have no prefix: the whole path is kept.
+pub fn route_prefix(route: &Route) -> &str {
+    route
+        .match_rule
+        .prefix
+        .as_deref()
+        .or(route.match_rule.path.as_deref())
+        .or(route.match_rule.path_separated_prefix.as_deref())
+        .unwrap_or("")
+}
+
+/// Path after applying the route's rewrite
+///
+/// `Dir` routes without an explicit path rewrite resolve the request path
+/// relative to the matched prefix. Other routes, and `rematch` routes whose
+/// action is not run, keep the path unchanged.
+pub fn rewrite_path(route: &Route, path: &str) -> String {
+    let rematch = route
+        .rewrite
+        .as_ref()
+        .is_some_and(|rewrite| rewrite.rematch);
+    match route.rewrite.as_ref() {
+        Some(RouteRewrite {
+            prefix_rewrite: Some(replacement),
+            ..
+        }) => replace_prefix(path, route_prefix(route), replacement),
+        Some(RouteRewrite {
+            regex_rewrite: Some(rewrite),
+            ..
+        }) => regex_rewrite_path(rewrite, path),
+        _ if !rematch && matches!(route.action, RouteAction::Dir { .. }) => {
+            replace_prefix(path, route_prefix(route), "/")
+        }
+        _ => path.to_string(),
+    }
+}
+
+/// Rebuild a path from the capture groups of a `regex_rewrite`
+///
+/// Paths the pattern does not match are returned unchanged.
+pub fn regex_rewrite_path(rewrite: &RegexRewrite, path: &str) -> String {
+    PathCaptures::from_regex(&rewrite.pattern, path).map_or_else(
+        || path.to_string(),
+        |captures| uri::normalize_rewritten_path(&captures.expand(&rewrite.substitution)),
+    )
+}
+
+/// Replace `prefix` at the start of `path` with `replacement`
+///
+/// A trailing '/' on the prefix is optional (prefix "/files/" also covers
+/// "/files"), and a '/' at the junction is not doubled. The prefix is only
+/// replaced at a segment boundary: paths that do not start with the prefix,
+/// or continue the last segment (prefix "/static" and "/static-old/x"), are
+/// returned unchanged.
+pub fn replace_prefix(path: &str, prefix: &str, replacement: &str) -> String {
+    let at_boundary = |rest: &&str| rest.is_empty() || rest.starts_with('/');
+    let Some(rest) = path
+        .strip_prefix(prefix)
+        .filter(|rest| prefix.ends_with('/') || at_boundary(rest))
+        .or_else(|| {
+            path.strip_prefix(prefix.trim_end_matches('/'))
+                .filter(at_boundary)
+        })
+    else {
+        return path.to_string();
+    };
+
+    let rewritten = match rest.strip_prefix('/') {
+        Some(tail) if replacement.ends_with('/') => format!("{replacement}{tail}"),
+        _ => format!("{replacement}{rest}"),
+    };
+    uri::normalize_rewritten_path(&rewritten)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{RouteMatch, SafeRegex};
+
+    fn route(rule: RouteMatch, rewrite: Option<RouteRewrite>, action: RouteAction) -> Route {
+        crate::routing::test_route(rule, rewrite, action)
+    }
+
+    fn dir() -> RouteAction {
+        RouteAction::Dir {
+            path: "static".to_string(),
+            autoindex: None,
+            try_files: None,
+        }
+    }
+
+    fn direct() -> RouteAction {
+        RouteAction::Direct {
+            status: 200,
+            body: None,
+            content_type: None,
+        }
+    }
+
+    fn regex_rewrite(pattern: &str, substitution: &str) -> RouteRewrite {
+        serde_json::from_value(serde_json::json!({
+            "regex_rewrite": { "pattern": pattern, "substitution": substitution }
+        }))
+        .unwrap()
+    }
+
+    fn prefix(p: &str) -> RouteMatch {
+        RouteMatch {
+            prefix: Some(p.to_string()),
+            ..RouteMatch::default()
+        }
+    }
+
+    #[test]
+    fn test_replace_prefix() {
+        assert_eq!(replace_prefix("/api/users", "/api", "/v2"), "/v2/users");
+        assert_eq!(replace_prefix("/api", "/api", "/v2"), "/v2");
+        assert_eq!(replace_prefix("/api/users", "/api/", "/"), "/users");
+        assert_eq!(replace_prefix("/api/users", "/api", "/"), "/users");
+        assert_eq!(replace_prefix("/files", "/files/", "/"), "/");
+        assert_eq!(replace_prefix("/apix", "/api", "/v2"), "/apix");
+        assert_eq!(
+            replace_prefix("/static-old/x", "/static", "/"),
+            "/static-old/x"
+        );
+        assert_eq!(
+            replace_prefix("/static-old/x", "/static/", "/"),
+            "/static-old/x"
+        );
+        assert_eq!(replace_prefix("/other", "/api", "/v2"), "/other");
+        assert_eq!(replace_prefix("/a/b", "/", "/"), "/a/b");
+    }
+
+    #[test]
+    fn test_dir_routes_strip_prefix_by_default() {
+        let r = route(prefix("/static"), None, dir());
+        assert_eq!(rewrite_path(&r, "/static/css/app.css"), "/css/app.css");
+        assert_eq!(rewrite_path(&r, "/static"), "/");
+
+        // Non-Dir routes keep the path
+        let r = route(prefix("/static"), None, direct());
+        assert_eq!(rewrite_path(&r, "/static/x"), "/static/x");
+
+        // Regex routes have no prefix to strip
+        let rule = RouteMatch {
+            safe_regex: Some(SafeRegex::new("/img/.*").unwrap()),
+            ..RouteMatch::default()
+        };
+        assert_eq!(
+            rewrite_path(&route(rule, None, dir()), "/img/a.png"),
+            "/img/a.png"
+        );
+    }
+
+    #[test]
+    fn test_prefix_rewrite() {
+        let rewrite = RouteRewrite {
+            prefix_rewrite: Some("/assets/".to_string()),
+            ..RouteRewrite::default()
+        };
+        let r = route(prefix("/static"), Some(rewrite), dir());
+        assert_eq!(rewrite_path(&r, "/static/app.js"), "/assets/app.js");
+
+        // A host-only rematch leaves the path alone, even on a Dir route
+        let rewrite = RouteRewrite {
+            host_rewrite: Some("internal".to_string()),
+            rematch: true,
+            ..RouteRewrite::default()
+        };
+        let r = route(prefix("/static"), Some(rewrite), dir());
+        assert_eq!(rewrite_path(&r, "/static/app.js"), "/static/app.js");
+    }
+
+    #[test]
+    fn test_regex_rewrite() {
+        let rewrite = regex_rewrite("/users/(?P<id>\\d+)/(.*)", "/profiles/${id}/$2");
+        let r = route(prefix("/users"), Some(rewrite), direct());
+        assert_eq!(
+            rewrite_path(&r, "/users/42/avatar.png"),
+            "/profiles/42/avatar.png"
+        );
+        // No match: unchanged
+        assert_eq!(rewrite_path(&r, "/users/abc"), "/users/abc");
+    }
+
+    #[test]
+    fn test_regex_rewrite_cannot_escape_root() {
+        let rewrite = regex_rewrite("/(.*)", "/../../$1");
+        let r = route(RouteMatch::default(), Some(rewrite), direct());
+        assert_eq!(rewrite_path(&r, "/etc/passwd"), "/etc/passwd");
+    }
+}
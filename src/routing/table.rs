@@ -0,0 +1,491 @@
+//! Compiled route table module
+//!
+//! Routes are compiled once when configuration is applied:
+//! - Legacy `custom_routes`: exact-match map + radix trie, longest prefix wins
+//! - xDS virtual host routes: exact-match map + radix trie of prefixes, plus a
+//!   list of routes that must always be checked (regex, match-all). Candidates
+//!   are evaluated in configuration order, so the first matching route still wins.
+//!
+//! The table is stored in `DynamicConfig` and replaced as a whole on updates.
+
+use super::matcher::{matches_route_rule, MatchRequest, RouteMatchResult};
+use super::vhost::DomainIndex;
+use crate::config::{RouteHandler, RoutesConfig, VirtualHost};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Radix (compressed prefix) trie keyed by byte strings
+#[derive(Debug)]
+pub struct RadixTrie<V> {
+    root: TrieNode<V>,
+}
+
+#[derive(Debug)]
+struct TrieNode<V> {
+    /// Edge label leading to this node (empty for the root)
+    label: Vec<u8>,
+    value: Option<V>,
+    /// Children sorted by the first byte of their label
+    children: Vec<Self>,
+}
+
+impl<V> TrieNode<V> {
+    const fn new(label: Vec<u8>) -> Self {
+        Self {
+            label,
+            value: None,
+            children: Vec::new(),
+        }
+    }
+
+    fn child_index(&self, first: u8) -> Result<usize, usize> {
+        self.children
+            .binary_search_by_key(&first, |child| child.label[0])
+    }
+}
+
+impl<V> Default for RadixTrie<V> {
+    fn default() -> Self {
+        Self {
+            root: TrieNode::new(Vec::new()),
+        }
+    }
+}
+
+impl<V> RadixTrie<V> {
+    /// Get the value slot for a key, creating nodes as needed
+    pub fn entry(&mut self, key: &str) -> &mut Option<V> {
+        let mut node = &mut self.root;
+        let mut rest = key.as_bytes();
+
+        while !rest.is_empty() {
+            let index = match node.child_index(rest[0]) {
+                Ok(index) => index,
+                Err(index) => {
+                    node.children.insert(index, TrieNode::new(rest.to_vec()));
+                    return &mut node.children[index].value;
+                }
+            };
+
+            let child = &mut node.children[index];
+            let common = child
+                .label
+                .iter()
+                .zip(rest)
+                .take_while(|(a, b)| a == b)
+                .count();
+
+            // Split the edge so the key ends on (or passes through) a node boundary
+            if common < child.label.len() {
+                let mut tail = std::mem::replace(child, TrieNode::new(rest[..common].to_vec()));
+                tail.label.drain(..common);
+                child.children.push(tail);
+            }
+
+            node = child;
+            rest = &rest[common..];
+        }
+
+        &mut node.value
+    }
+
+    /// Values of all keys that are prefixes of `path`, shortest first
+    pub fn prefixes_of(&self, path: &str) -> Vec<&V> {
+        let mut found = Vec::new();
+        let mut node = &self.root;
+        let mut rest = path.as_bytes();
+
+        loop {
+            if let Some(value) = &node.value {
+                found.push(value);
+            }
+            let Some(&first) = rest.first() else {
+                break;
+            };
+            let Ok(index) = node.child_index(first) else {
+                break;
+            };
+            let child = &node.children[index];
+            if !rest.starts_with(&child.label) {
+                break;
+            }
+            rest = &rest[child.label.len()..];
+            node = child;
+        }
+
+        found
+    }
+
+    /// Value of the longest key that is a prefix of `path`
+    pub fn longest_prefix(&self, path: &str) -> Option<&V> {
+        let mut longest = None;
+        let mut node = &self.root;
+        let mut rest = path.as_bytes();
+
+        loop {
+            longest = node.value.as_ref().or(longest);
+            let Some(&first) = rest.first() else {
+                break;
+            };
+            let Ok(index) = node.child_index(first) else {
+                break;
+            };
+            let child = &node.children[index];
+            if !rest.starts_with(&child.label) {
+                break;
+            }
+            rest = &rest[child.label.len()..];
+            node = child;
+        }
+
+        longest
+    }
+}
+
+/// Route index for one virtual host
+///
+/// Each exact path and prefix keeps the complete candidate list for requests
+/// it is the most specific match of (the always-checked routes and the routes
+/// of all shorter prefixes included), merged in configuration order when the
+/// table is built, so a lookup needs no allocation or sorting.
+#[derive(Debug, Default)]
+struct VhostRouteIndex {
+    /// Exact `path` matches: path -> candidate route indices
+    exact: HashMap<String, Vec<usize>>,
+    /// `prefix` and `path_separated_prefix` matches: prefix -> candidate route indices
+    prefixes: RadixTrie<Vec<usize>>,
+    /// Routes that must always be evaluated (`safe_regex`, no path rule)
+    always: Vec<usize>,
+}
+
+impl VhostRouteIndex {
+    fn build(vhost: &VirtualHost) -> Self {
+        let mut exact: HashMap<String, Vec<usize>> = HashMap::new();
+        let mut prefixes: HashMap<&str, Vec<usize>> = HashMap::new();
+        let mut always = Vec::new();
+        for (i, route) in vhost.routes.iter().enumerate() {
+            let rule = &route.match_rule;
+            // Same priority as `match_path`: path, prefix, regex, segment prefix
+            if let Some(path) = &rule.path {
+                exact.entry(path.clone()).or_default().push(i);
+            } else if let Some(prefix) = &rule.prefix {
+                prefixes.entry(prefix).or_default().push(i);
+            } else if rule.safe_regex.is_some() {
+                always.push(i);
+            } else if let Some(prefix) = &rule.path_separated_prefix {
+                prefixes.entry(prefix).or_default().push(i);
+            } else {
+                always.push(i);
+            }
+        }
+
+        let mut own_prefixes = RadixTrie::default();
+        for (prefix, indices) in &prefixes {
+            *own_prefixes.entry(prefix) = Some(indices.as_slice());
+        }
+        // Candidates of a path: always-checked routes plus the routes of every
+        // prefix of it, in configuration order
+        let merged = |path: &str, own: &[usize]| {
+            let mut candidates = always.clone();
+            candidates.extend_from_slice(own);
+            for indices in own_prefixes.prefixes_of(path) {
+                candidates.extend_from_slice(indices);
+            }
+            candidates.sort_unstable();
+            candidates
+        };
+
+        let mut index = Self::default();
+        for (path, indices) in &exact {
+            index.exact.insert(path.clone(), merged(path, indices));
+        }
+        for prefix in prefixes.keys() {
+            *index.prefixes.entry(prefix) = Some(merged(prefix, &[]));
+        }
+        index.always = always;
+        index
+    }
+
+    /// Indices of routes whose path rule may match, in configuration order
+    fn candidates(&self, path: &str) -> &[usize] {
+        if let Some(exact) = self.exact.get(path) {
+            return exact;
+        }
+        self.prefixes.longest_prefix(path).unwrap_or(&self.always)
+    }
+}
+
+/// Compiled routing configuration (legacy routes + virtual hosts)
+#[derive(Debug)]
+pub struct RouteTable {
+    pub routes: Arc<RoutesConfig>,
+    pub virtual_hosts: Arc<Vec<VirtualHost>>,
+    /// Legacy prefix routes: prefix -> key in `custom_routes`
+    legacy_prefixes: RadixTrie<String>,
+    /// Domain lookup for `virtual_hosts`
+    domains: DomainIndex,
+    /// Route indexes aligned with `virtual_hosts`
+    vhost_indexes: Vec<VhostRouteIndex>,
+}
+
+impl RouteTable {
+    /// Compile legacy routes and virtual hosts
+    pub fn build(routes: &Arc<RoutesConfig>, virtual_hosts: &Arc<Vec<VirtualHost>>) -> Self {
+        let mut legacy_prefixes = RadixTrie::default();
+        for prefix in routes.custom_routes.keys() {
+            *legacy_prefixes.entry(prefix) = Some(prefix.clone());
+        }
+
+        Self {
+            routes: Arc::clone(routes),
+            virtual_hosts: Arc::clone(virtual_hosts),
+            legacy_prefixes,
+            domains: DomainIndex::build(virtual_hosts),
+            vhost_indexes: virtual_hosts.iter().map(VhostRouteIndex::build).collect(),
+        }
+    }
+
+    /// Match a legacy route: exact match first, then the longest prefix
+    ///
+    /// Returns the matched route key (used as prefix) and its handler.
+    pub fn match_legacy(&self, path: &str) -> Option<(&str, &RouteHandler)> {
+        if let Some((key, handler)) = self.routes.custom_routes.get_key_value(path) {
+            return Some((key, handler));
+        }
+        let key = self.legacy_prefixes.longest_prefix(path)?;
+        let handler = self.routes.custom_routes.get(key)?;
+        Some((key, handler))
+    }
+
+    /// Resolve the virtual host for a `Host` header, returning its index
+    pub fn resolve_virtual_host(&self, host: &str) -> Option<usize> {
+        self.domains.resolve_host(host)
+    }
+
+    /// Match a route within a virtual host (first matching route in order wins)
+    pub fn match_vhost_route(
+        &self,
+        vhost_index: usize,
+        request: &MatchRequest<'_>,
+    ) -> Option<RouteMatchResult<'_>> {
+        let routes = &self.virtual_hosts.get(vhost_index)?.routes;
+        self.vhost_indexes[vhost_index]
+            .candidates(request.path)
+            .iter()
+            .find_map(|&i| {
+                let route = &routes[i];
+                let captures = matches_route_rule(&route.match_rule, request)?;
+                Some(RouteMatchResult {
+                    route,
+                    index: i,
+                    captures,
+                })
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{HealthConfig, Route, RouteAction, RouteMatch, SafeRegex};
+    use hyper::{HeaderMap, Method};
+    use std::time::Instant;
+
+    fn direct_route(rule: RouteMatch, status: u16) -> Route {
+        let action = RouteAction::Direct {
+            status,
+            body: None,
+            content_type: None,
+        };
+        crate::routing::test_route(rule, None, action)
+    }
+
+    fn prefix(p: &str) -> RouteMatch {
+        RouteMatch {
+            prefix: Some(p.to_string()),
+            ..RouteMatch::default()
+        }
+    }
+
+    fn table_with(routes: Vec<Route>, custom_routes: &[&str]) -> RouteTable {
+        let legacy = RoutesConfig {
+            index_files: Vec::new(),
+            custom_routes: custom_routes
+                .iter()
+                .map(|p| {
+                    (
+                        (*p).to_string(),
+                        RouteHandler::Redirect {
+                            target: (*p).to_string(),
+                        },
+                    )
+                })
+                .collect(),
+            health: HealthConfig::default(),
+        };
+        let mut vhost: VirtualHost =
+            serde_json::from_value(serde_json::json!({"name": "test", "domains": ["*"]})).unwrap();
+        vhost.routes = routes;
+        RouteTable::build(&Arc::new(legacy), &Arc::new(vec![vhost]))
+    }
+
+    fn status_for(table: &RouteTable, path: &str) -> Option<u16> {
+        let headers = HeaderMap::new();
+        let request = MatchRequest {
+            path,
+            method: &Method::GET,
+            query: None,
+            headers: &headers,
+        };
+        let matched = table.match_vhost_route(0, &request)?;
+        match matched.route.action {
+            RouteAction::Direct { status, .. } => Some(status),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_radix_trie_prefixes() {
+        let mut trie = RadixTrie::default();
+        for key in ["/api", "/api/v1", "/apple", "/", "/api/v1/users"] {
+            *trie.entry(key) = Some(key);
+        }
+        assert_eq!(
+            trie.prefixes_of("/api/v1/users/42"),
+            [&"/", &"/api", &"/api/v1", &"/api/v1/users"]
+        );
+        assert_eq!(trie.longest_prefix("/apples"), Some(&"/apple"));
+        assert_eq!(trie.longest_prefix("/ap"), Some(&"/"));
+        assert_eq!(trie.longest_prefix("x"), None);
+    }
+
+    #[test]
+    fn test_legacy_longest_prefix_wins() {
+        let table = table_with(Vec::new(), &["/", "/static", "/static/images", "/about"]);
+        assert_eq!(table.match_legacy("/about").map(|m| m.0), Some("/about"));
+        assert_eq!(
+            table.match_legacy("/static/images/a.png").map(|m| m.0),
+            Some("/static/images")
+        );
+        assert_eq!(
+            table.match_legacy("/static/css/a.css").map(|m| m.0),
+            Some("/static")
+        );
+        assert_eq!(table.match_legacy("/other").map(|m| m.0), Some("/"));
+    }
+
+    #[test]
+    fn test_vhost_routes_keep_order() {
+        let table = table_with(
+            vec![
+                direct_route(prefix("/api"), 201),
+                direct_route(prefix("/api/v1"), 202),
+                direct_route(
+                    RouteMatch {
+                        safe_regex: Some(SafeRegex::new("/users/[0-9]+").unwrap()),
+                        ..RouteMatch::default()
+                    },
+                    203,
+                ),
+                direct_route(
+                    RouteMatch {
+                        path: Some("/exact".to_string()),
+                        ..RouteMatch::default()
+                    },
+                    204,
+                ),
+                direct_route(
+                    RouteMatch {
+                        path_separated_prefix: Some("/docs".to_string()),
+                        ..RouteMatch::default()
+                    },
+                    205,
+                ),
+                direct_route(RouteMatch::default(), 299),
+            ],
+            &[],
+        );
+
+        // Earlier (shorter) prefix wins because xDS routes are ordered
+        assert_eq!(status_for(&table, "/api/v1/users"), Some(201));
+        assert_eq!(status_for(&table, "/users/42"), Some(203));
+        assert_eq!(status_for(&table, "/exact"), Some(204));
+        assert_eq!(status_for(&table, "/exact/more"), Some(299));
+        assert_eq!(status_for(&table, "/docs/guide"), Some(205));
+        assert_eq!(status_for(&table, "/docsx"), Some(299));
+    }
+
+    #[test]
+    fn test_vhost_candidates_precomputed() {
+        let exact = |path: &str| RouteMatch {
+            path: Some(path.to_string()),
+            ..RouteMatch::default()
+        };
+        let table = table_with(
+            vec![
+                direct_route(prefix("/api/v1"), 200),
+                direct_route(RouteMatch::default(), 200),
+                direct_route(exact("/api/v1/users"), 200),
+                direct_route(prefix("/api"), 200),
+                direct_route(prefix("/"), 200),
+            ],
+            &[],
+        );
+        let index = &table.vhost_indexes[0];
+
+        assert_eq!(index.candidates("/api/v1/users"), [0, 1, 2, 3, 4]);
+        assert_eq!(index.candidates("/api/v1/items"), [0, 1, 3, 4]);
+        assert_eq!(index.candidates("/api/v2"), [1, 3, 4]);
+        assert_eq!(index.candidates("/other"), [1, 4]);
+    }
+
+    /// Compare compiled lookups with a linear scan
+    ///
+    /// Run with `cargo test --release bench_ -- --ignored --nocapture`.
+    #[test]
+    #[ignore = "benchmark"]
+    fn bench_route_table_vs_linear_scan() {
+        const ROUTES: usize = 5_000;
+        const LOOKUPS: usize = 20_000;
+
+        let routes: Vec<Route> = (0..ROUTES)
+            .map(|i| direct_route(prefix(&format!("/service-{i}/api")), 200))
+            .collect();
+        let table = table_with(routes, &[]);
+        let vhost = &table.virtual_hosts[0];
+        let headers = HeaderMap::new();
+        let paths: Vec<String> = (0..LOOKUPS)
+            .map(|i| format!("/service-{}/api/items/{i}", (i * 7919) % ROUTES))
+            .collect();
+
+        let start = Instant::now();
+        for path in &paths {
+            let request = MatchRequest {
+                path,
+                method: &Method::GET,
+                query: None,
+                headers: &headers,
+            };
+            assert!(super::super::matcher::match_route(&request, &vhost.routes).is_some());
+        }
+        let linear = start.elapsed();
+
+        let start = Instant::now();
+        for path in &paths {
+            let request = MatchRequest {
+                path,
+                method: &Method::GET,
+                query: None,
+                headers: &headers,
+            };
+            assert!(table.match_vhost_route(0, &request).is_some());
+        }
+        let compiled = start.elapsed();
+
+        println!(
+            "{ROUTES} routes, {LOOKUPS} lookups: linear {linear:?}, compiled {compiled:?} ({:.1}x)",
+            linear.as_secs_f64() / compiled.as_secs_f64()
+        );
+        assert!(compiled < linear);
+    }
+}
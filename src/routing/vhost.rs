@@ -1,92 +1,181 @@
 //! Virtual host matching module
 //!
 //! Implements domain matching logic for xDS `VirtualHost`.
-//! Supports exact match, wildcard prefix (*.), and catch-all (*).
+//! Supports exact match, wildcard suffix (`*.example.com`), wildcard prefix
+//! (`api.*`) and catch-all (`*`).
+//!
+//! Domains are compiled into a `DomainIndex` when configuration is applied.
+//! The index works on bare server names, so it serves both the HTTP `Host`
+//! header (after stripping the port) and TLS SNI lookups.
 
 use crate::config::VirtualHost;
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+/// Trie over domain labels (reversed for suffix wildcards)
+#[derive(Debug, Default)]
+struct LabelTrie {
+    value: Option<usize>,
+    children: HashMap<String, Self>,
+}
 
-/// Resolve the matching virtual host for a given Host header
+impl LabelTrie {
+    /// Insert a pattern; the first virtual host registering a pattern keeps it
+    fn insert<'a>(&mut self, labels: impl Iterator<Item = &'a str>, vhost: usize) {
+        let node = labels.fold(self, |node, label| {
+            node.children.entry(label.to_string()).or_default()
+        });
+        node.value.get_or_insert(vhost);
+    }
+
+    /// Deepest value along the label path
+    ///
+    /// With `require_remaining`, a value only counts if at least one label
+    /// is left after it (the wildcard must cover something).
+    fn longest_match<'a>(
+        &self,
+        mut labels: impl Iterator<Item = &'a str>,
+        require_remaining: bool,
+    ) -> Option<usize> {
+        let mut node = self;
+        let mut best = None;
+        loop {
+            let next = labels.next();
+            if let Some(value) = node.value {
+                if next.is_some() || !require_remaining {
+                    best = Some(value);
+                }
+            }
+            match next.and_then(|label| node.children.get(label)) {
+                Some(child) => node = child,
+                None => return best,
+            }
+        }
+    }
+}
+
+/// Precompiled domain lookup for a list of virtual hosts
 ///
 /// Matching priority:
 /// 1. Exact domain match ("api.example.com")
-/// 2. Wildcard suffix match ("*.example.com")
-/// 3. Catch-all ("*")
+/// 2. Wildcard suffix match ("*.example.com"), longest suffix first.
+///    Also matches the bare domain ("example.com").
+/// 3. Wildcard prefix match ("api.*"), longest prefix first
+/// 4. Catch-all ("*")
 ///
-/// Returns None if no virtual host matches.
-pub fn resolve_virtual_host<'a>(
-    host: &str,
-    virtual_hosts: &'a [VirtualHost],
-) -> Option<&'a VirtualHost> {
-    // Strip port from host if present (e.g., "example.com:8080" -> "example.com")
-    let host = host.split(':').next().unwrap_or(host);
-
-    // First pass: look for exact match
-    for vhost in virtual_hosts {
-        for domain in &vhost.domains {
-            if domain == host {
-                return Some(vhost);
+/// Domains are case-insensitive. When several virtual hosts declare the same
+/// pattern, the first one wins.
+#[derive(Debug, Default)]
+pub struct DomainIndex {
+    exact: HashMap<String, usize>,
+    suffixes: LabelTrie,
+    prefixes: LabelTrie,
+    catch_all: Option<usize>,
+}
+
+impl DomainIndex {
+    /// Compile the domains of all virtual hosts (values are indices into `virtual_hosts`)
+    pub fn build(virtual_hosts: &[VirtualHost]) -> Self {
+        let mut index = Self::default();
+        for (i, vhost) in virtual_hosts.iter().enumerate() {
+            for domain in &vhost.domains {
+                let domain = domain.to_ascii_lowercase();
+                if domain == "*" {
+                    index.catch_all.get_or_insert(i);
+                } else if let Some(suffix) = domain.strip_prefix("*.") {
+                    index.suffixes.insert(suffix.rsplit('.'), i);
+                } else if let Some(prefix) = domain.strip_suffix(".*") {
+                    index.prefixes.insert(prefix.split('.'), i);
+                } else {
+                    index.exact.entry(domain).or_insert(i);
+                }
             }
         }
+        index
     }
 
-    // Second pass: look for wildcard match
-    for vhost in virtual_hosts {
-        for domain in &vhost.domains {
-            if domain.starts_with("*.") && match_wildcard_domain(domain, host) {
-                return Some(vhost);
-            }
-        }
+    /// Resolve a `Host` header value (port is ignored)
+    pub fn resolve_host(&self, host: &str) -> Option<usize> {
+        self.resolve(strip_port(host))
     }
 
-    // Third pass: look for catch-all
-    virtual_hosts
-        .iter()
-        .find(|vhost| vhost.domains.iter().any(|d| d == "*"))
+    /// Resolve a bare server name (e.g. TLS SNI)
+    pub fn resolve(&self, name: &str) -> Option<usize> {
+        let name = normalize_name(name);
+        let name = name.as_ref();
+
+        self.exact
+            .get(name)
+            .copied()
+            .or_else(|| self.suffixes.longest_match(name.rsplit('.'), false))
+            .or_else(|| self.prefixes.longest_match(name.split('.'), true))
+            .or(self.catch_all)
+    }
 }
 
-/// Match a domain against a pattern
+/// Check that a domain pattern is supported by `DomainIndex`
 ///
-/// Supports:
-/// - Exact match: "api.example.com" matches "api.example.com"
-/// - Wildcard prefix: "*.example.com" matches "api.example.com", "www.example.com"
-/// - Catch-all: "*" matches any domain
-#[allow(dead_code)] // Used in tests and may be useful for external callers
-pub fn match_domain(pattern: &str, host: &str) -> bool {
-    // Strip port from host
-    let host = host.split(':').next().unwrap_or(host);
-
-    if pattern == "*" {
-        return true;
+/// `*` may only appear alone, as a leading `*.` label or as a trailing `.*` label.
+pub fn validate_domain_pattern(domain: &str) -> Result<(), String> {
+    if domain == "*" {
+        return Ok(());
     }
+    let rest = domain
+        .strip_prefix("*.")
+        .or_else(|| domain.strip_suffix(".*"))
+        .unwrap_or(domain);
 
-    if pattern == host {
-        return true;
+    if rest.is_empty() || rest.contains('*') || rest.split('.').any(str::is_empty) {
+        return Err(format!(
+            "unsupported domain pattern '{domain}' (use 'example.com', '*.example.com', 'api.*' or '*')"
+        ));
     }
+    Ok(())
+}
 
-    if pattern.starts_with("*.") {
-        return match_wildcard_domain(pattern, host);
+/// Strip the port from a `Host` header value (`example.com:8080`, `[::1]:8080`)
+fn strip_port(host: &str) -> &str {
+    if let Some(rest) = host.strip_prefix('[') {
+        return rest.split(']').next().unwrap_or(rest);
     }
-
-    false
+    host.split(':').next().unwrap_or(host)
 }
 
-/// Match wildcard domain pattern (*.example.com)
-fn match_wildcard_domain(pattern: &str, host: &str) -> bool {
-    // "*.example.com" should match:
-    // - "api.example.com" (one level)
-    // - "www.api.example.com" (multiple levels)
-    // - "example.com" (the domain itself, without subdomain)
-
-    let suffix = &pattern[1..]; // ".example.com"
+/// Whether a domain pattern covers a domain (or a narrower domain pattern)
+///
+/// Follows the `DomainIndex` rules: case-insensitive, a trailing dot and a
+/// port are ignored, `*.example.com` also covers `example.com`, and `api.*`
+/// needs at least one label after the prefix. Every host a covered pattern
+/// matches is then matched by `pattern` as well.
+pub fn domain_covers(pattern: &str, domain: &str) -> bool {
+    let pattern = normalize_name(pattern);
+    let domain = normalize_name(strip_port(domain));
 
-    // Check if host ends with the suffix
-    if host.ends_with(suffix) {
+    if pattern == "*" || pattern == domain {
         return true;
     }
+    if let Some(suffix) = pattern.strip_prefix("*.") {
+        return domain
+            .strip_suffix(suffix)
+            .is_some_and(|rest| rest.is_empty() || rest.ends_with('.'));
+    }
+    if let Some(prefix) = pattern.strip_suffix(".*") {
+        return domain
+            .strip_prefix(prefix)
+            .and_then(|rest| rest.strip_prefix('.'))
+            .is_some_and(|rest| !rest.is_empty());
+    }
+    false
+}
 
-    // Also match the bare domain (e.g., "*.example.com" matches "example.com")
-    let bare_domain = &pattern[2..]; // "example.com"
-    host == bare_domain
+/// Lowercase a server name and drop a trailing dot
+fn normalize_name(name: &str) -> Cow<'_, str> {
+    let name = name.strip_suffix('.').unwrap_or(name);
+    if name.bytes().any(|b| b.is_ascii_uppercase()) {
+        Cow::Owned(name.to_ascii_lowercase())
+    } else {
+        Cow::Borrowed(name)
+    }
 }
 
 #[cfg(test)]
@@ -94,64 +183,124 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_match_domain_exact() {
-        assert!(match_domain("api.example.com", "api.example.com"));
-        assert!(!match_domain("api.example.com", "www.example.com"));
+    fn test_domain_covers_exact() {
+        assert!(domain_covers("api.example.com", "api.example.com"));
+        assert!(!domain_covers("api.example.com", "www.example.com"));
+        assert!(domain_covers("API.example.com", "api.EXAMPLE.com."));
     }
 
     #[test]
-    fn test_match_domain_wildcard() {
-        assert!(match_domain("*.example.com", "api.example.com"));
-        assert!(match_domain("*.example.com", "www.example.com"));
-        assert!(match_domain("*.example.com", "example.com"));
-        assert!(!match_domain("*.example.com", "api.other.com"));
+    fn test_domain_covers_wildcard_suffix() {
+        assert!(domain_covers("*.example.com", "api.example.com"));
+        assert!(domain_covers("*.example.com", "www.example.com"));
+        assert!(domain_covers("*.example.com", "example.com"));
+        assert!(domain_covers("*.example.com", "API.Example.com"));
+        assert!(domain_covers("*.example.com", "*.api.example.com"));
+        assert!(!domain_covers("*.example.com", "api.other.com"));
+        assert!(!domain_covers("*.example.com", "badexample.com"));
+        assert!(!domain_covers("*.example.com", "*"));
+        assert!(!domain_covers("*.example.com", "api.*"));
     }
 
     #[test]
-    fn test_match_domain_catch_all() {
-        assert!(match_domain("*", "anything.com"));
-        assert!(match_domain("*", "api.example.com"));
+    fn test_domain_covers_wildcard_prefix() {
+        assert!(domain_covers("api.*", "api.example.com"));
+        assert!(domain_covers("api.*", "API.example.org"));
+        assert!(domain_covers("api.*", "api.*"));
+        assert!(domain_covers("api.*", "api.v2.*"));
+        assert!(!domain_covers("api.*", "api"));
+        assert!(!domain_covers("api.*", "apix.example.com"));
+        assert!(!domain_covers("api.*", "*.example.com"));
     }
 
     #[test]
-    fn test_match_domain_with_port() {
-        assert!(match_domain("example.com", "example.com:8080"));
-        assert!(match_domain("*.example.com", "api.example.com:8080"));
+    fn test_domain_covers_catch_all() {
+        assert!(domain_covers("*", "anything.com"));
+        assert!(domain_covers("*", "api.*"));
+        assert!(!domain_covers("anything.com", "*"));
+    }
+
+    #[test]
+    fn test_domain_covers_with_port() {
+        assert!(domain_covers("example.com", "example.com:8080"));
+        assert!(domain_covers("*.example.com", "api.example.com:8080"));
+        assert!(domain_covers("::1", "[::1]:8080"));
+    }
+
+    fn vhost(name: &str, domains: &[&str]) -> VirtualHost {
+        serde_json::from_value(serde_json::json!({"name": name, "domains": domains})).unwrap()
+    }
+
+    fn resolve_name<'a>(vhosts: &'a [VirtualHost], host: &str) -> Option<&'a str> {
+        DomainIndex::build(vhosts)
+            .resolve_host(host)
+            .map(|i| vhosts[i].name.as_str())
     }
 
     #[test]
     fn test_resolve_virtual_host_priority() {
         let vhosts = vec![
-            VirtualHost {
-                name: "catch-all".to_string(),
-                domains: vec!["*".to_string()],
-                routes: vec![],
-                index_files: None,
-            },
-            VirtualHost {
-                name: "wildcard".to_string(),
-                domains: vec!["*.example.com".to_string()],
-                routes: vec![],
-                index_files: None,
-            },
-            VirtualHost {
-                name: "exact".to_string(),
-                domains: vec!["api.example.com".to_string()],
-                routes: vec![],
-                index_files: None,
-            },
+            vhost("catch-all", &["*"]),
+            vhost("wildcard", &["*.example.com"]),
+            vhost("exact", &["api.example.com"]),
         ];
 
         // Exact match takes priority
-        let result = resolve_virtual_host("api.example.com", &vhosts);
-        assert_eq!(result.unwrap().name, "exact");
+        assert_eq!(resolve_name(&vhosts, "api.example.com"), Some("exact"));
 
         // Wildcard matches when no exact
-        let result = resolve_virtual_host("www.example.com", &vhosts);
-        assert_eq!(result.unwrap().name, "wildcard");
+        assert_eq!(resolve_name(&vhosts, "www.example.com"), Some("wildcard"));
 
         // Catch-all for unknown hosts
-        let result = resolve_virtual_host("other.com", &vhosts);
-        assert_eq!(result.unwrap().name, "catch-all");
+        assert_eq!(resolve_name(&vhosts, "other.com"), Some("catch-all"));
+    }
+
+    #[test]
+    fn test_domain_index_wildcards() {
+        let vhosts = vec![
+            vhost("short-suffix", &["*.com"]),
+            vhost("long-suffix", &["*.example.com"]),
+            vhost("prefix", &["api.*"]),
+            vhost("long-prefix", &["api.v2.*"]),
+        ];
+
+        // Longest suffix wins, regardless of order
+        assert_eq!(
+            resolve_name(&vhosts, "www.example.com"),
+            Some("long-suffix")
+        );
+        assert_eq!(resolve_name(&vhosts, "example.com"), Some("long-suffix"));
+        assert_eq!(resolve_name(&vhosts, "other.com"), Some("short-suffix"));
+        // Suffix wildcards beat prefix wildcards
+        assert_eq!(
+            resolve_name(&vhosts, "api.example.com"),
+            Some("long-suffix")
+        );
+        assert_eq!(resolve_name(&vhosts, "api.internal"), Some("prefix"));
+        assert_eq!(
+            resolve_name(&vhosts, "api.v2.internal"),
+            Some("long-prefix")
+        );
+        // Prefix wildcard must cover at least one label
+        assert_eq!(resolve_name(&vhosts, "api"), None);
+    }
+
+    #[test]
+    fn test_domain_index_host_normalization() {
+        let vhosts = vec![vhost("exact", &["API.Example.com"]), vhost("v6", &["::1"])];
+        assert_eq!(resolve_name(&vhosts, "api.example.COM:8443"), Some("exact"));
+        assert_eq!(resolve_name(&vhosts, "api.example.com."), Some("exact"));
+        assert_eq!(resolve_name(&vhosts, "[::1]:8080"), Some("v6"));
+        assert_eq!(resolve_name(&vhosts, "unknown"), None);
+    }
+
+    #[test]
+    fn test_validate_domain_pattern() {
+        for ok in ["*", "example.com", "*.example.com", "api.*", "localhost"] {
+            assert!(validate_domain_pattern(ok).is_ok(), "{ok}");
+        }
+        for bad in ["", "a.*.com", "*example.com", "api*", "*.*", "a..b"] {
+            assert!(validate_domain_pattern(bad).is_err(), "{bad}");
+        }
     }
 }
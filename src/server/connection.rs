@@ -1,16 +1,23 @@
 // Connection handling module
 // Handles accepting and serving individual TCP connections
 
+use http_body_util::Either;
+use hyper::body::Body;
 use hyper::server::conn::http1;
 use hyper::service::service_fn;
 use hyper_util::rt::TokioIo;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
+use tokio::io::{AsyncRead, AsyncWrite};
 
 use crate::api;
 use crate::config;
+use crate::config::OverloadPolicy;
 use crate::handler;
 use crate::logger;
+use crate::server::overload::{self, LimitExceeded};
+use crate::server::timeout::{ConnectionTimers, TimedBody, TimedStream};
+use crate::server::tls::{self, ClientCertificate};
 
 /// Accept and process a connection, checking limits and logging.
 ///
@@ -32,27 +39,31 @@ pub fn accept_connection(
     log_prefix: &str,
     is_api_server: bool,
 ) {
-    // Increment counter first, then check limit (prevents race condition)
-    let prev_count = conn_counter.fetch_add(1, Ordering::SeqCst);
-
-    // Check connection limit if requested
-    if check_limits {
-        if let Some(max_conn) = state.config.performance.max_connections {
-            if prev_count >= usize::try_from(max_conn).unwrap_or(usize::MAX) {
-                // Exceeded limit: rollback counter and reject
-                // Note: stream is automatically dropped when function returns
-                conn_counter.fetch_sub(1, Ordering::SeqCst);
-                logger::log_warning(&format!(
-                    "Max connections reached: {prev_count}/{max_conn}. Connection rejected."
-                ));
-                return;
-            }
-        }
+    // Current configuration snapshot (lock-free)
+    let config = state.snapshot();
+
+    // Take a connection slot (limits are checked for the main server only)
+    let admitted = if check_limits {
+        state
+            .connection_limiter
+            .admit(conn_counter, peer_addr.ip(), &config.performance)
+    } else {
+        conn_counter.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    };
+    if let Err(limit) = admitted {
+        apply_overload_policy(
+            stream,
+            peer_addr,
+            state,
+            conn_counter,
+            is_api_server,
+            &limit,
+        );
+        return;
     }
 
-    // Check if access logging is enabled (lock-free)
-    let access_log = state.cached_access_log.load(Ordering::Relaxed);
-    if access_log {
+    if config.logging.access_log {
         if log_prefix.is_empty() {
             logger::log_connection_accepted(&peer_addr);
         } else {
@@ -65,18 +76,65 @@ pub fn accept_connection(
         stream,
         Arc::clone(state),
         Arc::clone(conn_counter),
+        check_limits,
         is_api_server,
         peer_addr,
     );
 }
 
+/// Handle a connection over `max_connections` or `max_connections_per_ip`
+/// according to `performance.overload.policy`.
+fn apply_overload_policy(
+    stream: tokio::net::TcpStream,
+    peer_addr: std::net::SocketAddr,
+    state: &Arc<config::AppState>,
+    conn_counter: &Arc<AtomicUsize>,
+    is_api_server: bool,
+    limit: &LimitExceeded,
+) {
+    let performance = &state.snapshot().performance;
+    match performance.overload.policy {
+        OverloadPolicy::Reject => {
+            // Note: stream is automatically dropped when function returns
+            logger::log_warning(&format!("{limit}. Connection rejected."));
+            state.connection_limiter.record_closed();
+        }
+        OverloadPolicy::ServiceUnavailable => {
+            logger::log_warning(&format!("{limit}. Responding 503."));
+            overload::serve_unavailable(stream, state);
+        }
+        OverloadPolicy::Queue => {
+            logger::log_warning(&format!("{limit}. Connection queued."));
+            let state = Arc::clone(state);
+            let conn_counter = Arc::clone(conn_counter);
+            tokio::spawn(async move {
+                let performance = state.snapshot().performance.clone();
+                let admitted = state
+                    .connection_limiter
+                    .wait_for_slot(&conn_counter, peer_addr.ip(), &performance)
+                    .await;
+                if admitted {
+                    handle_connection(stream, state, conn_counter, true, is_api_server, peer_addr);
+                } else {
+                    logger::log_warning(&format!(
+                        "No connection slot for {peer_addr} (queue full or timed out). Responding 503."
+                    ));
+                    overload::serve_unavailable(stream, &state);
+                }
+            });
+        }
+    }
+}
+
 /// Handle a single connection in a spawned task.
 ///
 /// This function:
-/// 1. Wraps the TCP stream in `TokioIo`
-/// 2. Configures HTTP/1.1 connection settings (keep-alive, timeouts)
+/// 1. Wraps the TCP stream in `TimedStream` (and TLS when `api_auth.tls` is set
+///    for the API server; the handshake must finish within `header_read_timeout`)
+/// 2. Configures HTTP/1.1 connection settings (keep-alive)
 /// 3. Serves the connection with the request handler
-/// 4. Applies timeout to the connection
+/// 4. Closes the connection when the deadline of its current phase expires
+///    (header read, request body read, response write or keep-alive idle)
 /// 5. Decrements connection counter when done
 ///
 /// # Arguments
@@ -84,64 +142,243 @@ pub fn accept_connection(
 /// * `stream` - The TCP stream to handle
 /// * `state` - Shared application state
 /// * `conn_counter` - Active connection counter to decrement when done
+/// * `limited` - Whether the connection holds a `ConnectionLimiter` slot to release when done
 /// * `is_api_server` - Whether this is handling API management requests
 /// * `peer_addr` - The peer's socket address for logging
 fn handle_connection(
     stream: tokio::net::TcpStream,
     state: Arc<config::AppState>,
     conn_counter: Arc<AtomicUsize>,
+    limited: bool,
     is_api_server: bool,
     peer_addr: std::net::SocketAddr,
 ) {
-    tokio::task::spawn_local(async move {
-        let io = TokioIo::new(stream);
-
-        // Read performance configuration (extract before move)
-        let keep_alive_timeout = state.config.performance.keep_alive_timeout;
-        let read_timeout = state.config.performance.read_timeout;
-        let write_timeout = state.config.performance.write_timeout;
-        let timeout_duration = std::time::Duration::from_secs(std::cmp::max(
-            read_timeout,
-            write_timeout,
-        ));
-
-        // Build HTTP/1 connection with keep-alive support
-        let mut builder = http1::Builder::new();
-        if keep_alive_timeout > 0 {
-            builder.keep_alive(true);
-        }
+    tokio::spawn(async move {
+        let timers = ConnectionTimers::new();
+        let stream = TimedStream::new(stream, Arc::clone(&timers));
+        let connection = ConnectionInfo {
+            state: Arc::clone(&state),
+            timers,
+            is_api_server,
+            peer_addr,
+        };
 
-        // Serve connection
-        let conn = builder.serve_connection(
-            io,
-            service_fn(move |req| {
-                let state_clone = Arc::clone(&state);
-                let addr = peer_addr;
-                async move {
-                    if is_api_server {
-                        // API server handles only API requests
-                        api::handle_api_config(req, state_clone).await
-                    } else {
-                        // Application server handles all non-API requests
-                        handler::handle_request(req, state_clone, addr).await
+        let tls = if is_api_server {
+            state.api_auth.tls_acceptor()
+        } else {
+            None
+        };
+        if let Some(acceptor) = tls {
+            tokio::select! {
+                result = acceptor.accept(stream) => match result {
+                    Ok(stream) => {
+                        let client_cert = tls::client_certificate(&stream);
+                        serve(stream, &connection, client_cert).await;
+                    }
+                    Err(e) => {
+                        logger::log_api_error(&format!("TLS handshake with {peer_addr} failed: {e}"));
                     }
+                },
+                reason = connection.timers.expired(&state) => {
+                    let limit = reason.limit(&state.snapshot().performance);
+                    logger::log_connection_timeout(&peer_addr, &reason.to_string(), limit);
                 }
-            }),
-        );
-
-        // Apply timeout and handle result
-        let timeout_secs = timeout_duration.as_secs();
-        match tokio::time::timeout(timeout_duration, conn).await {
-            Ok(Ok(())) => {}
-            Ok(Err(err)) => logger::log_connection_error(&err),
-            Err(_) => {
-                logger::log_warning(&format!(
-                    "Connection timeout after {timeout_secs}s (peer: {peer_addr}, api_server: {is_api_server}, keep_alive: {keep_alive_timeout}s, read_timeout: {read_timeout}s, write_timeout: {write_timeout}s)"
-                ));
             }
+        } else {
+            serve(stream, &connection, None).await;
         }
 
-        // Decrement active connection counter
+        // Decrement active connection counter and wake queued connections
         conn_counter.fetch_sub(1, Ordering::SeqCst);
+        if limited {
+            state.connection_limiter.release(peer_addr.ip());
+        }
     });
 }
+
+/// Per-connection values shared by every request of a connection
+struct ConnectionInfo {
+    state: Arc<config::AppState>,
+    timers: Arc<ConnectionTimers>,
+    is_api_server: bool,
+    peer_addr: std::net::SocketAddr,
+}
+
+/// Serve HTTP/1 on `io` until the connection ends or a phase deadline expires
+///
+/// `client_cert` (verified TLS client certificate) is added to each request's extensions.
+async fn serve<I>(io: I, connection: &ConnectionInfo, client_cert: Option<ClientCertificate>)
+where
+    I: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let ConnectionInfo {
+        state,
+        timers,
+        is_api_server,
+        peer_addr,
+    } = connection;
+    let (is_api_server, peer_addr) = (*is_api_server, *peer_addr);
+
+    // Build HTTP/1 connection (keep_alive_timeout = 0 disables keep-alive)
+    let keep_alive_timeout = state.snapshot().performance.keep_alive_timeout;
+    let mut builder = http1::Builder::new();
+    builder.keep_alive(keep_alive_timeout > 0);
+
+    // Serve connection
+    let service_state = Arc::clone(state);
+    let service_timers = Arc::clone(timers);
+    let conn = builder.serve_connection(
+        TokioIo::new(io),
+        service_fn(move |mut req: hyper::Request<hyper::body::Incoming>| {
+            let state_clone = Arc::clone(&service_state);
+            let timers = Arc::clone(&service_timers);
+            let addr = peer_addr;
+            if let Some(cert) = &client_cert {
+                req.extensions_mut().insert(cert.clone());
+            }
+            async move {
+                timers.request_started(!req.body().is_end_stream());
+                let response = if is_api_server {
+                    // API server handles only API requests
+                    api::handle_api_config(req, state_clone, addr)
+                        .await
+                        .map(|response| response.map(Either::Left))
+                } else {
+                    // Application server handles all non-API requests
+                    handler::handle_request(req, state_clone, addr)
+                        .await
+                        .map(|response| response.map(Either::Right))
+                };
+                timers.response_ready();
+                response.map(|response| {
+                    response.map(|body| TimedBody::new(body, Arc::clone(&timers)))
+                })
+            }
+        }),
+    );
+
+    // Serve until the connection ends or a phase deadline expires
+    tokio::select! {
+        result = conn => {
+            if let Err(err) = result {
+                logger::log_connection_error(&err);
+            }
+        }
+        reason = timers.expired(state) => {
+            let limit = reason.limit(&state.snapshot().performance);
+            logger::log_connection_timeout(&peer_addr, &reason.to_string(), limit);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+
+    const CLIENTS: usize = 64;
+    const REQUESTS_PER_CLIENT: usize = 500;
+
+    /// Start the main server on an ephemeral port inside `runtime`
+    fn start_server(runtime: &tokio::runtime::Runtime) -> std::net::SocketAddr {
+        runtime.block_on(async {
+            let mut cfg = config::Config::load_from("config").unwrap();
+            cfg.logging.access_log = false;
+            let state_manager = config::create_state_manager("config", false);
+            let state = Arc::new(config::AppState::new(&cfg, state_manager).await);
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            let counter = Arc::new(AtomicUsize::new(0));
+            tokio::spawn(async move {
+                while let Ok((stream, peer_addr)) = listener.accept().await {
+                    accept_connection(stream, peer_addr, &state, &counter, false, "", false);
+                }
+            });
+            addr
+        })
+    }
+
+    /// Read one response (headers plus `Content-Length` body) from a keep-alive connection
+    async fn read_response(stream: &mut TcpStream, buf: &mut Vec<u8>) {
+        let mut chunk = [0u8; 4096];
+        loop {
+            if let Some(end) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+                let headers = String::from_utf8_lossy(&buf[..end]).to_ascii_lowercase();
+                let length: usize = headers
+                    .lines()
+                    .find_map(|line| line.strip_prefix("content-length:"))
+                    .map_or(0, |v| v.trim().parse().unwrap());
+                if buf.len() >= end + 4 + length {
+                    buf.drain(..end + 4 + length);
+                    return;
+                }
+            }
+            let n = stream.read(&mut chunk).await.unwrap();
+            assert!(n > 0, "connection closed");
+            buf.extend_from_slice(&chunk[..n]);
+        }
+    }
+
+    /// Requests per second with `workers` server threads (clients run on their own runtime)
+    fn requests_per_second(workers: usize) -> f64 {
+        let server = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(workers)
+            .enable_all()
+            .build()
+            .unwrap();
+        let addr = start_server(&server);
+        let clients = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+
+        clients.block_on(async {
+            // Connect and warm up every client before the clock starts
+            let ready = Arc::new(tokio::sync::Barrier::new(CLIENTS + 1));
+            let tasks: Vec<_> = (0..CLIENTS)
+                .map(|_| {
+                    let ready = Arc::clone(&ready);
+                    tokio::spawn(async move {
+                        let mut stream = TcpStream::connect(addr).await.unwrap();
+                        let mut buf = Vec::new();
+                        for i in 0..=REQUESTS_PER_CLIENT {
+                            if i == 1 {
+                                ready.wait().await;
+                            }
+                            stream
+                                .write_all(b"GET / HTTP/1.1\r\nHost: bench\r\n\r\n")
+                                .await
+                                .unwrap();
+                            read_response(&mut stream, &mut buf).await;
+                        }
+                    })
+                })
+                .collect();
+            ready.wait().await;
+            let start = Instant::now();
+            for task in tasks {
+                task.await.unwrap();
+            }
+            #[allow(clippy::cast_precision_loss)]
+            let total = (CLIENTS * REQUESTS_PER_CLIENT) as f64;
+            total / start.elapsed().as_secs_f64()
+        })
+    }
+
+    /// Throughput of one worker thread versus all cores
+    ///
+    /// Run with `cargo test --release bench_ -- --ignored --nocapture`.
+    #[test]
+    #[ignore = "benchmark"]
+    fn bench_worker_scaling() {
+        let cores = std::thread::available_parallelism().map_or(1, std::num::NonZeroUsize::get);
+        let single = requests_per_second(1);
+        let all = requests_per_second(cores);
+        println!(
+            "{CLIENTS} keep-alive clients: 1 worker {single:.0} req/s, {cores} workers {all:.0} req/s ({:.2}x)",
+            all / single
+        );
+    }
+}
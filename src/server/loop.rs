@@ -145,7 +145,7 @@ where
                 let old_state = Arc::clone(&state);
                 let old_counter = Arc::clone(&active_connections);
 
-                tokio::task::spawn_local(async move {
+                tokio::spawn(async move {
                     drain_old_listener(old_listener, old_state, old_counter).await;
                 });
 
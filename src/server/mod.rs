@@ -3,8 +3,11 @@
 
 pub mod connection;
 pub mod listener;
+pub mod overload;
 pub mod restart;
 pub mod signal;
+pub mod timeout;
+pub mod tls;
 
 // Rust doesn't allow 'loop' as a module name (reserved keyword), renamed to server_loop
 #[path = "loop.rs"]
@@ -0,0 +1,397 @@
+// Overload module
+// Connection admission: global and per-IP limits, overload policy and rejection counters
+
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper_util::rt::TokioIo;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::fmt;
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+use tokio::sync::Notify;
+use tokio::time::Instant;
+
+use crate::config::{AppState, DynamicPerformanceConfig};
+use crate::handler::router::set_server_header;
+use crate::http;
+use crate::logger;
+
+/// Connections answered with a 503 at the same time; beyond this they are closed
+const MAX_UNAVAILABLE_RESPONDERS: usize = 256;
+
+/// Time a connection over a limit gets to send its request and read the 503
+const UNAVAILABLE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Limit a new connection ran into
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LimitExceeded {
+    /// `max_connections` reached
+    MaxConnections { active: usize, max: u64 },
+    /// `max_connections_per_ip` reached for the client's address
+    PerIp { ip: IpAddr, active: usize, max: u64 },
+}
+
+impl fmt::Display for LimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MaxConnections { active, max } => {
+                write!(f, "Max connections reached: {active}/{max}")
+            }
+            Self::PerIp { ip, active, max } => {
+                write!(f, "Max connections per IP reached for {ip}: {active}/{max}")
+            }
+        }
+    }
+}
+
+/// Overload counters, as reported by `GET /v1/stats`
+#[derive(Debug, Default, Serialize, PartialEq, Eq)]
+pub struct OverloadStats {
+    /// Connections that arrived while `max_connections` was reached
+    pub rejected_max_connections: u64,
+    /// Connections that arrived while their IP was at `max_connections_per_ip`
+    pub rejected_per_ip: u64,
+    /// 503 responses sent to connections over a limit
+    pub service_unavailable: u64,
+    /// Connections closed without a response (`reject` policy, or all 503 responders busy)
+    pub closed: u64,
+    /// Connections put in the queue
+    pub queued: u64,
+    /// Queued connections that got a slot
+    pub queue_admitted: u64,
+    /// Queued connections that waited `queue_timeout_ms` without a slot
+    pub queue_timeouts: u64,
+    /// Connections turned away because the queue was full
+    pub queue_full: u64,
+    /// Connections waiting in the queue right now
+    pub queue_waiting: usize,
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    rejected_max_connections: AtomicU64,
+    rejected_per_ip: AtomicU64,
+    service_unavailable: AtomicU64,
+    closed: AtomicU64,
+    queued: AtomicU64,
+    queue_admitted: AtomicU64,
+    queue_timeouts: AtomicU64,
+    queue_full: AtomicU64,
+}
+
+/// Admission control for the main server's connections
+#[derive(Debug, Default)]
+pub struct ConnectionLimiter {
+    /// Open connections per client IP
+    per_ip: Mutex<HashMap<IpAddr, usize>>,
+    /// Signalled whenever a connection slot is released
+    released: Notify,
+    /// Connections currently waiting in the queue
+    waiting: AtomicUsize,
+    /// Connections currently being answered with a 503
+    responders: AtomicUsize,
+    counters: Counters,
+}
+
+impl ConnectionLimiter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Take a connection slot, counting a rejection if a limit is reached
+    ///
+    /// `conn_counter` is the server's active connection counter; on success
+    /// both it and the per-IP count are incremented.
+    pub fn admit(
+        &self,
+        conn_counter: &AtomicUsize,
+        ip: IpAddr,
+        performance: &DynamicPerformanceConfig,
+    ) -> Result<(), LimitExceeded> {
+        let result = self.try_acquire(conn_counter, ip, performance);
+        match result {
+            Err(LimitExceeded::MaxConnections { .. }) => {
+                self.counters
+                    .rejected_max_connections
+                    .fetch_add(1, Ordering::Relaxed);
+            }
+            Err(LimitExceeded::PerIp { .. }) => {
+                self.counters
+                    .rejected_per_ip
+                    .fetch_add(1, Ordering::Relaxed);
+            }
+            Ok(()) => {}
+        }
+        result
+    }
+
+    /// Give back the per-IP slot of a closed connection and wake queued connections
+    ///
+    /// Call after decrementing the active connection counter.
+    pub fn release(&self, ip: IpAddr) {
+        let mut per_ip = self.lock();
+        if let Some(count) = per_ip.get_mut(&ip) {
+            *count -= 1;
+            if *count == 0 {
+                per_ip.remove(&ip);
+            }
+        }
+        drop(per_ip);
+        self.released.notify_waiters();
+    }
+
+    /// Current counter values
+    pub fn stats(&self) -> OverloadStats {
+        let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+        OverloadStats {
+            rejected_max_connections: load(&self.counters.rejected_max_connections),
+            rejected_per_ip: load(&self.counters.rejected_per_ip),
+            service_unavailable: load(&self.counters.service_unavailable),
+            closed: load(&self.counters.closed),
+            queued: load(&self.counters.queued),
+            queue_admitted: load(&self.counters.queue_admitted),
+            queue_timeouts: load(&self.counters.queue_timeouts),
+            queue_full: load(&self.counters.queue_full),
+            queue_waiting: self.waiting.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Count a connection closed without a response
+    pub fn record_closed(&self) {
+        self.counters.closed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Wait in the queue until a slot frees up
+    ///
+    /// Returns `false` when the queue is full or `queue_timeout_ms` passes
+    /// first; the caller then answers with a 503.
+    pub async fn wait_for_slot(
+        &self,
+        conn_counter: &AtomicUsize,
+        ip: IpAddr,
+        performance: &DynamicPerformanceConfig,
+    ) -> bool {
+        let queue_size = performance.overload.queue_size;
+        if self.waiting.fetch_add(1, Ordering::SeqCst) >= queue_size {
+            self.waiting.fetch_sub(1, Ordering::SeqCst);
+            self.counters.queue_full.fetch_add(1, Ordering::Relaxed);
+            return false;
+        }
+        self.counters.queued.fetch_add(1, Ordering::Relaxed);
+
+        let deadline =
+            Instant::now() + Duration::from_millis(performance.overload.queue_timeout_ms);
+        let admitted = loop {
+            // Register for the wake-up before checking, so a release in between is not missed
+            let released = self.released.notified();
+            tokio::pin!(released);
+            released.as_mut().enable();
+
+            if self.try_acquire(conn_counter, ip, performance).is_ok() {
+                break true;
+            }
+            if tokio::time::timeout_at(deadline, released).await.is_err() {
+                break false;
+            }
+        };
+
+        self.waiting.fetch_sub(1, Ordering::SeqCst);
+        let counter = if admitted {
+            &self.counters.queue_admitted
+        } else {
+            &self.counters.queue_timeouts
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        admitted
+    }
+
+    /// Take one of the `MAX_UNAVAILABLE_RESPONDERS` slots for a 503 response
+    fn reserve_responder(&self) -> bool {
+        if self.responders.fetch_add(1, Ordering::SeqCst) >= MAX_UNAVAILABLE_RESPONDERS {
+            self.responders.fetch_sub(1, Ordering::SeqCst);
+            return false;
+        }
+        true
+    }
+
+    fn release_responder(&self) {
+        self.responders.fetch_sub(1, Ordering::SeqCst);
+    }
+
+    fn try_acquire(
+        &self,
+        conn_counter: &AtomicUsize,
+        ip: IpAddr,
+        performance: &DynamicPerformanceConfig,
+    ) -> Result<(), LimitExceeded> {
+        // Increment counter first, then check limit (prevents race condition)
+        let prev_count = conn_counter.fetch_add(1, Ordering::SeqCst);
+
+        // 0 = unlimited
+        let max = performance.max_connections.unwrap_or(0);
+        if max > 0 && prev_count >= usize::try_from(max).unwrap_or(usize::MAX) {
+            conn_counter.fetch_sub(1, Ordering::SeqCst);
+            return Err(LimitExceeded::MaxConnections {
+                active: prev_count,
+                max,
+            });
+        }
+
+        let max_per_ip = performance.max_connections_per_ip;
+        let mut per_ip = self.lock();
+        let count = per_ip.entry(ip).or_insert(0);
+        if max_per_ip > 0 && *count >= usize::try_from(max_per_ip).unwrap_or(usize::MAX) {
+            let active = *count;
+            drop(per_ip);
+            conn_counter.fetch_sub(1, Ordering::SeqCst);
+            return Err(LimitExceeded::PerIp {
+                ip,
+                active,
+                max: max_per_ip,
+            });
+        }
+        *count += 1;
+        Ok(())
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<IpAddr, usize>> {
+        self.per_ip
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+/// Answer a connection over a limit with `503 Service Unavailable` and close it
+///
+/// The request is read before replying so the client sees the response rather
+/// than a reset, within `UNAVAILABLE_TIMEOUT`. At most `MAX_UNAVAILABLE_RESPONDERS`
+/// connections are answered at once; beyond that they are closed like `reject`,
+/// so a flood over the limits cannot hold unbounded tasks and sockets.
+pub fn serve_unavailable(stream: tokio::net::TcpStream, state: &Arc<AppState>) {
+    let limiter = &state.connection_limiter;
+    if !limiter.reserve_responder() {
+        limiter.record_closed();
+        return;
+    }
+    let config = state.snapshot();
+    let retry_after = config.performance.overload.retry_after;
+    let http_config = Arc::clone(&config.http);
+    limiter
+        .counters
+        .service_unavailable
+        .fetch_add(1, Ordering::Relaxed);
+
+    let state = Arc::clone(state);
+    tokio::spawn(async move {
+        let mut builder = http1::Builder::new();
+        builder.keep_alive(false);
+        let conn = builder.serve_connection(
+            TokioIo::new(stream),
+            service_fn(move |_req| {
+                let mut response = http::build_503_response(retry_after);
+                set_server_header(&mut response, &http_config);
+                async move { Ok::<_, Infallible>(response) }
+            }),
+        );
+        if let Ok(Err(err)) = tokio::time::timeout(UNAVAILABLE_TIMEOUT, conn).await {
+            logger::log_connection_error(&err);
+        }
+        state.connection_limiter.release_responder();
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn performance(max_connections: u64, max_connections_per_ip: u64) -> DynamicPerformanceConfig {
+        serde_json::from_value(serde_json::json!({
+            "keep_alive_timeout": 75,
+            "read_timeout": 30,
+            "write_timeout": 30,
+            "max_connections": max_connections,
+            "max_connections_per_ip": max_connections_per_ip,
+            "overload": {"policy": "queue", "queue_size": 1, "queue_timeout_ms": 50}
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_global_and_per_ip_limits() {
+        let limiter = ConnectionLimiter::new();
+        let counter = AtomicUsize::new(0);
+        let performance = performance(3, 2);
+        let a: IpAddr = "10.0.0.1".parse().unwrap();
+        let b: IpAddr = "10.0.0.2".parse().unwrap();
+
+        assert!(limiter.admit(&counter, a, &performance).is_ok());
+        assert!(limiter.admit(&counter, a, &performance).is_ok());
+        assert!(matches!(
+            limiter.admit(&counter, a, &performance),
+            Err(LimitExceeded::PerIp { active: 2, .. })
+        ));
+        assert!(limiter.admit(&counter, b, &performance).is_ok());
+        assert!(matches!(
+            limiter.admit(&counter, b, &performance),
+            Err(LimitExceeded::MaxConnections { active: 3, max: 3 })
+        ));
+        assert_eq!(counter.load(Ordering::SeqCst), 3);
+
+        // Releasing a connection from `a` frees both its IP slot and a global slot
+        counter.fetch_sub(1, Ordering::SeqCst);
+        limiter.release(a);
+        assert!(limiter.admit(&counter, a, &performance).is_ok());
+
+        let stats = limiter.stats();
+        assert_eq!(stats.rejected_per_ip, 1);
+        assert_eq!(stats.rejected_max_connections, 1);
+    }
+
+    #[tokio::test]
+    async fn test_queue_admits_on_release_and_times_out() {
+        let limiter = Arc::new(ConnectionLimiter::new());
+        let counter = Arc::new(AtomicUsize::new(0));
+        let performance = performance(1, 0);
+        let ip: IpAddr = "10.0.0.1".parse().unwrap();
+        assert!(limiter.admit(&counter, ip, &performance).is_ok());
+
+        // Nobody releases: the queued connection times out
+        assert!(!limiter.wait_for_slot(&counter, ip, &performance).await);
+
+        // A release while waiting admits the queued connection
+        let waiter = {
+            let (limiter, counter, performance) = (
+                Arc::clone(&limiter),
+                Arc::clone(&counter),
+                performance.clone(),
+            );
+            tokio::spawn(async move { limiter.wait_for_slot(&counter, ip, &performance).await })
+        };
+        tokio::task::yield_now().await;
+        counter.fetch_sub(1, Ordering::SeqCst);
+        limiter.release(ip);
+        assert!(waiter.await.unwrap());
+
+        let stats = limiter.stats();
+        assert_eq!(stats.queued, 2);
+        assert_eq!(stats.queue_admitted, 1);
+        assert_eq!(stats.queue_timeouts, 1);
+        assert_eq!(stats.queue_waiting, 0);
+    }
+
+    #[test]
+    fn test_unavailable_responders_bounded() {
+        let limiter = ConnectionLimiter::new();
+        for _ in 0..MAX_UNAVAILABLE_RESPONDERS {
+            assert!(limiter.reserve_responder());
+        }
+        assert!(!limiter.reserve_responder());
+        limiter.release_responder();
+        assert!(limiter.reserve_responder());
+        assert!(!limiter.reserve_responder());
+    }
+}
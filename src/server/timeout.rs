@@ -0,0 +1,407 @@
+// Connection timeout module
+// Tracks which phase an HTTP/1 connection is in so each phase gets its own deadline
+
+use hyper::body::{Body, Frame, SizeHint};
+use std::fmt;
+use std::io;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::sync::Notify;
+use tokio::time::Instant;
+
+use crate::config::{AppState, DynamicPerformanceConfig};
+
+/// Why the watchdog closed a connection
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeoutReason {
+    /// Request head not complete within `header_read_timeout` (slowloris protection)
+    HeaderRead,
+    /// No new request within `keep_alive_timeout` after the previous response
+    KeepAliveIdle,
+    /// Request body stalled for `read_timeout`
+    BodyRead,
+    /// Response write stalled for `write_timeout`
+    ResponseWrite,
+}
+
+impl TimeoutReason {
+    /// Configured limit (in seconds) that expired
+    pub const fn limit(self, performance: &DynamicPerformanceConfig) -> u64 {
+        match self {
+            Self::HeaderRead => performance.header_read_timeout,
+            Self::KeepAliveIdle => performance.keep_alive_timeout,
+            Self::BodyRead => performance.read_timeout,
+            Self::ResponseWrite => performance.write_timeout,
+        }
+    }
+}
+
+impl fmt::Display for TimeoutReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::HeaderRead => "header read timeout",
+            Self::KeepAliveIdle => "keep-alive idle timeout",
+            Self::BodyRead => "request body read timeout",
+            Self::ResponseWrite => "response write timeout",
+        })
+    }
+}
+
+/// Connection phase
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Phase {
+    /// Waiting for a complete request head (new connection, or bytes of the next request)
+    Header,
+    /// Request head parsed; `body` is whether a request body follows
+    Request { body: bool },
+    /// Response produced and being written; `finished` once its body is done
+    Response { finished: bool },
+    /// Between keep-alive requests
+    Idle,
+}
+
+#[derive(Debug)]
+struct TimerState {
+    phase: Phase,
+    phase_since: Instant,
+    /// Set while a read is pending (no data available)
+    read_waiting_since: Option<Instant>,
+    /// Set while a write or flush is pending (client not reading)
+    write_blocked_since: Option<Instant>,
+}
+
+/// Per-connection timeout state shared by the stream, the service and the watchdog
+#[derive(Debug)]
+pub struct ConnectionTimers {
+    state: Mutex<TimerState>,
+    /// Signalled whenever the next deadline may have moved earlier
+    changed: Notify,
+}
+
+impl ConnectionTimers {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self {
+            state: Mutex::new(TimerState {
+                phase: Phase::Header,
+                phase_since: Instant::now(),
+                read_waiting_since: None,
+                write_blocked_since: None,
+            }),
+            changed: Notify::new(),
+        })
+    }
+
+    /// The request head was parsed and the request is handed to the service
+    pub fn request_started(&self, has_body: bool) {
+        self.set_phase(Phase::Request { body: has_body });
+    }
+
+    /// The service produced a response
+    pub fn response_ready(&self) {
+        self.set_phase(Phase::Response { finished: false });
+    }
+
+    /// The response body was fully produced (or dropped)
+    fn response_finished(&self) {
+        let mut state = self.lock();
+        if let Phase::Response { finished } = &mut state.phase {
+            *finished = true;
+        }
+    }
+
+    /// Wait until the deadline of the current phase expires
+    ///
+    /// Deadlines are recomputed from the live performance config every time
+    /// the phase changes, so updates apply to open connections.
+    pub async fn expired(&self, state: &AppState) -> TimeoutReason {
+        loop {
+            let changed = self.changed.notified();
+            let deadline = self.deadline(&state.snapshot().performance);
+            match deadline {
+                Some((at, reason)) if at <= Instant::now() => return reason,
+                Some((at, _)) => {
+                    tokio::select! {
+                        () = tokio::time::sleep_until(at) => {}
+                        () = changed => {}
+                    }
+                }
+                None => changed.await,
+            }
+        }
+    }
+
+    /// Earliest deadline for the current phase and any blocked write
+    fn deadline(&self, performance: &DynamicPerformanceConfig) -> Option<(Instant, TimeoutReason)> {
+        // A limit of 0 disables that timeout
+        let after = |since: Instant, reason: TimeoutReason| {
+            let secs = reason.limit(performance);
+            (secs > 0).then(|| (since + Duration::from_secs(secs), reason))
+        };
+
+        let state = self.lock();
+        let phase_deadline = match state.phase {
+            Phase::Header => after(state.phase_since, TimeoutReason::HeaderRead),
+            Phase::Idle => after(state.phase_since, TimeoutReason::KeepAliveIdle),
+            Phase::Request { body: true } => state
+                .read_waiting_since
+                .and_then(|since| after(since, TimeoutReason::BodyRead)),
+            Phase::Request { body: false } | Phase::Response { .. } => None,
+        };
+        let write_deadline = state
+            .write_blocked_since
+            .and_then(|since| after(since, TimeoutReason::ResponseWrite));
+        drop(state);
+
+        [phase_deadline, write_deadline]
+            .into_iter()
+            .flatten()
+            .min_by_key(|(at, _)| *at)
+    }
+
+    fn set_phase(&self, phase: Phase) {
+        let mut state = self.lock();
+        state.phase = phase;
+        state.phase_since = Instant::now();
+        drop(state);
+        self.changed.notify_one();
+    }
+
+    fn on_read(&self, progress: Poll<bool>) {
+        let mut state = self.lock();
+        match progress {
+            Poll::Pending => {
+                if state.read_waiting_since.is_none() {
+                    state.read_waiting_since = Some(Instant::now());
+                    drop(state);
+                    self.changed.notify_one();
+                }
+            }
+            Poll::Ready(got_data) => {
+                state.read_waiting_since = None;
+                // First bytes of the next request end the idle phase
+                if got_data && state.phase == Phase::Idle {
+                    drop(state);
+                    self.set_phase(Phase::Header);
+                }
+            }
+        }
+    }
+
+    fn on_write(&self, blocked: bool) {
+        let mut state = self.lock();
+        if !blocked {
+            state.write_blocked_since = None;
+        } else if state.write_blocked_since.is_none() {
+            state.write_blocked_since = Some(Instant::now());
+            drop(state);
+            self.changed.notify_one();
+        }
+    }
+
+    fn on_flushed(&self) {
+        let mut state = self.lock();
+        state.write_blocked_since = None;
+        // A fully flushed response starts the keep-alive idle phase (streamed
+        // bodies are flushed chunk by chunk, so wait for the end of the body)
+        if state.phase == (Phase::Response { finished: true }) {
+            drop(state);
+            self.set_phase(Phase::Idle);
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, TimerState> {
+        self.state
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+/// Stream wrapper reporting read and write progress to `ConnectionTimers`
+pub struct TimedStream<S> {
+    inner: S,
+    timers: Arc<ConnectionTimers>,
+}
+
+impl<S> TimedStream<S> {
+    pub const fn new(inner: S, timers: Arc<ConnectionTimers>) -> Self {
+        Self { inner, timers }
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for TimedStream<S> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let filled = buf.filled().len();
+        let result = Pin::new(&mut self.inner).poll_read(cx, buf);
+        match &result {
+            Poll::Pending => self.timers.on_read(Poll::Pending),
+            Poll::Ready(Ok(())) => self
+                .timers
+                .on_read(Poll::Ready(buf.filled().len() > filled)),
+            Poll::Ready(Err(_)) => {}
+        }
+        result
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for TimedStream<S> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let result = Pin::new(&mut self.inner).poll_write(cx, buf);
+        self.timers.on_write(result.is_pending());
+        result
+    }
+
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        let result = Pin::new(&mut self.inner).poll_write_vectored(cx, bufs);
+        self.timers.on_write(result.is_pending());
+        result
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.inner.is_write_vectored()
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let result = Pin::new(&mut self.inner).poll_flush(cx);
+        if result.is_pending() {
+            self.timers.on_write(true);
+        } else {
+            self.timers.on_flushed();
+        }
+        result
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+/// Response body wrapper reporting the end of the body to `ConnectionTimers`
+pub struct TimedBody<B> {
+    inner: B,
+    timers: Arc<ConnectionTimers>,
+}
+
+impl<B> TimedBody<B> {
+    pub const fn new(inner: B, timers: Arc<ConnectionTimers>) -> Self {
+        Self { inner, timers }
+    }
+}
+
+impl<B: Body + Unpin> Body for TimedBody<B> {
+    type Data = B::Data;
+    type Error = B::Error;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        Pin::new(&mut self.inner).poll_frame(cx)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+impl<B> Drop for TimedBody<B> {
+    fn drop(&mut self) {
+        // hyper drops the body once it is complete (or the response is aborted)
+        self.timers.response_finished();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn performance() -> DynamicPerformanceConfig {
+        serde_json::from_value(serde_json::json!({
+            "keep_alive_timeout": 75,
+            "header_read_timeout": 10,
+            "read_timeout": 30,
+            "write_timeout": 20,
+            "max_connections": null
+        }))
+        .unwrap()
+    }
+
+    fn reason(
+        timers: &ConnectionTimers,
+        performance: &DynamicPerformanceConfig,
+    ) -> Option<TimeoutReason> {
+        timers.deadline(performance).map(|(_, reason)| reason)
+    }
+
+    #[test]
+    fn test_phase_deadlines() {
+        let performance = performance();
+        let timers = ConnectionTimers::new();
+        assert_eq!(
+            reason(&timers, &performance),
+            Some(TimeoutReason::HeaderRead)
+        );
+
+        // No read deadline while a body-less request is handled
+        timers.request_started(false);
+        timers.on_read(Poll::Pending);
+        assert_eq!(reason(&timers, &performance), None);
+
+        timers.request_started(true);
+        assert_eq!(reason(&timers, &performance), Some(TimeoutReason::BodyRead));
+
+        timers.response_ready();
+        timers.on_write(true);
+        assert_eq!(
+            reason(&timers, &performance),
+            Some(TimeoutReason::ResponseWrite)
+        );
+
+        // Flushes of a streamed body in progress do not start the idle phase
+        timers.on_flushed();
+        assert_eq!(reason(&timers, &performance), None);
+
+        drop(TimedBody::new((), Arc::clone(&timers)));
+        timers.on_flushed();
+        assert_eq!(
+            reason(&timers, &performance),
+            Some(TimeoutReason::KeepAliveIdle)
+        );
+
+        // The first byte of the next request starts the header phase
+        timers.on_read(Poll::Ready(true));
+        let (at, reason) = timers.deadline(&performance).unwrap();
+        assert_eq!(reason, TimeoutReason::HeaderRead);
+        assert!(at > Instant::now() + Duration::from_secs(9));
+    }
+
+    #[test]
+    fn test_zero_disables_timeout() {
+        let mut performance = performance();
+        performance.header_read_timeout = 0;
+        let timers = ConnectionTimers::new();
+        assert_eq!(reason(&timers, &performance), None);
+
+        performance.write_timeout = 0;
+        timers.on_write(true);
+        assert_eq!(reason(&timers, &performance), None);
+    }
+}
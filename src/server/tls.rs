@@ -0,0 +1,94 @@
+// TLS module
+// TLS for the management API, with optional client certificate (mTLS) verification
+
+use std::fs::File;
+use std::io::BufReader;
+use std::sync::Arc;
+use tokio_rustls::rustls::crypto::ring;
+use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use tokio_rustls::rustls::server::WebPkiClientVerifier;
+use tokio_rustls::rustls::{RootCertStore, ServerConfig};
+use tokio_rustls::server::TlsStream;
+use tokio_rustls::TlsAcceptor;
+
+use crate::config::ApiTlsConfig;
+
+/// Verified client certificate of a TLS connection, added to each request's extensions
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientCertificate {
+    /// Subject common name (the full subject when it has no CN)
+    pub subject: String,
+}
+
+/// Build the TLS acceptor of the management API
+pub fn build_acceptor(config: &ApiTlsConfig) -> Result<TlsAcceptor, String> {
+    let provider = Arc::new(ring::default_provider());
+    let certs = load_certs(&config.cert_file)?;
+    let key = load_key(&config.key_file)?;
+
+    let builder = ServerConfig::builder_with_provider(Arc::clone(&provider))
+        .with_safe_default_protocol_versions()
+        .map_err(|e| format!("TLS: {e}"))?;
+    let builder = match &config.client_ca_file {
+        Some(ca_file) => {
+            let mut roots = RootCertStore::empty();
+            for cert in load_certs(ca_file)? {
+                roots
+                    .add(cert)
+                    .map_err(|e| format!("TLS: invalid CA certificate in '{ca_file}': {e}"))?;
+            }
+            let verifier = WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider);
+            // Without a required certificate, clients may still use tokens or passwords
+            let verifier = if config.require_client_cert {
+                verifier
+            } else {
+                verifier.allow_unauthenticated()
+            };
+            let verifier = verifier
+                .build()
+                .map_err(|e| format!("TLS: client CA '{ca_file}': {e}"))?;
+            builder.with_client_cert_verifier(verifier)
+        }
+        None if config.require_client_cert => {
+            return Err("TLS: require_client_cert needs client_ca_file".to_string());
+        }
+        None => builder.with_no_client_auth(),
+    };
+
+    let mut server_config = builder
+        .with_single_cert(certs, key)
+        .map_err(|e| format!("TLS: invalid certificate or key: {e}"))?;
+    server_config.alpn_protocols = vec![b"http/1.1".to_vec()];
+    Ok(TlsAcceptor::from(Arc::new(server_config)))
+}
+
+/// Client certificate presented (and verified) during the handshake
+pub fn client_certificate<IO>(stream: &TlsStream<IO>) -> Option<ClientCertificate> {
+    let cert = stream.get_ref().1.peer_certificates()?.first()?;
+    let (_, cert) = x509_parser::parse_x509_certificate(cert.as_ref()).ok()?;
+    let subject = cert
+        .subject()
+        .iter_common_name()
+        .next()
+        .and_then(|cn| cn.as_str().ok())
+        .map_or_else(|| cert.subject().to_string(), str::to_string);
+    Some(ClientCertificate { subject })
+}
+
+fn load_certs(path: &str) -> Result<Vec<CertificateDer<'static>>, String> {
+    let file = File::open(path).map_err(|e| format!("TLS: cannot open '{path}': {e}"))?;
+    let certs = rustls_pemfile::certs(&mut BufReader::new(file))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("TLS: cannot read certificates from '{path}': {e}"))?;
+    if certs.is_empty() {
+        return Err(format!("TLS: no certificate found in '{path}'"));
+    }
+    Ok(certs)
+}
+
+fn load_key(path: &str) -> Result<PrivateKeyDer<'static>, String> {
+    let file = File::open(path).map_err(|e| format!("TLS: cannot open '{path}': {e}"))?;
+    rustls_pemfile::private_key(&mut BufReader::new(file))
+        .map_err(|e| format!("TLS: cannot read private key from '{path}': {e}"))?
+        .ok_or_else(|| format!("TLS: no private key found in '{path}'"))
+}